@@ -0,0 +1,551 @@
+// FROM: src/ast.hpp
+
+use std::rc::Rc;
+use environment::Environment;
+use fn_utils::BuiltinFunction;
+use functions::SassFunctionEntry;
+use operators::{BinaryOperator, UnaryOperator};
+use position::{ParserState, SourceFile};
+use values::{ListSeparator, Value};
+
+// Statements are shared between the stylesheet and any callables
+// declared in it, so blocks are reference counted.
+pub type Block = Rc<Vec<Statement>>;
+
+#[derive(Debug, Clone)]
+pub struct Stylesheet {
+    pub body: Block,
+    pub source: Rc<SourceFile>,
+}
+
+// Text that may contain interpolated expressions, such as a selector
+// or a property name.
+#[derive(Debug, Clone)]
+pub struct Interpolation {
+    pub parts: Vec<InterpolationPart>,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub enum InterpolationPart {
+    Text(String),
+    Expression(Expression),
+}
+
+impl Interpolation {
+    pub fn plain<S: Into<String>>(text: S, pstate: ParserState) -> Self {
+        Interpolation { parts: vec![InterpolationPart::Text(text.into())], pstate }
+    }
+
+    /// Returns the text if there is nothing interpolated.
+    pub fn as_plain(&self) -> Option<&str> {
+        match self.parts.len() {
+            0 => Some(""),
+            1 => match self.parts[0] {
+                InterpolationPart::Text(ref text) => Some(text),
+                InterpolationPart::Expression(_) => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The leading plain text, if any.
+    pub fn initial_plain(&self) -> &str {
+        match self.parts.first() {
+            Some(InterpolationPart::Text(text)) => text,
+            _ => "",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Statement {
+    StyleRule(StyleRule),
+    Declaration(Declaration),
+    VariableDecl(VariableDecl),
+    Import(Import),
+    Use(UseRule),
+    Forward(ForwardRule),
+    MixinRule(Rc<MixinRule>),
+    FunctionRule(Rc<FunctionRule>),
+    Return(ReturnRule),
+    Include(IncludeRule),
+    Extend(ExtendRule),
+    Media(MediaRule),
+    Supports(SupportsRule),
+    AtRoot(AtRootRule),
+    AtRule(AtRule),
+    Content(ContentRule),
+    Debug(MessageRule),
+    Warn(MessageRule),
+    Error(MessageRule),
+    If(IfRule),
+    Each(EachRule),
+    For(ForRule),
+    While(WhileRule),
+    LoudComment(LoudComment),
+}
+
+#[derive(Debug, Clone)]
+pub struct StyleRule {
+    pub selector: Interpolation,
+    pub body: Block,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub name: Interpolation,
+    // None for a property such as `font: {...}` that only has nested ones
+    pub value: Option<Expression>,
+    // the nested properties, as in `margin: 0 { left: 1px }`
+    pub children: Option<Block>,
+    pub is_important: bool,
+    // a `--name` property, whose value is kept as written
+    pub is_custom_property: bool,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct VariableDecl {
+    // the module of a variable such as `ns.$x`
+    pub namespace: Option<String>,
+    pub name: String,
+    pub expr: Expression,
+    pub is_default: bool,
+    pub is_global: bool,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub imports: Vec<ImportArgument>,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub enum ImportArgument {
+    // an import of a Sass file, resolved by the importers
+    Dynamic { url: String, pstate: ParserState },
+    // a plain CSS import that is passed through to the output
+    Static { url: Interpolation, modifiers: Option<Interpolation>, pstate: ParserState },
+}
+
+#[derive(Debug, Clone)]
+pub struct UseRule {
+    pub url: String,
+    // None for `as *`, which makes the members available as globals
+    pub namespace: Option<String>,
+    pub configuration: Vec<ConfiguredVariable>,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct ForwardRule {
+    pub url: String,
+    // from `as prefix-*`
+    pub prefix: Option<String>,
+    // the members listed by `show`, or None if all are shown, and those
+    // listed by `hide`; variable names start with `$`
+    pub shown: Option<Vec<String>>,
+    pub hidden: Vec<String>,
+    pub configuration: Vec<ConfiguredVariable>,
+    pub pstate: ParserState,
+}
+
+// A variable in the `with (...)` of a @use or @forward.
+#[derive(Debug, Clone)]
+pub struct ConfiguredVariable {
+    pub name: String,
+    pub expr: Expression,
+    // `!default`, only allowed in a @forward
+    pub is_guarded: bool,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug)]
+pub struct MixinRule {
+    pub name: String,
+    pub args: ArgumentDeclaration,
+    pub body: Block,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug)]
+pub struct FunctionRule {
+    pub name: String,
+    pub args: ArgumentDeclaration,
+    pub body: Block,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReturnRule {
+    pub value: Expression,
+    pub pstate: ParserState,
+}
+
+// A @debug, @warn or @error.
+#[derive(Debug, Clone)]
+pub struct MessageRule {
+    pub value: Expression,
+    pub pstate: ParserState,
+}
+
+// A `/* */` comment, which may be written to the output
+#[derive(Debug, Clone)]
+pub struct LoudComment {
+    // the whole comment, including the `/*` and `*/`
+    pub text: Interpolation,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtendRule {
+    pub selector: Interpolation,
+    // `!optional`: no error if the target is not found
+    pub optional: bool,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct MediaRule {
+    pub query: Interpolation,
+    pub body: Block,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct SupportsRule {
+    pub condition: Interpolation,
+    pub body: Block,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct AtRootRule {
+    // such as `(without: media)`; a plain @at-root only leaves style rules
+    pub query: Option<Interpolation>,
+    pub body: Block,
+    pub pstate: ParserState,
+}
+
+// An at-rule Sass doesn't know, such as @font-face, which is passed
+// through to the output.
+#[derive(Debug, Clone)]
+pub struct AtRule {
+    pub name: String,
+    pub value: Option<Interpolation>,
+    pub body: Option<Block>,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct IncludeRule {
+    pub namespace: Option<String>,
+    pub name: String,
+    pub args: ArgumentInvocation,
+    pub content: Option<Rc<ContentBlock>>,
+    pub pstate: ParserState,
+}
+
+// The block passed to a mixin, with the parameters declared by `using`.
+#[derive(Debug)]
+pub struct ContentBlock {
+    pub args: ArgumentDeclaration,
+    pub body: Block,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContentRule {
+    pub args: ArgumentInvocation,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct IfRule {
+    // the @if and each @else if, with their conditions
+    pub clauses: Vec<(Expression, Block)>,
+    pub otherwise: Option<Block>,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct EachRule {
+    // more than one variable destructures each element
+    pub variables: Vec<String>,
+    pub list: Expression,
+    pub body: Block,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct ForRule {
+    pub variable: String,
+    pub from: Expression,
+    pub to: Expression,
+    // `through` includes the end, `to` does not
+    pub inclusive: bool,
+    pub body: Block,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct WhileRule {
+    pub condition: Expression,
+    pub body: Block,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expression {
+    // a literal number, color, boolean or null
+    Value(Value, ParserState),
+    String(StringExpr),
+    Variable(VariableExpr),
+    Binary(Box<BinaryExpr>),
+    Unary(Box<UnaryExpr>),
+    FunctionCall(FunctionCall),
+    // a plain CSS function whose name is interpolated, such as `foo-#{$x}()`
+    InterpolatedFunctionCall(InterpolatedFunctionCall),
+    // `if($condition, $if-true, $if-false)`, which only evaluates one branch
+    If(IfExpr),
+    List(ListExpr),
+    Map(MapExpr),
+    Paren(Box<Expression>, ParserState),
+    // `&`, the selector of the enclosing style rule
+    ParentSelector(ParserState),
+}
+
+impl Expression {
+    pub fn pstate(&self) -> &ParserState {
+        match *self {
+            Expression::Value(_, ref pstate) => pstate,
+            Expression::String(ref e) => &e.text.pstate,
+            Expression::Variable(ref e) => &e.pstate,
+            Expression::Binary(ref e) => &e.pstate,
+            Expression::Unary(ref e) => &e.pstate,
+            Expression::FunctionCall(ref e) => &e.pstate,
+            Expression::InterpolatedFunctionCall(ref e) => &e.pstate,
+            Expression::If(ref e) => &e.pstate,
+            Expression::List(ref e) => &e.pstate,
+            Expression::Map(ref e) => &e.pstate,
+            Expression::Paren(_, ref pstate) => pstate,
+            Expression::ParentSelector(ref pstate) => pstate,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StringExpr {
+    pub text: Interpolation,
+    pub quoted: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct VariableExpr {
+    pub namespace: Option<String>,
+    pub name: String,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct BinaryExpr {
+    pub op: BinaryOperator,
+    pub left: Expression,
+    pub right: Expression,
+    // `/` between two literal numbers is printed as a slash in CSS
+    pub allows_slash: bool,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct UnaryExpr {
+    pub op: UnaryOperator,
+    pub operand: Expression,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionCall {
+    pub namespace: Option<String>,
+    pub name: String,
+    pub args: ArgumentInvocation,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct IfExpr {
+    pub args: ArgumentInvocation,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct InterpolatedFunctionCall {
+    pub name: Interpolation,
+    pub args: ArgumentInvocation,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct ArgumentInvocation {
+    pub positional: Vec<Expression>,
+    pub named: Vec<(String, Expression)>,
+    // `$list...`, and `$map...` after it
+    pub rest: Option<Box<Expression>>,
+    pub keyword_rest: Option<Box<Expression>>,
+    pub pstate: ParserState,
+}
+
+impl ArgumentInvocation {
+    pub fn empty(pstate: ParserState) -> Self {
+        ArgumentInvocation { positional: vec![], named: vec![], rest: None, keyword_rest: None, pstate }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positional.is_empty() && self.named.is_empty() && self.rest.is_none()
+    }
+}
+
+// The parameters of a mixin or function, or of a C function signature.
+#[derive(Debug, Clone)]
+pub struct ArgumentDeclaration {
+    pub params: Vec<Parameter>,
+    // the name of the `$args...` parameter, if any
+    pub rest: Option<String>,
+    pub pstate: ParserState,
+}
+
+impl ArgumentDeclaration {
+    pub fn empty(pstate: ParserState) -> Self {
+        ArgumentDeclaration { params: vec![], rest: None, pstate }
+    }
+
+    /// Whether a call with this many positional arguments and these
+    /// named ones can be bound to the parameters.
+    pub fn matches(&self, positional: usize, names: &[&str]) -> bool {
+        let mut named_used = 0;
+        for (i, param) in self.params.iter().enumerate() {
+            if i < positional {
+                if names.contains(&param.name.as_str()) {
+                    return false;
+                }
+            } else if names.contains(&param.name.as_str()) {
+                named_used += 1;
+            } else if param.default.is_none() {
+                return false;
+            }
+        }
+        if self.rest.is_some() {
+            return true;
+        }
+        positional <= self.params.len() && named_used == names.len()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub default: Option<Expression>,
+    pub pstate: ParserState,
+}
+
+// Something that can be called as a function.
+#[derive(Debug, Clone)]
+pub enum Callable {
+    // a function declared with @function
+    UserFunction(Rc<UserFunction>),
+    // a function provided by the compiler
+    Builtin(Rc<Builtin>),
+    // a function registered through the C API
+    CFunction(Rc<CFunction>),
+    // a plain CSS function, as returned by get-function($css: true)
+    Plain(String),
+}
+
+impl Callable {
+    pub fn name(&self) -> &str {
+        match *self {
+            Callable::UserFunction(ref f) => &f.rule.name,
+            Callable::Builtin(ref f) => &f.name,
+            Callable::CFunction(ref f) => &f.name,
+            Callable::Plain(ref name) => name,
+        }
+    }
+}
+
+// Function values are equal only if they are the same function.
+impl PartialEq for Callable {
+    fn eq(&self, other: &Callable) -> bool {
+        match (self, other) {
+            (Callable::UserFunction(a), Callable::UserFunction(b)) => Rc::ptr_eq(a, b),
+            (Callable::Builtin(a), Callable::Builtin(b)) => Rc::ptr_eq(a, b),
+            (Callable::CFunction(a), Callable::CFunction(b)) => Rc::ptr_eq(a, b),
+            (Callable::Plain(a), Callable::Plain(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+// A function, with the environment it was declared in.
+#[derive(Debug)]
+pub struct UserFunction {
+    pub rule: Rc<FunctionRule>,
+    pub env: Environment,
+}
+
+// A function provided by the compiler. Some have several signatures,
+// and the first that the arguments match is the one called.
+#[derive(Debug)]
+pub struct Builtin {
+    pub name: String,
+    pub overloads: Vec<(ArgumentDeclaration, BuiltinFunction)>,
+}
+
+impl Builtin {
+    /// The overload to call with the given arguments. If none matches,
+    /// the last is used so that its errors are reported.
+    pub fn overload_for(&self, positional: usize, names: &[&str]) -> &(ArgumentDeclaration, BuiltinFunction) {
+        self.overloads.iter()
+            .find(|(args, _)| args.matches(positional, names))
+            .unwrap_or_else(|| self.overloads.last().expect("built-in function without a signature"))
+    }
+}
+
+// A mixin, with the environment it was declared in.
+#[derive(Debug)]
+pub struct UserMixin {
+    pub rule: Rc<MixinRule>,
+    pub env: Environment,
+}
+
+#[derive(Debug, Clone)]
+pub enum Mixin {
+    // a mixin declared with @mixin
+    User(Rc<UserMixin>),
+    // a mixin provided by the compiler, such as meta.load-css()
+    Builtin(Rc<Builtin>),
+}
+
+#[derive(Debug)]
+pub struct CFunction {
+    // the name from the signature; `*` is called for unknown functions
+    pub name: String,
+    pub args: ArgumentDeclaration,
+    pub entry: SassFunctionEntry,
+}
+
+#[derive(Debug, Clone)]
+pub struct ListExpr {
+    pub items: Vec<Expression>,
+    pub separator: ListSeparator,
+    pub bracketed: bool,
+    pub pstate: ParserState,
+}
+
+#[derive(Debug, Clone)]
+pub struct MapExpr {
+    pub pairs: Vec<(Expression, Expression)>,
+    pub pstate: ParserState,
+}
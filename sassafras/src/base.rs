@@ -1,19 +1,65 @@
-/*
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use libc;
+
+// Reported by libsass_version(). Plugins built against libsass check
+// this, so it is the libsass release we are compatible with rather
+// than our own crate version.
+pub const LIBSASS_VERSION: &str = "3.6.6\0";
+
+// Reported by libsass_language_version().
+pub const LIBSASS_LANGUAGE_VERSION: &str = "3.5\0";
+
 // to allocate buffer to be filled
-ADDAPI void* ADDCALL sass_alloc_memory(size_t size);
+#[no_mangle]
+pub extern fn sass_alloc_memory(size: usize) -> *mut c_void {
+    let ptr = unsafe { libc::malloc(size) as *mut c_void };
+    if ptr.is_null() {
+        eprintln!("Out of memory.");
+        ::std::process::exit(1);
+    }
+    ptr
+}
+
 // to allocate a buffer from existing string
-ADDAPI char* ADDCALL sass_copy_c_string(const char* str);
-// to free overtaken memory when done
-ADDAPI void ADDCALL sass_free_memory(void* ptr);
+// Safety: as in libsass, `str` is trusted to be null or null-terminated.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern fn sass_copy_c_string(str: *const c_char) -> *mut c_char {
+    if str.is_null() {
+        return ptr::null_mut();
+    }
 
-// Some convenient string helper function
-ADDAPI char* ADDCALL sass_string_quote (const char* str, const char quote_mark);
-ADDAPI char* ADDCALL sass_string_unquote (const char* str);
+    unsafe {
+        let len = libc::strlen(str) + 1;
+        let copy = sass_alloc_memory(len) as *mut c_char;
+        ptr::copy_nonoverlapping(str, copy, len);
+        copy
+    }
+}
+
+// to free overtaken memory when done
+#[no_mangle]
+pub extern fn sass_free_memory(ptr: *mut c_void) {
+    if !ptr.is_null() {
+        unsafe { libc::free(ptr as *mut libc::c_void) };
+    }
+}
 
 // Implemented sass language version
-// Hardcoded version 3.4 for time being
-ADDAPI const char* ADDCALL libsass_version(void);
+#[no_mangle]
+pub extern fn libsass_version() -> *const c_char {
+    LIBSASS_VERSION.as_ptr() as *const c_char
+}
 
 // Get compiled libsass language
-ADDAPI const char* ADDCALL libsass_language_version(void);
-*/
\ No newline at end of file
+#[no_mangle]
+pub extern fn libsass_language_version() -> *const c_char {
+    LIBSASS_LANGUAGE_VERSION.as_ptr() as *const c_char
+}
+
+/*
+// Some convenient string helper function
+ADDAPI char* ADDCALL sass_string_quote (const char* str, const char quote_mark);
+ADDAPI char* ADDCALL sass_string_unquote (const char* str);
+*/
@@ -0,0 +1,149 @@
+// FROM: src/bind.cpp
+
+use ast::*;
+use error_handling::SassResult;
+use expand::Expand;
+use inspect::inspect_value;
+use parser::normalize_name;
+use position::ParserState;
+use values::*;
+
+// The arguments of a call, evaluated in the caller's environment.
+#[derive(Debug, Clone, Default)]
+pub struct EvaluatedArgs {
+    pub positional: Vec<Value>,
+    pub named: Vec<(String, Value)>,
+    // the separator of a list passed as `$args...`
+    pub separator: Option<ListSeparator>,
+}
+
+impl<'a> Expand<'a> {
+    /// Evaluates the arguments of a call. A list passed as `$args...` is
+    /// spread into positional arguments, and a map into keyword arguments.
+    pub fn eval_arguments(&mut self, args: &ArgumentInvocation) -> SassResult<EvaluatedArgs> {
+        self.evaluate_arguments(args, false)
+    }
+
+    /// Evaluates the arguments of a plain CSS function call, in which
+    /// numbers such as `1px/2px` keep their slash.
+    pub fn eval_css_arguments(&mut self, args: &ArgumentInvocation) -> SassResult<EvaluatedArgs> {
+        self.evaluate_arguments(args, true)
+    }
+
+    fn evaluate_arguments(&mut self, args: &ArgumentInvocation, keep_slash: bool) -> SassResult<EvaluatedArgs> {
+        let mut evaluated = EvaluatedArgs::default();
+        for arg in &args.positional {
+            let value = self.eval(arg)?;
+            evaluated.positional.push(if keep_slash { value } else { self.without_slash(value, arg.pstate())? });
+        }
+        for (name, arg) in &args.named {
+            let value = self.eval(arg)?;
+            evaluated.named.push((name.clone(), if keep_slash { value } else { self.without_slash(value, arg.pstate())? }));
+        }
+
+        if let Some(ref rest) = args.rest {
+            match self.eval(rest)? {
+                Value::Map(ref map) => self.add_rest_map(&mut evaluated, map, rest.pstate())?,
+                Value::List(list) => {
+                    if list.separator != ListSeparator::Undecided {
+                        evaluated.separator = Some(list.separator);
+                    }
+                    if let Some(ref keywords) = list.keywords {
+                        self.add_rest_map(&mut evaluated, keywords, rest.pstate())?;
+                    }
+                    evaluated.positional.extend(list.items);
+                }
+                other => evaluated.positional.push(other),
+            }
+        }
+
+        if let Some(ref keyword_rest) = args.keyword_rest {
+            match self.eval(keyword_rest)? {
+                Value::Map(ref map) => self.add_rest_map(&mut evaluated, map, keyword_rest.pstate())?,
+                other => {
+                    let msg = format!("Variable keyword arguments must be a map (was {}).", inspect_value(&other, self.ctx.precision));
+                    return Err(self.error(msg, keyword_rest.pstate()));
+                }
+            }
+        }
+        Ok(evaluated)
+    }
+
+    /// Adds the entries of a map passed as `$args...` as keyword
+    /// arguments. Arguments passed explicitly take precedence.
+    fn add_rest_map(&self, args: &mut EvaluatedArgs, map: &SassMap, pstate: &ParserState) -> SassResult<()> {
+        for (key, value) in map.pairs() {
+            let name = match *key {
+                Value::String(ref s) => normalize_name(&s.text),
+                _ => {
+                    let msg = format!("Variable keyword argument map must have string keys.\n{} is not a string in {}.",
+                                      inspect_value(key, self.ctx.precision), inspect_value(&Value::Map(map.clone()), self.ctx.precision));
+                    return Err(self.error(msg, pstate));
+                }
+            };
+            if !args.named.iter().any(|(n, _)| *n == name) {
+                args.named.push((name, value.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Binds arguments to the parameters of a mixin or function, as local
+    /// variables in the current scope. Defaults are evaluated in that
+    /// scope, so they can refer to earlier parameters. `callee` is
+    /// "Mixin" or "Function", for error messages.
+    pub fn bind_arguments(&mut self, callee: &str, name: &str, decl: &ArgumentDeclaration,
+                          mut args: EvaluatedArgs, pstate: &ParserState) -> SassResult<()> {
+        let params = &decl.params;
+        if args.positional.len() > params.len() && decl.rest.is_none() {
+            let msg = format!("wrong number of arguments ({} for {}) for `{}'", args.positional.len(), params.len(), name);
+            return Err(self.error(msg, pstate));
+        }
+
+        if decl.rest.is_none() {
+            if let Some((param, _)) = args.named.iter().find(|(n, _)| !params.iter().any(|p| p.name == *n)) {
+                let msg = format!("{} {} has no parameter named ${}", callee, name, param);
+                return Err(self.error(msg, pstate));
+            }
+        }
+
+        let mut positional = args.positional.drain(..);
+        for param in params {
+            let named = args.named.iter().position(|(n, _)| *n == param.name);
+            let value = match positional.next() {
+                Some(value) => {
+                    if named.is_some() {
+                        let msg = format!("{} {} was passed argument ${} both by position and by name.", callee, name, param.name);
+                        return Err(self.error(msg, pstate));
+                    }
+                    value
+                }
+                None => match (named, param.default.as_ref()) {
+                    (Some(i), _) => args.named.remove(i).1,
+                    (None, Some(default)) => self.eval(default)?,
+                    (None, None) => {
+                        let msg = format!("{} {} is missing argument ${}.", callee, name, param.name);
+                        return Err(self.error(msg, pstate));
+                    }
+                },
+            };
+            self.env.set_local_variable(&param.name, value);
+        }
+
+        if let Some(ref rest) = decl.rest {
+            // Keyword arguments that match no parameter are kept
+            // on the argument list.
+            let mut keywords = SassMap::new();
+            for (name, value) in args.named.drain(..) {
+                keywords.insert(Value::unquoted_string(name), value);
+            }
+            let separator = match args.separator {
+                Some(ListSeparator::Undecided) | None => ListSeparator::Comma,
+                Some(separator) => separator,
+            };
+            let items: Vec<Value> = positional.collect();
+            self.env.set_local_variable(rest, Value::List(List::arglist(items, separator, keywords)));
+        }
+        Ok(())
+    }
+}
@@ -71,24 +71,23 @@ pub fn c_char_ptr_to_vec(ptr: *const c_char) -> Vec<u8> {
     s.to_vec()
 }
 
-/// Converts a C string pointer to an OsString, including a trailing null byte.
+/// Converts a C string pointer to an OsString, without the trailing null byte.
 #[cfg(unix)]
 fn c_char_ptr_to_osstring(ptr: *const c_char) -> OsString {
     use std::os::unix::ffi::OsStrExt;
 
-    //let slice = c_char_ptr_to_cstr(ptr);
-    let bytes = c_char_ptr_to_bytes_with_nul(ptr);
+    let bytes = c_char_ptr_to_cstr(ptr).to_bytes();
     OsString::from(OsStr::from_bytes(bytes))
 }
 
-/// Converts a C string pointer to an OsString, including a trailing null byte.
+/// Converts a C string pointer to an OsString, without the trailing null byte.
 #[cfg(windows)]
 pub fn c_char_ptr_to_osstring(ptr: *const c_char) -> OsString {
-    let bytes = c_char_ptr_to_vec(ptr);
-    OsString::from_vec(bytes)
+    let s = c_char_ptr_to_cstr(ptr).to_string_lossy().into_owned();
+    OsString::from(s)
 }
 
-/// Converts a C string pointer to a PathBuf. The PathBuf should have a trailing null byte.
+/// Converts a C string pointer to a PathBuf.
 pub fn c_char_ptr_to_pathbuf(ptr: *const c_char) -> PathBuf {
     let osstr = c_char_ptr_to_osstring(ptr);
     PathBuf::from(osstr)
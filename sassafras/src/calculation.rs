@@ -0,0 +1,190 @@
+use inspect::inspect_value;
+use values::*;
+
+// Units that CSS may be able to combine in a calculation although Sass
+// can't convert between them, grouped by what they measure. A unit in
+// none of these, such as `%`, may be compatible with anything.
+const LENGTHS: &[&str] = &[
+    "em", "rem", "ex", "rex", "cap", "rcap", "ch", "rch", "ic", "ric", "lh", "rlh",
+    "vw", "lvw", "svw", "dvw", "vh", "lvh", "svh", "dvh", "vi", "lvi", "svi", "dvi",
+    "vb", "lvb", "svb", "dvb", "vmin", "lvmin", "svmin", "dvmin", "vmax", "lvmax", "svmax", "dvmax",
+    "cqw", "cqh", "cqi", "cqb", "cqmin", "cqmax", "cm", "mm", "q", "in", "pt", "pc", "px",
+];
+const ANGLES: &[&str] = &["deg", "grad", "rad", "turn"];
+const TIMES: &[&str] = &["s", "ms"];
+const FREQUENCIES: &[&str] = &["hz", "khz"];
+const RESOLUTIONS: &[&str] = &["dpi", "dpcm", "dppx", "x"];
+
+fn known_compatibilities(unit: &str) -> Option<&'static [&'static str]> {
+    [LENGTHS, ANGLES, TIMES, FREQUENCIES, RESOLUTIONS].iter().cloned().find(|units| units.contains(&unit))
+}
+
+/// Whether two numbers may be added once the browser knows the sizes
+/// involved, such as `1em` and `1px`, but not `1px` and `1s`.
+fn possibly_compatible(a: &Number, b: &Number) -> bool {
+    if a.is_unitless() || b.is_unitless() {
+        return a.is_unitless() && b.is_unitless();
+    }
+    let (a_unit, b_unit) = (a.numerators[0].to_ascii_lowercase(), b.numerators[0].to_ascii_lowercase());
+    match (known_compatibilities(&a_unit), known_compatibilities(&b_unit)) {
+        (Some(units), Some(_)) => units.contains(&b_unit.as_str()),
+        _ => true,
+    }
+}
+
+/// Whether Sass can convert between the units of two numbers. Unlike
+/// comparison, unitless numbers only match other unitless numbers.
+fn has_compatible_units(a: &Number, b: &Number) -> bool {
+    b.convert_value(&a.numerators, &a.denominators).is_some()
+}
+
+fn number_string(n: &Number, precision: u8) -> String {
+    inspect_value(&Value::Number(n.clone()), precision)
+}
+
+/// Checks that the numbers among the arguments of a calculation could
+/// ever be combined in CSS.
+fn verify_compatible(args: &[&CalcValue], precision: u8) -> Result<(), String> {
+    let numbers: Vec<&Number> = args.iter().filter_map(|arg| match **arg {
+        CalcValue::Number(ref n) => Some(n),
+        _ => None,
+    }).collect();
+
+    for n in &numbers {
+        if !n.has_valid_css_units() {
+            return Err(format!("Number {} isn't compatible with CSS calculations.", number_string(n, precision)));
+        }
+    }
+    for (i, a) in numbers.iter().enumerate() {
+        for b in &numbers[i + 1..] {
+            if !possibly_compatible(a, b) {
+                return Err(format!("{} and {} are incompatible.", number_string(a, precision), number_string(b, precision)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A nested calc() is the same as its argument.
+fn simplify(value: CalcValue) -> CalcValue {
+    match value {
+        CalcValue::Calculation(ref calc) if calc.name == "calc" => calc.args[0].clone(),
+        value => value,
+    }
+}
+
+/// Converts a value used in a calculation. Only numbers, unquoted
+/// strings and other calculations can be.
+pub fn to_calc_value(value: Value, precision: u8) -> Result<CalcValue, String> {
+    match value {
+        Value::Number(ref n) => Ok(CalcValue::Number(n.without_slash())),
+        Value::Calculation(calc) => Ok(CalcValue::Calculation(calc)),
+        Value::String(ref s) if !s.quoted => Ok(CalcValue::String(s.text.clone())),
+        Value::String(_) => Err(format!("Quoted string {} can't be used in a calculation.", inspect_value(&value, precision))),
+        _ => Err(format!("Value {} can't be used in a calculation.", inspect_value(&value, precision))),
+    }
+}
+
+/// The value of `calc()`, which is a number if its argument simplifies
+/// to one.
+pub fn calc(arg: CalcValue) -> Value {
+    match simplify(arg) {
+        CalcValue::Number(n) => Value::Number(n),
+        CalcValue::Calculation(calc) => Value::Calculation(calc),
+        arg => Value::Calculation(Calculation { name: "calc".to_string(), args: vec![arg] }),
+    }
+}
+
+/// The value of `min()` or `max()`: the smallest or largest argument if
+/// they are all comparable numbers, or else the calculation.
+pub fn min_max(name: &str, args: Vec<CalcValue>, precision: u8) -> Result<Value, String> {
+    let args: Vec<CalcValue> = args.into_iter().map(simplify).collect();
+    if let Some(n) = extremum(&args, name == "max") {
+        return Ok(Value::Number(n));
+    }
+    verify_compatible(&args.iter().collect::<Vec<_>>(), precision)?;
+    Ok(Value::Calculation(Calculation { name: name.to_string(), args }))
+}
+
+fn extremum(args: &[CalcValue], largest: bool) -> Option<Number> {
+    let mut result: Option<&Number> = None;
+    for arg in args {
+        let n = match *arg {
+            CalcValue::Number(ref n) => n,
+            _ => return None,
+        };
+        result = match result {
+            None => Some(n),
+            Some(current) => {
+                let value = n.coerce_value(&current.numerators, &current.denominators)?;
+                let replace = if largest { value > current.value } else { value < current.value };
+                Some(if replace && !fuzzy_equals(value, current.value) { n } else { current })
+            }
+        };
+    }
+    result.cloned()
+}
+
+/// The value of `clamp()`: the middle argument limited by the others if
+/// they are all compatible numbers, or else the calculation.
+pub fn clamp(args: Vec<CalcValue>, precision: u8) -> Result<Value, String> {
+    let args: Vec<CalcValue> = args.into_iter().map(simplify).collect();
+    if let [CalcValue::Number(ref min), CalcValue::Number(ref value), CalcValue::Number(ref max)] = args[..] {
+        if has_compatible_units(min, value) && has_compatible_units(min, max) {
+            let v = value.convert_value(&min.numerators, &min.denominators).unwrap();
+            let upper = max.convert_value(&min.numerators, &min.denominators).unwrap();
+            let result = if v <= min.value || fuzzy_equals(v, min.value) {
+                min
+            } else if v >= upper || fuzzy_equals(v, upper) {
+                max
+            } else {
+                value
+            };
+            return Ok(Value::Number(result.clone()));
+        }
+    }
+
+    verify_compatible(&args.iter().collect::<Vec<_>>(), precision)?;
+    // A string such as `var(--range)` may stand for several arguments.
+    let has_string = args.iter().any(|arg| matches!(*arg, CalcValue::String(_)));
+    if args.len() != 3 && !has_string {
+        return Err(format!("3 arguments required, but only {} {} passed.",
+                           args.len(), if args.len() == 1 { "was" } else { "were" }));
+    }
+    Ok(Value::Calculation(Calculation { name: "clamp".to_string(), args }))
+}
+
+/// Applies an operator in a calculation, giving a number when both
+/// operands are numbers that can be combined.
+pub fn operate(op: CalcOperator, left: CalcValue, right: CalcValue, precision: u8) -> Result<CalcValue, String> {
+    let (left, right) = (simplify(left), simplify(right));
+    match op {
+        CalcOperator::Plus | CalcOperator::Minus => {
+            if let (CalcValue::Number(l), CalcValue::Number(r)) = (&left, &right) {
+                if let Some(value) = r.convert_value(&l.numerators, &l.denominators) {
+                    let value = if op == CalcOperator::Plus { l.value + value } else { l.value - value };
+                    return Ok(CalcValue::Number(Number::with_units(value, l.numerators.clone(), l.denominators.clone())));
+                }
+            }
+            verify_compatible(&[&left, &right], precision)?;
+
+            // `a - -b` is written `a + b`.
+            let (op, right) = match right {
+                CalcValue::Number(ref r) if r.value < 0.0 && !fuzzy_equals(r.value, 0.0) => {
+                    let op = if op == CalcOperator::Plus { CalcOperator::Minus } else { CalcOperator::Plus };
+                    (op, CalcValue::Number(Number { value: -r.value, ..r.clone() }))
+                }
+                right => (op, right),
+            };
+            Ok(CalcValue::Operation(Box::new(CalcOperation { op, left, right })))
+        }
+        CalcOperator::Times | CalcOperator::Divide => match (left, right) {
+            (CalcValue::Number(ref l), CalcValue::Number(ref r)) => Ok(CalcValue::Number(if op == CalcOperator::Times {
+                l.multiply(r)
+            } else {
+                l.divide(r)
+            })),
+            (left, right) => Ok(CalcValue::Operation(Box::new(CalcOperation { op, left, right }))),
+        },
+    }
+}
@@ -0,0 +1,583 @@
+// FROM: src/context.cpp
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::env;
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::ptr;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use ast::Stylesheet;
+use base::sass_copy_c_string;
+use c_api_helpers::drop_raw_ptr;
+use deprecation::Deprecation;
+use environment::Environment;
+use error_handling::{rel_path, Backtrace, SassError, SassResult};
+use functions::*;
+use libc;
+use logger::Logger;
+use module::Module;
+use parser::{is_plain_import_url, Parser};
+use position::{ParserState, SourceFile};
+use sass_compiler::SassCompiler;
+use sass_context::SassContext;
+use sass_options::SassDivision;
+use sass_output_options::SassOutputStyle;
+
+// The default extensions tried when resolving an import.
+const DEFAULT_EXTENSIONS: &[&str] = &[".scss", ".sass", ".css"];
+
+// A loaded stylesheet, before it is parsed.
+#[derive(Debug, Clone)]
+pub struct Resource {
+    // the url as written in the @import, or the input path
+    pub imp_path: String,
+    // the resolved absolute path, or the key given by an importer
+    pub abs_path: String,
+    pub contents: String,
+    pub srcmap: Option<String>,
+}
+
+// What an @import resolved to.
+#[derive(Debug, Clone)]
+pub enum LoadedImport {
+    // a stylesheet to evaluate in place of the @import
+    Resource(Resource),
+    // a url to emit as a plain CSS @import
+    Css(String),
+}
+
+// The compiler state for one compilation, shared by the parser and
+// evaluator. Importers can inspect it through the SassCompiler.
+#[derive(Debug)]
+pub struct Context {
+    // the compiler that owns us, passed to custom importers and functions
+    pub c_compiler: *mut SassCompiler,
+
+    pub input_path: String,
+    // the source of a data context, which has no input file
+    source_string: Option<String>,
+
+    pub output_style: SassOutputStyle,
+    pub precision: u8,
+    pub indent: String,
+    pub linefeed: String,
+    pub source_comments: bool,
+    pub division: SassDivision,
+
+    include_paths: Vec<PathBuf>,
+    extensions: Vec<String>,
+
+    // custom callbacks, sorted by descending priority
+    c_importers: Vec<SassImporterEntry>,
+    c_headers: Vec<SassImporterEntry>,
+    pub c_functions: Vec<SassFunctionEntry>,
+
+    // the file being evaluated is on top, the entry file at the bottom
+    pub import_stack: RefCell<Vec<SassImportEntry>>,
+    // the mixin and function calls being evaluated, innermost on top
+    pub callee_stack: RefCell<Vec<SassCalleeEntry>>,
+    // the modules loaded by @use and @forward, by canonical url, so that
+    // each is only evaluated once
+    pub modules: RefCell<HashMap<String, Rc<Module>>>,
+    // the state of the sequence random() draws from
+    pub random_state: Cell<u64>,
+    // the last id returned by unique-id(), once it has been called
+    pub unique_id: Cell<Option<u64>>,
+    pub logger: Rc<dyn Logger>,
+    fatal_deprecations: Vec<Deprecation>,
+    silence_deprecations: Vec<Deprecation>,
+    quiet_deps: bool,
+
+    included_files: RefCell<Vec<String>>,
+    sheets: RefCell<HashMap<String, Rc<Stylesheet>>>,
+}
+
+impl Context {
+    pub fn new(c_ctx: &SassContext, source_string: Option<String>) -> Self {
+        let options = &c_ctx.options;
+
+        let mut input_path = options.input_path.to_string_lossy().into_owned();
+        if input_path.is_empty() {
+            input_path = "stdin".to_string();
+        }
+
+        // The current directory is always searched first.
+        let mut include_paths = vec![cwd()];
+        include_paths.extend(options.include_paths());
+
+        let mut extensions: Vec<String> = DEFAULT_EXTENSIONS.iter().map(|s| s.to_string()).collect();
+        for ext in options.extensions() {
+            let ext = ext.to_string_lossy();
+            let ext = if ext.starts_with('.') { ext.into_owned() } else { format!(".{}", ext) };
+            if !extensions.contains(&ext) {
+                extensions.push(ext);
+            }
+        }
+
+        Context {
+            c_compiler: ptr::null_mut(),
+            input_path,
+            source_string,
+            output_style: options.output_options.inspect_options.output_style,
+            precision: options.output_options.inspect_options.precision,
+            indent: options.output_options.indent.clone(),
+            linefeed: options.output_options.linefeed.clone(),
+            source_comments: options.output_options.source_comments,
+            division: options.division,
+            include_paths,
+            extensions,
+            c_importers: sort_by_priority(&options.importers()),
+            c_headers: sort_by_priority(&options.headers()),
+            c_functions: options.functions(),
+            import_stack: RefCell::new(Vec::new()),
+            callee_stack: RefCell::new(Vec::new()),
+            modules: RefCell::new(HashMap::new()),
+            random_state: Cell::new(options.random_seed.unwrap_or_else(clock_seed)),
+            unique_id: Cell::new(None),
+            logger: options.logger(),
+            fatal_deprecations: options.fatal_deprecations().to_vec(),
+            silence_deprecations: options.silence_deprecations().to_vec(),
+            quiet_deps: options.quiet_deps,
+            included_files: RefCell::new(Vec::new()),
+            sheets: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Loads the entry stylesheet, from the input file or the data
+    /// context's source string.
+    pub fn load_entry(&self) -> Result<Resource, String> {
+        let abs_path = make_absolute(Path::new(&self.input_path));
+        let contents = match self.source_string {
+            Some(ref source) => source.clone(),
+            None => fs::read_to_string(&self.input_path)
+                .map_err(|_| format!("File to read not found or unreadable: {}", self.input_path))?,
+        };
+
+        Ok(Resource { imp_path: self.input_path.clone(), abs_path, contents, srcmap: None })
+    }
+
+    /// Parses a loaded stylesheet. Stylesheets are only parsed once,
+    /// however often they are imported, so the deprecations found by the
+    /// parser are only reported the first time.
+    pub fn parse(&self, resource: &Resource) -> SassResult<Rc<Stylesheet>> {
+        if let Some(sheet) = self.sheets.borrow().get(&resource.abs_path) {
+            return Ok(sheet.clone());
+        }
+
+        let source = SourceFile::new(resource.abs_path.clone(), resource.contents.clone());
+        let mut parser = Parser::new(source);
+        parser.set_division(self.division);
+        let sheet = Rc::new(parser.parse()?);
+        for (deprecation, message, pstate) in parser.take_deprecations() {
+            self.warn_deprecation(deprecation, &message, &pstate, &[Backtrace::new(pstate.clone(), "")])?;
+        }
+        self.sheets.borrow_mut().insert(resource.abs_path.clone(), sheet.clone());
+        Ok(sheet)
+    }
+
+    /// Reports a use of a deprecated feature. It is an error if the
+    /// deprecation is fatal, and isn't reported if it is silenced or is
+    /// in a dependency and `quiet_deps` is set.
+    pub fn warn_deprecation(&self, deprecation: Deprecation, message: &str, pstate: &ParserState,
+                            traces: &[Backtrace]) -> SassResult<()> {
+        if self.fatal_deprecations.contains(&deprecation) {
+            let message = format!("{}\n\nThis is only an error because you've set the {} deprecation to be fatal.\n\
+                                   Remove this setting if you need to keep using this feature.", message, deprecation);
+            return Err(SassError::with_traces(message, pstate, &traces[..traces.len().saturating_sub(1)]));
+        }
+        if self.silence_deprecations.contains(&deprecation) || (self.quiet_deps && self.is_dependency(pstate.path())) {
+            return Ok(());
+        }
+        self.logger.deprecation(deprecation, message, pstate, traces);
+        Ok(())
+    }
+
+    /// Whether a stylesheet was loaded from one of the include paths
+    /// given in the options, rather than being part of the project of
+    /// the entry file.
+    fn is_dependency(&self, abs_path: &str) -> bool {
+        let entry = make_absolute(Path::new(&self.input_path));
+        // The first include path is the current directory.
+        self.include_paths[1..].iter().map(|dir| make_absolute(dir)).any(|dir| {
+            Path::new(abs_path).starts_with(&dir) && !Path::new(&entry).starts_with(&dir)
+        })
+    }
+
+    /// Pushes a stylesheet onto the import stack while it is evaluated.
+    pub fn push_import(&self, resource: &Resource) {
+        self.included_files.borrow_mut().push(resource.abs_path.clone());
+
+        let source = CString::new(resource.contents.clone())
+            .map(|s| sass_copy_c_string(s.as_ptr()))
+            .unwrap_or(ptr::null_mut());
+        let srcmap = resource.srcmap.as_ref()
+            .and_then(|s| CString::new(s.clone()).ok())
+            .map(|s| sass_copy_c_string(s.as_ptr()))
+            .unwrap_or(ptr::null_mut());
+
+        let imp_path = CString::new(resource.imp_path.clone()).unwrap_or_default();
+        let abs_path = CString::new(resource.abs_path.clone()).unwrap_or_default();
+        self.import_stack.borrow_mut().push(sass_make_import(imp_path.as_ptr(), abs_path.as_ptr(), source, srcmap));
+    }
+
+    pub fn pop_import(&self) {
+        if let Some(entry) = self.import_stack.borrow_mut().pop() {
+            sass_delete_import(entry);
+        }
+    }
+
+    /// Pushes a mixin or function call onto the callee stack while it
+    /// runs. The environment is the caller's.
+    pub fn push_callee(&self, name: &str, pstate: &ParserState, callee_type: SassCalleeType, env: Environment) {
+        let callee = SassCallee {
+            name: CString::new(name).unwrap_or_default(),
+            path: CString::new(pstate.path()).unwrap_or_default(),
+            line: pstate.line() + 1,
+            column: pstate.column() + 1,
+            callee_type,
+            env: SassEnv { frame: env },
+        };
+        self.callee_stack.borrow_mut().push(Box::into_raw(Box::new(callee)));
+    }
+
+    pub fn pop_callee(&self) {
+        if let Some(entry) = self.callee_stack.borrow_mut().pop() {
+            drop_raw_ptr(entry);
+        }
+    }
+
+    /// The absolute path of the stylesheet currently being evaluated.
+    fn current_abs_path(&self) -> Option<String> {
+        let stack = self.import_stack.borrow();
+        stack.last().and_then(|&entry| {
+            let entry = unsafe { &*entry };
+            entry.abs_path.as_ref().map(|p| p.to_string_lossy().into_owned())
+        })
+    }
+
+    /// Resolves an @import url, first with the custom importers and then
+    /// on the file system, relative to the importing file and then in
+    /// the include paths.
+    pub fn load_import(&self, url: &str, pstate: &ParserState, traces: &[Backtrace]) -> SassResult<Vec<LoadedImport>> {
+        for &importer in &self.c_importers {
+            if let Some(imports) = self.call_importer(importer, url, true, pstate, traces)? {
+                return Ok(imports);
+            }
+        }
+
+        let resource = self.load_file(url, pstate, traces)?;
+        Ok(vec![LoadedImport::Resource(resource)])
+    }
+
+    /// Resolves the url of a @use or @forward, like an @import except
+    /// that it must load a stylesheet.
+    pub fn load_module(&self, url: &str, pstate: &ParserState, traces: &[Backtrace]) -> SassResult<Resource> {
+        let not_found = || SassError::with_traces("Can't find stylesheet to import.", pstate, traces);
+        for &importer in &self.c_importers {
+            if let Some(imports) = self.call_importer(importer, url, true, pstate, traces)? {
+                return match imports.into_iter().next() {
+                    Some(LoadedImport::Resource(resource)) => Ok(resource),
+                    _ => Err(not_found()),
+                };
+            }
+        }
+
+        let path = self.find_include(url, pstate, traces)?.ok_or_else(not_found)?;
+        let contents = fs::read_to_string(&path).map_err(|_| not_found())?;
+        Ok(Resource { imp_path: url.to_string(), abs_path: make_absolute(&path), contents, srcmap: None })
+    }
+
+    pub fn get_module(&self, abs_path: &str) -> Option<Rc<Module>> {
+        self.modules.borrow().get(abs_path).cloned()
+    }
+
+    pub fn add_module(&self, abs_path: &str, module: Rc<Module>) {
+        self.modules.borrow_mut().insert(abs_path.to_string(), module);
+    }
+
+    /// Calls the header importers for the entry file. Their imports are
+    /// evaluated before the entry stylesheet itself.
+    pub fn call_headers(&self, pstate: &ParserState) -> SassResult<Vec<LoadedImport>> {
+        let mut imports = Vec::new();
+        for &header in &self.c_headers {
+            if let Some(loaded) = self.call_importer(header, &self.input_path, false, pstate, &[])? {
+                imports.extend(loaded);
+            }
+        }
+        Ok(imports)
+    }
+
+    /// Calls one custom importer. Returns None if the importer declined
+    /// to handle the url by returning a null list.
+    fn call_importer(&self, importer: SassImporterEntry, url: &str, only_one: bool,
+                     pstate: &ParserState, traces: &[Backtrace]) -> SassResult<Option<Vec<LoadedImport>>> {
+        let function = sass_importer_get_function(importer);
+        let c_url = CString::new(url).unwrap_or_default();
+        let list = function(c_url.as_ptr(), importer, self.c_compiler);
+        if list.is_null() {
+            return Ok(None);
+        }
+
+        let mut result = Ok(Vec::new());
+        for (i, entry) in list_to_vec(list).into_iter().enumerate() {
+            // Multiple results from a header get unique keys.
+            let uniq_path = if only_one { url.to_string() } else { format!("{}:{}", url, i + 1) };
+            let source = take_c_string(sass_import_take_source(entry));
+            let srcmap = take_c_string(sass_import_take_srcmap(entry));
+            let abs_path = c_str_to_string(sass_import_get_abs_path(entry));
+
+            if let Some(message) = c_str_to_string(sass_import_get_error_message(entry)) {
+                let path = abs_path.unwrap_or(uniq_path);
+                result = Err(self.importer_error(entry, message, path, source, pstate, traces));
+                break;
+            }
+
+            let loaded = match (source, abs_path) {
+                (Some(contents), abs_path) => Ok(LoadedImport::Resource(Resource {
+                    imp_path: uniq_path.clone(),
+                    abs_path: abs_path.unwrap_or(uniq_path),
+                    contents,
+                    srcmap,
+                })),
+                // Only a path was returned, so load it like a normal import.
+                (None, Some(abs_path)) => {
+                    if is_plain_import_url(&abs_path) {
+                        Ok(LoadedImport::Css(abs_path))
+                    } else {
+                        self.load_file(&abs_path, pstate, traces).map(LoadedImport::Resource)
+                    }
+                }
+                (None, None) => continue,
+            };
+
+            match loaded {
+                Ok(loaded) => {
+                    if let Ok(ref mut imports) = result {
+                        imports.push(loaded);
+                    }
+                }
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+
+        sass_delete_import_list(list);
+        result.map(Some)
+    }
+
+    /// Builds the error for an import that an importer rejected. If the
+    /// importer gave a position within the source it returned, the error
+    /// points there, with the @import below it in the trace; otherwise
+    /// it points at the @import.
+    fn importer_error(&self, entry: SassImportEntry, message: String, path: String, source: Option<String>,
+                      pstate: &ParserState, traces: &[Backtrace]) -> Box<SassError> {
+        let line = sass_import_get_error_line(entry);
+        let column = sass_import_get_error_column(entry);
+        match source {
+            Some(source) if line != usize::MAX => {
+                let file = SourceFile::new(path, source);
+                let offset = file.offset(line - 1, if column == usize::MAX { 0 } else { column - 1 });
+                let mut traces = traces.to_vec();
+                traces.push(Backtrace::new(pstate.clone(), ""));
+                SassError::with_traces(message, &ParserState::new(&file, offset, 0), &traces)
+            }
+            _ => SassError::with_traces(message, pstate, traces),
+        }
+    }
+
+    /// Finds and reads the file for an import url.
+    fn load_file(&self, url: &str, pstate: &ParserState, traces: &[Backtrace]) -> SassResult<Resource> {
+        let path = self.find_include(url, pstate, traces)?
+            .ok_or_else(|| SassError::with_traces(format!("File to import not found or unreadable: {}.", url), pstate, traces))?;
+        let abs_path = make_absolute(&path);
+
+        let contents = fs::read_to_string(&path)
+            .map_err(|_| SassError::with_traces(format!("File to import not found or unreadable: {}.", url), pstate, traces))?;
+
+        Ok(Resource { imp_path: url.to_string(), abs_path, contents, srcmap: None })
+    }
+
+    /// Searches for the file an import refers to, relative to the current
+    /// file and then in each include path.
+    fn find_include(&self, url: &str, pstate: &ParserState, traces: &[Backtrace]) -> SassResult<Option<PathBuf>> {
+        let mut dirs = Vec::new();
+        if let Some(current) = self.current_abs_path() {
+            if let Some(dir) = Path::new(&current).parent() {
+                dirs.push(dir.to_path_buf());
+            }
+        }
+        dirs.extend(self.include_paths.iter().cloned());
+
+        for dir in dirs {
+            let found = self.resolve_in_dir(&dir, url);
+            if found.len() > 1 {
+                let mut msg = format!("It's not clear which file to import for '@import \"{}\"'.\nCandidates:\n", url);
+                for candidate in &found {
+                    let candidate = candidate.strip_prefix(&dir).unwrap_or(candidate);
+                    msg.push_str(&format!("  {}\n", candidate.to_string_lossy()));
+                }
+                msg.push_str("Please delete or rename all but one of these files.\n");
+                return Err(SassError::with_traces(msg, pstate, traces));
+            }
+            if let Some(found) = found.into_iter().next() {
+                return Ok(Some(found));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the existing files an import url could refer to within one
+    /// directory: the partial and the plain file for each extension, then
+    /// index files if the url names a directory.
+    fn resolve_in_dir(&self, dir: &Path, url: &str) -> Vec<PathBuf> {
+        let path = dir.join(url);
+        let (parent, name) = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => (parent.to_path_buf(), name.to_string_lossy().into_owned()),
+            _ => return vec![],
+        };
+
+        let candidates = |base: &Path, name: &str| -> Vec<PathBuf> {
+            let has_extension = self.extensions.iter().any(|ext| name.ends_with(ext.as_str()));
+            let mut paths = Vec::new();
+            if has_extension {
+                paths.push(base.join(format!("_{}", name)));
+                paths.push(base.join(name));
+            } else {
+                for ext in &self.extensions {
+                    paths.push(base.join(format!("_{}{}", name, ext)));
+                    paths.push(base.join(format!("{}{}", name, ext)));
+                }
+            }
+            paths.into_iter().filter(|p| p.is_file()).collect()
+        };
+
+        let found = candidates(&parent, &name);
+        if !found.is_empty() {
+            return found;
+        }
+        if path.is_dir() {
+            return candidates(&path, "index");
+        }
+        vec![]
+    }
+
+    /// Checks that importing a file would not import itself again.
+    pub fn check_import_loop(&self, abs_path: &str, pstate: &ParserState, traces: &[Backtrace]) -> SassResult<()> {
+        let paths = self.import_stack_paths();
+
+        let start = match paths.iter().position(|p| p == abs_path) {
+            Some(start) => start,
+            None => return Ok(()),
+        };
+
+        let mut msg = "An @import loop has been found:".to_string();
+        for i in start..paths.len() {
+            let next = if i + 1 < paths.len() { &paths[i + 1] } else { abs_path };
+            msg.push_str(&format!("\n    {} imports {}", rel_path(&paths[i]), rel_path(next)));
+        }
+        Err(SassError::with_traces(msg, pstate, traces))
+    }
+
+    /// Whether a stylesheet is being evaluated, so that loading it as a
+    /// module again would be a loop.
+    pub fn is_loading(&self, abs_path: &str) -> bool {
+        self.import_stack_paths().iter().any(|p| p == abs_path)
+    }
+
+    fn import_stack_paths(&self) -> Vec<String> {
+        self.import_stack.borrow().iter().map(|&entry| {
+            let entry = unsafe { &*entry };
+            entry.abs_path.as_ref().map(|p| p.to_string_lossy().into_owned()).unwrap_or_default()
+        }).collect()
+    }
+
+    /// The files that were loaded, with the entry file first and the
+    /// rest sorted. Data contexts skip the entry, which is not a file.
+    pub fn included_files(&self, skip_entry: bool) -> Vec<String> {
+        let files = self.included_files.borrow();
+        let mut rest: Vec<String> = files.iter().skip(1).cloned().collect();
+        rest.sort();
+        rest.dedup();
+
+        let mut result = Vec::new();
+        if !skip_entry {
+            if let Some(entry) = files.first() {
+                result.push(entry.clone());
+                rest.retain(|f| f != entry);
+            }
+        }
+        result.extend(rest);
+        result
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        for entry in self.import_stack.borrow_mut().drain(..) {
+            sass_delete_import(entry);
+        }
+        for entry in self.callee_stack.borrow_mut().drain(..) {
+            drop_raw_ptr(entry);
+        }
+    }
+}
+
+fn sort_by_priority(entries: &[SassImporterEntry]) -> Vec<SassImporterEntry> {
+    let mut sorted = entries.to_vec();
+    sorted.sort_by(|&a, &b| {
+        let (a, b) = (sass_importer_get_priority(a), sass_importer_get_priority(b));
+        b.partial_cmp(&a).unwrap_or(::std::cmp::Ordering::Equal)
+    });
+    sorted
+}
+
+/// Takes ownership of a malloc'd C string.
+fn take_c_string(ptr: *mut libc::c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    let s = unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned();
+    unsafe { libc::free(ptr as *mut libc::c_void) };
+    Some(s)
+}
+
+fn c_str_to_string(ptr: *const libc::c_char) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned())
+    }
+}
+
+/// The seed random() starts from when the options don't give one.
+fn clock_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs().wrapping_mul(1_000_000_000).wrapping_add(u64::from(d.subsec_nanos())))
+        .unwrap_or(0)
+}
+
+fn cwd() -> PathBuf {
+    env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
+}
+
+/// Makes a path absolute and removes `.` and `..` components.
+pub fn make_absolute(path: &Path) -> String {
+    let path = if path.is_absolute() { path.to_path_buf() } else { cwd().join(path) };
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized.to_string_lossy().into_owned()
+}
@@ -0,0 +1,478 @@
+// FROM: src/ast.hpp (the nodes that remain after expansion)
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::{Rc, Weak};
+use position::ParserState;
+use selector::SelectorList;
+
+pub type CssRef = Rc<RefCell<CssNode>>;
+
+// A node of the CSS tree built by the expander and written by the output.
+#[derive(Debug)]
+pub struct CssNode {
+    pub kind: CssKind,
+    pub children: Vec<CssRef>,
+    // the node this is a child of in the tree
+    pub parent: Option<Weak<RefCell<CssNode>>>,
+    // The style rule this node was nested in within the source. Nested
+    // output indents rules below a parent that has declarations.
+    pub nesting_parent: Option<Weak<RefCell<CssNode>>>,
+    pub pstate: Option<ParserState>,
+}
+
+#[derive(Debug, Clone)]
+pub enum CssKind {
+    Root,
+    StyleRule(SelectorList),
+    // an @media block, which holds style rules
+    MediaRule(Vec<MediaQuery>),
+    // an @supports block, with its evaluated condition
+    SupportsRule(String),
+    // any other at-rule, such as @font-face or @keyframes
+    AtRule(CssAtRule),
+    // a block within @keyframes, such as `from` or `50%`
+    KeyframeBlock(Vec<String>),
+    Declaration(CssDeclaration),
+    // a plain CSS @import
+    Import(String),
+    // a `/* */` comment, with any interpolation evaluated
+    Comment(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct CssAtRule {
+    // the name without the `@`
+    pub name: String,
+    pub value: Option<String>,
+    // whether the rule ends with `;` rather than a block
+    pub childless: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct CssDeclaration {
+    pub name: String,
+    // the serialized value
+    pub value: String,
+    pub is_important: bool,
+    // whether the value is the raw text of a custom property
+    pub is_custom_property: bool,
+}
+
+impl CssNode {
+    pub fn new(kind: CssKind, pstate: Option<ParserState>) -> CssRef {
+        Rc::new(RefCell::new(CssNode { kind, children: Vec::new(), parent: None, nesting_parent: None, pstate }))
+    }
+
+    pub fn is_style_rule(&self) -> bool {
+        matches!(self.kind, CssKind::StyleRule(_))
+    }
+
+    pub fn is_declaration(&self) -> bool {
+        matches!(self.kind, CssKind::Declaration(_))
+    }
+
+    pub fn is_comment(&self) -> bool {
+        matches!(self.kind, CssKind::Comment(_))
+    }
+
+    /// Whether this node contains anything that would be written: any
+    /// declarations or comments.
+    pub fn has_declarations(&self) -> bool {
+        self.children.iter().any(|c| {
+            let c = c.borrow();
+            c.is_declaration() || c.is_comment()
+        })
+    }
+
+    /// Whether nothing would be written for this node, such as a rule
+    /// without declarations or a media block with only such rules.
+    pub fn is_invisible(&self) -> bool {
+        match self.kind {
+            CssKind::StyleRule(ref selector) => !self.has_declarations() || selector.is_invisible(),
+            CssKind::MediaRule(_) | CssKind::SupportsRule(_) => self.children.iter().all(|c| c.borrow().is_invisible()),
+            CssKind::KeyframeBlock(_) => !self.has_declarations(),
+            _ => false,
+        }
+    }
+}
+
+/// Appends a child to a node.
+pub fn add_child(parent: &CssRef, child: CssRef) {
+    child.borrow_mut().parent = Some(Rc::downgrade(parent));
+    parent.borrow_mut().children.push(child);
+}
+
+/// Adds a plain CSS @import to the root. Browsers ignore imports that
+/// follow anything but comments and other imports, so one that comes
+/// after other CSS is moved up to the end of the leading imports.
+pub fn add_import(root: &CssRef, import: CssRef) {
+    import.borrow_mut().parent = Some(Rc::downgrade(root));
+    let mut root = root.borrow_mut();
+    let end = root.children.iter()
+        .position(|child| !matches!(child.borrow().kind, CssKind::Import(_) | CssKind::Comment(_)))
+        .unwrap_or(root.children.len());
+    root.children.insert(end, import);
+}
+
+/// The node a node was added to. Every node but the root has one.
+pub fn tree_parent(node: &CssRef) -> CssRef {
+    node.borrow().parent.as_ref().and_then(|p| p.upgrade()).expect("a node within the tree")
+}
+
+/// A copy of a node without its children, such as a style rule that an
+/// at-rule bubbles out of.
+pub fn copy_without_children(node: &CssRef) -> CssRef {
+    let node = node.borrow();
+    let copy = CssNode::new(node.kind.clone(), node.pstate.clone());
+    copy.borrow_mut().nesting_parent = node.nesting_parent.clone();
+    copy
+}
+
+// ---------------------------------------------------------------------------
+// Media queries
+
+// A single media query, such as `only screen and (color)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQuery {
+    // `not` or `only`
+    pub modifier: Option<String>,
+    // the media type, such as `screen`
+    pub media_type: Option<String>,
+    // the features, such as `(color)`, as written
+    pub features: Vec<String>,
+}
+
+impl fmt::Display for MediaQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref modifier) = self.modifier {
+            write!(f, "{} ", modifier)?;
+        }
+        if let Some(ref media_type) = self.media_type {
+            write!(f, "{}", media_type)?;
+            if !self.features.is_empty() {
+                write!(f, " and ")?;
+            }
+        }
+        write!(f, "{}", self.features.join(" and "))
+    }
+}
+
+impl MediaQuery {
+    fn matches_all_types(&self) -> bool {
+        self.media_type.as_ref().map_or(true, |t| t.eq_ignore_ascii_case("all"))
+    }
+
+    /// The query that matches what both queries match, or None if there
+    /// is none or it can't be written as a single query.
+    pub fn merge(&self, other: &MediaQuery) -> Option<MediaQuery> {
+        let lower = |s: &Option<String>| s.as_ref().map(|s| s.to_ascii_lowercase());
+        let (our_modifier, our_type) = (lower(&self.modifier), lower(&self.media_type));
+        let (their_modifier, their_type) = (lower(&other.modifier), lower(&other.media_type));
+        let our_not = our_modifier.as_ref().map_or(false, |m| m == "not");
+        let their_not = their_modifier.as_ref().map_or(false, |m| m == "not");
+        let all_features = || self.features.iter().chain(&other.features).cloned().collect();
+
+        if our_type.is_none() && their_type.is_none() {
+            return Some(MediaQuery { modifier: None, media_type: None, features: all_features() });
+        }
+
+        let (use_ours, media_type, features) = if our_not != their_not {
+            if our_type == their_type || self.matches_all_types() || other.matches_all_types() {
+                return None;
+            }
+            if our_not {
+                (false, other.media_type.clone(), other.features.clone())
+            } else {
+                (true, self.media_type.clone(), self.features.clone())
+            }
+        } else if our_not {
+            // CSS can't express "neither screen nor print".
+            if our_type != their_type {
+                return None;
+            }
+            let (more, fewer) = if self.features.len() > other.features.len() {
+                (&self.features, &other.features)
+            } else {
+                (&other.features, &self.features)
+            };
+            // The larger set of features is strictly narrower.
+            if !fewer.iter().all(|f| more.contains(f)) {
+                return None;
+            }
+            (true, self.media_type.clone(), more.clone())
+        } else if self.matches_all_types() {
+            // Omit the type if either query did, as neither targets a
+            // browser that needs "all and".
+            let media_type = if other.matches_all_types() && our_type.is_none() { None } else { other.media_type.clone() };
+            (false, media_type, all_features())
+        } else if other.matches_all_types() {
+            (true, self.media_type.clone(), all_features())
+        } else if our_type != their_type {
+            return None;
+        } else {
+            let use_ours = self.modifier.is_some() || other.modifier.is_none();
+            (use_ours, self.media_type.clone(), all_features())
+        };
+
+        let modifier = if use_ours { self.modifier.clone() } else { other.modifier.clone() };
+        Some(MediaQuery { modifier, media_type, features })
+    }
+}
+
+// The query of an @at-root, such as `(without: media)`, which decides
+// the enclosing rules it is moved out of.
+#[derive(Debug, Clone)]
+pub struct AtRootQuery {
+    // whether the names are kept rather than excluded
+    include: bool,
+    names: Vec<String>,
+}
+
+impl Default for AtRootQuery {
+    /// The query of a plain @at-root, which only leaves style rules.
+    fn default() -> Self {
+        AtRootQuery { include: false, names: vec!["rule".to_string()] }
+    }
+}
+
+impl AtRootQuery {
+    /// Parses a query once any Sass expressions in it have been evaluated.
+    pub fn parse(text: &str) -> Result<AtRootQuery, String> {
+        let mut parser = MediaQueryParser { text, pos: 0 };
+        if !parser.scan_char('(') {
+            return Err("expected \"(\".".to_string());
+        }
+        parser.skip_ws();
+        let include = match parser.identifier().ok() {
+            Some(ref word) if word.eq_ignore_ascii_case("with") => true,
+            Some(ref word) if word.eq_ignore_ascii_case("without") => false,
+            _ => return Err("Expected \"with\" or \"without\".".to_string()),
+        };
+        parser.skip_ws();
+        if !parser.scan_char(':') {
+            return Err("expected \":\".".to_string());
+        }
+        parser.skip_ws();
+        let mut names = Vec::new();
+        loop {
+            names.push(parser.identifier()?.to_ascii_lowercase());
+            parser.skip_ws();
+            if !parser.looking_at_identifier() {
+                break;
+            }
+        }
+        if !parser.scan_char(')') {
+            return Err("expected \")\".".to_string());
+        }
+        parser.skip_ws();
+        if parser.pos < text.len() {
+            return Err("expected no more input.".to_string());
+        }
+        Ok(AtRootQuery { include, names })
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.names.iter().any(|n| n == name)
+    }
+
+    pub fn excludes_name(&self, name: &str) -> bool {
+        (self.has("all") || self.has(name)) != self.include
+    }
+
+    pub fn excludes_style_rules(&self) -> bool {
+        (self.has("all") || self.has("rule")) != self.include
+    }
+
+    /// Whether an @at-root with this query leaves the given node.
+    pub fn excludes(&self, node: &CssNode) -> bool {
+        match node.kind {
+            _ if self.has("all") => !self.include,
+            CssKind::StyleRule(_) => self.excludes_style_rules(),
+            CssKind::MediaRule(_) => self.excludes_name("media"),
+            CssKind::SupportsRule(_) => self.excludes_name("supports"),
+            CssKind::AtRule(ref rule) => self.excludes_name(&rule.name.to_ascii_lowercase()),
+            _ => false,
+        }
+    }
+}
+
+/// Merges the queries of a nested @media with those of the enclosing
+/// one. Queries that can't be merged are dropped.
+pub fn merge_media_queries(outer: &[MediaQuery], inner: &[MediaQuery]) -> Vec<MediaQuery> {
+    let mut result = Vec::new();
+    for query1 in outer {
+        for query2 in inner {
+            if let Some(merged) = query1.merge(query2) {
+                result.push(merged);
+            }
+        }
+    }
+    result
+}
+
+/// Parses a media query list once any Sass expressions in it have been
+/// evaluated.
+pub fn parse_media_queries(text: &str) -> Result<Vec<MediaQuery>, String> {
+    let mut parser = MediaQueryParser { text, pos: 0 };
+    let mut queries = Vec::new();
+    loop {
+        parser.skip_ws();
+        queries.push(parser.query()?);
+        parser.skip_ws();
+        if !parser.scan_char(',') {
+            break;
+        }
+    }
+    if parser.pos < text.len() {
+        return Err("expected \"{\".".to_string());
+    }
+    Ok(queries)
+}
+
+struct MediaQueryParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> MediaQueryParser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().map_or(false, char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn scan_char(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn looking_at_identifier(&self) -> bool {
+        self.peek().map_or(false, |c| c.is_alphabetic() || c == '-' || c == '_' || c == '\\')
+    }
+
+    fn identifier(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c == '\\' {
+                self.pos += 1;
+                if let Some(c) = self.peek() {
+                    self.pos += c.len_utf8();
+                }
+            } else if c.is_alphanumeric() || c == '-' || c == '_' || c as u32 >= 0x80 {
+                self.pos += c.len_utf8();
+            } else {
+                break;
+            }
+        }
+        if self.pos == start {
+            return Err("Expected identifier.".to_string());
+        }
+        Ok(self.text[start..self.pos].to_string())
+    }
+
+    /// Scans `and` followed by whitespace.
+    fn scan_and(&mut self) -> bool {
+        let rest = &self.text[self.pos..];
+        if rest.len() > 3 && rest[..3].eq_ignore_ascii_case("and") && rest[3..].starts_with(char::is_whitespace) {
+            self.pos += 3;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn query(&mut self) -> Result<MediaQuery, String> {
+        let mut query = MediaQuery { modifier: None, media_type: None, features: Vec::new() };
+        if self.peek() != Some('(') {
+            let identifier1 = self.identifier()?;
+            self.skip_ws();
+            if identifier1.eq_ignore_ascii_case("not") && self.peek() == Some('(') {
+                query.modifier = Some(identifier1);
+            } else if !self.looking_at_identifier() {
+                query.media_type = Some(identifier1);
+                return Ok(query);
+            } else {
+                let identifier2 = self.identifier()?;
+                self.skip_ws();
+                if identifier2.eq_ignore_ascii_case("and") {
+                    query.media_type = Some(identifier1);
+                } else {
+                    query.modifier = Some(identifier1);
+                    query.media_type = Some(identifier2);
+                    if !self.scan_and() {
+                        return Ok(query);
+                    }
+                    self.skip_ws();
+                }
+            }
+        }
+
+        loop {
+            query.features.push(self.in_parens()?);
+            self.skip_ws();
+            if !self.scan_and() {
+                break;
+            }
+            self.skip_ws();
+        }
+        Ok(query)
+    }
+
+    /// Reads a parenthesized feature as written.
+    fn in_parens(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        if !self.scan_char('(') {
+            return Err("expected media condition in parentheses.".to_string());
+        }
+        let mut depth = 1;
+        while depth > 0 {
+            match self.peek() {
+                None => return Err("expected \")\".".to_string()),
+                Some(c) => {
+                    self.pos += c.len_utf8();
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Ok(self.text[start..self.pos].to_string())
+    }
+}
+
+/// Parses the selector of a block within @keyframes, such as
+/// `from, 50%`.
+pub fn parse_keyframe_selectors(text: &str) -> Result<Vec<String>, String> {
+    let mut selectors = Vec::new();
+    for selector in text.split(',') {
+        let selector = selector.trim();
+        if selector.starts_with(|c: char| c.is_alphabetic()) {
+            if !selector.eq_ignore_ascii_case("from") && !selector.eq_ignore_ascii_case("to") {
+                return Err("Expected \"to\" or \"from\".".to_string());
+            }
+        } else {
+            let number = match selector.strip_suffix('%') {
+                Some(number) => number,
+                None => return Err("expected \"%\".".to_string()),
+            };
+            let valid = number.trim_start_matches(['+', '-']).starts_with(|c: char| c.is_ascii_digit() || c == '.') &&
+                number.parse::<f64>().is_ok();
+            if !valid {
+                return Err("Expected number.".to_string());
+            }
+        }
+        selectors.push(selector.to_string());
+    }
+    Ok(selectors)
+}
@@ -0,0 +1,160 @@
+use std::fmt;
+use std::str::FromStr;
+
+// A deprecated feature of the language. Each has an id, the same as
+// dart-sass uses, by which its warnings can be made fatal or silenced.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Deprecation {
+    // passing a string to call() instead of a function reference
+    CallString,
+    // `!default` or `!global` written more than once on a variable
+    DuplicateVarFlags,
+    // `@elseif` instead of `@else if`
+    Elseif,
+    // the feature-exists() function
+    FeatureExists,
+    // `!global` assignments to variables that don't exist
+    NewGlobal,
+    // `/` for division outside of calc()
+    SlashDiv,
+    // an ambiguous `-` that is parsed as subtraction, as in `$a -$b`
+    StrictUnary,
+    // lighten(), darken() and the other functions replaced by color.adjust()
+    ColorFunctions,
+    // @import of Sass stylesheets
+    Import,
+    // global built-in functions that have a module equivalent
+    GlobalBuiltin,
+}
+
+const ALL: &[Deprecation] = &[
+    Deprecation::CallString,
+    Deprecation::DuplicateVarFlags,
+    Deprecation::Elseif,
+    Deprecation::FeatureExists,
+    Deprecation::NewGlobal,
+    Deprecation::SlashDiv,
+    Deprecation::StrictUnary,
+    Deprecation::ColorFunctions,
+    Deprecation::Import,
+    Deprecation::GlobalBuiltin,
+];
+
+impl Deprecation {
+    pub fn all() -> &'static [Deprecation] {
+        ALL
+    }
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Deprecation::CallString => "call-string",
+            Deprecation::DuplicateVarFlags => "duplicate-var-flags",
+            Deprecation::Elseif => "elseif",
+            Deprecation::FeatureExists => "feature-exists",
+            Deprecation::NewGlobal => "new-global",
+            Deprecation::SlashDiv => "slash-div",
+            Deprecation::StrictUnary => "strict-unary",
+            Deprecation::ColorFunctions => "color-functions",
+            Deprecation::Import => "import",
+            Deprecation::GlobalBuiltin => "global-builtin",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Deprecation> {
+        ALL.iter().cloned().find(|d| d.id() == id)
+    }
+}
+
+impl fmt::Display for Deprecation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+impl FromStr for Deprecation {
+    type Err = String;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        Deprecation::from_id(id).ok_or_else(|| {
+            let ids: Vec<&str> = ALL.iter().map(|d| d.id()).collect();
+            format!("Invalid deprecation \"{}\". Expected one of: {}.", id, ids.join(", "))
+        })
+    }
+}
+
+/// The module member that replaces a global built-in function, such as
+/// `map.get` for `map-get`. Functions that are also plain CSS functions,
+/// such as rgb(), min() and max(), are not deprecated and have none.
+pub fn global_builtin_replacement(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "red" => "color.red",
+        "green" => "color.green",
+        "blue" => "color.blue",
+        "mix" => "color.mix",
+        "invert" => "color.invert",
+        "hue" => "color.hue",
+        "saturation" => "color.saturation",
+        "lightness" => "color.lightness",
+        "complement" => "color.complement",
+        "grayscale" => "color.grayscale",
+        "adjust-hue" | "lighten" | "darken" | "saturate" | "desaturate" | "opacify" | "fade-in" |
+        "transparentize" | "fade-out" | "adjust-color" => "color.adjust",
+        "alpha" | "opacity" => "color.alpha",
+        "ie-hex-str" => "color.ie-hex-str",
+        "scale-color" => "color.scale",
+        "change-color" => "color.change",
+        "length" => "list.length",
+        "nth" => "list.nth",
+        "set-nth" => "list.set-nth",
+        "join" => "list.join",
+        "append" => "list.append",
+        "zip" => "list.zip",
+        "index" => "list.index",
+        "is-bracketed" => "list.is-bracketed",
+        "list-separator" => "list.separator",
+        "map-get" => "map.get",
+        "map-merge" => "map.merge",
+        "map-remove" => "map.remove",
+        "map-keys" => "map.keys",
+        "map-values" => "map.values",
+        "map-has-key" => "map.has-key",
+        "percentage" => "math.percentage",
+        "round" => "math.round",
+        "ceil" => "math.ceil",
+        "floor" => "math.floor",
+        "abs" => "math.abs",
+        "random" => "math.random",
+        "unit" => "math.unit",
+        "unitless" => "math.is-unitless",
+        "comparable" => "math.compatible",
+        "feature-exists" => "meta.feature-exists",
+        "inspect" => "meta.inspect",
+        "type-of" => "meta.type-of",
+        "keywords" => "meta.keywords",
+        "variable-exists" => "meta.variable-exists",
+        "global-variable-exists" => "meta.global-variable-exists",
+        "function-exists" => "meta.function-exists",
+        "mixin-exists" => "meta.mixin-exists",
+        "content-exists" => "meta.content-exists",
+        "get-function" => "meta.get-function",
+        "call" => "meta.call",
+        "selector-nest" => "selector.nest",
+        "selector-append" => "selector.append",
+        "selector-extend" => "selector.extend",
+        "selector-replace" => "selector.replace",
+        "selector-unify" => "selector.unify",
+        "is-superselector" => "selector.is-superselector",
+        "simple-selectors" => "selector.simple-selectors",
+        "selector-parse" => "selector.parse",
+        "unquote" => "string.unquote",
+        "quote" => "string.quote",
+        "to-upper-case" => "string.to-upper-case",
+        "to-lower-case" => "string.to-lower-case",
+        "unique-id" => "string.unique-id",
+        "str-length" => "string.length",
+        "str-insert" => "string.insert",
+        "str-index" => "string.index",
+        "str-slice" => "string.slice",
+        _ => return None,
+    })
+}
@@ -0,0 +1,267 @@
+// FROM: src/environment.hpp
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use ast::{Callable, Mixin};
+use module::{Forward, Module};
+use values::Value;
+
+// One level of lexical scope. Scopes are shared so that callables can
+// close over the scopes they were declared in.
+pub type Scope<T> = Rc<RefCell<HashMap<String, T>>>;
+
+fn new_scope<T>() -> Scope<T> {
+    Rc::new(RefCell::new(HashMap::new()))
+}
+
+// The modules a stylesheet has loaded with @use and @forward.
+#[derive(Debug, Default)]
+pub struct Modules {
+    // by namespace
+    namespaced: HashMap<String, Rc<Module>>,
+    // loaded with `as *`, whose members are used like globals
+    global: Vec<Rc<Module>>,
+    // the modules passed on to the stylesheets that use this one
+    forwarded: Vec<Forward>,
+}
+
+// The lexical environment, from the global scope (first) to the
+// innermost scope (last).
+#[derive(Debug, Clone)]
+pub struct Environment {
+    variables: Vec<Scope<Value>>,
+    functions: Vec<Scope<Callable>>,
+    mixins: Vec<Scope<Mixin>>,
+    // For each scope, whether it is a control flow scope at the root of
+    // the stylesheet, whose assignments go to existing globals.
+    semi_global: Vec<bool>,
+    modules: Rc<RefCell<Modules>>,
+    // the built-in and C functions, which every module can call but
+    // which are not members of any
+    builtins: Scope<Callable>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Environment {
+            variables: vec![new_scope()],
+            functions: vec![new_scope()],
+            mixins: vec![new_scope()],
+            semi_global: vec![false],
+            modules: Rc::new(RefCell::new(Modules::default())),
+            builtins: new_scope(),
+        }
+    }
+
+    /// An environment sharing only the global scope, in which callables
+    /// declared outside the stylesheet are run.
+    pub fn global(&self) -> Environment {
+        Environment {
+            variables: vec![self.variables[0].clone()],
+            functions: vec![self.functions[0].clone()],
+            mixins: vec![self.mixins[0].clone()],
+            semi_global: vec![false],
+            modules: self.modules.clone(),
+            builtins: self.builtins.clone(),
+        }
+    }
+
+    /// A new environment for a stylesheet loaded as a module, sharing
+    /// only the built-in functions.
+    pub fn for_module(&self) -> Environment {
+        Environment { builtins: self.builtins.clone(), ..Environment::new() }
+    }
+
+    /// The module whose members are this environment's globals and the
+    /// members it forwards.
+    pub fn to_module(&self, url: String) -> Module {
+        let forwarded = self.modules.borrow().forwarded.clone();
+        Module::new(url, self.variables[0].clone(), self.functions[0].clone(), self.mixins[0].clone(), forwarded)
+    }
+
+    /// Makes a module's members available, by namespace or, without one,
+    /// as globals.
+    pub fn add_module(&mut self, namespace: Option<String>, module: Rc<Module>) -> Result<(), String> {
+        let mut modules = self.modules.borrow_mut();
+        match namespace {
+            Some(namespace) => {
+                if modules.namespaced.contains_key(&namespace) {
+                    return Err(format!("There's already a module with namespace \"{}\".", namespace));
+                }
+                modules.namespaced.insert(namespace, module);
+            }
+            None => modules.global.push(module),
+        }
+        Ok(())
+    }
+
+    pub fn get_module(&self, namespace: &str) -> Option<Rc<Module>> {
+        self.modules.borrow().namespaced.get(namespace).cloned()
+    }
+
+    pub fn add_forward(&mut self, forward: Forward) {
+        self.modules.borrow_mut().forwarded.push(forward);
+    }
+
+    /// Looks a member up in the modules loaded with `as *`.
+    fn find_in_global_modules<T, F>(&self, f: F) -> Option<T>
+        where F: Fn(&Module) -> Option<T>
+    {
+        self.modules.borrow().global.iter().filter_map(|module| f(module)).next()
+    }
+
+    /// A copy of this environment sharing the same scopes, used by
+    /// callables to capture the environment they were declared in.
+    pub fn closure(&self) -> Environment {
+        self.clone()
+    }
+
+    pub fn at_root(&self) -> bool {
+        self.variables.len() == 1
+    }
+
+    fn in_semi_global_scope(&self) -> bool {
+        *self.semi_global.last().unwrap()
+    }
+
+    /// Enters a new innermost scope. A scope is semi-global if it is
+    /// created by a control flow rule at the root of the stylesheet, or
+    /// within another semi-global scope.
+    pub fn push_scope(&mut self, semi_global: bool) {
+        let semi_global = semi_global && (self.at_root() || self.in_semi_global_scope());
+        self.variables.push(new_scope());
+        self.functions.push(new_scope());
+        self.mixins.push(new_scope());
+        self.semi_global.push(semi_global);
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.variables.pop();
+        self.functions.pop();
+        self.mixins.pop();
+        self.semi_global.pop();
+    }
+
+    fn variable_index(&self, name: &str) -> Option<usize> {
+        self.variables.iter().rposition(|scope| scope.borrow().contains_key(name))
+    }
+
+    pub fn get_variable(&self, name: &str) -> Option<Value> {
+        match self.variable_index(name) {
+            Some(i) => self.variables[i].borrow().get(name).cloned(),
+            None => self.find_in_global_modules(|m| m.get_variable(name)),
+        }
+    }
+
+    /// Looks a variable up in the innermost scope only.
+    pub fn get_local_variable(&self, name: &str) -> Option<Value> {
+        self.variables.last().and_then(|scope| scope.borrow().get(name).cloned())
+    }
+
+    pub fn get_global_variable(&self, name: &str) -> Option<Value> {
+        let value = self.variables[0].borrow().get(name).cloned();
+        value.or_else(|| self.find_in_global_modules(|m| m.get_variable(name)))
+    }
+
+    pub fn global_variable_exists(&self, name: &str) -> bool {
+        self.get_global_variable(name).is_some()
+    }
+
+    pub fn variable_exists(&self, name: &str) -> bool {
+        self.get_variable(name).is_some()
+    }
+
+    /// Assigns a variable. Without `!global`, this assigns the innermost
+    /// existing local variable of that name, or creates a new one in the
+    /// innermost scope. Globals are only assigned from the root or from
+    /// semi-global scopes.
+    pub fn set_variable(&mut self, name: &str, value: Value, global: bool) {
+        if global || self.at_root() {
+            self.set_global_variable(name, value);
+            return;
+        }
+
+        let mut index = self.variable_index(name).unwrap_or(self.variables.len() - 1);
+        if index == 0 && !self.in_semi_global_scope() {
+            index = self.variables.len() - 1;
+        }
+        self.variables[index].borrow_mut().insert(name.to_string(), value);
+    }
+
+    /// Assigns a global variable. A variable that is not declared here
+    /// but comes from a module loaded with `as *` is assigned there.
+    pub fn set_global_variable(&mut self, name: &str, value: Value) {
+        if !self.variables[0].borrow().contains_key(name) {
+            let modules = self.modules.borrow();
+            if modules.global.iter().any(|m| m.set_variable(name, value.clone())) {
+                return;
+            }
+        }
+        self.variables[0].borrow_mut().insert(name.to_string(), value);
+    }
+
+    /// Assigns a variable in the innermost scope, as for arguments.
+    pub fn set_local_variable(&mut self, name: &str, value: Value) {
+        let last = self.variables.len() - 1;
+        self.variables[last].borrow_mut().insert(name.to_string(), value);
+    }
+
+    pub fn get_function(&self, name: &str) -> Option<Callable> {
+        self.functions.iter().rev().filter_map(|scope| scope.borrow().get(name).cloned()).next()
+            .or_else(|| self.find_in_global_modules(|m| m.get_function(name)))
+            .or_else(|| self.builtins.borrow().get(name).cloned())
+    }
+
+    /// Whether a name refers to one of the global built-in functions,
+    /// rather than to a function of the stylesheet or of a module.
+    pub fn is_global_builtin(&self, name: &str) -> bool {
+        self.functions.iter().all(|scope| !scope.borrow().contains_key(name)) &&
+            self.find_in_global_modules(|m| m.get_function(name)).is_none() &&
+            matches!(self.builtins.borrow().get(name), Some(&Callable::Builtin(_)))
+    }
+
+    pub fn function_exists(&self, name: &str) -> bool {
+        self.get_function(name).is_some()
+    }
+
+    /// Declares a function in the innermost scope.
+    pub fn set_function(&mut self, name: &str, callable: Callable) {
+        let last = self.functions.len() - 1;
+        self.functions[last].borrow_mut().insert(name.to_string(), callable);
+    }
+
+    pub fn set_global_function(&mut self, name: &str, callable: Callable) {
+        self.functions[0].borrow_mut().insert(name.to_string(), callable);
+    }
+
+    /// Declares a function that is available in every module.
+    pub fn set_builtin_function(&mut self, name: &str, callable: Callable) {
+        self.builtins.borrow_mut().insert(name.to_string(), callable);
+    }
+
+    pub fn get_mixin(&self, name: &str) -> Option<Mixin> {
+        self.mixins.iter().rev().filter_map(|scope| scope.borrow().get(name).cloned()).next()
+            .or_else(|| self.find_in_global_modules(|m| m.get_mixin(name)))
+    }
+
+    pub fn mixin_exists(&self, name: &str) -> bool {
+        self.get_mixin(name).is_some()
+    }
+
+    /// Declares a mixin in the innermost scope.
+    pub fn set_mixin(&mut self, name: &str, mixin: Mixin) {
+        let last = self.mixins.len() - 1;
+        self.mixins[last].borrow_mut().insert(name.to_string(), mixin);
+    }
+
+    pub fn set_global_mixin(&mut self, name: &str, mixin: Mixin) {
+        self.mixins[0].borrow_mut().insert(name.to_string(), mixin);
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment::new()
+    }
+}
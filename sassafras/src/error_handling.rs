@@ -0,0 +1,89 @@
+// FROM: src/error_handling.hpp, src/backtrace.hpp
+
+use std::env;
+use std::fmt;
+use std::path::Path;
+use position::ParserState;
+
+// One frame of the Sass stack, e.g. a mixin include or a function call.
+#[derive(Debug, Clone)]
+pub struct Backtrace {
+    pub pstate: ParserState,
+    pub caller: String,
+}
+
+impl Backtrace {
+    pub fn new<S: Into<String>>(pstate: ParserState, caller: S) -> Self {
+        Backtrace { pstate, caller: caller.into() }
+    }
+}
+
+pub type Backtraces = Vec<Backtrace>;
+
+// The error type used throughout the parser and evaluator.
+#[derive(Debug, Clone)]
+pub struct SassError {
+    pub message: String,
+    pub pstate: ParserState,
+    pub traces: Backtraces,
+}
+
+pub type SassResult<T> = Result<T, Box<SassError>>;
+
+impl SassError {
+    pub fn new<S: Into<String>>(message: S, pstate: &ParserState) -> Box<SassError> {
+        Box::new(SassError {
+            message: message.into(),
+            pstate: pstate.clone(),
+            traces: vec![Backtrace::new(pstate.clone(), "")],
+        })
+    }
+
+    /// Builds an error whose stack is the given traces plus the error position.
+    pub fn with_traces<S: Into<String>>(message: S, pstate: &ParserState, traces: &[Backtrace]) -> Box<SassError> {
+        let mut traces = traces.to_vec();
+        traces.push(Backtrace::new(pstate.clone(), ""));
+        Box::new(SassError { message: message.into(), pstate: pstate.clone(), traces })
+    }
+}
+
+impl fmt::Display for SassError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Makes a path relative to the current directory, for use in messages.
+pub fn rel_path(path: &str) -> String {
+    match env::current_dir() {
+        Ok(cwd) => match Path::new(path).strip_prefix(&cwd) {
+            Ok(rel) => rel.to_string_lossy().into_owned(),
+            Err(_) => path.to_string(),
+        },
+        Err(_) => path.to_string(),
+    }
+}
+
+/// Formats the traces innermost first, the way libsass prints them
+/// below an error or warning message.
+pub fn traces_to_string(traces: &[Backtrace], indent: &str) -> String {
+    let mut ss = String::new();
+    let mut first = true;
+
+    for trace in traces.iter().rev() {
+        let path = rel_path(trace.pstate.path());
+        if first {
+            ss.push_str(indent);
+            ss.push_str(&format!("on line {}:{} of {}", trace.pstate.line() + 1, trace.pstate.column() + 1, path));
+            first = false;
+        } else {
+            ss.push_str(&trace.caller);
+            ss.push('\n');
+            ss.push_str(indent);
+            ss.push_str(&format!("from line {}:{} of {}", trace.pstate.line() + 1, trace.pstate.column() + 1, path));
+        }
+    }
+
+    ss.push('\n');
+    ss
+}
@@ -0,0 +1,488 @@
+// FROM: src/eval.cpp
+
+use std::mem;
+use ast::*;
+use bind::EvaluatedArgs;
+use calculation;
+use deprecation::{self, Deprecation};
+use environment::Environment;
+use error_handling::SassResult;
+use expand::Expand;
+use fn_utils::{Args, FnError};
+use functions::{sass_function_get_function, SassCalleeType};
+use inspect::Inspect;
+use operators::{self, BinaryOperator, UnaryOperator};
+use parser::normalize_name;
+use position::ParserState;
+use sass_options::SassDivision;
+use sass_values::{sass_delete_value, value_from_c, value_to_c, SassValue};
+use values::*;
+
+impl<'a> Expand<'a> {
+    /// Evaluates an expression to a value.
+    pub fn eval(&mut self, expr: &Expression) -> SassResult<Value> {
+        self.eval_expression(expr, true)
+    }
+
+    /// Evaluates an expression. A `/` between two numbers is only kept
+    /// as a slash where `allow_slash` is set; inside parentheses and in
+    /// arithmetic it always divides.
+    fn eval_expression(&mut self, expr: &Expression, allow_slash: bool) -> SassResult<Value> {
+        match *expr {
+            Expression::Value(ref value, _) => Ok(value.clone()),
+            Expression::String(ref s) => {
+                let text = self.interpolate(&s.text)?;
+                Ok(Value::String(SassString { text, quoted: s.quoted }))
+            }
+            Expression::Variable(ref var) => match var.namespace {
+                Some(ref namespace) => {
+                    self.check_public(&var.name, &var.pstate)?;
+                    self.get_module(namespace, &var.pstate)?.get_variable(&var.name)
+                        .ok_or_else(|| self.error("Undefined variable.", &var.pstate))
+                }
+                None => self.env.get_variable(&var.name)
+                    .ok_or_else(|| self.error(format!("Undefined variable: \"${}\".", var.name), &var.pstate)),
+            },
+            Expression::Binary(ref b) => self.eval_binary(b, allow_slash),
+            Expression::Unary(ref u) => {
+                let operand = self.eval_expression(&u.operand, false)?;
+                operators::unary_op(u.op, &operand, self.ctx.precision).map_err(|msg| self.error(msg, &u.pstate))
+            }
+            Expression::FunctionCall(ref call) => self.eval_function_call(call),
+            Expression::If(ref expr) => self.eval_if(expr),
+            Expression::InterpolatedFunctionCall(ref call) => {
+                let name = self.interpolate(&call.name)?;
+                let args = self.eval_css_arguments(&call.args)?;
+                self.call_function(&Callable::Plain(name.clone()), &name, args, &call.pstate)
+            }
+            Expression::List(ref list) => {
+                let mut items = Vec::with_capacity(list.items.len());
+                for item in &list.items {
+                    items.push(self.eval_expression(item, true)?);
+                }
+                Ok(Value::List(List::new(items, list.separator, list.bracketed)))
+            }
+            Expression::Map(ref map) => {
+                let mut result = SassMap::new();
+                for (key, value) in &map.pairs {
+                    let k = self.eval(key)?;
+                    let v = self.eval(value)?;
+                    if result.contains_key(&k) {
+                        return Err(self.error("Duplicate key.", key.pstate()));
+                    }
+                    result.insert(k, v);
+                }
+                Ok(Value::Map(result))
+            }
+            Expression::Paren(ref inner, _) => self.eval_expression(inner, false),
+            Expression::ParentSelector(_) => Ok(self.parent_selector().map_or(Value::Null, |s| s.to_value())),
+        }
+    }
+
+    fn eval_binary(&mut self, b: &BinaryExpr, allow_slash: bool) -> SassResult<Value> {
+        match b.op {
+            BinaryOperator::And => {
+                let left = self.eval_expression(&b.left, false)?;
+                if !left.is_truthy() {
+                    return Ok(left);
+                }
+                self.eval_expression(&b.right, false)
+            }
+            BinaryOperator::Or => {
+                let left = self.eval_expression(&b.left, false)?;
+                if left.is_truthy() {
+                    return Ok(left);
+                }
+                self.eval_expression(&b.right, false)
+            }
+            BinaryOperator::Eq | BinaryOperator::Neq => {
+                let left = self.eval_expression(&b.left, false)?;
+                let right = self.eval_expression(&b.right, false)?;
+                let equal = left == right;
+                Ok(Value::Boolean(if b.op == BinaryOperator::Eq { equal } else { !equal }))
+            }
+            BinaryOperator::Div if self.ctx.division == SassDivision::Slash => {
+                // `/` only separates, so `a/b/c` is one list of three.
+                let left = self.eval_expression(&b.left, false)?;
+                let right = self.eval_expression(&b.right, false)?;
+                let mut items = match (&b.left, left) {
+                    (Expression::Binary(l), Value::List(list)) if l.op == BinaryOperator::Div => list.items,
+                    (_, left) => vec![left],
+                };
+                items.push(right);
+                Ok(Value::List(List::new(items, ListSeparator::Slash, false)))
+            }
+            BinaryOperator::Div if b.allows_slash && allow_slash => {
+                // `1px/2px` in plain CSS is a separator, so the number is
+                // divided but still written with the slash.
+                let left = self.eval_expression(&b.left, true)?;
+                let right = self.eval_expression(&b.right, true)?;
+                match (left, right) {
+                    (Value::Number(left), Value::Number(right)) => Ok(Value::Number(Number::slash(left, right))),
+                    (left, right) => operators::op(b.op, &left, &right, self.ctx.precision).map_err(|msg| self.error(msg, &b.pstate)),
+                }
+            }
+            op => {
+                let left = self.eval_expression(&b.left, false)?;
+                let right = self.eval_expression(&b.right, false)?;
+                // Where `/` only separates, `1 + (4/2)` adds to a list
+                // rather than to a number, which is an error.
+                let is_slash_list = |v: &Value| matches!(*v, Value::List(ref l) if l.separator == ListSeparator::Slash);
+                if self.ctx.division == SassDivision::Slash && (is_slash_list(&left) || is_slash_list(&right)) {
+                    return Err(self.error(operators::undefined_operation(op, &left, &right, self.ctx.precision), &b.pstate));
+                }
+                if let (BinaryOperator::Div, &Value::Number(_), &Value::Number(_)) = (op, &left, &right) {
+                    let (left, right) = (b.left.pstate().text(), b.right.pstate().text());
+                    let message = format!("Using / for division outside of calc() is deprecated and will be removed in Dart Sass 2.0.0.\n\n\
+                                           Recommendation: math.div({}, {}) or calc({} / {})", left, right, left, right);
+                    self.warn_deprecation(Deprecation::SlashDiv, message, &b.pstate)?;
+                }
+                operators::op(op, &left, &right, self.ctx.precision).map_err(|msg| self.error(msg, &b.pstate))
+            }
+        }
+    }
+
+    /// Calls a function. Functions that are not defined are passed to
+    /// the `*` C function if there is one, or emitted as plain CSS calls.
+    fn eval_function_call(&mut self, call: &FunctionCall) -> SassResult<Value> {
+        let name = normalize_name(&call.name);
+        if call.namespace.is_none() {
+            // min() and max() are only calculations when their arguments
+            // could be, and otherwise are the Sass functions.
+            let lower = name.to_ascii_lowercase();
+            let calculation = match lower.as_str() {
+                "calc" | "clamp" => true,
+                "min" | "max" => self.env.is_global_builtin(&lower) && call.args.named.is_empty() &&
+                    call.args.rest.is_none() && call.args.positional.iter().all(is_calculation_safe),
+                _ => false,
+            };
+            if calculation {
+                return self.eval_calculation(&lower, call);
+            }
+        }
+        let function = match call.namespace {
+            Some(ref namespace) => {
+                self.check_public(&name, &call.pstate)?;
+                self.get_module(namespace, &call.pstate)?.get_function(&name)
+                    .ok_or_else(|| self.error("Undefined function.", &call.pstate))?
+            }
+            None => self.env.get_function(&name).unwrap_or_else(|| Callable::Plain(call.name.clone())),
+        };
+        let args = match function {
+            Callable::Plain(_) => self.eval_css_arguments(&call.args)?,
+            _ => self.eval_arguments(&call.args)?,
+        };
+        if call.namespace.is_none() && self.env.is_global_builtin(&name) {
+            self.warn_global_builtin(&name, &args, &call.pstate)?;
+        }
+        self.call_function(&function, &call.name, args, &call.pstate)
+    }
+
+    /// Evaluates `if($condition, $if-true, $if-false)`. Only the branch
+    /// that is returned is evaluated, so the other may be an error.
+    fn eval_if(&mut self, expr: &IfExpr) -> SassResult<Value> {
+        const PARAMS: [&str; 3] = ["condition", "if-true", "if-false"];
+
+        // Spread arguments can only be bound once they are evaluated.
+        let spread;
+        let args = if expr.args.rest.is_some() || expr.args.keyword_rest.is_some() {
+            let evaluated = self.eval_arguments(&expr.args)?;
+            let as_expr = |value| Expression::Value(value, expr.pstate.clone());
+            spread = ArgumentInvocation {
+                positional: evaluated.positional.into_iter().map(as_expr).collect(),
+                named: evaluated.named.into_iter().map(|(name, value)| (name, as_expr(value))).collect(),
+                rest: None,
+                keyword_rest: None,
+                pstate: expr.args.pstate.clone(),
+            };
+            &spread
+        } else {
+            &expr.args
+        };
+
+        if args.positional.len() > PARAMS.len() {
+            let msg = format!("wrong number of arguments ({} for {}) for `if'", args.positional.len(), PARAMS.len());
+            return Err(self.error(msg, &expr.pstate));
+        }
+        let mut bound: Vec<Option<&Expression>> = args.positional.iter().map(Some).collect();
+        bound.resize(PARAMS.len(), None);
+        for (name, arg) in &args.named {
+            let i = match PARAMS.iter().position(|p| p == name) {
+                Some(i) => i,
+                None => return Err(self.error(format!("Function if has no parameter named ${}", name), &expr.pstate)),
+            };
+            if bound[i].is_some() {
+                let msg = format!("Function if was passed argument ${} both by position and by name.", name);
+                return Err(self.error(msg, &expr.pstate));
+            }
+            bound[i] = Some(arg);
+        }
+        if let Some(i) = bound.iter().position(|arg| arg.is_none()) {
+            return Err(self.error(format!("Function if is missing argument ${}.", PARAMS[i]), &expr.pstate));
+        }
+
+        let condition = self.eval(bound[0].unwrap())?;
+        let branch = if condition.is_truthy() { bound[1].unwrap() } else { bound[2].unwrap() };
+        let value = self.eval(branch)?;
+        self.without_slash(value, branch.pstate())
+    }
+
+    /// Evaluates a call of calc(), clamp(), min() or max() as a
+    /// calculation, simplified as far as its operands allow.
+    fn eval_calculation(&mut self, name: &str, call: &FunctionCall) -> SassResult<Value> {
+        let args = &call.args;
+        if !args.named.is_empty() || args.keyword_rest.is_some() {
+            return Err(self.error("Keyword arguments can't be used with calculations.", &call.pstate));
+        }
+        if args.rest.is_some() {
+            return Err(self.error("Rest arguments can't be used with calculations.", &call.pstate));
+        }
+        let count = args.positional.len();
+        if count == 0 {
+            return Err(self.error("Missing argument.", &call.pstate));
+        }
+        let max_count = match name {
+            "calc" => 1,
+            "clamp" => 3,
+            _ => count,
+        };
+        if count > max_count {
+            let message = format!("Only {} argument{} allowed, but {} were passed.",
+                                  max_count, if max_count == 1 { "" } else { "s" }, count);
+            return Err(self.error(message, &call.pstate));
+        }
+
+        let mut values = Vec::with_capacity(count);
+        for arg in &args.positional {
+            values.push(self.eval_calc_value(arg)?);
+        }
+        let precision = self.ctx.precision;
+        let result = match name {
+            "calc" => Ok(calculation::calc(values.pop().unwrap())),
+            "clamp" => calculation::clamp(values, precision),
+            _ => calculation::min_max(name, values, precision),
+        };
+        result.map_err(|msg| self.error(msg, &call.pstate))
+    }
+
+    /// Evaluates an argument of a calculation, or an operand in one.
+    /// Interpolation is taken as plain CSS.
+    fn eval_calc_value(&mut self, expr: &Expression) -> SassResult<CalcValue> {
+        let precision = self.ctx.precision;
+        match *expr {
+            Expression::Paren(ref inner, _) => match self.eval_calc_value(inner)? {
+                CalcValue::String(text) => Ok(CalcValue::String(format!("({})", text))),
+                value => Ok(value),
+            },
+            Expression::Binary(ref b) => {
+                let op = match b.op {
+                    BinaryOperator::Add => CalcOperator::Plus,
+                    BinaryOperator::Sub => CalcOperator::Minus,
+                    BinaryOperator::Mul => CalcOperator::Times,
+                    BinaryOperator::Div => CalcOperator::Divide,
+                    _ => return Err(self.error("This operation can't be used in a calculation.", &b.pstate)),
+                };
+                let left = self.eval_calc_value(&b.left)?;
+                let right = self.eval_calc_value(&b.right)?;
+                calculation::operate(op, left, right, precision).map_err(|msg| self.error(msg, &b.pstate))
+            }
+            Expression::Unary(ref u) if u.op == UnaryOperator::Plus || u.op == UnaryOperator::Minus => {
+                let operand = self.eval_calc_value(&u.operand)?;
+                match (u.op, operand) {
+                    (UnaryOperator::Plus, operand) => Ok(operand),
+                    (_, CalcValue::Number(n)) => Ok(CalcValue::Number(Number { value: -n.value, ..n })),
+                    (_, operand) => calculation::operate(CalcOperator::Times, CalcValue::Number(Number::new(-1.0)), operand, precision)
+                        .map_err(|msg| self.error(msg, &u.pstate)),
+                }
+            }
+            Expression::List(ref list) if list.separator == ListSeparator::Space && !list.bracketed => {
+                // Only interpolation may stand between other operands, as
+                // in `calc(1px #{$op} 2px)`.
+                let is_interpolation = |e: &Expression| match *e {
+                    Expression::String(ref s) => !s.quoted && s.text.as_plain().is_none(),
+                    _ => false,
+                };
+                for pair in list.items.windows(2) {
+                    if !is_interpolation(&pair[0]) && !is_interpolation(&pair[1]) {
+                        return Err(self.error("Missing math operator.", pair[1].pstate()));
+                    }
+                }
+                let mut parts = Vec::with_capacity(list.items.len());
+                for item in &list.items {
+                    let value = self.eval(item)?;
+                    parts.push(self.to_css_string(&value, item.pstate())?);
+                }
+                Ok(CalcValue::String(parts.join(" ")))
+            }
+            Expression::Value(..) | Expression::String(_) | Expression::Variable(_) | Expression::If(_) |
+            Expression::FunctionCall(_) | Expression::InterpolatedFunctionCall(_) => {
+                let value = self.eval(expr)?;
+                calculation::to_calc_value(value, precision).map_err(|msg| self.error(msg, expr.pstate()))
+            }
+            _ => Err(self.error("This expression can't be used in a calculation.", expr.pstate())),
+        }
+    }
+
+    /// Reports a call of a global built-in function that has a module
+    /// equivalent. Calls that are plain CSS filters, such as
+    /// `grayscale(50%)`, are not deprecated.
+    fn warn_global_builtin(&self, name: &str, args: &EvaluatedArgs, pstate: &ParserState) -> SassResult<()> {
+        let replacement = match deprecation::global_builtin_replacement(name) {
+            Some(replacement) => replacement,
+            None => return Ok(()),
+        };
+        let css_filter = ["saturate", "grayscale", "invert", "opacity", "alpha"].contains(&name) &&
+            match args.positional.first() {
+                Some(&Value::Color(_)) => false,
+                _ => args.named.is_empty(),
+            };
+        if css_filter {
+            return Ok(());
+        }
+        let message = format!("Global built-in functions are deprecated and will be removed in Dart Sass 3.0.0.\n\
+                               Use {} instead.", replacement);
+        self.warn_deprecation(Deprecation::GlobalBuiltin, message, pstate)
+    }
+
+    /// Calls a function with evaluated arguments. `name` is the name
+    /// it was called by, for messages.
+    pub fn call_function(&mut self, function: &Callable, name: &str, args: EvaluatedArgs, pstate: &ParserState) -> SassResult<Value> {
+        match *function {
+            Callable::UserFunction(ref function) => self.call_user_function(function, name, args, pstate),
+            Callable::Builtin(ref function) => self.call_builtin(function, name, args, pstate),
+            Callable::CFunction(ref function) => self.call_c_function(function, name, args, pstate),
+            Callable::Plain(ref css_name) => {
+                if !args.named.is_empty() {
+                    return Err(self.error("Plain CSS functions don't support keyword arguments.", pstate));
+                }
+                if let Some(Callable::CFunction(ref function)) = self.env.get_function("*") {
+                    return self.call_c_function(function, css_name, args, pstate);
+                }
+
+                let separator = if self.inspect().compressed { "," } else { ", " };
+                let mut values = Vec::with_capacity(args.positional.len());
+                for value in &args.positional {
+                    values.push(self.to_css_string(value, pstate)?);
+                }
+                Ok(Value::unquoted_string(format!("{}({})", css_name, values.join(separator))))
+            }
+        }
+    }
+
+    /// Calls a built-in function or mixin, with the first overload the
+    /// arguments match.
+    pub fn call_builtin(&mut self, function: &Builtin, name: &str, args: EvaluatedArgs, pstate: &ParserState) -> SassResult<Value> {
+        let &(ref decl, callback) = {
+            let names: Vec<&str> = args.named.iter().map(|(n, _)| n.as_str()).collect();
+            function.overload_for(args.positional.len(), &names)
+        };
+        let values = self.bind_to_values(name, decl, args, pstate)?;
+        let args = Args::new(values, decl, self.ctx.precision, pstate.clone());
+        callback(self, &args).map_err(|e| match e {
+            FnError::Message(msg) => self.error(msg, pstate),
+            FnError::Sass(error) => error,
+        })
+    }
+
+    /// Binds arguments to the parameters of a function declared outside
+    /// the stylesheet, in a new scope of the global environment. Returns
+    /// the values in the order of the parameters, then the rest argument.
+    fn bind_to_values(&mut self, name: &str, decl: &ArgumentDeclaration, args: EvaluatedArgs, pstate: &ParserState) -> SassResult<Vec<Value>> {
+        let caller_env = mem::replace(&mut self.env, Environment::new());
+        self.env = caller_env.global();
+        let bound = self.with_scope(false, |this| {
+            this.bind_arguments("Function", name, decl, args, pstate)?;
+            let mut values: Vec<Value> = decl.params.iter()
+                .map(|p| this.env.get_local_variable(&p.name).unwrap_or(Value::Null))
+                .collect();
+            if let Some(ref rest) = decl.rest {
+                values.push(this.env.get_local_variable(rest).unwrap_or(Value::Null));
+            }
+            Ok(values)
+        });
+        self.env = caller_env;
+        bound
+    }
+
+    /// Calls a function registered through the C API. The arguments are
+    /// passed as a comma separated list, in the order of the signature.
+    /// The `*` function gets the name of the function called, then its
+    /// arguments.
+    fn call_c_function(&mut self, function: &CFunction, name: &str, args: EvaluatedArgs, pstate: &ParserState) -> SassResult<Value> {
+        let values = if function.name == "*" {
+            let mut values = vec![Value::quoted_string(name)];
+            values.extend(args.positional);
+            values
+        } else {
+            self.bind_to_values(name, &function.args, args, pstate)?
+        };
+
+        let c_args = value_to_c(&Value::List(List::new(values, ListSeparator::Comma, false)));
+        self.ctx.push_callee(name, pstate, SassCalleeType::CFunction, self.env.clone());
+        let c_function = sass_function_get_function(function.entry);
+        let c_value = c_function(c_args, function.entry, self.ctx.c_compiler);
+        self.ctx.pop_callee();
+
+        let result = if c_value.is_null() {
+            Ok(Value::Null)
+        } else {
+            match *unsafe { &*c_value } {
+                SassValue::Error(ref msg) => {
+                    Err(self.error(format!("error in C function {}: {}", name, msg.to_string_lossy()), pstate))
+                }
+                SassValue::Warning(ref msg) => {
+                    Err(self.error(format!("warning in C function {}: {}", name, msg.to_string_lossy()), pstate))
+                }
+                _ => Ok(value_from_c(c_value)),
+            }
+        };
+
+        if c_value != c_args {
+            sass_delete_value(c_value);
+        }
+        sass_delete_value(c_args);
+        result
+    }
+
+    /// Serializes a value as it would appear in CSS, with strings unquoted.
+    pub fn to_css_string(&self, value: &Value, pstate: &ParserState) -> SassResult<String> {
+        match *value {
+            Value::String(ref s) if !s.quoted => Ok(s.text.clone()),
+            _ => self.inspect().value(value).map_err(|msg| self.error(msg, pstate)),
+        }
+    }
+
+    /// Evaluates interpolated text. Interpolated strings are unquoted.
+    pub fn interpolate(&mut self, interpolation: &Interpolation) -> SassResult<String> {
+        let mut text = String::new();
+        for part in &interpolation.parts {
+            match *part {
+                InterpolationPart::Text(ref s) => text.push_str(s),
+                InterpolationPart::Expression(ref expr) => {
+                    let value = self.eval(expr)?;
+                    let inspect = Inspect { inspect: false, quote: false, ..self.inspect() };
+                    text.push_str(&inspect.value(&value).map_err(|msg| self.error(msg, expr.pstate()))?);
+                }
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// Whether an expression can be an argument of a calculation, so that
+/// min() or max() called with it is one.
+fn is_calculation_safe(expr: &Expression) -> bool {
+    match *expr {
+        Expression::Value(Value::Number(_), _) | Expression::Variable(_) | Expression::If(_) |
+        Expression::FunctionCall(_) | Expression::InterpolatedFunctionCall(_) => true,
+        Expression::String(ref s) => !s.quoted,
+        Expression::Paren(ref inner, _) => is_calculation_safe(inner),
+        Expression::Binary(ref b) => match b.op {
+            BinaryOperator::Add | BinaryOperator::Sub | BinaryOperator::Mul | BinaryOperator::Div => {
+                is_calculation_safe(&b.left) && is_calculation_safe(&b.right)
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
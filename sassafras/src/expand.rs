@@ -0,0 +1,1286 @@
+// FROM: src/expand.cpp
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem;
+use std::rc::Rc;
+use ast::*;
+use bind::EvaluatedArgs;
+use c_api_helpers::c_char_ptr_to_string;
+use context::{Context, LoadedImport, Resource};
+use css::*;
+use deprecation::Deprecation;
+use environment::Environment;
+use error_handling::{Backtrace, Backtraces, SassError, SassResult};
+use extend::{ExtensionStore, MediaContext};
+use fn_colors;
+use fn_lists;
+use fn_maps;
+use fn_miscs;
+use fn_numbers;
+use fn_selectors;
+use fn_strings;
+use functions::{sass_function_get_signature, SassCalleeType};
+use inspect::{inspect_value, Inspect};
+use module::{builtin_module, forwarded_name, is_private, Configuration, ConfiguredValue, Forward, Module};
+use parser::{normalize_name, Parser};
+use position::{ParserState, SourceFile};
+use selector::{parse_selector, unvendor, ComplexComponent, SelectorList};
+use values::{fuzzy_round, Number, Value};
+
+// Expands a parsed stylesheet into the CSS tree, evaluating variables,
+// expressions and imports along the way.
+pub struct Expand<'a> {
+    pub ctx: &'a Context,
+    pub env: Environment,
+    root: CssRef,
+    // the node style rules are added to: the root, or an @media
+    container: CssRef,
+    // the node @media blocks are added to, as nested ones bubble up
+    media_parent: CssRef,
+    // the node declarations are added to
+    parent: CssRef,
+    // the style rule being expanded, and its resolved selector
+    style_rule: Option<CssRef>,
+    selector: Option<SelectorList>,
+    // the queries of the @media being expanded, merged with any it is in
+    media_queries: Option<Vec<MediaQuery>>,
+    // whether an @at-root has left the style rule, so that selectors no
+    // longer nest within it unless they use `&`
+    at_root_excluding_style_rule: bool,
+    // whether declarations are allowed outside style rules, as they are
+    // in @keyframes and unknown at-rules such as @font-face
+    in_keyframes: bool,
+    in_unknown_at_rule: bool,
+    // whether a mixin is being expanded, outside any function it calls
+    in_mixin: bool,
+    // the name of the property whose nested properties are being
+    // expanded, such as `font` for `font: { family: x }`
+    declaration_name: Option<String>,
+    // the content block passed to the mixin being expanded
+    content: Option<Rc<Content>>,
+    extender: ExtensionStore,
+    // the values configured for the `!default` variables of the module
+    // being evaluated
+    configuration: Configuration,
+    pub traces: Backtraces,
+}
+
+// A content block, with the environment of the @include that passed it.
+struct Content {
+    block: Rc<ContentBlock>,
+    env: Environment,
+    // the content block in effect at the @include
+    outer: Option<Rc<Content>>,
+}
+
+impl<'a> Expand<'a> {
+    pub fn new(ctx: &'a Context) -> Self {
+        let root = CssNode::new(CssKind::Root, None);
+        Expand {
+            ctx,
+            env: Environment::new(),
+            root: root.clone(),
+            container: root.clone(),
+            media_parent: root.clone(),
+            parent: root,
+            style_rule: None,
+            selector: None,
+            media_queries: None,
+            at_root_excluding_style_rule: false,
+            in_keyframes: false,
+            in_unknown_at_rule: false,
+            in_mixin: false,
+            declaration_name: None,
+            content: None,
+            extender: ExtensionStore::new(),
+            configuration: Configuration::default(),
+            traces: Vec::new(),
+        }
+    }
+
+    /// Expands the entry stylesheet, preceded by anything the header
+    /// importers return, and returns the root of the CSS tree.
+    pub fn expand_root(mut self, entry: &Resource, sheet: &Stylesheet) -> SassResult<CssRef> {
+        self.ctx.push_import(entry);
+        let result = self.expand_entry(sheet);
+        self.ctx.pop_import();
+        result.map(|_| self.root)
+    }
+
+    fn expand_entry(&mut self, sheet: &Stylesheet) -> SassResult<()> {
+        fn_miscs::register(&mut self.env);
+        fn_colors::register(&mut self.env);
+        fn_strings::register(&mut self.env);
+        fn_lists::register(&mut self.env);
+        fn_maps::register(&mut self.env);
+        fn_selectors::register(&mut self.env);
+        fn_numbers::register(&mut self.env);
+        self.register_c_functions()?;
+        let pstate = ParserState::new(&sheet.source, 0, 0);
+        for import in self.ctx.call_headers(&pstate)? {
+            self.expand_loaded_import(import, &pstate)?;
+        }
+        self.expand_block(&sheet.body)?;
+        self.extender.check_unsatisfied()
+    }
+
+    /// Declares the custom functions from the options as globals.
+    fn register_c_functions(&mut self) -> SassResult<()> {
+        for &entry in &self.ctx.c_functions {
+            let signature = c_char_ptr_to_string(sass_function_get_signature(entry));
+            let source = SourceFile::new("[c function]", signature);
+            let (name, args) = Parser::new(source).parse_signature()?;
+            let function = CFunction { name: name.clone(), args, entry };
+            self.env.set_builtin_function(&name, Callable::CFunction(Rc::new(function)));
+        }
+        Ok(())
+    }
+
+    pub fn error<S: Into<String>>(&self, message: S, pstate: &ParserState) -> Box<SassError> {
+        SassError::with_traces(message, pstate, &self.traces)
+    }
+
+    /// Passes a warning to the logger, with the current traces.
+    pub fn warn<S: Into<String>>(&self, message: S, pstate: &ParserState) {
+        let mut traces = self.traces.clone();
+        traces.push(Backtrace::new(pstate.clone(), ""));
+        self.ctx.logger.warn(&message.into(), pstate, &traces);
+    }
+
+    /// Reports a use of a deprecated feature, which is an error if the
+    /// deprecation has been made fatal.
+    pub fn warn_deprecation<S: Into<String>>(&self, deprecation: Deprecation, message: S, pstate: &ParserState) -> SassResult<()> {
+        let mut traces = self.traces.clone();
+        traces.push(Backtrace::new(pstate.clone(), ""));
+        self.ctx.warn_deprecation(deprecation, &message.into(), pstate, &traces)
+    }
+
+    /// A value as it is used rather than written: a number written with
+    /// a slash, such as `1px/2px`, becomes the result of the division.
+    /// Dividing that way is deprecated.
+    pub fn without_slash(&self, value: Value, pstate: &ParserState) -> SassResult<Value> {
+        if let Value::Number(ref number) = value {
+            if number.as_slash.is_some() {
+                let message = format!("Using / for division is deprecated and will be removed in Dart Sass 2.0.0.\n\n\
+                                       Recommendation: {}", self.slash_recommendation(number));
+                self.warn_deprecation(Deprecation::SlashDiv, message, pstate)?;
+            }
+        }
+        Ok(value.without_slash())
+    }
+
+    /// The math.div() calls that replace a number written with slashes.
+    fn slash_recommendation(&self, number: &Number) -> String {
+        match number.as_slash {
+            Some(ref slash) => format!("math.div({}, {})", self.slash_recommendation(&slash.0), self.slash_recommendation(&slash.1)),
+            None => inspect_value(&Value::Number(number.clone()), self.ctx.precision),
+        }
+    }
+
+    /// The text of a message for @debug, @warn or @error: a string
+    /// without its quotes, or any other value as it is written in Sass.
+    fn eval_message(&mut self, expr: &Expression) -> SassResult<String> {
+        Ok(match self.eval(expr)? {
+            Value::String(s) => s.text,
+            value => inspect_value(&value, self.ctx.precision),
+        })
+    }
+
+    /// Adds the current traces below those of an error raised elsewhere,
+    /// such as by the parser.
+    fn with_traces(&self, mut error: Box<SassError>) -> Box<SassError> {
+        let mut traces = self.traces.clone();
+        traces.append(&mut error.traces);
+        error.traces = traces;
+        error
+    }
+
+    /// The module loaded under a namespace.
+    pub fn get_module(&self, namespace: &str, pstate: &ParserState) -> SassResult<Rc<Module>> {
+        self.env.get_module(namespace)
+            .ok_or_else(|| self.error(format!("There is no module with the namespace \"{}\".", namespace), pstate))
+    }
+
+    /// Checks that a member of another module is not private to it.
+    pub fn check_public(&self, name: &str, pstate: &ParserState) -> SassResult<()> {
+        if is_private(name) {
+            return Err(self.error("Private members can't be accessed from outside their modules.", pstate));
+        }
+        Ok(())
+    }
+
+    /// Whether the mixin being expanded was passed a content block, or
+    /// None outside a mixin.
+    pub fn content_exists(&self) -> Option<bool> {
+        if self.in_mixin { Some(self.content.is_some()) } else { None }
+    }
+
+    /// The selector `&` refers to, if within a style rule.
+    pub fn parent_selector(&self) -> Option<&SelectorList> {
+        self.selector.as_ref()
+    }
+
+    pub fn inspect(&self) -> Inspect {
+        Inspect::new(self.ctx.output_style, self.ctx.precision)
+    }
+
+    /// Runs `f` in a new variable scope.
+    pub fn with_scope<T, F>(&mut self, semi_global: bool, f: F) -> SassResult<T>
+        where F: FnOnce(&mut Self) -> SassResult<T>
+    {
+        self.env.push_scope(semi_global);
+        let result = f(self);
+        self.env.pop_scope();
+        result
+    }
+
+    /// Expands the statements of a block. In a function body, this stops
+    /// at the first @return and gives its value.
+    pub fn expand_block(&mut self, block: &Block) -> SassResult<Option<Value>> {
+        for statement in block.iter() {
+            if let Some(value) = self.expand_statement(statement)? {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    fn expand_statement(&mut self, statement: &Statement) -> SassResult<Option<Value>> {
+        match *statement {
+            Statement::StyleRule(ref rule) => self.expand_style_rule(rule)?,
+            Statement::Declaration(ref decl) => self.expand_declaration(decl)?,
+            Statement::VariableDecl(ref decl) => self.expand_variable_decl(decl)?,
+            Statement::Import(ref import) => self.expand_import(import)?,
+            Statement::Use(ref rule) => self.expand_use(rule)?,
+            Statement::Forward(ref rule) => self.expand_forward(rule)?,
+            Statement::MixinRule(ref rule) => {
+                let mixin = UserMixin { rule: rule.clone(), env: self.env.closure() };
+                self.env.set_mixin(&rule.name, Mixin::User(Rc::new(mixin)));
+            }
+            Statement::FunctionRule(ref rule) => {
+                let function = UserFunction { rule: rule.clone(), env: self.env.closure() };
+                self.env.set_function(&rule.name, Callable::UserFunction(Rc::new(function)));
+            }
+            Statement::Return(ref rule) => {
+                let value = self.eval(&rule.value)?;
+                return self.without_slash(value, rule.value.pstate()).map(Some);
+            }
+            Statement::Debug(ref rule) => {
+                let message = self.eval_message(&rule.value)?;
+                self.ctx.logger.debug(&message, &rule.pstate);
+            }
+            Statement::Warn(ref rule) => {
+                let message = self.eval_message(&rule.value)?;
+                self.warn(message, &rule.pstate);
+            }
+            Statement::Error(ref rule) => {
+                let message = self.eval_message(&rule.value)?;
+                let error = self.error(message, &rule.pstate);
+                self.ctx.logger.error(&error.message, &error.pstate, &error.traces);
+                return Err(error);
+            }
+            Statement::Include(ref include) => self.expand_include(include)?,
+            Statement::Extend(ref rule) => self.expand_extend(rule)?,
+            Statement::Media(ref rule) => self.expand_media(rule)?,
+            Statement::Supports(ref rule) => self.expand_supports(rule)?,
+            Statement::AtRoot(ref rule) => self.expand_at_root(rule)?,
+            Statement::AtRule(ref rule) => self.expand_at_rule(rule)?,
+            Statement::Content(ref content) => self.expand_content(content)?,
+            Statement::If(ref rule) => return self.expand_if(rule),
+            Statement::Each(ref rule) => return self.expand_each(rule),
+            Statement::For(ref rule) => return self.expand_for(rule),
+            Statement::While(ref rule) => return self.expand_while(rule),
+            Statement::LoudComment(ref comment) => self.expand_loud_comment(comment)?,
+        }
+        Ok(None)
+    }
+
+    fn expand_style_rule(&mut self, rule: &StyleRule) -> SassResult<()> {
+        if self.in_keyframes {
+            return self.expand_keyframe_block(rule);
+        }
+
+        let text = self.interpolate(&rule.selector)?;
+        let pstate = &rule.selector.pstate;
+        let parsed = parse_selector(&text, true).map_err(|msg| self.error(msg, pstate))?;
+        let resolved = parsed.resolve_parent_selectors(self.selector.as_ref(), !self.at_root_excluding_style_rule)
+            .map_err(|msg| self.error(msg, pstate))?;
+
+        let node = CssNode::new(CssKind::StyleRule(resolved.clone()), Some(rule.pstate.clone()));
+        node.borrow_mut().nesting_parent = self.style_rule.as_ref().map(Rc::downgrade);
+        add_child(&self.container, node.clone());
+        let media = self.media_context();
+        self.extender.add_rule(&node, media)?;
+
+        let old_parent = mem::replace(&mut self.parent, node.clone());
+        let old_rule = mem::replace(&mut self.style_rule, Some(node));
+        let old_selector = mem::replace(&mut self.selector, Some(resolved));
+        let old_at_root = mem::replace(&mut self.at_root_excluding_style_rule, false);
+
+        let result = self.with_scope(false, |this| this.expand_block(&rule.body).map(|_| ()));
+
+        self.parent = old_parent;
+        self.style_rule = old_rule;
+        self.selector = old_selector;
+        self.at_root_excluding_style_rule = old_at_root;
+        result
+    }
+
+    /// Expands a rule within @keyframes, whose selector is a list of
+    /// percentages rather than a CSS selector.
+    fn expand_keyframe_block(&mut self, rule: &StyleRule) -> SassResult<()> {
+        let text = self.interpolate(&rule.selector)?;
+        let selectors = parse_keyframe_selectors(&text).map_err(|msg| self.error(msg, &rule.selector.pstate))?;
+        let node = CssNode::new(CssKind::KeyframeBlock(selectors), Some(rule.pstate.clone()));
+        add_child(&self.parent, node.clone());
+
+        let old_parent = mem::replace(&mut self.parent, node);
+        let result = self.with_scope(false, |this| this.expand_block(&rule.body).map(|_| ()));
+        self.parent = old_parent;
+        result
+    }
+
+    fn expand_extend(&mut self, rule: &ExtendRule) -> SassResult<()> {
+        let style_rule = match self.style_rule {
+            Some(ref node) => node.clone(),
+            None => return Err(self.error("@extend may only be used within style rules.", &rule.pstate)),
+        };
+        // The extender is the rule's selector as extended so far.
+        let extender = match style_rule.borrow().kind {
+            CssKind::StyleRule(ref selector) => selector.clone(),
+            _ => unreachable!("the style rule is a style rule"),
+        };
+
+        let text = self.interpolate(&rule.selector)?;
+        let targets = parse_selector(&text, false).map_err(|msg| self.error(msg, &rule.selector.pstate))?;
+        let mut origin = self.traces.clone();
+        origin.push(Backtrace::new(rule.pstate.clone(), ""));
+        let origin = Rc::new(origin);
+
+        for complex in &targets.components {
+            let compound = match complex.components.as_slice() {
+                [ComplexComponent::Compound(ref compound)] => compound,
+                _ => return Err(self.error("complex selectors may not be extended.", &rule.pstate)),
+            };
+            if compound.components.len() != 1 {
+                let simples: Vec<String> = compound.components.iter().map(|simple| simple.to_string()).collect();
+                return Err(self.error(format!("compound selectors may no longer be extended.\nConsider `@extend {}` instead.\n\
+                                               See http://bit.ly/ExtendCompound for details.", simples.join(", ")), &rule.pstate));
+            }
+            let media = self.media_context();
+            self.extender.add_extension(&extender, &compound.components[0], rule.optional, media, origin.clone())?;
+        }
+        Ok(())
+    }
+
+    /// The media queries in effect, as @extend compares them.
+    fn media_context(&self) -> Option<MediaContext> {
+        self.media_queries.as_ref().map(|queries| queries.iter().map(|q| q.to_string()).collect())
+    }
+
+    /// Expands an @media, which bubbles up to the root or the enclosing
+    /// at-rule. Its queries are merged with those of an enclosing @media,
+    /// and it is dropped if nothing can match both.
+    fn expand_media(&mut self, rule: &MediaRule) -> SassResult<()> {
+        let text = self.interpolate(&rule.query)?;
+        let queries = parse_media_queries(&text).map_err(|msg| self.error(msg, &rule.query.pstate))?;
+        let queries = match self.media_queries {
+            Some(ref outer) => merge_media_queries(outer, &queries),
+            None => queries,
+        };
+        if queries.is_empty() {
+            return Ok(());
+        }
+
+        let node = CssNode::new(CssKind::MediaRule(queries.clone()), Some(rule.pstate.clone()));
+        node.borrow_mut().nesting_parent = self.style_rule.as_ref().map(Rc::downgrade);
+        add_child(&self.media_parent, node.clone());
+
+        let old_media = mem::replace(&mut self.media_queries, Some(queries));
+        let result = self.expand_at_rule_body(&node, &rule.body, true);
+        self.media_queries = old_media;
+        result
+    }
+
+    /// Expands an @supports, which bubbles up out of style rules.
+    fn expand_supports(&mut self, rule: &SupportsRule) -> SassResult<()> {
+        let condition = self.interpolate(&rule.condition)?;
+        let node = CssNode::new(CssKind::SupportsRule(condition), Some(rule.pstate.clone()));
+        node.borrow_mut().nesting_parent = self.style_rule.as_ref().map(Rc::downgrade);
+        add_child(&self.container, node.clone());
+        self.expand_at_rule_body(&node, &rule.body, true)
+    }
+
+    /// Passes an at-rule Sass doesn't know through with its prelude
+    /// evaluated, bubbling it up out of style rules.
+    fn expand_at_rule(&mut self, rule: &AtRule) -> SassResult<()> {
+        let value = match rule.value {
+            Some(ref value) => Some(self.interpolate(value)?.trim().to_string()),
+            None => None,
+        };
+        let at_rule = CssAtRule { name: rule.name.clone(), value, childless: rule.body.is_none() };
+        let node = CssNode::new(CssKind::AtRule(at_rule), Some(rule.pstate.clone()));
+        let body = match rule.body {
+            Some(ref body) => body,
+            None => {
+                add_child(&self.parent, node);
+                return Ok(());
+            }
+        };
+        node.borrow_mut().nesting_parent = self.style_rule.as_ref().map(Rc::downgrade);
+        add_child(&self.container, node.clone());
+
+        let is_keyframes = unvendor(&rule.name) == "keyframes";
+        let old_keyframes = self.in_keyframes;
+        let old_unknown = self.in_unknown_at_rule;
+        if is_keyframes {
+            self.in_keyframes = true;
+        } else {
+            self.in_unknown_at_rule = true;
+        }
+        let result = self.expand_at_rule_body(&node, body, !is_keyframes);
+        self.in_keyframes = old_keyframes;
+        self.in_unknown_at_rule = old_unknown;
+        result
+    }
+
+    /// Expands the body of an at-rule that has been added to the tree.
+    /// Style rules within it are added to it, and declarations directly
+    /// within it go in a copy of the enclosing style rule, if any.
+    fn expand_at_rule_body(&mut self, node: &CssRef, body: &Block, copy_style_rule: bool) -> SassResult<()> {
+        self.with_at_rule(node, copy_style_rule, |this| this.with_scope(false, |this| this.expand_block(body).map(|_| ())))
+    }
+
+    /// Runs `f` to add the contents of an at-rule that has been added to
+    /// the tree, as expand_at_rule_body() describes.
+    fn with_at_rule<F>(&mut self, node: &CssRef, copy_style_rule: bool, f: F) -> SassResult<()>
+        where F: FnOnce(&mut Self) -> SassResult<()> {
+        let is_media = matches!(node.borrow().kind, CssKind::MediaRule(_));
+        let old_container = mem::replace(&mut self.container, node.clone());
+        let old_media_parent = if is_media { self.media_parent.clone() } else { mem::replace(&mut self.media_parent, node.clone()) };
+        let old_parent = mem::replace(&mut self.parent, node.clone());
+        let old_rule = self.style_rule.clone();
+
+        let mut result = Ok(());
+        if copy_style_rule && self.style_rule.is_some() {
+            if let Some(ref selector) = self.selector {
+                let copy = CssNode::new(CssKind::StyleRule(selector.clone()), node.borrow().pstate.clone());
+                add_child(node, copy.clone());
+                let media = self.media_context();
+                result = self.extender.add_rule(&copy, media);
+                self.parent = copy.clone();
+                self.style_rule = Some(copy);
+            }
+        }
+        if result.is_ok() {
+            result = f(self);
+        }
+
+        self.container = old_container;
+        self.media_parent = old_media_parent;
+        self.parent = old_parent;
+        self.style_rule = old_rule;
+        result
+    }
+
+    /// Expands an @at-root, moving its contents out of the enclosing
+    /// rules its query excludes. The rules it stays within are copied to
+    /// hold it.
+    fn expand_at_root(&mut self, rule: &AtRootRule) -> SassResult<()> {
+        let query = match rule.query {
+            Some(ref query) => {
+                let text = self.interpolate(query)?;
+                AtRootQuery::parse(&text).map_err(|msg| self.error(msg, &query.pstate))?
+            }
+            None => AtRootQuery::default(),
+        };
+
+        // The enclosing rules that are kept, innermost first.
+        let mut included = Vec::new();
+        let mut node = self.parent.clone();
+        loop {
+            if let CssKind::Root = node.borrow().kind {
+                break;
+            }
+            if !query.excludes(&node.borrow()) {
+                included.push(node.clone());
+            }
+            node = tree_parent(&node);
+        }
+        let root = self.trim_included(&mut included);
+
+        // Nothing was excluded, so there is nothing to move.
+        if Rc::ptr_eq(&root, &self.parent) {
+            return self.with_scope(false, |this| this.expand_block(&rule.body).map(|_| ()));
+        }
+
+        let inner_copy = included.first().map(copy_without_children);
+        let mut outer_copy = inner_copy.clone();
+        for node in included.iter().skip(1) {
+            let copy = copy_without_children(node);
+            add_child(&copy, outer_copy.take().unwrap());
+            outer_copy = Some(copy);
+        }
+        if let Some(outer_copy) = outer_copy {
+            add_child(&root, outer_copy);
+        }
+        let parent = inner_copy.unwrap_or(root);
+
+        let old_parent = mem::replace(&mut self.parent, parent.clone());
+        let old_container = self.container.clone();
+        let old_media_parent = self.media_parent.clone();
+        let old_rule = self.style_rule.clone();
+        let old_media = self.media_queries.clone();
+        let old_at_root = self.at_root_excluding_style_rule;
+        let old_keyframes = self.in_keyframes;
+        let old_unknown = self.in_unknown_at_rule;
+
+        // Style rules and @media are added to the nearest rules that they
+        // don't bubble out of.
+        let mut container = parent.clone();
+        while container.borrow().is_style_rule() {
+            container = tree_parent(&container);
+        }
+        let mut media_parent = container.clone();
+        while let CssKind::MediaRule(_) = media_parent.clone().borrow().kind {
+            media_parent = tree_parent(&media_parent);
+        }
+        self.container = container;
+        self.media_parent = media_parent;
+
+        if query.excludes_name("media") {
+            self.media_queries = None;
+        }
+        if self.in_keyframes && query.excludes_name("keyframes") {
+            self.in_keyframes = false;
+        }
+        if self.in_unknown_at_rule && !included.iter().any(|node| matches!(node.borrow().kind, CssKind::AtRule(_))) {
+            self.in_unknown_at_rule = false;
+        }
+
+        let mut result = Ok(());
+        if query.excludes_style_rules() {
+            self.at_root_excluding_style_rule = true;
+            self.style_rule = None;
+        } else if parent.borrow().is_style_rule() {
+            let media = self.media_context();
+            result = self.extender.add_rule(&parent, media);
+            self.style_rule = Some(parent);
+        }
+        if result.is_ok() {
+            result = self.with_scope(false, |this| this.expand_block(&rule.body).map(|_| ()));
+        }
+
+        self.parent = old_parent;
+        self.container = old_container;
+        self.media_parent = old_media_parent;
+        self.style_rule = old_rule;
+        self.media_queries = old_media;
+        self.at_root_excluding_style_rule = old_at_root;
+        self.in_keyframes = old_keyframes;
+        self.in_unknown_at_rule = old_unknown;
+        result
+    }
+
+    /// Finds the node to add the copies of the kept rules to: the
+    /// innermost of them that the @at-root is directly within along with
+    /// everything outside it, which is then removed from `nodes`, or
+    /// otherwise the root.
+    fn trim_included(&self, nodes: &mut Vec<CssRef>) -> CssRef {
+        if nodes.is_empty() {
+            return self.root.clone();
+        }
+
+        let mut parent = self.parent.clone();
+        let mut innermost_contiguous = None;
+        for (i, node) in nodes.iter().enumerate() {
+            while !Rc::ptr_eq(&parent, node) {
+                innermost_contiguous = None;
+                parent = tree_parent(&parent);
+            }
+            innermost_contiguous.get_or_insert(i);
+            parent = tree_parent(&parent);
+        }
+
+        if !Rc::ptr_eq(&parent, &self.root) {
+            return self.root.clone();
+        }
+        let i = innermost_contiguous.unwrap();
+        let root = nodes[i].clone();
+        nodes.truncate(i);
+        root
+    }
+
+    fn expand_declaration(&mut self, decl: &Declaration) -> SassResult<()> {
+        if self.style_rule.is_none() && !self.in_keyframes && !self.in_unknown_at_rule && self.declaration_name.is_none() {
+            return Err(self.error("Properties are only allowed within rules, directives, mixin includes, or other properties.", &decl.pstate));
+        }
+
+        let mut name = self.interpolate(&decl.name)?;
+        if let Some(ref prefix) = self.declaration_name {
+            name = format!("{}-{}", prefix, name);
+        }
+
+        if let Some(ref expr) = decl.value {
+            let value = self.eval(expr)?;
+            // A null or empty value leaves the declaration out, but `()`
+            // is still an error.
+            let empty_list = match value {
+                Value::List(ref l) => l.items.is_empty() && !l.bracketed,
+                _ => false,
+            };
+            if !value.is_blank() || empty_list || decl.is_custom_property {
+                let value = self.inspect().value(&value).map_err(|msg| self.error(msg, expr.pstate()))?;
+                let node = CssNode::new(CssKind::Declaration(CssDeclaration {
+                    name: name.clone(),
+                    value,
+                    is_important: decl.is_important,
+                    is_custom_property: decl.is_custom_property,
+                }), Some(decl.pstate.clone()));
+                add_child(&self.parent, node);
+            }
+        }
+
+        if let Some(ref children) = decl.children {
+            let outer = mem::replace(&mut self.declaration_name, Some(name));
+            let result = self.with_scope(false, |this| this.expand_block(children).map(|_| ()));
+            self.declaration_name = outer;
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Adds a `/* */` comment to the output. Compressed output only keeps
+    /// those written `/*!`.
+    fn expand_loud_comment(&mut self, comment: &LoudComment) -> SassResult<()> {
+        let text = self.interpolate(&comment.text)?;
+        if self.inspect().compressed && !text.starts_with("/*!") {
+            return Ok(());
+        }
+        add_child(&self.parent, CssNode::new(CssKind::Comment(text), Some(comment.pstate.clone())));
+        Ok(())
+    }
+
+    fn expand_variable_decl(&mut self, decl: &VariableDecl) -> SassResult<()> {
+        if let Some(ref namespace) = decl.namespace {
+            return self.expand_module_variable_decl(namespace, decl);
+        }
+
+        if decl.is_default && self.env.at_root() {
+            // A configured value replaces the default of a module.
+            let configured = self.configuration.borrow_mut().remove(&decl.name);
+            if let Some(configured) = configured.filter(|c| !c.value.is_null()) {
+                self.env.set_variable(&decl.name, configured.value, decl.is_global);
+                return Ok(());
+            }
+        }
+
+        if decl.is_default {
+            let existing = if decl.is_global {
+                self.env.get_global_variable(&decl.name)
+            } else {
+                self.env.get_variable(&decl.name)
+            };
+            if existing.map_or(false, |v| !v.is_null()) {
+                return Ok(());
+            }
+        }
+
+        if decl.is_global && self.env.get_global_variable(&decl.name).is_none() {
+            let message = if self.env.at_root() {
+                "As of Dart Sass 2.0.0, !global assignments won't be able to declare new variables.\n\n\
+                 Since this assignment is at the root of the stylesheet, the !global flag is\n\
+                 unnecessary and can safely be removed.".to_string()
+            } else {
+                format!("As of Dart Sass 2.0.0, !global assignments won't be able to declare new variables.\n\n\
+                         Recommendation: add `${}: null` at the stylesheet root.", decl.name)
+            };
+            self.warn_deprecation(Deprecation::NewGlobal, message, &decl.pstate)?;
+        }
+
+        let value = self.eval(&decl.expr)?;
+        let value = self.without_slash(value, decl.expr.pstate())?;
+        self.env.set_variable(&decl.name, value, decl.is_global);
+        Ok(())
+    }
+
+    /// Assigns a variable of another module, such as `ns.$x: 1`.
+    fn expand_module_variable_decl(&mut self, namespace: &str, decl: &VariableDecl) -> SassResult<()> {
+        if decl.is_global {
+            return Err(self.error("!global isn't allowed for variables in other modules.", &decl.pstate));
+        }
+        self.check_public(&decl.name, &decl.pstate)?;
+        let module = self.get_module(namespace, &decl.pstate)?;
+        let existing = module.get_variable(&decl.name)
+            .ok_or_else(|| self.error("Undefined variable.", &decl.pstate))?;
+        if module.is_builtin() {
+            return Err(self.error("Cannot modify built-in variable.", &decl.pstate));
+        }
+        if decl.is_default && !existing.is_null() {
+            return Ok(());
+        }
+
+        let value = self.eval(&decl.expr)?;
+        let value = self.without_slash(value, decl.expr.pstate())?;
+        module.set_variable(&decl.name, value);
+        Ok(())
+    }
+
+    /// Runs `f` in the given environment, with `content` as the content
+    /// block, restoring the caller's afterwards.
+    fn with_environment<T, F>(&mut self, env: Environment, content: Option<Rc<Content>>, f: F) -> SassResult<T>
+        where F: FnOnce(&mut Self) -> SassResult<T>
+    {
+        let caller_env = mem::replace(&mut self.env, env);
+        let caller_content = mem::replace(&mut self.content, content);
+        let result = f(self);
+        self.env = caller_env;
+        self.content = caller_content;
+        result
+    }
+
+    fn expand_include(&mut self, include: &IncludeRule) -> SassResult<()> {
+        let name = normalize_name(&include.name);
+        let mixin = match include.namespace {
+            Some(ref namespace) => {
+                self.check_public(&name, &include.pstate)?;
+                self.get_module(namespace, &include.pstate)?.get_mixin(&name)
+                    .ok_or_else(|| self.error("Undefined mixin.", &include.pstate))?
+            }
+            None => match self.env.get_mixin(&name) {
+                Some(mixin) => mixin,
+                None => return Err(self.error(format!("no mixin named {}", include.name), &include.pstate)),
+            },
+        };
+
+        let args = self.eval_arguments(&include.args)?;
+        let mixin = match mixin {
+            Mixin::User(mixin) => mixin,
+            Mixin::Builtin(mixin) => {
+                if include.content.is_some() {
+                    return Err(self.error("Mixin doesn't accept a content block.", &include.pstate));
+                }
+                self.ctx.push_callee(&include.name, &include.pstate, SassCalleeType::Mixin, self.env.clone());
+                let result = self.call_builtin(&mixin, &include.name, args, &include.pstate);
+                self.ctx.pop_callee();
+                return result.map(|_| ());
+            }
+        };
+        let content = include.content.as_ref().map(|block| Rc::new(Content {
+            block: block.clone(),
+            env: self.env.closure(),
+            outer: self.content.clone(),
+        }));
+
+        self.ctx.push_callee(&include.name, &include.pstate, SassCalleeType::Mixin, self.env.clone());
+        let rule = mixin.rule.clone();
+        let in_mixin = mem::replace(&mut self.in_mixin, true);
+        let result = self.with_environment(mixin.env.closure(), content, |this| {
+            this.with_scope(false, |this| {
+                this.bind_arguments("Mixin", &include.name, &rule.args, args, &include.pstate)?;
+                this.traces.push(Backtrace::new(include.pstate.clone(), format!(", in mixin `{}`", include.name)));
+                let result = this.expand_block(&rule.body);
+                this.traces.pop();
+                result.map(|_| ())
+            })
+        });
+        self.in_mixin = in_mixin;
+        self.ctx.pop_callee();
+        result
+    }
+
+    /// Runs the body of a function in its closure environment, and gives
+    /// the value of its @return.
+    pub fn call_user_function(&mut self, function: &UserFunction, name: &str, args: EvaluatedArgs,
+                              pstate: &ParserState) -> SassResult<Value> {
+        self.ctx.push_callee(name, pstate, SassCalleeType::Function, self.env.clone());
+        let rule = function.rule.clone();
+        let in_mixin = mem::replace(&mut self.in_mixin, false);
+        let result = self.with_environment(function.env.closure(), None, |this| {
+            this.with_scope(false, |this| {
+                this.bind_arguments("Function", name, &rule.args, args, pstate)?;
+                this.traces.push(Backtrace::new(pstate.clone(), format!(", in function `{}`", name)));
+                let result = match this.expand_block(&rule.body) {
+                    Ok(Some(value)) => Ok(value),
+                    Ok(None) => Err(this.error(format!("Function {} finished without @return", rule.name), &rule.pstate)),
+                    Err(error) => Err(error),
+                };
+                this.traces.pop();
+                result
+            })
+        });
+        self.in_mixin = in_mixin;
+        self.ctx.pop_callee();
+        result
+    }
+
+    /// Expands the content block passed to the current mixin, in the
+    /// environment of the @include. Does nothing if there is none.
+    fn expand_content(&mut self, rule: &ContentRule) -> SassResult<()> {
+        let content = match self.content.clone() {
+            Some(content) => content,
+            None => return Ok(()),
+        };
+
+        let args = self.eval_arguments(&rule.args)?;
+        self.with_environment(content.env.closure(), content.outer.clone(), |this| {
+            this.with_scope(false, |this| {
+                this.bind_arguments("Mixin", "@content", &content.block.args, args, &rule.pstate)?;
+                this.traces.push(Backtrace::new(rule.pstate.clone(), ", in mixin `@content`"));
+                let result = this.expand_block(&content.block.body);
+                this.traces.pop();
+                result.map(|_| ())
+            })
+        })
+    }
+
+    fn expand_if(&mut self, rule: &IfRule) -> SassResult<Option<Value>> {
+        for (condition, body) in &rule.clauses {
+            if self.eval(condition)?.is_truthy() {
+                return self.with_scope(true, |this| this.expand_block(body));
+            }
+        }
+        match rule.otherwise {
+            Some(ref body) => self.with_scope(true, |this| this.expand_block(body)),
+            None => Ok(None),
+        }
+    }
+
+    /// Assigns the loop variables of an @each. With more than one
+    /// variable, each element is destructured as a list, and variables
+    /// without an element are null.
+    fn set_each_variables(&mut self, variables: &[String], element: Value) {
+        if variables.len() == 1 {
+            self.env.set_local_variable(&variables[0], element);
+            return;
+        }
+        let items = element.as_list();
+        for (i, variable) in variables.iter().enumerate() {
+            self.env.set_local_variable(variable, items.get(i).cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    fn expand_each(&mut self, rule: &EachRule) -> SassResult<Option<Value>> {
+        let list = self.eval(&rule.list)?;
+        self.with_scope(true, |this| {
+            for element in list.as_list() {
+                let element = this.without_slash(element, rule.list.pstate())?;
+                this.set_each_variables(&rule.variables, element);
+                if let Some(value) = this.expand_block(&rule.body)? {
+                    return Ok(Some(value));
+                }
+            }
+            Ok(None)
+        })
+    }
+
+    /// Evaluates a bound of a @for loop, which must be an integer.
+    fn eval_for_bound(&mut self, expr: &Expression) -> SassResult<Number> {
+        match self.eval(expr)? {
+            Value::Number(ref n) if n.is_int() => Ok(n.clone()),
+            other => {
+                let kind = if let Value::Number(_) = other { "an int" } else { "a number" };
+                let msg = format!("{} is not {}.", inspect_value(&other, self.ctx.precision), kind);
+                Err(self.error(msg, expr.pstate()))
+            }
+        }
+    }
+
+    fn expand_for(&mut self, rule: &ForRule) -> SassResult<Option<Value>> {
+        let from = self.eval_for_bound(&rule.from)?;
+        let to = self.eval_for_bound(&rule.to)?;
+        let to_value = match to.coerce_value(&from.numerators, &from.denominators) {
+            Some(value) => value,
+            None => return Err(self.error(format!("Incompatible units {} and {}.", to.unit(), from.unit()), rule.to.pstate())),
+        };
+
+        let start = fuzzy_round(from.value) as i64;
+        let mut end = fuzzy_round(to_value) as i64;
+        let step = if start <= end { 1 } else { -1 };
+        if !rule.inclusive {
+            if start == end {
+                return Ok(None);
+            }
+            end -= step;
+        }
+
+        self.with_scope(true, |this| {
+            let mut i = start;
+            loop {
+                let value = Number::with_units(i as f64, from.numerators.clone(), from.denominators.clone());
+                this.env.set_local_variable(&rule.variable, Value::Number(value));
+                if let Some(value) = this.expand_block(&rule.body)? {
+                    return Ok(Some(value));
+                }
+                if i == end {
+                    return Ok(None);
+                }
+                i += step;
+            }
+        })
+    }
+
+    fn expand_while(&mut self, rule: &WhileRule) -> SassResult<Option<Value>> {
+        self.with_scope(true, |this| {
+            while this.eval(&rule.condition)?.is_truthy() {
+                if let Some(value) = this.expand_block(&rule.body)? {
+                    return Ok(Some(value));
+                }
+            }
+            Ok(None)
+        })
+    }
+
+    fn expand_use(&mut self, rule: &UseRule) -> SassResult<()> {
+        let configuration = self.eval_configuration(&rule.configuration)?;
+        let explicit = !rule.configuration.is_empty();
+        let module = self.load_module(&rule.url, configuration.clone(), explicit, &rule.pstate)?;
+
+        for variable in &rule.configuration {
+            if configuration.borrow().contains_key(&variable.name) {
+                return Err(self.error("This variable was not declared with !default in the @used module.", &variable.pstate));
+            }
+        }
+        self.env.add_module(rule.namespace.clone(), module).map_err(|msg| self.error(msg, &rule.pstate))
+    }
+
+    /// Loads a module to pass its members on to the stylesheets that use
+    /// this one. The configuration this module was given is passed on
+    /// too, so that it can configure the forwarded module.
+    fn expand_forward(&mut self, rule: &ForwardRule) -> SassResult<()> {
+        let mut configuration = HashMap::new();
+        // the names of the passed on values in this module's configuration
+        let mut outer_names = Vec::new();
+        for (name, value) in self.configuration.borrow().iter() {
+            if let Some(inner) = forwarded_name(&rule.prefix, &rule.shown, &rule.hidden, name, true) {
+                configuration.insert(inner.clone(), value.clone());
+                outer_names.push((inner, name.clone()));
+            }
+        }
+        for variable in &rule.configuration {
+            if variable.is_guarded && configuration.contains_key(&variable.name) {
+                continue;
+            }
+            let value = self.eval(&variable.expr)?;
+            let value = self.without_slash(value, variable.expr.pstate())?;
+            configuration.insert(variable.name.clone(), ConfiguredValue { value, pstate: variable.pstate.clone() });
+        }
+
+        let configuration = Rc::new(RefCell::new(configuration));
+        let explicit = !rule.configuration.is_empty();
+        let module = self.load_module(&rule.url, configuration.clone(), explicit, &rule.pstate)?;
+
+        for (inner, outer) in &outer_names {
+            if !configuration.borrow().contains_key(inner) {
+                self.configuration.borrow_mut().remove(outer);
+            }
+        }
+        for variable in rule.configuration.iter().filter(|v| !v.is_guarded) {
+            if configuration.borrow().contains_key(&variable.name) {
+                return Err(self.error("This variable was not declared with !default in the @used module.", &variable.pstate));
+            }
+        }
+
+        self.env.add_forward(Forward::new(module, rule));
+        Ok(())
+    }
+
+    fn eval_configuration(&mut self, variables: &[ConfiguredVariable]) -> SassResult<Configuration> {
+        let mut configuration = HashMap::new();
+        for variable in variables {
+            let value = self.eval(&variable.expr)?;
+            configuration.insert(variable.name.clone(), ConfiguredValue { value, pstate: variable.pstate.clone() });
+        }
+        Ok(Rc::new(RefCell::new(configuration)))
+    }
+
+    /// Loads the module at a url. A stylesheet is only evaluated the
+    /// first time it is loaded, when its CSS is added to the output;
+    /// after that the same module is returned. `explicit` is set when the
+    /// configuration comes from the rule itself rather than being passed
+    /// on from the module that forwards it.
+    fn load_module(&mut self, url: &str, configuration: Configuration, explicit: bool,
+                   pstate: &ParserState) -> SassResult<Rc<Module>> {
+        if url.starts_with("sass:") {
+            return self.load_builtin_module(url, explicit, pstate);
+        }
+        let resource = self.ctx.load_module(url, pstate, &self.traces)?;
+        if let Some(module) = self.ctx.get_module(&resource.abs_path) {
+            if explicit {
+                return Err(self.error("This module was already loaded, so it can't be configured using \"with\".", pstate));
+            }
+            return Ok(module);
+        }
+        if self.ctx.is_loading(&resource.abs_path) {
+            return Err(self.error("Module loop: this module is already being loaded.", pstate));
+        }
+        let module = Rc::new(self.evaluate_module(&resource, configuration, pstate)?);
+        self.ctx.add_module(&resource.abs_path, module.clone());
+        self.add_module_css(&module, pstate)?;
+        Ok(module)
+    }
+
+    /// Evaluates a stylesheet as a module. Its CSS goes in a tree of its
+    /// own, kept with the module, as it doesn't depend on where the
+    /// module is loaded.
+    fn evaluate_module(&mut self, resource: &Resource, configuration: Configuration, pstate: &ParserState) -> SassResult<Module> {
+        let sheet = self.ctx.parse(resource).map_err(|e| self.with_traces(e))?;
+
+        // The module is evaluated in a new environment, with its path on
+        // the import stack like an imported file.
+        let env = self.env.for_module();
+        let root = CssNode::new(CssKind::Root, None);
+        let old_configuration = mem::replace(&mut self.configuration, configuration);
+        self.traces.push(Backtrace::new(pstate.clone(), ""));
+        self.ctx.push_import(resource);
+        let result = self.with_css_root(&root, |this| {
+            this.with_environment(env, None, |this| {
+                this.expand_block(&sheet.body)?;
+                Ok(this.env.to_module(resource.abs_path.clone()))
+            })
+        });
+        self.ctx.pop_import();
+        self.traces.pop();
+        self.configuration = old_configuration;
+
+        let mut module = result?;
+        module.css = Some(root);
+        Ok(module)
+    }
+
+    /// Runs `f` with `root` as the whole CSS tree, outside any rules.
+    fn with_css_root<T, F>(&mut self, root: &CssRef, f: F) -> SassResult<T>
+        where F: FnOnce(&mut Self) -> SassResult<T> {
+        let old_root = mem::replace(&mut self.root, root.clone());
+        let old_container = mem::replace(&mut self.container, root.clone());
+        let old_media_parent = mem::replace(&mut self.media_parent, root.clone());
+        let old_parent = mem::replace(&mut self.parent, root.clone());
+        let old_rule = self.style_rule.take();
+        let old_selector = self.selector.take();
+        let old_media = self.media_queries.take();
+        let old_at_root = mem::replace(&mut self.at_root_excluding_style_rule, false);
+        let old_keyframes = mem::replace(&mut self.in_keyframes, false);
+        let old_unknown = mem::replace(&mut self.in_unknown_at_rule, false);
+        let old_declaration_name = self.declaration_name.take();
+
+        let result = f(self);
+
+        self.root = old_root;
+        self.container = old_container;
+        self.media_parent = old_media_parent;
+        self.parent = old_parent;
+        self.style_rule = old_rule;
+        self.selector = old_selector;
+        self.media_queries = old_media;
+        self.at_root_excluding_style_rule = old_at_root;
+        self.in_keyframes = old_keyframes;
+        self.in_unknown_at_rule = old_unknown;
+        self.declaration_name = old_declaration_name;
+        result
+    }
+
+    /// Adds the CSS of a module that has just been evaluated at the
+    /// current position. At the top level, as for @use, its nodes are
+    /// moved to the output so that @extend still reaches them.
+    fn add_module_css(&mut self, module: &Module, pstate: &ParserState) -> SassResult<()> {
+        let root = match module.css {
+            Some(ref root) => root,
+            None => return Ok(()),
+        };
+        let at_top_level = self.style_rule.is_none() && self.media_queries.is_none() && Rc::ptr_eq(&self.container, &self.root);
+        if !at_top_level {
+            return self.add_css(root, pstate);
+        }
+        let children = root.borrow().children.clone();
+        for child in children {
+            if matches!(child.borrow().kind, CssKind::Import(_)) {
+                add_import(&self.root, child);
+            } else {
+                add_child(&self.container, child);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a copy of CSS that has already been evaluated at the current
+    /// position, nesting its style rules within the current one, as
+    /// meta.load-css() does with the CSS of a module.
+    fn add_css(&mut self, node: &CssRef, pstate: &ParserState) -> SassResult<()> {
+        let kind = node.borrow().kind.clone();
+        let children = node.borrow().children.clone();
+        let add_children = |this: &mut Self| -> SassResult<()> {
+            for child in &children {
+                this.add_css(child, pstate)?;
+            }
+            Ok(())
+        };
+
+        match kind {
+            CssKind::Root => add_children(self),
+            CssKind::StyleRule(ref selector) => {
+                let resolved = selector.resolve_parent_selectors(self.selector.as_ref(), !self.at_root_excluding_style_rule)
+                    .map_err(|msg| self.error(msg, pstate))?;
+                let copy = CssNode::new(CssKind::StyleRule(resolved.clone()), node.borrow().pstate.clone());
+                copy.borrow_mut().nesting_parent = self.style_rule.as_ref().map(Rc::downgrade);
+                add_child(&self.container, copy.clone());
+                let media = self.media_context();
+                self.extender.add_rule(&copy, media)?;
+
+                let old_parent = mem::replace(&mut self.parent, copy.clone());
+                let old_rule = self.style_rule.replace(copy);
+                let old_selector = self.selector.replace(resolved);
+                let old_at_root = mem::replace(&mut self.at_root_excluding_style_rule, false);
+                let result = add_children(self);
+                self.parent = old_parent;
+                self.style_rule = old_rule;
+                self.selector = old_selector;
+                self.at_root_excluding_style_rule = old_at_root;
+                result
+            }
+            CssKind::MediaRule(ref queries) => {
+                let queries = match self.media_queries {
+                    Some(ref outer) => merge_media_queries(outer, queries),
+                    None => queries.clone(),
+                };
+                if queries.is_empty() {
+                    return Ok(());
+                }
+                let copy = CssNode::new(CssKind::MediaRule(queries.clone()), node.borrow().pstate.clone());
+                copy.borrow_mut().nesting_parent = self.style_rule.as_ref().map(Rc::downgrade);
+                add_child(&self.media_parent, copy.clone());
+
+                let old_media = self.media_queries.replace(queries);
+                let result = self.with_at_rule(&copy, true, add_children);
+                self.media_queries = old_media;
+                result
+            }
+            CssKind::AtRule(ref at_rule) if at_rule.childless => {
+                add_child(&self.parent, copy_without_children(node));
+                Ok(())
+            }
+            CssKind::SupportsRule(_) | CssKind::AtRule(_) => {
+                let is_keyframes = match kind {
+                    CssKind::AtRule(ref at_rule) => unvendor(&at_rule.name) == "keyframes",
+                    _ => false,
+                };
+                let copy = copy_without_children(node);
+                copy.borrow_mut().nesting_parent = self.style_rule.as_ref().map(Rc::downgrade);
+                add_child(&self.container, copy.clone());
+                self.with_at_rule(&copy, !is_keyframes, add_children)
+            }
+            CssKind::KeyframeBlock(_) => {
+                let copy = copy_without_children(node);
+                add_child(&self.parent, copy.clone());
+                let old_parent = mem::replace(&mut self.parent, copy);
+                let result = add_children(self);
+                self.parent = old_parent;
+                result
+            }
+            CssKind::Import(_) => {
+                add_import(&self.root, copy_without_children(node));
+                Ok(())
+            }
+            CssKind::Declaration(_) | CssKind::Comment(_) => {
+                add_child(&self.parent, copy_without_children(node));
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds the CSS of a module at the current position, for
+    /// meta.load-css(). It goes through the same loading as @use. A
+    /// module that was loaded already isn't evaluated again; the CSS it
+    /// produced then is added again.
+    pub fn load_css(&mut self, url: &str, configuration: Configuration, explicit: bool, pstate: &ParserState) -> SassResult<()> {
+        if url.starts_with("sass:") {
+            return self.load_builtin_module(url, explicit, pstate).map(|_| ());
+        }
+        let resource = self.ctx.load_module(url, pstate, &self.traces)?;
+        match self.ctx.get_module(&resource.abs_path) {
+            Some(module) => {
+                if explicit {
+                    return Err(self.error("This module was already loaded, so it can't be configured using \"with\".", pstate));
+                }
+                self.add_css(module.css.as_ref().expect("a module loaded from a stylesheet has CSS"), pstate)?;
+            }
+            None => {
+                self.load_module(url, configuration.clone(), explicit, pstate)?;
+            }
+        }
+
+        if !configuration.borrow().is_empty() {
+            return Err(self.error("This variable was not declared with !default in the @used module.", pstate));
+        }
+        Ok(())
+    }
+
+    /// Loads one of the `sass:` modules, which are made the first time
+    /// they are used and can't be configured.
+    fn load_builtin_module(&mut self, url: &str, explicit: bool, pstate: &ParserState) -> SassResult<Rc<Module>> {
+        if explicit {
+            return Err(self.error("Built-in modules can't be configured.", pstate));
+        }
+        if let Some(module) = self.ctx.get_module(url) {
+            return Ok(module);
+        }
+        let module = builtin_module(url, self.ctx.precision)
+            .ok_or_else(|| self.error("Can't find stylesheet to import.", pstate))?;
+        let module = Rc::new(module);
+        self.ctx.add_module(url, module.clone());
+        Ok(module)
+    }
+
+    fn expand_import(&mut self, import: &Import) -> SassResult<()> {
+        for argument in &import.imports {
+            match *argument {
+                ImportArgument::Static { ref url, ref modifiers, .. } => {
+                    let mut text = self.interpolate(url)?;
+                    if let Some(ref modifiers) = *modifiers {
+                        text.push(' ');
+                        text.push_str(&self.interpolate(modifiers)?);
+                    }
+                    add_import(&self.root, CssNode::new(CssKind::Import(text), Some(import.pstate.clone())));
+                }
+                ImportArgument::Dynamic { ref url, ref pstate } => {
+                    self.warn_deprecation(Deprecation::Import, "Sass @import rules are deprecated and will be removed in Dart Sass 3.0.0.\n\n\
+                                                               Use @use or @forward instead.", pstate)?;
+                    self.traces.push(Backtrace::new(pstate.clone(), ""));
+                    let result = self.expand_dynamic_import(url, pstate);
+                    self.traces.pop();
+                    result?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn expand_dynamic_import(&mut self, url: &str, pstate: &ParserState) -> SassResult<()> {
+        let traces = self.traces[..self.traces.len() - 1].to_vec();
+        for import in self.ctx.load_import(url, pstate, &traces)? {
+            self.expand_loaded_import(import, pstate)?;
+        }
+        Ok(())
+    }
+
+    fn expand_loaded_import(&mut self, import: LoadedImport, pstate: &ParserState) -> SassResult<()> {
+        match import {
+            LoadedImport::Css(url) => {
+                let text = format!("url({})", url);
+                add_import(&self.root, CssNode::new(CssKind::Import(text), Some(pstate.clone())));
+                Ok(())
+            }
+            LoadedImport::Resource(resource) => {
+                self.ctx.check_import_loop(&resource.abs_path, pstate, &self.traces[..self.traces.len().saturating_sub(1)])?;
+                let sheet = self.ctx.parse(&resource).map_err(|e| self.with_traces(e))?;
+
+                // The imported file is evaluated in place, with its path
+                // on the import stack for any importers it calls.
+                self.ctx.push_import(&resource);
+                let result = self.expand_block(&sheet.body);
+                self.ctx.pop_import();
+                result.map(|_| ())
+            }
+        }
+    }
+}
@@ -0,0 +1,738 @@
+// FROM: src/extender.cpp, src/extension.cpp
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::rc::Rc;
+use css::{CssKind, CssRef};
+use error_handling::{Backtraces, SassError, SassResult};
+use selector::*;
+use selector_ops::{paths, unify_complex, weave, Components};
+
+// The media queries a rule or an @extend is nested in.
+pub type MediaContext = Vec<String>;
+
+// A selector that can stand in for a target selector: either one added
+// by @extend, or one already in the selector being extended.
+#[derive(Debug, Clone)]
+pub struct Extension {
+    pub extender: ComplexSelector,
+    pub target: SimpleSelector,
+    // the specificity of the selector the extender came from
+    pub specificity: u64,
+    pub is_optional: bool,
+    // whether this is part of the selector being extended
+    pub is_original: bool,
+    pub media: Option<MediaContext>,
+    // the stack of the @extend, for errors
+    pub origin: Option<Rc<Backtraces>>,
+}
+
+impl Extension {
+    /// An extension that isn't from an @extend, such as one for a
+    /// selector being extended.
+    fn one_off(extender: ComplexSelector, specificity: u64, is_original: bool) -> Self {
+        Extension {
+            target: SimpleSelector::Placeholder(String::new()),
+            extender,
+            specificity,
+            is_optional: true,
+            is_original,
+            media: None,
+            origin: None,
+        }
+    }
+
+    fn with_extender(&self, extender: ComplexSelector) -> Self {
+        Extension { extender, ..self.clone() }
+    }
+
+    fn error<S: Into<String>>(&self, message: S) -> Box<SassError> {
+        let traces = self.origin.as_ref().map(|traces| (**traces).clone()).unwrap_or_default();
+        let pstate = traces.last().expect("an @extend has a position").pstate.clone();
+        Box::new(SassError { message: message.into(), pstate, traces })
+    }
+
+    /// Checks that this extension can apply to a selector in the given
+    /// media context.
+    fn assert_compatible_media(&self, media: Option<&MediaContext>) -> SassResult<()> {
+        match self.media {
+            None => Ok(()),
+            Some(ref own) if Some(own) == media => Ok(()),
+            Some(_) => Err(self.error("You may not @extend selectors across media queries.")),
+        }
+    }
+}
+
+/// Combines two extensions with the same extender and target.
+fn merge_extensions(left: Extension, right: Extension) -> SassResult<Extension> {
+    if let (Some(left_media), Some(right_media)) = (&left.media, &right.media) {
+        if left_media != right_media {
+            return Err(right.error("You may not @extend the same selector from within different media queries."));
+        }
+    }
+
+    // An optional extension without a media context adds nothing.
+    if right.is_optional && right.media.is_none() {
+        return Ok(left);
+    }
+    if left.is_optional && left.media.is_none() {
+        return Ok(right);
+    }
+    let (kept, other) = if left.is_optional { (right, left) } else { (left, right) };
+    Ok(Extension { media: kept.media.clone().or(other.media), is_optional: kept.is_optional && other.is_optional, ..kept })
+}
+
+// The extensions of each target, in the order they were added. Each
+// extender appears at most once per target.
+type Extensions = HashMap<SimpleSelector, Vec<Extension>>;
+
+fn find_extender(extensions: &[Extension], extender: &ComplexSelector) -> Option<usize> {
+    extensions.iter().position(|e| e.extender == *extender)
+}
+
+/// Adds an extension to a target's list, merging it with an existing
+/// extension with the same extender.
+fn upsert_extension(extensions: &mut Vec<Extension>, extension: Extension) -> SassResult<()> {
+    match find_extender(extensions, &extension.extender) {
+        Some(i) => {
+            let existing = extensions[i].clone();
+            extensions[i] = merge_extensions(existing, extension)?;
+        }
+        None => extensions.push(extension),
+    }
+    Ok(())
+}
+
+// How extensions are applied.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExtendMode {
+    // as by @extend, keeping the original selector
+    Normal,
+    // as by selector.replace(), dropping the original selector
+    Replace,
+    // as by selector.extend(), which like @extend keeps the original
+    // selector, but only extends compounds that contain every target
+    AllTargets,
+}
+
+impl Default for ExtendMode {
+    fn default() -> Self {
+        ExtendMode::Normal
+    }
+}
+
+// A style rule whose selector is updated as extensions are added.
+struct ExtendedRule {
+    node: CssRef,
+    media: Option<MediaContext>,
+}
+
+impl ExtendedRule {
+    fn selector(&self) -> SelectorList {
+        match self.node.borrow().kind {
+            CssKind::StyleRule(ref selector) => selector.clone(),
+            _ => unreachable!("only style rules are extended"),
+        }
+    }
+
+    fn set_selector(&self, selector: SelectorList) {
+        if let CssKind::StyleRule(ref mut own) = self.node.borrow_mut().kind {
+            *own = selector;
+        }
+    }
+}
+
+// Applies @extend to style rules. Rules are extended as they are added,
+// and again each time an extension for one of their selectors is added,
+// so the order of rules and @extends doesn't matter.
+#[derive(Default)]
+pub struct ExtensionStore {
+    rules: Vec<ExtendedRule>,
+    // the rules whose selectors contain each simple selector
+    selectors: HashMap<SimpleSelector, Vec<usize>>,
+    extensions: Extensions,
+    // the extensions whose extenders contain each simple selector
+    extensions_by_extender: HashMap<SimpleSelector, Vec<Extension>>,
+    // the specificity of the rule each simple selector first appeared
+    // in as an extender
+    source_specificity: HashMap<SimpleSelector, u64>,
+    // selectors written in the stylesheet, which are never trimmed
+    originals: RefCell<HashSet<ComplexSelector>>,
+    // one extension for each target of each @extend, in order
+    extends: Vec<Extension>,
+    mode: ExtendMode,
+}
+
+impl ExtensionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Extends `selector` by `source` as if `source` had each of the
+    /// compound selectors in `targets` as its target, as selector.extend()
+    /// and selector.replace() do.
+    pub fn extend_or_replace(selector: &SelectorList, source: &SelectorList, targets: &SelectorList,
+                             mode: ExtendMode) -> Result<SelectorList, String> {
+        let extenders: Vec<Extension> = source.components.iter()
+            .map(|complex| Extension::one_off(complex.clone(), complex.max_specificity(), false))
+            .collect();
+
+        let mut selector = selector.clone();
+        for complex in &targets.components {
+            let compound = match complex.components.as_slice() {
+                [ComplexComponent::Compound(ref compound)] => compound,
+                _ => return Err(format!("Can't extend complex selector {}.", complex)),
+            };
+            let extensions: Extensions = compound.components.iter()
+                .map(|simple| (simple.clone(), extenders.clone()))
+                .collect();
+
+            let store = ExtensionStore { mode, ..Self::default() };
+            if !selector.is_invisible() {
+                store.originals.borrow_mut().extend(selector.components.iter().cloned());
+            }
+            // One-off extensions have no media context, so this can't fail.
+            if let Ok(Some(extended)) = store.extend_list(&selector, &extensions, None) {
+                selector = extended;
+            }
+        }
+        Ok(selector)
+    }
+
+    /// Registers a style rule, extending its selector with the
+    /// extensions added so far.
+    pub fn add_rule(&mut self, node: &CssRef, media: Option<MediaContext>) -> SassResult<()> {
+        let rule = ExtendedRule { node: node.clone(), media };
+        let original = rule.selector();
+        if !original.is_invisible() {
+            self.originals.borrow_mut().extend(original.components.iter().cloned());
+        }
+
+        let selector = if self.extensions.is_empty() {
+            original
+        } else {
+            match self.extend_list(&original, &self.extensions, rule.media.as_ref())? {
+                Some(extended) => {
+                    rule.set_selector(extended.clone());
+                    extended
+                }
+                None => original,
+            }
+        };
+
+        let index = self.rules.len();
+        self.rules.push(rule);
+        self.register_selector(&selector, index);
+        Ok(())
+    }
+
+    fn register_selector(&mut self, list: &SelectorList, index: usize) {
+        for complex in &list.components {
+            for compound in complex.compounds() {
+                for simple in &compound.components {
+                    let rules = self.selectors.entry(simple.clone()).or_insert_with(Vec::new);
+                    if !rules.contains(&index) {
+                        rules.push(index);
+                    }
+                    if let SimpleSelector::Pseudo(PseudoSelector { selector: Some(ref inner), .. }) = *simple {
+                        self.register_selector(inner, index);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adds an extension of `target` by every selector in `extender`,
+    /// and applies it to the rules and extensions added so far.
+    pub fn add_extension(&mut self, extender: &SelectorList, target: &SimpleSelector, is_optional: bool,
+                         media: Option<MediaContext>, origin: Rc<Backtraces>) -> SassResult<()> {
+        let has_rules = self.selectors.contains_key(target);
+        let existing_extensions = self.extensions_by_extender.get(target).cloned();
+
+        let mut new_extensions: Option<Vec<Extension>> = None;
+        for (i, complex) in extender.components.iter().enumerate() {
+            let state = Extension {
+                extender: complex.clone(),
+                target: target.clone(),
+                specificity: complex.max_specificity(),
+                is_optional,
+                is_original: false,
+                media: media.clone(),
+                origin: Some(origin.clone()),
+            };
+            if i == 0 {
+                self.extends.push(state.clone());
+            }
+            let sources = self.extensions.entry(target.clone()).or_insert_with(Vec::new);
+            if let Some(i) = find_extender(sources, complex) {
+                let existing = sources[i].clone();
+                sources[i] = merge_extensions(existing, state)?;
+                continue;
+            }
+            sources.push(state.clone());
+
+            for compound in complex.compounds() {
+                for simple in &compound.components {
+                    self.extensions_by_extender.entry(simple.clone()).or_insert_with(Vec::new).push(state.clone());
+                    // Only the specificity of the original selector is
+                    // relevant, not that of selectors @extend generates.
+                    self.source_specificity.entry(simple.clone()).or_insert_with(|| complex.max_specificity());
+                }
+            }
+
+            if has_rules || existing_extensions.is_some() {
+                new_extensions.get_or_insert_with(Vec::new).push(state);
+            }
+        }
+
+        let new_extensions = match new_extensions {
+            Some(new_extensions) => new_extensions,
+            None => return Ok(()),
+        };
+        let mut new_by_target = Extensions::new();
+        new_by_target.insert(target.clone(), new_extensions);
+
+        if let Some(existing) = existing_extensions {
+            if let Some(additional) = self.extend_existing_extensions(&existing, &new_by_target)? {
+                for (target, extensions) in additional {
+                    let entry = new_by_target.entry(target).or_insert_with(Vec::new);
+                    for extension in extensions {
+                        match find_extender(entry, &extension.extender) {
+                            Some(i) => entry[i] = extension,
+                            None => entry.push(extension),
+                        }
+                    }
+                }
+            }
+        }
+
+        if has_rules {
+            self.extend_existing_rules(target, &new_by_target)?;
+        }
+        Ok(())
+    }
+
+    /// Extends the extenders of existing extensions with new extensions,
+    /// so that extending an extender also extends its targets. Returns
+    /// the extensions this adds for the new extensions' targets.
+    fn extend_existing_extensions(&mut self, extensions: &[Extension], new_extensions: &Extensions) -> SassResult<Option<Extensions>> {
+        let mut additional: Option<Extensions> = None;
+
+        for extension in extensions {
+            let selectors = match self.extend_complex(&extension.extender, new_extensions, extension.media.as_ref())? {
+                Some(selectors) => selectors,
+                None => continue,
+            };
+            let contains_extension = selectors.first() == Some(&extension.extender);
+
+            for complex in selectors {
+                let with_extender = extension.with_extender(complex.clone());
+                let sources = self.extensions.entry(extension.target.clone()).or_insert_with(Vec::new);
+                if let Some(i) = find_extender(sources, &complex) {
+                    let existing = sources[i].clone();
+                    sources[i] = merge_extensions(existing, with_extender)?;
+                    continue;
+                }
+                sources.push(with_extender.clone());
+
+                for compound in complex.compounds() {
+                    for simple in &compound.components {
+                        self.extensions_by_extender.entry(simple.clone()).or_insert_with(Vec::new).push(with_extender.clone());
+                    }
+                }
+
+                if new_extensions.contains_key(&extension.target) {
+                    let sources = additional.get_or_insert_with(Extensions::new)
+                        .entry(extension.target.clone()).or_insert_with(Vec::new);
+                    upsert_extension(sources, with_extender)?;
+                }
+            }
+
+            // If the extender was replaced, for example by extending a
+            // :not() within it, the old version is gone.
+            if !contains_extension {
+                let sources = self.extensions.entry(extension.target.clone()).or_insert_with(Vec::new);
+                if let Some(i) = find_extender(sources, &extension.extender) {
+                    sources.remove(i);
+                }
+            }
+        }
+
+        Ok(additional)
+    }
+
+    /// Extends the selectors of the rules that contain `target`.
+    fn extend_existing_rules(&mut self, target: &SimpleSelector, new_extensions: &Extensions) -> SassResult<()> {
+        let indices = self.selectors.get(target).cloned().unwrap_or_default();
+        for index in indices {
+            let extended = {
+                let rule = &self.rules[index];
+                self.extend_list(&rule.selector(), new_extensions, rule.media.as_ref())?
+            };
+            // Nothing to register if no extension applied, for example
+            // because unification failed.
+            if let Some(extended) = extended {
+                self.rules[index].set_selector(extended.clone());
+                self.register_selector(&extended, index);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reports the first @extend whose target appears in no selector,
+    /// unless it is `!optional`.
+    pub fn check_unsatisfied(&self) -> SassResult<()> {
+        for extension in &self.extends {
+            if !extension.is_optional && !self.selectors.contains_key(&extension.target) {
+                let message = format!("The target selector was not found.\nUse \"@extend {} !optional\" to avoid this error.", extension.target);
+                return Err(extension.error(message));
+            }
+        }
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Extending selectors
+
+    /// Extends a selector list, or returns None if no extension applies.
+    fn extend_list(&self, list: &SelectorList, extensions: &Extensions, media: Option<&MediaContext>) -> SassResult<Option<SelectorList>> {
+        let mut extended: Option<Vec<ComplexSelector>> = None;
+        for (i, complex) in list.components.iter().enumerate() {
+            match self.extend_complex(complex, extensions, media)? {
+                Some(result) => extended.get_or_insert_with(|| list.components[..i].to_vec()).extend(result),
+                None => {
+                    if let Some(ref mut extended) = extended {
+                        extended.push(complex.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(extended.map(|extended| {
+            let originals = self.originals.borrow();
+            SelectorList { components: self.trim(extended, |complex| originals.contains(complex)) }
+        }))
+    }
+
+    /// Extends a complex selector, giving the selectors it expands to,
+    /// or None if no extension applies.
+    fn extend_complex(&self, complex: &ComplexSelector, extensions: &Extensions, media: Option<&MediaContext>) -> SassResult<Option<Vec<ComplexSelector>>> {
+        // The complex selectors each component can expand to. Given
+        //
+        //     .a .b {...}
+        //     .x .y {@extend .b}
+        //
+        // this is `[[.a], [.b, .x .y]]`.
+        let single = |component: &ComplexComponent, line_break: bool| {
+            vec![ComplexSelector { components: vec![component.clone()], line_break }]
+        };
+        let is_original = self.originals.borrow().contains(complex);
+
+        let mut extended_not_expanded: Option<Vec<Vec<ComplexSelector>>> = None;
+        for (i, component) in complex.components.iter().enumerate() {
+            let extended = match *component {
+                ComplexComponent::Compound(ref compound) => self.extend_compound(compound, extensions, media, is_original)?,
+                ComplexComponent::Combinator(_) => None,
+            };
+            match extended {
+                Some(extended) => {
+                    extended_not_expanded.get_or_insert_with(|| {
+                        complex.components[..i].iter().map(|c| single(c, complex.line_break)).collect()
+                    }).push(extended);
+                }
+                None => {
+                    if let Some(ref mut options) = extended_not_expanded {
+                        options.push(single(component, false));
+                    }
+                }
+            }
+        }
+
+        let extended_not_expanded = match extended_not_expanded {
+            Some(options) => options,
+            None => return Ok(None),
+        };
+
+        let mut result = Vec::new();
+        for path in paths(&extended_not_expanded) {
+            let line_break = complex.line_break || path.iter().any(|c| c.line_break);
+            for components in weave(path.into_iter().map(|c| c.components).collect()) {
+                let output = ComplexSelector { components, line_break };
+                // Copies of an original selector are original too, such as
+                // when a :not() within it was extended.
+                if result.is_empty() && is_original {
+                    self.originals.borrow_mut().insert(output.clone());
+                }
+                result.push(output);
+            }
+        }
+        Ok(Some(result))
+    }
+
+    /// Extends a compound selector, giving the complex selectors it
+    /// expands to, or None if no extension applies.
+    fn extend_compound(&self, compound: &CompoundSelector, extensions: &Extensions, media: Option<&MediaContext>,
+                       in_original: bool) -> SassResult<Option<Vec<ComplexSelector>>> {
+        // The extensions each simple selector can be replaced by.
+        let mut options: Option<Vec<Vec<Extension>>> = None;
+        let mut targets_used = HashSet::new();
+        for (i, simple) in compound.components.iter().enumerate() {
+            match self.extend_simple(simple, extensions, media, &mut targets_used)? {
+                Some(extended) => {
+                    options.get_or_insert_with(|| {
+                        if i == 0 {
+                            vec![]
+                        } else {
+                            vec![vec![self.extension_for_compound(&compound.components[..i])]]
+                        }
+                    }).extend(extended);
+                }
+                None => {
+                    if let Some(ref mut options) = options {
+                        options.push(vec![self.extension_for_simple(simple)]);
+                    }
+                }
+            }
+        }
+
+        let options = match options {
+            Some(options) => options,
+            None => return Ok(None),
+        };
+        // Except for @extend, the compound must contain every target when
+        // there are several. A single target may instead have been
+        // extended inside a pseudo-class argument, as in `:is(.b)`.
+        if self.mode != ExtendMode::Normal && extensions.len() > 1 && targets_used.len() != extensions.len() {
+            return Ok(None);
+        }
+
+        // A single simple selector needs no unification.
+        if options.len() == 1 {
+            let mut result = Vec::with_capacity(options[0].len());
+            for state in &options[0] {
+                state.assert_compatible_media(media)?;
+                result.push(state.extender.clone());
+            }
+            return Ok(Some(result));
+        }
+
+        // Each path through the options is a different unification of the
+        // compound. Given
+        //
+        //     .a.b {...}
+        //     .w .x {@extend .a}
+        //     .y .z {@extend .b}
+        //
+        // the paths are `[.a, .b]`, `[.a, .y .z]`, `[.w .x, .b]` and
+        // `[.w .x, .y .z]`, which unify to `.a.b`, `.y .a.z`, `.w .x.b`
+        // and `.w .y .x.z, .y .w .x.z`.
+        let keep_original = self.mode != ExtendMode::Replace;
+        let mut unified_paths: Vec<Vec<ComplexSelector>> = Vec::new();
+        let mut first_original = None;
+        for (i, path) in paths(&options).into_iter().enumerate() {
+            let complexes: Vec<Components> = if i == 0 && keep_original {
+                // The first path is always the original selector, which
+                // needs no unification.
+                let components = path.iter().flat_map(|state| last_compound(&state.extender).components.clone()).collect();
+                vec![vec![ComplexComponent::Compound(CompoundSelector { components })]]
+            } else {
+                let mut to_unify: VecDeque<Components> = VecDeque::new();
+                let mut originals: Option<Vec<SimpleSelector>> = None;
+                for state in &path {
+                    if state.is_original {
+                        originals.get_or_insert_with(Vec::new).extend(last_compound(&state.extender).components.iter().cloned());
+                    } else {
+                        to_unify.push_back(state.extender.components.clone());
+                    }
+                }
+                if let Some(originals) = originals {
+                    to_unify.push_front(vec![ComplexComponent::Compound(CompoundSelector { components: originals })]);
+                }
+                match unify_complex(to_unify.into_iter().collect()) {
+                    Some(complexes) => complexes,
+                    None => continue,
+                }
+            };
+
+            let mut line_break = false;
+            for state in &path {
+                state.assert_compatible_media(media)?;
+                line_break = line_break || state.extender.line_break;
+            }
+            let complexes: Vec<ComplexSelector> = complexes.into_iter()
+                .map(|components| ComplexSelector { components, line_break })
+                .collect();
+            if i == 0 && in_original && keep_original {
+                first_original = complexes.first().cloned();
+            }
+            unified_paths.push(complexes);
+        }
+
+        // The unchanged selector is kept so that trimming doesn't remove it.
+        let all = unified_paths.into_iter().flatten().collect();
+        Ok(Some(self.trim(all, |complex| first_original.as_ref() == Some(complex))))
+    }
+
+    /// The ways a simple selector can be extended, one list for each
+    /// version of it, or None if no extension applies. The targets that
+    /// apply are added to `targets_used`.
+    fn extend_simple(&self, simple: &SimpleSelector, extensions: &Extensions, media: Option<&MediaContext>,
+                     targets_used: &mut HashSet<SimpleSelector>) -> SassResult<Option<Vec<Vec<Extension>>>> {
+        // Extends a simple selector without extending the contents of
+        // any selector pseudo it contains.
+        let mut without_pseudo = |simple: &SimpleSelector| -> Option<Vec<Extension>> {
+            let extenders = extensions.get(simple)?;
+            targets_used.insert(simple.clone());
+            if self.mode == ExtendMode::Replace {
+                return Some(extenders.clone());
+            }
+            let mut result = vec![self.extension_for_simple(simple)];
+            result.extend(extenders.iter().cloned());
+            Some(result)
+        };
+
+        if let SimpleSelector::Pseudo(ref pseudo) = *simple {
+            if pseudo.selector.is_some() {
+                if let Some(extended) = self.extend_pseudo(pseudo, extensions, media)? {
+                    return Ok(Some(extended.into_iter().map(|pseudo| {
+                        let simple = SimpleSelector::Pseudo(pseudo);
+                        without_pseudo(&simple).unwrap_or_else(|| vec![self.extension_for_simple(&simple)])
+                    }).collect()));
+                }
+            }
+        }
+
+        Ok(without_pseudo(simple).map(|result| vec![result]))
+    }
+
+    fn extension_for_simple(&self, simple: &SimpleSelector) -> Extension {
+        let compound = CompoundSelector { components: vec![simple.clone()] };
+        let specificity = self.source_specificity.get(simple).cloned().unwrap_or(0);
+        Extension::one_off(ComplexSelector { components: vec![ComplexComponent::Compound(compound)], line_break: false }, specificity, true)
+    }
+
+    fn extension_for_compound(&self, simples: &[SimpleSelector]) -> Extension {
+        let compound = CompoundSelector { components: simples.to_vec() };
+        let specificity = self.source_specificity_for(&compound);
+        Extension::one_off(ComplexSelector { components: vec![ComplexComponent::Compound(compound)], line_break: false }, specificity, true)
+    }
+
+    /// Extends the selector argument of a pseudo selector such as
+    /// `:not()`, giving the pseudos it expands to.
+    fn extend_pseudo(&self, pseudo: &PseudoSelector, extensions: &Extensions, media: Option<&MediaContext>) -> SassResult<Option<Vec<PseudoSelector>>> {
+        let selector = pseudo.selector.as_ref().expect("a selector pseudo");
+        let extended = match self.extend_list(selector, extensions, media)? {
+            Some(extended) => extended,
+            None => return Ok(None),
+        };
+        let name = pseudo.normalized_name();
+
+        // Complex selectors in `:not()` fail to parse in most browsers, so
+        // they are dropped unless the original had them or the result
+        // has nothing else.
+        let mut complexes = extended.components;
+        if name == "not" && !selector.components.iter().any(|c| c.components.len() > 1) &&
+            complexes.iter().any(|c| c.components.len() == 1) {
+            complexes.retain(|c| c.components.len() <= 1);
+        }
+
+        let complexes: Vec<ComplexSelector> = complexes.into_iter().flat_map(|complex| {
+            let inner = match complex.components.as_slice() {
+                [ComplexComponent::Compound(ref compound)] if compound.components.len() == 1 => match compound.components[0] {
+                    SimpleSelector::Pseudo(ref inner) if inner.selector.is_some() => inner.clone(),
+                    _ => return vec![complex],
+                },
+                _ => return vec![complex],
+            };
+            let inner_selector = inner.selector.clone().unwrap();
+
+            match name.as_str() {
+                // A :not() within a :not() would have to be unified with
+                // the result, which isn't supported.
+                "not" => match inner.normalized_name().as_str() {
+                    "is" | "matches" => inner_selector.components,
+                    _ => vec![],
+                },
+                "is" | "matches" | "where" | "any" | "current" | "nth-child" | "nth-last-child" => {
+                    if inner.name != pseudo.name || inner.argument != pseudo.argument {
+                        vec![]
+                    } else {
+                        inner_selector.components
+                    }
+                }
+                // Each level of these adds meaning, so nested ones can't
+                // be flattened.
+                "has" | "host" | "host-context" | "slotted" => vec![complex],
+                _ => vec![],
+            }
+        }).collect();
+
+        // Older browsers only support a single complex selector in a
+        // `:not()`, so it is split up unless it was a list already.
+        if name == "not" && selector.components.len() == 1 {
+            let result: Vec<PseudoSelector> = complexes.into_iter()
+                .map(|complex| pseudo.with_selector(SelectorList { components: vec![complex] }))
+                .collect();
+            Ok(if result.is_empty() { None } else { Some(result) })
+        } else {
+            Ok(Some(vec![pseudo.with_selector(SelectorList { components: complexes })]))
+        }
+    }
+
+    /// Removes selectors that are subselectors of others in the list, as
+    /// long as the superselector is at least as specific as the source
+    /// of the subselector. Of two identical selectors, the first is kept.
+    fn trim<F>(&self, selectors: Vec<ComplexSelector>, is_original: F) -> Vec<ComplexSelector>
+        where F: Fn(&ComplexSelector) -> bool
+    {
+        // Avoid truly horrific quadratic behavior.
+        if selectors.len() > 100 {
+            return selectors;
+        }
+
+        let mut result: VecDeque<ComplexSelector> = VecDeque::new();
+        let mut num_originals = 0;
+        'outer: for i in (0..selectors.len()).rev() {
+            let complex1 = &selectors[i];
+            if is_original(complex1) {
+                // Don't include duplicate originals, which can happen if a
+                // rule extends part of its own selector.
+                for j in 0..num_originals {
+                    if result[j] == *complex1 {
+                        let original = result.remove(j).unwrap();
+                        result.push_front(original);
+                        continue 'outer;
+                    }
+                }
+                num_originals += 1;
+                result.push_front(complex1.clone());
+                continue;
+            }
+
+            // The specificity of the sources that generated `complex1`.
+            let max_specificity = complex1.compounds().iter().map(|c| self.source_specificity_for(c)).max().unwrap_or(0);
+            let trimmed_by = |complex2: &ComplexSelector| {
+                complex2.min_specificity() >= max_specificity && complex2.is_superselector(complex1)
+            };
+            if result.iter().any(&trimmed_by) || selectors[..i].iter().any(trimmed_by) {
+                continue;
+            }
+            result.push_front(complex1.clone());
+        }
+        result.into_iter().collect()
+    }
+
+    fn source_specificity_for(&self, compound: &CompoundSelector) -> u64 {
+        compound.components.iter()
+            .map(|simple| self.source_specificity.get(simple).cloned().unwrap_or(0))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+fn last_compound(complex: &ComplexSelector) -> &CompoundSelector {
+    match complex.components.last() {
+        Some(ComplexComponent::Compound(compound)) => compound,
+        _ => unreachable!("extenders end with a compound selector"),
+    }
+}
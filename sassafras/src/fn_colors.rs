@@ -0,0 +1,879 @@
+// FROM: src/fn_colors.cpp
+
+use deprecation::Deprecation;
+use environment::Environment;
+use expand::Expand;
+use fn_utils::{self, Args, FnResult};
+use inspect::inspect_value;
+use module::Module;
+use values::*;
+
+pub fn register(env: &mut Environment) {
+    fn_utils::register(env, &[
+        ("rgb($red, $green, $blue, $alpha)", rgb),
+        ("rgb($red, $green, $blue)", rgb),
+        ("rgb($color, $alpha)", rgb_two_args),
+        ("rgb($channels)", rgb_channels),
+        ("rgba($red, $green, $blue, $alpha)", rgba),
+        ("rgba($red, $green, $blue)", rgba),
+        ("rgba($color, $alpha)", rgba_two_args),
+        ("rgba($channels)", rgba_channels),
+        ("red($color)", red),
+        ("green($color)", green),
+        ("blue($color)", blue),
+        ("mix($color1, $color2, $weight: 50%)", mix),
+        ("invert($color, $weight: 100%)", invert),
+
+        ("hsl($hue, $saturation, $lightness, $alpha)", hsl),
+        ("hsl($hue, $saturation, $lightness)", hsl),
+        ("hsl($hue, $saturation)", hsl_two_args),
+        ("hsl($channels)", hsl_channels),
+        ("hsla($hue, $saturation, $lightness, $alpha)", hsla),
+        ("hsla($hue, $saturation, $lightness)", hsla),
+        ("hsla($hue, $saturation)", hsla_two_args),
+        ("hsla($channels)", hsla_channels),
+        ("hue($color)", hue),
+        ("saturation($color)", saturation),
+        ("lightness($color)", lightness),
+        ("complement($color)", complement),
+        ("grayscale($color)", grayscale),
+        ("adjust-hue($color, $degrees)", adjust_hue),
+        ("lighten($color, $amount)", lighten),
+        ("darken($color, $amount)", darken),
+        ("saturate($amount)", saturate_css),
+        ("saturate($color, $amount)", saturate),
+        ("desaturate($color, $amount)", desaturate),
+
+        ("opacify($color, $amount)", opacify),
+        ("fade-in($color, $amount)", fade_in),
+        ("transparentize($color, $amount)", transparentize),
+        ("fade-out($color, $amount)", fade_out),
+        ("alpha($color)", alpha),
+        ("alpha($args...)", alpha_args),
+        ("opacity($color)", opacity),
+
+        ("ie-hex-str($color)", ie_hex_str),
+        ("adjust-color($color, $kwargs...)", adjust),
+        ("scale-color($color, $kwargs...)", scale),
+        ("change-color($color, $kwargs...)", change),
+    ]);
+}
+
+/// The `sass:color` module. The functions that only exist for
+/// compatibility, such as lighten(), are left out; calling them explains
+/// how to use adjust() instead.
+pub fn module() -> Module {
+    let mut env = Environment::new();
+    fn_utils::register_members(&mut env, &[
+        ("red($color)", red),
+        ("green($color)", green),
+        ("blue($color)", blue),
+        ("mix($color1, $color2, $weight: 50%)", mix),
+        ("invert($color, $weight: 100%)", invert),
+
+        ("hue($color)", hue),
+        ("saturation($color)", saturation),
+        ("lightness($color)", lightness),
+        ("complement($color)", complement),
+        ("adjust-hue($color, $amount)", removed_adjust_hue),
+        ("lighten($color, $amount)", removed_lighten),
+        ("darken($color, $amount)", removed_darken),
+        ("saturate($color, $amount)", removed_saturate),
+        ("desaturate($color, $amount)", removed_desaturate),
+        ("grayscale($color)", grayscale),
+
+        ("hwb($hue, $whiteness, $blackness, $alpha: 1)", hwb),
+        ("hwb($channels)", hwb_channels),
+        ("whiteness($color)", whiteness),
+        ("blackness($color)", blackness),
+
+        ("opacify($color, $amount)", removed_opacify),
+        ("fade-in($color, $amount)", removed_fade_in),
+        ("transparentize($color, $amount)", removed_transparentize),
+        ("fade-out($color, $amount)", removed_fade_out),
+        ("alpha($color)", alpha),
+        ("alpha($args...)", alpha_args),
+        ("opacity($color)", opacity),
+
+        ("adjust($color, $kwargs...)", adjust),
+        ("scale($color, $kwargs...)", scale),
+        ("change($color, $kwargs...)", change),
+        ("ie-hex-str($color)", ie_hex_str),
+    ]);
+    env.to_module("sass:color".to_string())
+}
+
+// ---------------------------------------------------------------------------------
+// Helpers
+
+fn show(number: &Number, precision: u8) -> String {
+    inspect_value(&Value::Number(number.clone()), precision)
+}
+
+/// Whether a value is a `var()` call, which could stand for any number
+/// of arguments once the CSS custom property is substituted.
+fn is_var(value: &Value) -> bool {
+    match *value {
+        Value::String(ref s) => !s.quoted && s.text.len() >= 4 && s.text[..4].eq_ignore_ascii_case("var("),
+        _ => false,
+    }
+}
+
+/// Whether a value is a CSS function that may compute a number, which
+/// makes the color function it's passed to plain CSS.
+fn is_special_number(value: &Value) -> bool {
+    match *value {
+        Value::String(ref s) if !s.quoted => {
+            let lower = s.text.to_ascii_lowercase();
+            ["calc(", "var(", "env(", "clamp(", "min(", "max("].iter().any(|prefix| lower.starts_with(prefix))
+        }
+        Value::Calculation(_) => true,
+        _ => false,
+    }
+}
+
+/// A call to the plain CSS function of the same name.
+fn function_string(expand: &Expand, name: &str, values: &[Value], args: &Args) -> FnResult<Value> {
+    let mut css = Vec::with_capacity(values.len());
+    for value in values {
+        css.push(expand.to_css_string(value, &args.pstate)?);
+    }
+    Ok(Value::unquoted_string(format!("{}({})", name, css.join(", "))))
+}
+
+/// The value of a number if it is within a range. The bounds are
+/// written with `unit` in the error.
+fn value_in_range(number: &Number, min: f64, max: f64, name: &str, unit: &str, precision: u8) -> FnResult<f64> {
+    if fuzzy_equals(number.value, min) {
+        return Ok(min);
+    }
+    if fuzzy_equals(number.value, max) {
+        return Ok(max);
+    }
+    if number.value > min && number.value < max {
+        return Ok(number.value);
+    }
+    Err(format!("${}: Expected {} to be within {}{} and {}{}.", name, show(number, precision), min, unit, max, unit).into())
+}
+
+fn assert_percent(number: &Number, name: &str, precision: u8) -> FnResult<()> {
+    if number.numerators.len() == 1 && number.denominators.is_empty() && number.has_unit("%") {
+        return Ok(());
+    }
+    Err(format!("${}: Expected {} to have unit \"%\".", name, show(number, precision)).into())
+}
+
+/// A channel given as a unitless number or a percentage of `max`,
+/// clamped to the range of the channel.
+fn percentage_or_unitless(number: &Number, max: f64, name: &str, precision: u8) -> FnResult<f64> {
+    let value = if number.is_unitless() {
+        number.value
+    } else if number.numerators.len() == 1 && number.denominators.is_empty() && number.has_unit("%") {
+        max * number.value / 100.0
+    } else {
+        return Err(format!("${}: Expected {} to have no units or \"%\".", name, show(number, precision)).into());
+    };
+    Ok(value.max(0.0).min(max))
+}
+
+/// An angle in degrees. Unitless numbers are taken to be degrees, and
+/// so are numbers with units that aren't angles.
+/// Reports a call of one of the functions that only adjust one channel,
+/// suggesting the color.adjust() call that replaces it. The amount is
+/// the second argument, negated if `sign` is.
+fn warn_color_function(expand: &Expand, args: &Args, name: &str, channel: &str, sign: f64) -> FnResult<()> {
+    let amount = args.number(1)?;
+    let amount = Number { value: sign * amount.value, ..amount.clone() };
+    let message = format!("{}() is deprecated. Suggestion:\n\ncolor.adjust($color, ${}: {})",
+                          name, channel, show(&amount, args.precision()));
+    Ok(expand.warn_deprecation(Deprecation::ColorFunctions, message, &args.pstate)?)
+}
+
+fn angle_value(number: &Number) -> f64 {
+    let deg = vec!["deg".to_string()];
+    number.coerce_value(&deg, &[]).unwrap_or(number.value)
+}
+
+/// Mixes two colors by weight, taking their alpha channels into account.
+fn mix_colors(color1: &Color, color2: &Color, weight: &Number, precision: u8) -> FnResult<Value> {
+    let weight_scale = value_in_range(weight, 0.0, 100.0, "weight", "%", precision)? / 100.0;
+    let normalized_weight = weight_scale * 2.0 - 1.0;
+    let alpha_distance = color1.alpha() - color2.alpha();
+
+    let combined_weight1 = if normalized_weight * alpha_distance == -1.0 {
+        normalized_weight
+    } else {
+        (normalized_weight + alpha_distance) / (1.0 + normalized_weight * alpha_distance)
+    };
+    let weight1 = (combined_weight1 + 1.0) / 2.0;
+    let weight2 = 1.0 - weight1;
+
+    let channel = |a: u8, b: u8| f64::from(a) * weight1 + f64::from(b) * weight2;
+    Ok(Value::Color(Color::rgba(
+        channel(color1.red(), color2.red()),
+        channel(color1.green(), color2.green()),
+        channel(color1.blue(), color2.blue()),
+        color1.alpha() * weight_scale + color2.alpha() * (1.0 - weight_scale),
+    )))
+}
+
+fn with_hsl(color: &Color, hue: f64, saturation: f64, lightness: f64) -> Value {
+    Value::Color(Color::hsla(hue, saturation, lightness, color.alpha()))
+}
+
+// ---------------------------------------------------------------------------------
+// RGB
+
+fn rgb(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    make_rgb(expand, "rgb", args.values(), args)
+}
+
+fn rgba(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    make_rgb(expand, "rgba", args.values(), args)
+}
+
+fn rgb_two_args(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    make_rgb_two_args(expand, "rgb", args)
+}
+
+fn rgba_two_args(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    make_rgb_two_args(expand, "rgba", args)
+}
+
+fn rgb_channels(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    match parse_channels(expand, "rgb", &["red", "green", "blue"], args)? {
+        Channels::Css(css) => Ok(css),
+        Channels::Values(values) => make_rgb(expand, "rgb", &values, args),
+    }
+}
+
+fn rgba_channels(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    match parse_channels(expand, "rgba", &["red", "green", "blue"], args)? {
+        Channels::Css(css) => Ok(css),
+        Channels::Values(values) => make_rgb(expand, "rgba", &values, args),
+    }
+}
+
+/// A color from red, green and blue channels and an optional alpha.
+fn make_rgb(expand: &Expand, name: &str, values: &[Value], args: &Args) -> FnResult<Value> {
+    if values.iter().any(is_special_number) {
+        return function_string(expand, name, values, args);
+    }
+
+    let precision = args.precision();
+    let mut channels = [0.0; 3];
+    for (i, &channel) in ["red", "green", "blue"].iter().enumerate() {
+        let number = number_value(&values[i], channel, precision)?;
+        channels[i] = percentage_or_unitless(number, 255.0, channel, precision)?;
+    }
+    let alpha = match values.get(3) {
+        Some(alpha) => percentage_or_unitless(number_value(alpha, "alpha", precision)?, 1.0, "alpha", precision)?,
+        None => 1.0,
+    };
+    let color = Color::rgba(channels[0], channels[1], channels[2], alpha);
+    Ok(Value::Color(color.with_format(ColorFormat::RgbFunction)))
+}
+
+/// A color with a different alpha channel, as in `rgba(#fff, 0.5)`.
+fn make_rgb_two_args(expand: &Expand, name: &str, args: &Args) -> FnResult<Value> {
+    // rgba(var(--foo), 0.5) is valid CSS, because --foo might be
+    // `123, 456, 789` and functions are parsed after the substitution.
+    if is_var(args.get(0)) {
+        return function_string(expand, name, args.values(), args);
+    }
+    if is_var(args.get(1)) || is_special_number(args.get(1)) {
+        if let Value::Color(ref color) = *args.get(0) {
+            let alpha = expand.to_css_string(args.get(1), &args.pstate)?;
+            return Ok(Value::unquoted_string(format!("{}({}, {}, {}, {})", name, color.red(), color.green(),
+                                                     color.blue(), alpha)));
+        }
+        if is_var(args.get(1)) {
+            return function_string(expand, name, args.values(), args);
+        }
+    }
+
+    let color = args.color(0)?;
+    let alpha = percentage_or_unitless(args.number(1)?, 1.0, "alpha", args.precision())?;
+    Ok(Value::Color(color.with_alpha(alpha)))
+}
+
+fn number_value<'a>(value: &'a Value, name: &str, precision: u8) -> FnResult<&'a Number> {
+    match *value {
+        Value::Number(ref n) => Ok(n),
+        _ => Err(format!("${}: {} is not a number.", name, inspect_value(value, precision)).into()),
+    }
+}
+
+fn red(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::number(f64::from(args.color(0)?.red())))
+}
+
+fn green(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::number(f64::from(args.color(0)?.green())))
+}
+
+fn blue(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::number(f64::from(args.color(0)?.blue())))
+}
+
+fn mix(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    mix_colors(args.color(0)?, args.color(1)?, args.number(2)?, args.precision())
+}
+
+/// Inverts a color, mixed with the original by `$weight`. A number is
+/// passed on to the plain CSS filter function.
+fn invert(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    let weight = args.number(1)?;
+    if let Value::Number(_) = *args.get(0) {
+        if weight.value != 100.0 || !weight.has_unit("%") {
+            return Err("Only one argument may be passed to the plain-CSS invert() function.".into());
+        }
+        return function_string(expand, "invert", &args.values()[..1], args);
+    }
+
+    let color = args.color(0)?;
+    let inverse = Color::rgba(255.0 - f64::from(color.red()), 255.0 - f64::from(color.green()),
+                              255.0 - f64::from(color.blue()), color.alpha());
+    mix_colors(&inverse, color, weight, args.precision())
+}
+
+// ---------------------------------------------------------------------------------
+// HSL
+
+fn hsl(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    make_hsl(expand, "hsl", args.values(), args)
+}
+
+fn hsla(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    make_hsl(expand, "hsla", args.values(), args)
+}
+
+fn hsl_two_args(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    make_hsl_two_args(expand, "hsl", args)
+}
+
+fn hsla_two_args(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    make_hsl_two_args(expand, "hsla", args)
+}
+
+fn hsl_channels(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    match parse_channels(expand, "hsl", &["hue", "saturation", "lightness"], args)? {
+        Channels::Css(css) => Ok(css),
+        Channels::Values(values) => make_hsl(expand, "hsl", &values, args),
+    }
+}
+
+fn hsla_channels(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    match parse_channels(expand, "hsla", &["hue", "saturation", "lightness"], args)? {
+        Channels::Css(css) => Ok(css),
+        Channels::Values(values) => make_hsl(expand, "hsla", &values, args),
+    }
+}
+
+/// A color from hue, saturation and lightness and an optional alpha.
+fn make_hsl(expand: &Expand, name: &str, values: &[Value], args: &Args) -> FnResult<Value> {
+    if values.iter().any(is_special_number) {
+        return function_string(expand, name, values, args);
+    }
+
+    let precision = args.precision();
+    let hue = angle_value(number_value(&values[0], "hue", precision)?);
+    let saturation = number_value(&values[1], "saturation", precision)?;
+    let lightness = number_value(&values[2], "lightness", precision)?;
+    let alpha = match values.get(3) {
+        Some(alpha) => percentage_or_unitless(number_value(alpha, "alpha", precision)?, 1.0, "alpha", precision)?,
+        None => 1.0,
+    };
+    Ok(Value::Color(Color::hsla(hue, saturation.value.clamp(0.0, 100.0), lightness.value.clamp(0.0, 100.0), alpha)))
+}
+
+/// hsl() with two arguments is only valid CSS if one of them is a
+/// `var()`, which could supply the rest.
+fn make_hsl_two_args(expand: &Expand, name: &str, args: &Args) -> FnResult<Value> {
+    if is_var(args.get(0)) || is_var(args.get(1)) {
+        return function_string(expand, name, args.values(), args);
+    }
+    Err("Missing argument $lightness.".into())
+}
+
+fn hue(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::Number(Number::with_unit(args.color(0)?.hue(), "deg")))
+}
+
+fn saturation(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::Number(Number::with_unit(args.color(0)?.saturation(), "%")))
+}
+
+fn lightness(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::Number(Number::with_unit(args.color(0)?.lightness(), "%")))
+}
+
+fn complement(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let color = args.color(0)?;
+    let (hue, saturation, lightness) = color.hsl();
+    Ok(with_hsl(color, hue + 180.0, saturation, lightness))
+}
+
+/// Removes the saturation of a color. A number is passed on to the
+/// plain CSS filter function.
+fn grayscale(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    if let Value::Number(_) = *args.get(0) {
+        return function_string(expand, "grayscale", args.values(), args);
+    }
+    let color = args.color(0)?;
+    let (hue, _, lightness) = color.hsl();
+    Ok(with_hsl(color, hue, 0.0, lightness))
+}
+
+fn adjust_hue(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    let color = args.color(0)?;
+    let degrees = angle_value(args.number(1)?);
+    warn_color_function(expand, args, "adjust-hue", "hue", 1.0)?;
+    let (hue, saturation, lightness) = color.hsl();
+    Ok(with_hsl(color, hue + degrees, saturation, lightness))
+}
+
+fn lighten(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    adjust_lightness(expand, args, "lighten", 1.0)
+}
+
+fn darken(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    adjust_lightness(expand, args, "darken", -1.0)
+}
+
+fn adjust_lightness(expand: &mut Expand, args: &Args, name: &str, sign: f64) -> FnResult<Value> {
+    let color = args.color(0)?;
+    let amount = value_in_range(args.number(1)?, 0.0, 100.0, "amount", "%", args.precision())?;
+    warn_color_function(expand, args, name, "lightness", sign)?;
+    let (hue, saturation, lightness) = color.hsl();
+    Ok(with_hsl(color, hue, saturation, (lightness + sign * amount).clamp(0.0, 100.0)))
+}
+
+/// saturate() with a single argument is the plain CSS filter function.
+fn saturate_css(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    args.number(0)?;
+    function_string(expand, "saturate", args.values(), args)
+}
+
+fn saturate(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    adjust_saturation(expand, args, "saturate", 1.0)
+}
+
+fn desaturate(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    adjust_saturation(expand, args, "desaturate", -1.0)
+}
+
+fn adjust_saturation(expand: &mut Expand, args: &Args, name: &str, sign: f64) -> FnResult<Value> {
+    let color = args.color(0)?;
+    let amount = value_in_range(args.number(1)?, 0.0, 100.0, "amount", "%", args.precision())?;
+    warn_color_function(expand, args, name, "saturation", sign)?;
+    let (hue, saturation, lightness) = color.hsl();
+    Ok(with_hsl(color, hue, (saturation + sign * amount).clamp(0.0, 100.0), lightness))
+}
+
+// ---------------------------------------------------------------------------------
+// HWB
+
+fn hwb(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    make_hwb(args.values(), args)
+}
+
+fn hwb_channels(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    match parse_channels(expand, "hwb", &["hue", "whiteness", "blackness"], args)? {
+        Channels::Css(css) => {
+            let css = expand.to_css_string(&css, &args.pstate)?;
+            Err(format!("Expected numeric channels, got \"{}\".", css).into())
+        }
+        Channels::Values(values) => make_hwb(&values, args),
+    }
+}
+
+/// A color from hue, whiteness and blackness and an optional alpha.
+fn make_hwb(values: &[Value], args: &Args) -> FnResult<Value> {
+    let precision = args.precision();
+    let hue = angle_value(number_value(&values[0], "hue", precision)?);
+    let whiteness = number_value(&values[1], "whiteness", precision)?;
+    let blackness = number_value(&values[2], "blackness", precision)?;
+    assert_percent(whiteness, "whiteness", precision)?;
+    assert_percent(blackness, "blackness", precision)?;
+    let whiteness = value_in_range(whiteness, 0.0, 100.0, "whiteness", "", precision)?;
+    let blackness = value_in_range(blackness, 0.0, 100.0, "blackness", "", precision)?;
+    let alpha = match values.get(3) {
+        Some(alpha) => percentage_or_unitless(number_value(alpha, "alpha", precision)?, 1.0, "alpha", precision)?,
+        None => 1.0,
+    };
+    Ok(Value::Color(Color::hwba(hue, whiteness, blackness, alpha)))
+}
+
+fn whiteness(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::Number(Number::with_unit(args.color(0)?.whiteness(), "%")))
+}
+
+fn blackness(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::Number(Number::with_unit(args.color(0)?.blackness(), "%")))
+}
+
+// ---------------------------------------------------------------------------------
+// Opacity
+
+fn opacify(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    adjust_alpha(expand, args, "opacify", 1.0)
+}
+
+fn fade_in(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    adjust_alpha(expand, args, "fade-in", 1.0)
+}
+
+fn transparentize(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    adjust_alpha(expand, args, "transparentize", -1.0)
+}
+
+fn fade_out(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    adjust_alpha(expand, args, "fade-out", -1.0)
+}
+
+fn adjust_alpha(expand: &mut Expand, args: &Args, name: &str, sign: f64) -> FnResult<Value> {
+    let color = args.color(0)?;
+    let amount = value_in_range(args.number(1)?, 0.0, 1.0, "amount", "", args.precision())?;
+    warn_color_function(expand, args, name, "alpha", sign)?;
+    Ok(Value::Color(color.with_alpha((color.alpha() + sign * amount).clamp(0.0, 1.0))))
+}
+
+/// Whether a value is an argument of the proprietary Microsoft alpha()
+/// filter, such as `opacity=50`.
+fn is_microsoft_filter(value: &Value) -> bool {
+    match *value {
+        Value::String(ref s) if !s.quoted => {
+            let name_len = s.text.chars().take_while(|c| c.is_ascii_alphabetic()).count();
+            name_len > 0 && s.text[name_len..].trim_start().starts_with('=')
+        }
+        _ => false,
+    }
+}
+
+fn alpha(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    if is_microsoft_filter(args.get(0)) {
+        return function_string(expand, "alpha", args.values(), args);
+    }
+    Ok(Value::number(args.color(0)?.alpha()))
+}
+
+/// alpha() with several arguments is only valid as the Microsoft filter.
+fn alpha_args(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    let items = &args.rest(0).items;
+    if !items.is_empty() && items.iter().all(is_microsoft_filter) {
+        return function_string(expand, "alpha", items, args);
+    }
+    if items.is_empty() {
+        return Err("Missing argument $color.".into());
+    }
+    Err(format!("Only 1 argument allowed, but {} were passed.", items.len()).into())
+}
+
+/// The alpha channel of a color. A number is passed on to the plain CSS
+/// filter function.
+fn opacity(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    if let Value::Number(_) = *args.get(0) {
+        return function_string(expand, "opacity", args.values(), args);
+    }
+    Ok(Value::number(args.color(0)?.alpha()))
+}
+
+// ---------------------------------------------------------------------------------
+// Miscellaneous
+
+/// The color in the `#AARRGGBB` format used by Internet Explorer filters.
+fn ie_hex_str(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let color = args.color(0)?;
+    let alpha = fuzzy_round(color.alpha() * 255.0) as u8;
+    Ok(Value::unquoted_string(format!("#{:02X}{:02X}{:02X}{:02X}", alpha, color.red(), color.green(), color.blue())))
+}
+
+fn adjust(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    update_components(args, Update::Adjust)
+}
+
+fn scale(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    update_components(args, Update::Scale)
+}
+
+fn change(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    update_components(args, Update::Change)
+}
+
+// How adjust(), scale() and change() update the channels they are given.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Update {
+    // by adding to them
+    Adjust,
+    // by moving them a percentage of the way to their maximum or minimum
+    Scale,
+    // by replacing them
+    Change,
+}
+
+// The keyword arguments of adjust(), scale() and change(), which are
+// removed as they are read.
+struct ChannelArgs {
+    keywords: Vec<(String, Value)>,
+    update: Update,
+    precision: u8,
+}
+
+impl ChannelArgs {
+    fn remove(&mut self, name: &str) -> Option<Value> {
+        let i = self.keywords.iter().position(|(n, _)| n == name)?;
+        Some(self.keywords.remove(i).1)
+    }
+
+    /// Reads a channel whose maximum is `max`, checking it's in range for
+    /// the update. Scale amounts are percentages, as are whiteness and
+    /// blackness; `unit` is shown in the range of other channels.
+    fn channel(&mut self, name: &str, max: f64, unit: &str, percent: bool) -> FnResult<Option<f64>> {
+        let value = match self.remove(name) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let number = number_value(&value, name, self.precision)?;
+        let scale = self.update == Update::Scale;
+        let min = |max: f64| if self.update == Update::Change { 0.0 } else { -max };
+        if scale || percent {
+            assert_percent(number, name, self.precision)?;
+            let max = if scale { 100.0 } else { max };
+            value_in_range(number, min(max), max, name, "", self.precision).map(Some)
+        } else {
+            value_in_range(number, min(max), max, name, unit, self.precision).map(Some)
+        }
+    }
+
+    /// The new value of a channel whose maximum is `max`.
+    fn update(&self, current: f64, param: Option<f64>, max: f64) -> f64 {
+        let param = match param {
+            Some(param) => param,
+            None => return current,
+        };
+        match self.update {
+            Update::Change => param,
+            Update::Adjust => (current + param).max(0.0).min(max),
+            Update::Scale => current + (if param > 0.0 { max - current } else { current }) * (param / 100.0),
+        }
+    }
+}
+
+/// Updates the channels of a color given as keyword arguments. Channels
+/// of different color models can't be mixed.
+fn update_components(args: &Args, update: Update) -> FnResult<Value> {
+    let color = args.color(0)?;
+    let rest = args.rest(1);
+    if !rest.items.is_empty() {
+        return Err("Only one positional argument is allowed. All other arguments must be passed by name.".into());
+    }
+
+    let keywords = rest.keywords.as_ref().map_or(vec![], |keywords| {
+        keywords.pairs().iter().filter_map(|(k, v)| match *k {
+            Value::String(ref s) => Some((s.text.clone(), v.clone())),
+            _ => None,
+        }).collect()
+    });
+    let mut channels = ChannelArgs { keywords, update, precision: args.precision() };
+
+    let alpha = channels.channel("alpha", 1.0, "", false)?;
+    let red = channels.channel("red", 255.0, "", false)?;
+    let green = channels.channel("green", 255.0, "", false)?;
+    let blue = channels.channel("blue", 255.0, "", false)?;
+    let hue = match update {
+        Update::Scale => None,
+        _ => match channels.remove("hue") {
+            Some(value) => Some(angle_value(number_value(&value, "hue", args.precision())?)),
+            None => None,
+        },
+    };
+    let saturation = channels.channel("saturation", 100.0, "%", false)?;
+    let lightness = channels.channel("lightness", 100.0, "%", false)?;
+    let whiteness = channels.channel("whiteness", 100.0, "", true)?;
+    let blackness = channels.channel("blackness", 100.0, "", true)?;
+
+    if !channels.keywords.is_empty() {
+        let names: Vec<String> = channels.keywords.iter().map(|(n, _)| format!("${}", n)).collect();
+        let (last, init) = names.split_last().unwrap();
+        let list = if init.is_empty() { last.clone() } else { format!("{} or {}", init.join(", "), last) };
+        let plural = if names.len() == 1 { "argument" } else { "arguments" };
+        return Err(format!("No {} named {}.", plural, list).into());
+    }
+
+    let has_rgb = red.is_some() || green.is_some() || blue.is_some();
+    let has_sl = saturation.is_some() || lightness.is_some();
+    let has_wb = whiteness.is_some() || blackness.is_some();
+    if has_rgb && (has_sl || has_wb || hue.is_some()) {
+        return Err(format!("RGB parameters may not be passed along with {} parameters.",
+                           if has_wb { "HWB" } else { "HSL" }).into());
+    }
+    if has_sl && has_wb {
+        return Err("HSL parameters may not be passed along with HWB parameters.".into());
+    }
+
+    let alpha = channels.update(color.alpha(), alpha, 1.0);
+    let new_hue = |current: f64| match (update, hue) {
+        (Update::Change, Some(hue)) => hue,
+        (_, hue) => current + hue.unwrap_or(0.0),
+    };
+
+    let color = if has_rgb {
+        let channel = |current: u8, param| fuzzy_round(channels.update(f64::from(current), param, 255.0));
+        Color::rgba(channel(color.red(), red), channel(color.green(), green), channel(color.blue(), blue), alpha)
+    } else if has_wb {
+        Color::hwba(new_hue(color.hue()),
+                    channels.update(color.whiteness(), whiteness, 100.0),
+                    channels.update(color.blackness(), blackness, 100.0),
+                    alpha)
+    } else if hue.is_some() || has_sl {
+        Color::hsla(new_hue(color.hue()),
+                    channels.update(color.saturation(), saturation, 100.0),
+                    channels.update(color.lightness(), lightness, 100.0),
+                    alpha)
+    } else {
+        color.with_alpha(alpha)
+    };
+    Ok(Value::Color(color))
+}
+
+/// The error for the functions of the global scope that `sass:color`
+/// leaves out, which recommends the adjust() call to use instead.
+fn removed(args: &Args, name: &str, channel: &str, negative: bool) -> FnResult<Value> {
+    let precision = args.precision();
+    Err(format!("The function {}() isn't in the sass:color module.\n\n\
+                 Recommendation: color.adjust({}, ${}: {}{})\n\n\
+                 More info: https://sass-lang.com/documentation/functions/color#{}",
+                name, inspect_value(args.get(0), precision), channel, if negative { "-" } else { "" },
+                inspect_value(args.get(1), precision), name).into())
+}
+
+fn removed_adjust_hue(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    removed(args, "adjust-hue", "hue", false)
+}
+
+fn removed_lighten(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    removed(args, "lighten", "lightness", false)
+}
+
+fn removed_darken(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    removed(args, "darken", "lightness", true)
+}
+
+fn removed_saturate(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    removed(args, "saturate", "saturation", false)
+}
+
+fn removed_desaturate(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    removed(args, "desaturate", "saturation", true)
+}
+
+fn removed_opacify(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    removed(args, "opacify", "alpha", false)
+}
+
+fn removed_fade_in(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    removed(args, "fade-in", "alpha", false)
+}
+
+fn removed_transparentize(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    removed(args, "transparentize", "alpha", true)
+}
+
+fn removed_fade_out(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    removed(args, "fade-out", "alpha", true)
+}
+
+// ---------------------------------------------------------------------------------
+// Channel lists
+
+// The result of reading the channels of `rgb()`, `hsl()` or `hwb()` from
+// a single space-separated list, as in `rgb(0 0 0 / 50%)`.
+enum Channels {
+    // the call can only be plain CSS, because of a var() in the channels
+    Css(Value),
+    // the channels, followed by the alpha if there is one
+    Values(Vec<Value>),
+}
+
+fn parse_channels(expand: &Expand, name: &str, channel_names: &[&str], args: &Args) -> FnResult<Channels> {
+    let original = args.get(0);
+    if is_var(original) {
+        return Ok(Channels::Css(function_string(expand, name, args.values(), args)?));
+    }
+
+    let mut channels = original.clone();
+    let mut alpha = None;
+    if original.separator() == ListSeparator::Slash {
+        let list = original.as_list();
+        if list.len() != 2 {
+            return Err(format!("Only 2 slash-separated elements allowed, but {} {} passed.",
+                               list.len(), if list.len() == 1 { "was" } else { "were" }).into());
+        }
+        if !is_special_number(&list[1]) {
+            number_value(&list[1], "alpha", args.precision())?;
+        }
+        if is_var(&list[0]) {
+            return Ok(Channels::Css(function_string(expand, name, args.values(), args)?));
+        }
+        channels = list[0].clone();
+        alpha = Some(list[1].clone());
+    }
+
+    let comma = channels.separator() == ListSeparator::Comma;
+    let bracketed = channels.is_bracketed();
+    if comma || bracketed {
+        let mut message = String::from("$channels must be");
+        if bracketed {
+            message.push_str(" an unbracketed");
+        }
+        if comma {
+            message.push_str(if bracketed { "," } else { " a" });
+            message.push_str(" space-separated");
+        }
+        message.push_str(" list.");
+        return Err(message.into());
+    }
+
+    let mut list = channels.as_list();
+    if list.len() > 3 {
+        return Err(format!("Only 3 elements allowed, but {} were passed.", list.len()).into());
+    }
+    if list.len() < 3 {
+        let var_slash = list.last().map_or(false, |last| match *last {
+            Value::String(ref s) => is_var(last) && s.text.contains('/'),
+            _ => false,
+        });
+        if list.iter().any(is_var) || var_slash {
+            return Ok(Channels::Css(function_string(expand, name, args.values(), args)?));
+        }
+        return Err(format!("Missing element ${}.", channel_names[list.len()]).into());
+    }
+
+    if let Some(alpha) = alpha {
+        list.push(alpha);
+        return Ok(Channels::Values(list));
+    }
+    // The last channel and the alpha may be one number written with a
+    // slash, as in `0 0 0/0.5`, or a slash separated list.
+    let split = match list[2] {
+        Value::Number(ref n) => n.as_slash.as_ref().map(|slash| (Value::Number(slash.0.clone()), Value::Number(slash.1.clone()))),
+        Value::List(ref l) if l.separator == ListSeparator::Slash && !l.bracketed && l.items.len() == 2 => {
+            Some((l.items[0].clone(), l.items[1].clone()))
+        }
+        _ => None,
+    };
+    if let Some((channel, alpha)) = split {
+        list[2] = channel;
+        list.push(alpha);
+        return Ok(Channels::Values(list));
+    }
+    // A slash in an unquoted string, such as from interpolation, can't be
+    // taken apart into the last channel and the alpha.
+    if let Value::String(ref s) = list[2] {
+        if !s.quoted && s.text.contains('/') {
+            return Ok(Channels::Css(function_string(expand, name, &[channels.clone()], args)?));
+        }
+    }
+    Ok(Channels::Values(list))
+}
@@ -0,0 +1,156 @@
+// FROM: src/fn_lists.cpp
+
+use environment::Environment;
+use expand::Expand;
+use fn_utils::{self, Args, FnResult};
+use inspect::inspect_value;
+use module::Module;
+use values::*;
+
+pub fn register(env: &mut Environment) {
+    fn_utils::register(env, &[
+        ("length($list)", length),
+        ("nth($list, $n)", nth),
+        ("set-nth($list, $n, $value)", set_nth),
+        ("join($list1, $list2, $separator: auto, $bracketed: auto)", join),
+        ("append($list, $val, $separator: auto)", append),
+        ("zip($lists...)", zip),
+        ("index($list, $value)", index),
+        ("is-bracketed($list)", is_bracketed),
+        ("list-separator($list)", separator),
+    ]);
+}
+
+/// The `sass:list` module.
+pub fn module() -> Module {
+    let mut env = Environment::new();
+    fn_utils::register_members(&mut env, &[
+        ("length($list)", length),
+        ("nth($list, $n)", nth),
+        ("set-nth($list, $n, $value)", set_nth),
+        ("join($list1, $list2, $separator: auto, $bracketed: auto)", join),
+        ("append($list, $val, $separator: auto)", append),
+        ("zip($lists...)", zip),
+        ("index($list, $value)", index),
+        ("is-bracketed($list)", is_bracketed),
+        ("separator($list)", separator),
+        ("slash($elements...)", slash),
+    ]);
+    env.to_module("sass:list".to_string())
+}
+
+/// The index in a list's items of a 1-based Sass index, which counts
+/// from the end if it's negative.
+fn list_index(args: &Args, i: usize, len: usize) -> FnResult<usize> {
+    let index = args.int(i)?;
+    if index == 0 {
+        return Err(format!("${}: List index may not be 0.", args.name(i)).into());
+    }
+    if index.unsigned_abs() as usize > len {
+        return Err(format!("${}: Invalid index {} for a list with {} elements.",
+                           args.name(i), inspect_value(args.get(i), args.precision()), len).into());
+    }
+    Ok(if index < 0 { (len as i64 + index) as usize } else { index as usize - 1 })
+}
+
+/// Reads a `$separator` argument, where `auto` is None.
+fn separator_arg(args: &Args, i: usize) -> FnResult<Option<ListSeparator>> {
+    match args.string(i)?.text.as_str() {
+        "auto" => Ok(None),
+        "space" => Ok(Some(ListSeparator::Space)),
+        "comma" => Ok(Some(ListSeparator::Comma)),
+        "slash" => Ok(Some(ListSeparator::Slash)),
+        _ => Err(format!("${}: Must be \"space\", \"comma\", \"slash\", or \"auto\".", args.name(i)).into()),
+    }
+}
+
+fn length(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::number(args.get(0).as_list().len() as f64))
+}
+
+fn nth(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let mut items = args.get(0).as_list();
+    let i = list_index(args, 1, items.len())?;
+    Ok(items.swap_remove(i))
+}
+
+/// A copy of a list with one item replaced, keeping its separator and
+/// brackets.
+fn set_nth(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let list = args.get(0);
+    let mut items = list.as_list();
+    let i = list_index(args, 1, items.len())?;
+    items[i] = args.get(2).clone();
+    Ok(Value::List(List::new(items, list.separator(), list.is_bracketed())))
+}
+
+/// Joins two lists. With `auto`, the separator is the first one that
+/// either list has, and the brackets are those of the first list.
+fn join(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let list1 = args.get(0);
+    let list2 = args.get(1);
+    let separator = match separator_arg(args, 2)? {
+        Some(separator) => separator,
+        None if list1.separator() != ListSeparator::Undecided => list1.separator(),
+        None if list2.separator() != ListSeparator::Undecided => list2.separator(),
+        None => ListSeparator::Space,
+    };
+    let bracketed = match *args.get(3) {
+        Value::String(ref s) if s.text == "auto" => list1.is_bracketed(),
+        ref value => value.is_truthy(),
+    };
+
+    let mut items = list1.as_list();
+    items.extend(list2.as_list());
+    Ok(Value::List(List::new(items, separator, bracketed)))
+}
+
+fn append(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let list = args.get(0);
+    let separator = match separator_arg(args, 2)? {
+        Some(separator) => separator,
+        None if list.separator() == ListSeparator::Undecided => ListSeparator::Space,
+        None => list.separator(),
+    };
+
+    let mut items = list.as_list();
+    items.push(args.get(1).clone());
+    Ok(Value::List(List::new(items, separator, list.is_bracketed())))
+}
+
+/// Combines lists into a list of lists, the first of which has the first
+/// item of each, and so on. It is as long as the shortest list.
+fn zip(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let lists: Vec<Vec<Value>> = args.rest(0).items.iter().map(|list| list.as_list()).collect();
+    let len = lists.iter().map(|list| list.len()).min().unwrap_or(0);
+    let zipped = (0..len).map(|i| {
+        let items = lists.iter().map(|list| list[i].clone()).collect();
+        Value::List(List::new(items, ListSeparator::Space, false))
+    }).collect();
+    Ok(Value::List(List::new(zipped, ListSeparator::Comma, false)))
+}
+
+/// The 1-based index of the first item equal to a value, or null.
+fn index(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    match args.get(0).as_list().iter().position(|item| item == args.get(1)) {
+        Some(i) => Ok(Value::number((i + 1) as f64)),
+        None => Ok(Value::Null),
+    }
+}
+
+fn is_bracketed(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::Boolean(args.get(0).is_bracketed()))
+}
+
+fn separator(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::unquoted_string(args.get(0).separator().name()))
+}
+
+/// A slash-separated list of the arguments.
+fn slash(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let items = args.rest(0).items.clone();
+    if items.len() < 2 {
+        return Err("At least two elements are required.".into());
+    }
+    Ok(Value::List(List::new(items, ListSeparator::Slash, false)))
+}
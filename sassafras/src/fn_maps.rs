@@ -0,0 +1,218 @@
+// FROM: src/fn_maps.cpp
+
+use environment::Environment;
+use expand::Expand;
+use fn_utils::{self, Args, FnResult};
+use inspect::inspect_value;
+use module::Module;
+use values::*;
+
+pub fn register(env: &mut Environment) {
+    fn_utils::register(env, &[
+        ("map-get($map, $key, $keys...)", get),
+        ("map-merge($map1, $map2)", merge),
+        ("map-merge($map1, $args...)", merge_nested),
+        ("map-remove($map)", remove_nothing),
+        ("map-remove($map, $key, $keys...)", remove),
+        ("map-keys($map)", keys),
+        ("map-values($map)", values),
+        ("map-has-key($map, $key, $keys...)", has_key),
+    ]);
+}
+
+/// The `sass:map` module.
+pub fn module() -> Module {
+    let mut env = Environment::new();
+    fn_utils::register_members(&mut env, &[
+        ("get($map, $key, $keys...)", get),
+        ("set($map, $key, $value)", set),
+        ("set($map, $args...)", set_nested),
+        ("merge($map1, $map2)", merge),
+        ("merge($map1, $args...)", merge_nested),
+        ("remove($map)", remove_nothing),
+        ("remove($map, $key, $keys...)", remove),
+        ("keys($map)", keys),
+        ("values($map)", values),
+        ("has-key($map, $key, $keys...)", has_key),
+        ("deep-merge($map1, $map2)", deep_merge),
+        ("deep-remove($map, $key, $keys...)", deep_remove),
+    ]);
+    env.to_module("sass:map".to_string())
+}
+
+/// The `$key` argument followed by the `$keys...`.
+fn key_path(args: &Args) -> Vec<Value> {
+    let mut keys = vec![args.get(1).clone()];
+    keys.extend(args.rest(2).items.iter().cloned());
+    keys
+}
+
+/// The map nested in `map` by all but the last key, if there is one.
+fn nested_map(map: SassMap, keys: &[Value]) -> Option<SassMap> {
+    let mut map = map;
+    for key in keys {
+        map = map.get(key)?.try_map()?;
+    }
+    Some(map)
+}
+
+/// Replaces the value at a path of keys in nested maps with the result
+/// of `f`, which is passed the old value or null. With `add_nesting`,
+/// maps are created for keys on the path that have none; otherwise the
+/// map is returned unchanged. With no keys, `f` gets the map itself.
+fn modify<F>(map: SassMap, keys: &[Value], add_nesting: bool, f: &F) -> Value
+    where F: Fn(Value) -> Value
+{
+    if keys.is_empty() {
+        return f(Value::Map(map));
+    }
+    Value::Map(modify_nested(map, keys, add_nesting, f))
+}
+
+fn modify_nested<F>(mut map: SassMap, keys: &[Value], add_nesting: bool, f: &F) -> SassMap
+    where F: Fn(Value) -> Value
+{
+    let key = &keys[0];
+    if keys.len() == 1 {
+        let old = map.get(key).cloned().unwrap_or(Value::Null);
+        map.insert(key.clone(), f(old));
+        return map;
+    }
+
+    let nested = map.get(key).and_then(|value| value.try_map());
+    if nested.is_none() && !add_nesting {
+        return map;
+    }
+    let nested = modify_nested(nested.unwrap_or_default(), &keys[1..], add_nesting, f);
+    map.insert(key.clone(), Value::Map(nested));
+    map
+}
+
+/// Merges two maps. Values in the second replace those in the first,
+/// keeping their positions.
+fn merge_maps(mut map1: SassMap, map2: &SassMap) -> SassMap {
+    for (key, value) in map2.pairs() {
+        map1.insert(key.clone(), value.clone());
+    }
+    map1
+}
+
+/// Merges two maps, merging the values that are maps in both rather
+/// than replacing them.
+fn deep_merge_maps(map1: SassMap, map2: SassMap) -> SassMap {
+    if map1.is_empty() {
+        return map2;
+    }
+    if map2.is_empty() {
+        return map1;
+    }
+
+    let mut result = map1;
+    for (key, value) in map2.pairs() {
+        let merged = match (result.get(key).and_then(|v| v.try_map()), value.try_map()) {
+            (Some(existing), Some(map)) => Value::Map(deep_merge_maps(existing, map)),
+            _ => value.clone(),
+        };
+        result.insert(key.clone(), merged);
+    }
+    result
+}
+
+/// The value at a path of keys in nested maps, or null.
+fn get(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let keys = key_path(args);
+    let (last, path) = keys.split_last().unwrap();
+    Ok(nested_map(args.map(0)?, path).and_then(|map| map.get(last).cloned()).unwrap_or(Value::Null))
+}
+
+fn has_key(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let keys = key_path(args);
+    let (last, path) = keys.split_last().unwrap();
+    Ok(Value::Boolean(nested_map(args.map(0)?, path).is_some_and(|map| map.contains_key(last))))
+}
+
+fn set(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let value = args.get(2).clone();
+    Ok(modify(args.map(0)?, &[args.get(1).clone()], true, &|_| value.clone()))
+}
+
+/// Sets the value at a path of keys, creating the maps on the path if
+/// needed. The last argument is the value.
+fn set_nested(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let map = args.map(0)?;
+    let rest = &args.rest(1).items;
+    match rest.len() {
+        0 => Err("Expected $args to contain a key.".into()),
+        1 => Err("Expected $args to contain a value.".into()),
+        _ => {
+            let (value, keys) = rest.split_last().unwrap();
+            Ok(modify(map, keys, true, &|_| value.clone()))
+        }
+    }
+}
+
+fn merge(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::Map(merge_maps(args.map(0)?, &args.map(1)?)))
+}
+
+/// Merges a map into the one at a path of keys. The last argument is
+/// the map to merge.
+fn merge_nested(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let map1 = args.map(0)?;
+    let rest = &args.rest(1).items;
+    match rest.len() {
+        0 => return Err("Expected $args to contain a key.".into()),
+        1 => return Err("Expected $args to contain a map.".into()),
+        _ => {}
+    }
+    let (last, keys) = rest.split_last().unwrap();
+    let map2 = last.try_map().ok_or_else(|| {
+        format!("$map2: {} is not a map.", inspect_value(last, args.precision()))
+    })?;
+    Ok(modify(map1, keys, true, &|old| match old.try_map() {
+        Some(nested) => Value::Map(merge_maps(nested, &map2)),
+        None => Value::Map(map2.clone()),
+    }))
+}
+
+fn deep_merge(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::Map(deep_merge_maps(args.map(0)?, args.map(1)?)))
+}
+
+/// Removes the key at the end of a path from the map nested there. Maps
+/// that don't have the path are returned unchanged.
+fn deep_remove(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let keys = key_path(args);
+    let (last, path) = keys.split_last().unwrap();
+    Ok(modify(args.map(0)?, path, false, &|value| match value.try_map() {
+        Some(mut nested) => {
+            if nested.remove(last).is_some() {
+                Value::Map(nested)
+            } else {
+                value
+            }
+        }
+        None => value,
+    }))
+}
+
+/// map.remove() with no keys, which returns the map.
+fn remove_nothing(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::Map(args.map(0)?))
+}
+
+fn remove(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let mut map = args.map(0)?;
+    for key in key_path(args) {
+        map.remove(&key);
+    }
+    Ok(Value::Map(map))
+}
+
+fn keys(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::List(List::new(args.map(0)?.keys(), ListSeparator::Comma, false)))
+}
+
+fn values(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::List(List::new(args.map(0)?.values(), ListSeparator::Comma, false)))
+}
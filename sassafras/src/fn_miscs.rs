@@ -0,0 +1,232 @@
+// FROM: src/fn_miscs.cpp
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use ast::Callable;
+use bind::EvaluatedArgs;
+use deprecation::Deprecation;
+use environment::Environment;
+use expand::Expand;
+use fn_utils::{self, Args, FnResult};
+use inspect::inspect_value;
+use module::{ConfiguredValue, Module};
+use parser::normalize_name;
+use values::*;
+
+// The language features feature-exists() reports.
+const FEATURES: &[&str] = &[
+    "global-variable-shadowing", "extend-selector-pseudoclass", "units-level-3", "at-error", "custom-property",
+];
+
+pub fn register(env: &mut Environment) {
+    fn_utils::register(env, &[
+        ("feature-exists($feature)", feature_exists),
+        ("inspect($value)", inspect),
+        ("type-of($value)", type_of),
+        ("keywords($args)", keywords),
+        ("variable-exists($name)", variable_exists),
+        ("global-variable-exists($name, $module: null)", global_variable_exists),
+        ("function-exists($name, $module: null)", function_exists),
+        ("mixin-exists($name, $module: null)", mixin_exists),
+        ("content-exists()", content_exists),
+        ("get-function($name, $css: false, $module: null)", get_function),
+        ("call($function, $args...)", call),
+    ]);
+}
+
+/// The `sass:meta` module.
+pub fn module() -> Module {
+    let mut env = Environment::new();
+    fn_utils::register_members(&mut env, &[
+        ("feature-exists($feature)", feature_exists),
+        ("inspect($value)", inspect),
+        ("type-of($value)", type_of),
+        ("keywords($args)", keywords),
+        ("variable-exists($name)", variable_exists),
+        ("global-variable-exists($name, $module: null)", global_variable_exists),
+        ("function-exists($name, $module: null)", function_exists),
+        ("mixin-exists($name, $module: null)", mixin_exists),
+        ("content-exists()", content_exists),
+        ("module-variables($module)", module_variables),
+        ("module-functions($module)", module_functions),
+        ("get-function($name, $css: false, $module: null)", get_function),
+        ("call($function, $args...)", call),
+    ]);
+    fn_utils::register_mixins(&mut env, &[
+        ("load-css($url, $with: null)", load_css),
+    ]);
+    env.to_module("sass:meta".to_string())
+}
+
+/// The module named by a `$module` argument, or None if it is null.
+fn module_arg(expand: &Expand, args: &Args, i: usize) -> FnResult<Option<Rc<Module>>> {
+    if args.get(i).is_null() {
+        return Ok(None);
+    }
+    let namespace = &args.string(i)?.text;
+    Ok(Some(expand.get_module(namespace, &args.pstate)?))
+}
+
+fn feature_exists(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    expand.warn_deprecation(Deprecation::FeatureExists, "The feature-exists() function is deprecated.", &args.pstate)?;
+    Ok(Value::Boolean(FEATURES.contains(&args.string(0)?.text.as_str())))
+}
+
+/// The value as it would be written in Sass, as an unquoted string.
+fn inspect(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::unquoted_string(inspect_value(args.get(0), args.precision())))
+}
+
+fn type_of(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let name = match *args.get(0) {
+        Value::Null => "null",
+        Value::Boolean(_) => "bool",
+        Value::Number(_) => "number",
+        Value::Color(_) => "color",
+        Value::String(_) => "string",
+        Value::List(ref list) if list.keywords.is_some() => "arglist",
+        Value::List(_) => "list",
+        Value::Map(_) => "map",
+        Value::Function(_) => "function",
+        Value::Calculation(_) => "calculation",
+    };
+    Ok(Value::unquoted_string(name))
+}
+
+/// The keyword arguments passed to a rest parameter, by name without
+/// the `$`.
+fn keywords(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    match *args.get(0) {
+        Value::List(List { keywords: Some(ref keywords), .. }) => Ok(Value::Map(keywords.clone())),
+        _ => Err(args.type_error(0, "an argument list")),
+    }
+}
+
+fn variable_exists(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    let name = normalize_name(&args.string(0)?.text);
+    Ok(Value::Boolean(expand.env.variable_exists(&name)))
+}
+
+fn global_variable_exists(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    let name = normalize_name(&args.string(0)?.text);
+    Ok(Value::Boolean(match module_arg(expand, args, 1)? {
+        Some(module) => module.get_variable(&name).is_some(),
+        None => expand.env.global_variable_exists(&name),
+    }))
+}
+
+fn function_exists(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    let name = normalize_name(&args.string(0)?.text);
+    Ok(Value::Boolean(match module_arg(expand, args, 1)? {
+        Some(module) => module.get_function(&name).is_some(),
+        None => expand.env.function_exists(&name),
+    }))
+}
+
+fn mixin_exists(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    let name = normalize_name(&args.string(0)?.text);
+    Ok(Value::Boolean(match module_arg(expand, args, 1)? {
+        Some(module) => module.get_mixin(&name).is_some(),
+        None => expand.env.mixin_exists(&name),
+    }))
+}
+
+/// Whether the current mixin was passed a content block.
+fn content_exists(expand: &mut Expand, _: &Args) -> FnResult<Value> {
+    match expand.content_exists() {
+        Some(exists) => Ok(Value::Boolean(exists)),
+        None => Err("content-exists() may only be called within a mixin.".into()),
+    }
+}
+
+/// The public variables of a module, as a map from their names without
+/// the `$` to their values.
+fn module_variables(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    let module = expand.get_module(&args.string(0)?.text, &args.pstate)?;
+    let mut map = SassMap::new();
+    for (name, value) in module.variables() {
+        map.insert(Value::quoted_string(name), value);
+    }
+    Ok(Value::Map(map))
+}
+
+/// The public functions of a module, as a map from their names to
+/// function values.
+fn module_functions(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    let module = expand.get_module(&args.string(0)?.text, &args.pstate)?;
+    let mut map = SassMap::new();
+    for (name, function) in module.functions() {
+        map.insert(Value::quoted_string(name), Value::Function(function));
+    }
+    Ok(Value::Map(map))
+}
+
+fn get_function(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    let name = args.string(0)?.text.clone();
+    let module = module_arg(expand, args, 2)?;
+    if args.get(1).is_truthy() {
+        if module.is_some() {
+            return Err("$css and $module may not both be passed at once.".into());
+        }
+        return Ok(Value::Function(Callable::Plain(name)));
+    }
+    let function = match module {
+        Some(module) => module.get_function(&normalize_name(&name)),
+        None => expand.env.get_function(&normalize_name(&name)),
+    };
+    match function {
+        Some(function) => Ok(Value::Function(function)),
+        None => Err(format!("Function not found: {}", name).into()),
+    }
+}
+
+/// Calls a function value with the remaining arguments. A function name
+/// is also accepted, and names no function is declared for are called
+/// as plain CSS functions.
+fn call(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    let function = match *args.get(0) {
+        Value::Function(ref function) => function.clone(),
+        Value::String(ref s) => {
+            let message = format!("Passing a string to call() is deprecated and will be illegal in Dart Sass 2.0.0.\n\n\
+                                   Recommendation: call(get-function({}))", inspect_value(args.get(0), args.precision()));
+            expand.warn_deprecation(Deprecation::CallString, message, &args.pstate)?;
+            expand.env.get_function(&normalize_name(&s.text)).unwrap_or_else(|| Callable::Plain(s.text.clone()))
+        }
+        _ => return Err(args.type_error(0, "a function reference")),
+    };
+
+    let rest = args.rest(1);
+    let named = rest.keywords.as_ref().map_or(vec![], |keywords| {
+        keywords.pairs().iter().filter_map(|(k, v)| match *k {
+            Value::String(ref s) => Some((s.text.clone(), v.clone())),
+            _ => None,
+        }).collect()
+    });
+    let call_args = EvaluatedArgs { positional: rest.items.clone(), named, separator: Some(rest.separator) };
+    let name = function.name().to_string();
+    Ok(expand.call_function(&function, &name, call_args, &args.pstate)?)
+}
+
+/// Adds the CSS of the module at `$url` at the current position,
+/// configured by the variables in `$with`.
+fn load_css(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    let url = args.string(0)?.text.clone();
+    let explicit = !args.get(1).is_null();
+    let mut configuration = HashMap::new();
+    if explicit {
+        let with = args.map(1)?;
+        for (key, value) in with.pairs() {
+            let name = match *key {
+                Value::String(ref s) => normalize_name(&s.text),
+                _ => return Err(format!("$with key: {} is not a string.", inspect_value(key, args.precision())).into()),
+            };
+            if configuration.contains_key(&name) {
+                return Err(format!("The variable ${} was configured twice.", name).into());
+            }
+            configuration.insert(name, ConfiguredValue { value: value.clone(), pstate: args.pstate.clone() });
+        }
+    }
+    expand.load_css(&url, Rc::new(RefCell::new(configuration)), explicit, &args.pstate)?;
+    Ok(Value::Null)
+}
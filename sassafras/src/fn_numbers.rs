@@ -0,0 +1,341 @@
+// FROM: src/fn_numbers.cpp
+
+use std::cell::Cell;
+use std::f64;
+use std::f64::consts;
+use environment::Environment;
+use expand::Expand;
+use fn_utils::{self, Args, FnResult};
+use inspect::inspect_value;
+use module::Module;
+use operators::{self, BinaryOperator};
+use units::{conversion_factor, unit_class, UnitClass};
+use values::*;
+
+pub fn register(env: &mut Environment) {
+    fn_utils::register(env, &[
+        ("percentage($number)", percentage),
+        ("round($number)", round),
+        ("ceil($number)", ceil),
+        ("floor($number)", floor),
+        ("abs($number)", abs),
+        ("min($numbers...)", min),
+        ("max($numbers...)", max),
+        ("random($limit: null)", random),
+        ("unit($number)", unit),
+        ("unitless($number)", is_unitless),
+        ("comparable($number1, $number2)", compatible),
+    ]);
+}
+
+/// The `sass:math` module. Its `$epsilon` depends on the precision.
+pub fn module(precision: u8) -> Module {
+    let mut env = Environment::new();
+    fn_utils::register_members(&mut env, &[
+        ("div($number1, $number2)", div),
+        ("percentage($number)", percentage),
+        ("round($number)", round),
+        ("ceil($number)", ceil),
+        ("floor($number)", floor),
+        ("abs($number)", abs),
+        ("min($numbers...)", min),
+        ("max($numbers...)", max),
+        ("clamp($min, $number, $max)", clamp),
+        ("sqrt($number)", sqrt),
+        ("pow($base, $exponent)", pow),
+        ("log($number, $base: null)", log),
+        ("hypot($numbers...)", hypot),
+        ("sin($number)", sin),
+        ("cos($number)", cos),
+        ("tan($number)", tan),
+        ("asin($number)", asin),
+        ("acos($number)", acos),
+        ("atan($number)", atan),
+        ("atan2($y, $x)", atan2),
+        ("compatible($number1, $number2)", compatible),
+        ("is-unitless($number)", is_unitless),
+        ("unit($number)", unit),
+        ("random($limit: null)", random),
+    ]);
+
+    let variables = [
+        ("e", consts::E),
+        ("pi", consts::PI),
+        ("epsilon", epsilon(precision)),
+        ("max-safe-integer", 9_007_199_254_740_991.0),
+        ("min-safe-integer", -9_007_199_254_740_991.0),
+        ("max-number", f64::MAX),
+        ("min-number", 5e-324),
+    ];
+    for &(name, value) in &variables {
+        env.set_global_variable(name, Value::number(value));
+    }
+    env.to_module("sass:math".to_string())
+}
+
+/// The difference below which two numbers are equal when written with
+/// the given precision.
+fn epsilon(precision: u8) -> f64 {
+    10f64.powi(-i32::from(precision) - 1)
+}
+
+fn show(number: &Number, precision: u8) -> String {
+    inspect_value(&Value::Number(number.clone()), precision)
+}
+
+/// Makes a number with the units of another.
+fn with_units_of(value: f64, number: &Number) -> Value {
+    Value::Number(Number::with_units(value, number.numerators.clone(), number.denominators.clone()))
+}
+
+/// The value of a number converted to the units of another. Unitless
+/// numbers only match unitless numbers.
+fn convert_to_match(number: &Number, name: &str, other: &Number, other_name: &str, precision: u8) -> FnResult<f64> {
+    number.convert_value(&other.numerators, &other.denominators).ok_or_else(|| {
+        let mut message = format!("${}: {} and ${}: {} have incompatible units",
+                                  name, show(number, precision), other_name, show(other, precision));
+        if number.is_unitless() != other.is_unitless() {
+            message.push_str(" (one has units and the other doesn't)");
+        }
+        message.push('.');
+        message.into()
+    })
+}
+
+/// The value of an angle argument in radians. Unitless numbers are
+/// taken to be radians already.
+fn radians(args: &Args, i: usize) -> FnResult<f64> {
+    let number = args.number(i)?;
+    if number.is_unitless() {
+        return Ok(number.value);
+    }
+    let unit = number.unit();
+    if number.has_valid_css_units() && unit_class(&unit) == UnitClass::Angle {
+        if let Some(factor) = conversion_factor(&unit, "rad") {
+            return Ok(number.value * factor);
+        }
+    }
+    Err(format!("${}: Expected {} to have an angle unit (deg, grad, rad, turn).",
+                args.name(i), show(number, args.precision())).into())
+}
+
+fn degrees(radians: f64) -> Value {
+    Value::Number(Number::with_unit(radians.to_degrees(), "deg"))
+}
+
+fn percentage(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let value = args.unitless(0)?;
+    Ok(Value::Number(Number::with_unit(value * 100.0, "%")))
+}
+
+/// Rounds `.5` away from zero, treating numbers within the precision
+/// of `.5` as `.5`.
+fn round_value(value: f64, precision: u8) -> f64 {
+    let epsilon = epsilon(precision);
+    if value > 0.0 {
+        if value % 1.0 >= 0.5 - epsilon { value.ceil() } else { value.floor() }
+    } else if value % 1.0 <= -0.5 + epsilon {
+        value.floor()
+    } else {
+        value.ceil()
+    }
+}
+
+fn round(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let number = args.number(0)?;
+    Ok(with_units_of(round_value(number.value, args.precision()), number))
+}
+
+fn ceil(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let number = args.number(0)?;
+    Ok(with_units_of(number.value.ceil(), number))
+}
+
+fn floor(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let number = args.number(0)?;
+    Ok(with_units_of(number.value.floor(), number))
+}
+
+fn abs(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let number = args.number(0)?;
+    Ok(with_units_of(number.value.abs(), number))
+}
+
+fn min(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    extremum(args, false)
+}
+
+fn max(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    extremum(args, true)
+}
+
+/// The smallest or largest of the numbers passed, keeping its units.
+fn extremum(args: &Args, largest: bool) -> FnResult<Value> {
+    let precision = args.precision();
+    let mut result: Option<&Number> = None;
+    for value in &args.rest(0).items {
+        let number = match *value {
+            Value::Number(ref n) => n,
+            _ => return Err(format!("{} is not a number.", inspect_value(value, precision)).into()),
+        };
+        let replace = match result {
+            None => true,
+            Some(current) => {
+                let value = number.coerce_value(&current.numerators, &current.denominators).ok_or_else(|| {
+                    format!("Incompatible units {} and {}.", number.unit(), current.unit())
+                })?;
+                let difference = if largest { value - current.value } else { current.value - value };
+                difference > epsilon(precision)
+            }
+        };
+        if replace {
+            result = Some(number);
+        }
+    }
+    match result {
+        Some(number) => Ok(Value::Number(number.clone())),
+        None => Err("At least one argument must be passed.".into()),
+    }
+}
+
+fn clamp(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let precision = args.precision();
+    let min = args.number(0)?;
+    let number = args.number(1)?;
+    let max = args.number(2)?;
+    let value = convert_to_match(number, "number", min, "min", precision)?;
+    let max_value = convert_to_match(max, "max", min, "min", precision)?;
+
+    let epsilon = epsilon(precision);
+    let result = if min.value > max_value - epsilon || min.value > value - epsilon {
+        min
+    } else if value > max_value - epsilon {
+        max
+    } else {
+        number
+    };
+    Ok(Value::Number(result.clone()))
+}
+
+fn sqrt(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::number(args.unitless(0)?.sqrt()))
+}
+
+fn pow(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let base = args.unitless(0)?;
+    let exponent = args.unitless(1)?;
+    Ok(Value::number(base.powf(exponent)))
+}
+
+fn log(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let number = args.unitless(0)?;
+    if args.get(1).is_null() {
+        return Ok(Value::number(number.ln()));
+    }
+    let base = args.unitless(1)?;
+    Ok(Value::number(number.ln() / base.ln()))
+}
+
+/// The length of the vector the numbers are the components of, in the
+/// units of the first.
+fn hypot(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let precision = args.precision();
+    let mut numbers = Vec::new();
+    for value in &args.rest(0).items {
+        match *value {
+            Value::Number(ref n) => numbers.push(n),
+            _ => return Err(format!("{} is not a number.", inspect_value(value, precision)).into()),
+        }
+    }
+    let first = match numbers.first() {
+        Some(&first) => first,
+        None => return Err("At least one argument must be passed.".into()),
+    };
+
+    let mut sum = 0.0;
+    for (i, number) in numbers.iter().enumerate() {
+        let value = convert_to_match(number, &format!("numbers[{}]", i + 1), first, "numbers[1]", precision)?;
+        sum += value * value;
+    }
+    Ok(with_units_of(sum.sqrt(), first))
+}
+
+fn sin(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::number(radians(args, 0)?.sin()))
+}
+
+fn cos(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::number(radians(args, 0)?.cos()))
+}
+
+fn tan(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::number(radians(args, 0)?.tan()))
+}
+
+fn asin(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(degrees(args.unitless(0)?.asin()))
+}
+
+fn acos(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(degrees(args.unitless(0)?.acos()))
+}
+
+fn atan(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(degrees(args.unitless(0)?.atan()))
+}
+
+/// The angle of the point `($x, $y)`, which may have any units as long
+/// as they are compatible.
+fn atan2(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let y = args.number(0)?;
+    let x = args.number(1)?;
+    let x_value = convert_to_match(x, "x", y, "y", args.precision())?;
+    Ok(degrees(y.value.atan2(x_value)))
+}
+
+fn compatible(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let number1 = args.number(0)?;
+    let number2 = args.number(1)?;
+    Ok(Value::Boolean(number1.is_comparable_to(number2)))
+}
+
+fn is_unitless(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::Boolean(args.number(0)?.is_unitless()))
+}
+
+fn unit(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(Value::quoted_string(args.number(0)?.unit()))
+}
+
+fn div(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(operators::op(BinaryOperator::Div, args.get(0), args.get(1), args.precision())?)
+}
+
+/// A random number between 0 and 1, or with a `$limit`, a random
+/// integer between 1 and the limit.
+fn random(expand: &mut Expand, args: &Args) -> FnResult<Value> {
+    let random = next_random(&expand.ctx.random_state);
+    if args.get(0).is_null() {
+        return Ok(Value::number(random));
+    }
+
+    let limit = args.number(0)?;
+    let shown = show(limit, args.precision());
+    let limit = fuzzy_as_int(limit.value).ok_or_else(|| format!("$limit: {} is not an int.", shown))?;
+    if limit < 1 {
+        return Err(format!("$limit: Must be greater than 0, was {}.", shown).into());
+    }
+    Ok(Value::number((random * limit as f64).floor() + 1.0))
+}
+
+/// The next number in [0, 1) from the compilation's random sequence,
+/// which is determined by the seed it starts from (SplitMix64).
+pub fn next_random(state: &Cell<u64>) -> f64 {
+    let seed = state.get().wrapping_add(0x9E37_79B9_7F4A_7C15);
+    state.set(seed);
+    let mut z = seed;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
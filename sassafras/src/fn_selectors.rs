@@ -0,0 +1,211 @@
+// FROM: src/fn_selectors.cpp
+
+use environment::Environment;
+use expand::Expand;
+use extend::{ExtendMode, ExtensionStore};
+use fn_utils::{self, Args, FnResult};
+use inspect::inspect_value;
+use module::Module;
+use selector::*;
+use selector_ops::unify_complex;
+use values::*;
+
+pub fn register(env: &mut Environment) {
+    fn_utils::register(env, &[
+        ("selector-nest($selectors...)", nest),
+        ("selector-append($selectors...)", append),
+        ("selector-extend($selector, $extendee, $extender)", extend),
+        ("selector-replace($selector, $original, $replacement)", replace),
+        ("selector-unify($selector1, $selector2)", unify),
+        ("is-superselector($super, $sub)", is_superselector),
+        ("simple-selectors($selector)", simple_selectors),
+        ("selector-parse($selector)", parse),
+    ]);
+}
+
+/// The `sass:selector` module.
+pub fn module() -> Module {
+    let mut env = Environment::new();
+    fn_utils::register_members(&mut env, &[
+        ("nest($selectors...)", nest),
+        ("append($selectors...)", append),
+        ("extend($selector, $extendee, $extender)", extend),
+        ("replace($selector, $original, $replacement)", replace),
+        ("unify($selector1, $selector2)", unify),
+        ("is-superselector($super, $sub)", is_superselector),
+        ("simple-selectors($selector)", simple_selectors),
+        ("parse($selector)", parse),
+    ]);
+    env.to_module("sass:selector".to_string())
+}
+
+/// The text of a selector value, which may be a string, a list of
+/// strings, or a comma separated list of those.
+fn selector_text(value: &Value) -> Option<String> {
+    let list = match *value {
+        Value::String(ref s) => return Some(s.text.clone()),
+        Value::List(ref list) if !list.items.is_empty() => list,
+        _ => return None,
+    };
+
+    let mut parts = Vec::with_capacity(list.items.len());
+    match list.separator {
+        ListSeparator::Comma => {
+            for complex in &list.items {
+                match *complex {
+                    Value::String(ref s) => parts.push(s.text.clone()),
+                    Value::List(ref inner) if inner.separator == ListSeparator::Space => parts.push(selector_text(complex)?),
+                    _ => return None,
+                }
+            }
+        }
+        ListSeparator::Slash => return None,
+        _ => {
+            for compound in &list.items {
+                match *compound {
+                    Value::String(ref s) => parts.push(s.text.clone()),
+                    _ => return None,
+                }
+            }
+        }
+    }
+    Some(parts.join(if list.separator == ListSeparator::Comma { ", " } else { " " }))
+}
+
+/// Reads a selector value as text, reporting it against the argument
+/// named `name` if it isn't one.
+fn selector_arg_text(value: &Value, name: &str, precision: u8) -> FnResult<String> {
+    selector_text(value).ok_or_else(|| {
+        format!("${}: {} is not a valid selector: it must be a string,\na list of strings, or a list of lists of strings.",
+                name, inspect_value(value, precision)).into()
+    })
+}
+
+/// Parses a selector value, which may only contain `&` if
+/// `allow_parent` is set.
+fn selector_value(value: &Value, name: &str, allow_parent: bool, precision: u8) -> FnResult<SelectorList> {
+    let text = selector_arg_text(value, name, precision)?;
+    parse_selector(&text, allow_parent).map_err(|message| format!("${}: {}", name, message).into())
+}
+
+fn selector_arg(args: &Args, i: usize) -> FnResult<SelectorList> {
+    selector_value(args.get(i), args.name(i), false, args.precision())
+}
+
+/// The `$selectors...`, of which there must be at least one.
+fn selectors_rest(args: &Args) -> FnResult<&[Value]> {
+    let selectors = &args.rest(0).items;
+    if selectors.is_empty() {
+        return Err("$selectors: At least one selector must be passed.".into());
+    }
+    Ok(selectors)
+}
+
+/// Nests each selector within the previous one, as if they were nested
+/// style rules. All but the first may contain `&`.
+fn nest(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let precision = args.precision();
+    let selectors = selectors_rest(args)?;
+    let mut result = selector_value(&selectors[0], "selectors", false, precision)?;
+    for value in &selectors[1..] {
+        let child = selector_value(value, "selectors", true, precision)?;
+        result = child.resolve_parent_selectors(Some(&result), true)?;
+    }
+    Ok(result.to_value())
+}
+
+/// Appends each selector to the previous one without a descendant
+/// combinator, so that `a` and `.b` give `a.b`.
+fn append(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let precision = args.precision();
+    let selectors = selectors_rest(args)?;
+    let mut result = selector_value(&selectors[0], "selectors", false, precision)?;
+    for value in &selectors[1..] {
+        let child = selector_value(value, "selectors", false, precision)?;
+        let mut components = Vec::with_capacity(child.components.len());
+        for complex in child.components {
+            match prepend_parent(&complex) {
+                Some(prepended) => components.push(prepended),
+                None => return Err(format!("Can't append {} to {}.", complex, result).into()),
+            }
+        }
+        result = SelectorList { components }.resolve_parent_selectors(Some(&result), false)?;
+    }
+    Ok(result.to_value())
+}
+
+/// Adds `&` to the start of a complex selector, as a suffix for a type
+/// selector. Selectors starting with a combinator, `*` or a namespace
+/// can't be appended to.
+fn prepend_parent(complex: &ComplexSelector) -> Option<ComplexSelector> {
+    let mut components = complex.components.clone();
+    let compound = match components.first_mut() {
+        Some(&mut ComplexComponent::Compound(ref mut compound)) => compound,
+        _ => return None,
+    };
+    let parent = match compound.components[0] {
+        SimpleSelector::Universal(_) => return None,
+        SimpleSelector::Type(ref name) if name.contains('|') => return None,
+        SimpleSelector::Type(ref name) => Some(SimpleSelector::Parent(Some(name.clone()))),
+        _ => None,
+    };
+    match parent {
+        Some(parent) => compound.components[0] = parent,
+        None => compound.components.insert(0, SimpleSelector::Parent(None)),
+    }
+    Some(ComplexSelector { components, line_break: complex.line_break })
+}
+
+fn extend(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let selector = selector_arg(args, 0)?;
+    let targets = selector_arg(args, 1)?;
+    let source = selector_arg(args, 2)?;
+    Ok(ExtensionStore::extend_or_replace(&selector, &source, &targets, ExtendMode::AllTargets)?.to_value())
+}
+
+fn replace(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let selector = selector_arg(args, 0)?;
+    let targets = selector_arg(args, 1)?;
+    let source = selector_arg(args, 2)?;
+    Ok(ExtensionStore::extend_or_replace(&selector, &source, &targets, ExtendMode::Replace)?.to_value())
+}
+
+/// A selector that matches only elements both selectors match, or null
+/// if there can be no such element.
+fn unify(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let selector1 = selector_arg(args, 0)?;
+    let selector2 = selector_arg(args, 1)?;
+
+    let mut components = Vec::new();
+    for complex1 in &selector1.components {
+        for complex2 in &selector2.components {
+            let unified = unify_complex(vec![complex1.components.clone(), complex2.components.clone()]);
+            for complex in unified.unwrap_or_default() {
+                components.push(ComplexSelector { components: complex, line_break: false });
+            }
+        }
+    }
+    if components.is_empty() {
+        return Ok(Value::Null);
+    }
+    Ok(SelectorList { components }.to_value())
+}
+
+fn is_superselector(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let superselector = selector_arg(args, 0)?;
+    let subselector = selector_arg(args, 1)?;
+    Ok(Value::Boolean(superselector.is_superselector(&subselector)))
+}
+
+/// The simple selectors of a compound selector, as a comma separated
+/// list of strings.
+fn simple_selectors(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let text = selector_arg_text(args.get(0), args.name(0), args.precision())?;
+    let compound = parse_compound_selector(&text).map_err(|message| format!("${}: {}", args.name(0), message))?;
+    let items = compound.components.iter().map(|simple| Value::unquoted_string(simple.to_string())).collect();
+    Ok(Value::List(List::new(items, ListSeparator::Comma, false)))
+}
+
+fn parse(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    Ok(selector_arg(args, 0)?.to_value())
+}
@@ -0,0 +1,183 @@
+// FROM: src/fn_strings.cpp
+
+use environment::Environment;
+use expand::Expand;
+use fn_numbers::next_random;
+use fn_utils::{self, Args, FnResult};
+use inspect::inspect_value;
+use module::Module;
+use values::*;
+
+// unique-id() returns six base-36 digits.
+const UNIQUE_ID_LIMIT: u64 = 36 * 36 * 36 * 36 * 36 * 36;
+
+pub fn register(env: &mut Environment) {
+    fn_utils::register(env, &[
+        ("unquote($string)", unquote),
+        ("quote($string)", quote),
+        ("to-upper-case($string)", to_upper_case),
+        ("to-lower-case($string)", to_lower_case),
+        ("unique-id()", unique_id),
+        ("str-length($string)", length),
+        ("str-insert($string, $insert, $index)", insert),
+        ("str-index($string, $substring)", index),
+        ("str-slice($string, $start-at, $end-at: -1)", slice),
+    ]);
+}
+
+/// The `sass:string` module.
+pub fn module() -> Module {
+    let mut env = Environment::new();
+    fn_utils::register_members(&mut env, &[
+        ("unquote($string)", unquote),
+        ("quote($string)", quote),
+        ("to-upper-case($string)", to_upper_case),
+        ("to-lower-case($string)", to_lower_case),
+        ("length($string)", length),
+        ("insert($string, $insert, $index)", insert),
+        ("index($string, $substring)", index),
+        ("slice($string, $start-at, $end-at: -1)", slice),
+        ("unique-id()", unique_id),
+    ]);
+    env.to_module("sass:string".to_string())
+}
+
+/// A string with the same quotes as another.
+fn like(string: &SassString, text: String) -> Value {
+    Value::String(SassString { text, quoted: string.quoted })
+}
+
+/// The index in a string's characters of a 1-based Sass index, which
+/// counts from the end if it's negative. Indexes outside the string are
+/// clamped to it, except that negative ones may stay below 0 if
+/// `allow_negative` is set.
+fn char_index(index: i64, len: usize, allow_negative: bool) -> i64 {
+    let len = len as i64;
+    if index == 0 {
+        0
+    } else if index > 0 {
+        (index - 1).min(len)
+    } else if len + index < 0 && !allow_negative {
+        0
+    } else {
+        len + index
+    }
+}
+
+/// The byte offset of a character index, which may be the length of
+/// the string.
+fn byte_offset(text: &str, index: i64) -> usize {
+    text.char_indices().nth(index as usize).map_or(text.len(), |(i, _)| i)
+}
+
+/// Checks an index is a unitless integer. Indexes are named after the
+/// parameters of str-slice() in errors, without the `-at`.
+fn index_arg(args: &Args, i: usize, name: &str) -> FnResult<i64> {
+    let number = args.number(i)?;
+    if !number.is_unitless() {
+        return Err(format!("${}: Expected {} to have no units.", name, inspect_value(args.get(i), args.precision())).into());
+    }
+    args.int(i)
+}
+
+fn unquote(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let string = args.string(0)?;
+    Ok(Value::unquoted_string(string.text.clone()))
+}
+
+fn quote(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let string = args.string(0)?;
+    Ok(Value::quoted_string(string.text.clone()))
+}
+
+/// Upper-cases the ASCII letters of a string.
+fn to_upper_case(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let string = args.string(0)?;
+    Ok(like(string, string.text.to_ascii_uppercase()))
+}
+
+/// Lower-cases the ASCII letters of a string.
+fn to_lower_case(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let string = args.string(0)?;
+    Ok(like(string, string.text.to_ascii_lowercase()))
+}
+
+/// The number of characters in a string.
+fn length(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let string = args.string(0)?;
+    Ok(Value::number(string.text.chars().count() as f64))
+}
+
+/// Inserts a string so that it starts at `$index`. A negative index
+/// counts from the end, so the inserted string ends there instead.
+fn insert(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let string = args.string(0)?;
+    let inserted = args.string(1)?;
+    let mut index = index_arg(args, 2, "index")?;
+
+    let len = string.text.chars().count();
+    if index < 0 {
+        // +1 because negative indexes count from -1 rather than 0, and
+        // another +1 to insert after the character at that index.
+        index += len as i64 + 2;
+    }
+    let offset = byte_offset(&string.text, char_index(index, len, false));
+    let mut text = string.text.clone();
+    text.insert_str(offset, &inserted.text);
+    Ok(like(string, text))
+}
+
+/// The 1-based index of the first occurrence of a substring, or null.
+fn index(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let string = args.string(0)?;
+    let substring = args.string(1)?;
+    match string.text.find(&substring.text) {
+        Some(offset) => Ok(Value::number((string.text[..offset].chars().count() + 1) as f64)),
+        None => Ok(Value::Null),
+    }
+}
+
+/// The characters from `$start-at` to `$end-at`, both inclusive.
+fn slice(_: &mut Expand, args: &Args) -> FnResult<Value> {
+    let string = args.string(0)?;
+    let start = index_arg(args, 1, "start")?;
+    let end = index_arg(args, 2, "end")?;
+
+    // No matter where it starts, a slice that ends at 0 is empty.
+    if end == 0 {
+        return Ok(like(string, String::new()));
+    }
+    let len = string.text.chars().count();
+    let start = char_index(start, len, false);
+    let mut end = char_index(end, len, true);
+    if end == len as i64 {
+        end -= 1;
+    }
+    if end < start {
+        return Ok(like(string, String::new()));
+    }
+    let text = &string.text[byte_offset(&string.text, start)..byte_offset(&string.text, end + 1)];
+    Ok(like(string, text.to_string()))
+}
+
+/// An identifier that is different each time this is called. The ids
+/// increase by a random amount, so that they are hard to guess.
+fn unique_id(expand: &mut Expand, _: &Args) -> FnResult<Value> {
+    let state = &expand.ctx.random_state;
+    let previous = match expand.ctx.unique_id.get() {
+        Some(id) => id,
+        None => (next_random(state) * UNIQUE_ID_LIMIT as f64) as u64,
+    };
+    let id = (previous + (next_random(state) * 36.0) as u64 + 1) % UNIQUE_ID_LIMIT;
+    expand.ctx.unique_id.set(Some(id));
+
+    // The leading "u" makes the id a valid identifier.
+    let mut digits = Vec::new();
+    let mut n = id;
+    for _ in 0..6 {
+        digits.push(::std::char::from_digit((n % 36) as u32, 36).unwrap());
+        n /= 36;
+    }
+    let digits: String = digits.into_iter().rev().collect();
+    Ok(Value::unquoted_string(format!("u{}", digits)))
+}
@@ -0,0 +1,176 @@
+// FROM: src/fn_utils.cpp
+
+use std::rc::Rc;
+use ast::{ArgumentDeclaration, Builtin, Callable, Mixin};
+use environment::Environment;
+use error_handling::SassError;
+use expand::Expand;
+use inspect::inspect_value;
+use parser::Parser;
+use position::{ParserState, SourceFile};
+use values::*;
+
+// An error raised by a built-in function. Messages are reported at the
+// call site, errors from Sass code the function called are passed on.
+#[derive(Debug)]
+pub enum FnError {
+    Message(String),
+    Sass(Box<SassError>),
+}
+
+impl From<String> for FnError {
+    fn from(message: String) -> Self {
+        FnError::Message(message)
+    }
+}
+
+impl<'a> From<&'a str> for FnError {
+    fn from(message: &'a str) -> Self {
+        FnError::Message(message.to_string())
+    }
+}
+
+impl From<Box<SassError>> for FnError {
+    fn from(error: Box<SassError>) -> Self {
+        FnError::Sass(error)
+    }
+}
+
+pub type FnResult<T> = Result<T, FnError>;
+
+pub type BuiltinFunction = fn(&mut Expand, &Args) -> FnResult<Value>;
+
+// The arguments of a built-in function, in the order of its parameters,
+// followed by the rest argument if it has one.
+pub struct Args {
+    values: Vec<Value>,
+    names: Vec<String>,
+    precision: u8,
+    pub pstate: ParserState,
+}
+
+impl Args {
+    pub fn new(values: Vec<Value>, decl: &ArgumentDeclaration, precision: u8, pstate: ParserState) -> Self {
+        let mut names: Vec<String> = decl.params.iter().map(|p| p.name.clone()).collect();
+        names.extend(decl.rest.iter().cloned());
+        Args { values, names, precision, pstate }
+    }
+
+    pub fn get(&self, i: usize) -> &Value {
+        &self.values[i]
+    }
+
+    /// The values of all the arguments.
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// The name of a parameter, without the `$`.
+    pub fn name(&self, i: usize) -> &str {
+        &self.names[i]
+    }
+
+    /// The precision numbers are written with, which is also the
+    /// precision they are compared at.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// The error for an argument of the wrong type, such as
+    /// `$number: "a" is not a number.`
+    pub fn type_error(&self, i: usize, expected: &str) -> FnError {
+        FnError::Message(format!("${}: {} is not {}.", self.names[i], inspect_value(&self.values[i], self.precision), expected))
+    }
+
+    pub fn number(&self, i: usize) -> FnResult<&Number> {
+        match self.values[i] {
+            Value::Number(ref n) => Ok(n),
+            _ => Err(self.type_error(i, "a number")),
+        }
+    }
+
+    /// The value of a number argument that must have no units.
+    pub fn unitless(&self, i: usize) -> FnResult<f64> {
+        let number = self.number(i)?;
+        if !number.is_unitless() {
+            return Err(format!("${}: Expected {} to have no units.", self.names[i], inspect_value(&self.values[i], self.precision)).into());
+        }
+        Ok(number.value)
+    }
+
+    /// A number argument that must be an integer.
+    pub fn int(&self, i: usize) -> FnResult<i64> {
+        let number = self.number(i)?;
+        fuzzy_as_int(number.value).ok_or_else(|| {
+            format!("${}: {} is not an int.", self.names[i], inspect_value(&self.values[i], self.precision)).into()
+        })
+    }
+
+    /// A map argument, which may also be an empty list.
+    pub fn map(&self, i: usize) -> FnResult<SassMap> {
+        self.values[i].try_map().ok_or_else(|| self.type_error(i, "a map"))
+    }
+
+    pub fn color(&self, i: usize) -> FnResult<&Color> {
+        match self.values[i] {
+            Value::Color(ref c) => Ok(c),
+            _ => Err(self.type_error(i, "a color")),
+        }
+    }
+
+    pub fn string(&self, i: usize) -> FnResult<&SassString> {
+        match self.values[i] {
+            Value::String(ref s) => Ok(s),
+            _ => Err(self.type_error(i, "a string")),
+        }
+    }
+
+    /// The rest argument, which is always an argument list.
+    pub fn rest(&self, i: usize) -> &List {
+        match self.values[i] {
+            Value::List(ref l) => l,
+            _ => panic!("${} is not a rest argument", self.names[i]),
+        }
+    }
+}
+
+/// Declares built-in functions as globals, from their signatures.
+pub fn register(env: &mut Environment, functions: &[(&str, BuiltinFunction)]) {
+    for builtin in builtins(functions) {
+        env.set_builtin_function(&builtin.name.clone(), Callable::Builtin(Rc::new(builtin)));
+    }
+}
+
+/// Declares built-in functions as members of a built-in module.
+pub fn register_members(env: &mut Environment, functions: &[(&str, BuiltinFunction)]) {
+    for builtin in builtins(functions) {
+        env.set_global_function(&builtin.name.clone(), Callable::Builtin(Rc::new(builtin)));
+    }
+}
+
+/// Declares built-in mixins as members of a built-in module. They are
+/// called like functions, and their result is ignored.
+pub fn register_mixins(env: &mut Environment, mixins: &[(&str, BuiltinFunction)]) {
+    for builtin in builtins(mixins) {
+        env.set_global_mixin(&builtin.name.clone(), Mixin::Builtin(Rc::new(builtin)));
+    }
+}
+
+/// Makes the built-ins for a list of signatures. Consecutive signatures
+/// with the same name are overloads of one function.
+fn builtins(functions: &[(&str, BuiltinFunction)]) -> Vec<Builtin> {
+    let mut builtins: Vec<Builtin> = Vec::new();
+    for &(signature, function) in functions {
+        let source = SourceFile::new("[built-in function]", signature);
+        let (name, args) = Parser::new(source).parse_signature().expect("invalid built-in function signature");
+        match builtins.last_mut() {
+            Some(ref mut builtin) if builtin.name == name => {
+                builtin.overloads.push((args, function));
+                continue;
+            }
+            _ => {}
+        }
+        builtins.push(Builtin { name, overloads: vec![(args, function)] });
+    }
+    builtins
+}
@@ -1,122 +1,483 @@
 // FROM: include/sass/functions.h
 
-// // Forward declaration
-//struct Sass_Env;
-//struct Sass_Callee;
-//struct Sass_Import;
-//struct Sass_Options;
-//struct Sass_Compiler;
-//struct Sass_Importer;
-//struct Sass_Function;
-//
-//// Typedef helpers for callee lists
-//typedef struct Sass_Env (*Sass_Env_Frame);
-//// Typedef helpers for callee lists
-//typedef struct Sass_Callee (*Sass_Callee_Entry);
-//// Typedef helpers for import lists
-//typedef struct Sass_Import (*Sass_Import_Entry);
-//typedef struct Sass_Import* (*Sass_Import_List);
-//// Typedef helpers for custom importer lists
-//typedef struct Sass_Importer (*Sass_Importer_Entry);
-//typedef struct Sass_Importer* (*Sass_Importer_List);
-//// Typedef defining importer signature and return type
-//typedef Sass_Import_List (*Sass_Importer_Fn)
-//(const char* url, Sass_Importer_Entry cb, struct Sass_Compiler* compiler);
-//
-//// Typedef helpers for custom functions lists
-//typedef struct Sass_Function (*Sass_Function_Entry);
-//typedef struct Sass_Function* (*Sass_Function_List);
-//// Typedef defining function signature and return type
-//typedef union Sass_Value* (*Sass_Function_Fn)
-//(const union Sass_Value*, Sass_Function_Entry cb, struct Sass_Compiler* compiler);
-//
-//// Type of function calls
-//enum Sass_Callee_Type {
-//    SASS_CALLEE_MIXIN,
-//    SASS_CALLEE_FUNCTION,
-//    SASS_CALLEE_C_FUNCTION,
-//};
-//
-//// Creator for sass custom importer return argument list
-//ADDAPI Sass_Importer_List ADDCALL sass_make_importer_list (size_t length);
-//ADDAPI Sass_Importer_Entry ADDCALL sass_importer_get_list_entry (Sass_Importer_List list, size_t idx);
-//ADDAPI void ADDCALL sass_importer_set_list_entry (Sass_Importer_List list, size_t idx, Sass_Importer_Entry entry);
-//ADDAPI void ADDCALL sass_delete_importer_list (Sass_Importer_List list);
-//
-//
-//// Creators for custom importer callback (with some additional pointer)
-//// The pointer is mostly used to store the callback into the actual binding
-//ADDAPI Sass_Importer_Entry ADDCALL sass_make_importer (Sass_Importer_Fn importer, double priority, void* cookie);
-//
-//// Getters for import function descriptors
-//ADDAPI Sass_Importer_Fn ADDCALL sass_importer_get_function (Sass_Importer_Entry cb);
-//ADDAPI double ADDCALL sass_importer_get_priority (Sass_Importer_Entry cb);
-//ADDAPI void* ADDCALL sass_importer_get_cookie (Sass_Importer_Entry cb);
-//
-//// Deallocator for associated memory
-//ADDAPI void ADDCALL sass_delete_importer (Sass_Importer_Entry cb);
-//
-//// Creator for sass custom importer return argument list
-//ADDAPI Sass_Import_List ADDCALL sass_make_import_list (size_t length);
-//// Creator for a single import entry returned by the custom importer inside the list
-//ADDAPI Sass_Import_Entry ADDCALL sass_make_import_entry (const char* path, char* source, char* srcmap);
-//ADDAPI Sass_Import_Entry ADDCALL sass_make_import (const char* imp_path, const char* abs_base, char* source, char* srcmap);
-//// set error message to abort import and to print out a message (path from existing object is used in output)
-//ADDAPI Sass_Import_Entry ADDCALL sass_import_set_error(Sass_Import_Entry import, const char* message, size_t line, size_t col);
-//
-//// Setters to insert an entry into the import list (you may also use [] access directly)
-//// Since we are dealing with pointers they should have a guaranteed and fixed size
-//ADDAPI void ADDCALL sass_import_set_list_entry (Sass_Import_List list, size_t idx, Sass_Import_Entry entry);
-//ADDAPI Sass_Import_Entry ADDCALL sass_import_get_list_entry (Sass_Import_List list, size_t idx);
-//
-//// Getters for callee entry
-//ADDAPI const char* ADDCALL sass_callee_get_name (Sass_Callee_Entry);
-//ADDAPI const char* ADDCALL sass_callee_get_path (Sass_Callee_Entry);
-//ADDAPI size_t ADDCALL sass_callee_get_line (Sass_Callee_Entry);
-//ADDAPI size_t ADDCALL sass_callee_get_column (Sass_Callee_Entry);
-//ADDAPI enum Sass_Callee_Type ADDCALL sass_callee_get_type (Sass_Callee_Entry);
-//ADDAPI Sass_Env_Frame ADDCALL sass_callee_get_env (Sass_Callee_Entry);
-//
-//// Getters and Setters for environments (lexical, local and global)
-//ADDAPI union Sass_Value* ADDCALL sass_env_get_lexical (Sass_Env_Frame, const char*);
-//ADDAPI void ADDCALL sass_env_set_lexical (Sass_Env_Frame, const char*, union Sass_Value*);
-//ADDAPI union Sass_Value* ADDCALL sass_env_get_local (Sass_Env_Frame, const char*);
-//ADDAPI void ADDCALL sass_env_set_local (Sass_Env_Frame, const char*, union Sass_Value*);
-//ADDAPI union Sass_Value* ADDCALL sass_env_get_global (Sass_Env_Frame, const char*);
-//ADDAPI void ADDCALL sass_env_set_global (Sass_Env_Frame, const char*, union Sass_Value*);
-//
-//// Getters for import entry
-//ADDAPI const char* ADDCALL sass_import_get_imp_path (Sass_Import_Entry);
-//ADDAPI const char* ADDCALL sass_import_get_abs_path (Sass_Import_Entry);
-//ADDAPI const char* ADDCALL sass_import_get_source (Sass_Import_Entry);
-//ADDAPI const char* ADDCALL sass_import_get_srcmap (Sass_Import_Entry);
-//// Explicit functions to take ownership of these items
-//// The property on our struct will be reset to NULL
-//ADDAPI char* ADDCALL sass_import_take_source (Sass_Import_Entry);
-//ADDAPI char* ADDCALL sass_import_take_srcmap (Sass_Import_Entry);
-//// Getters from import error entry
-//ADDAPI size_t ADDCALL sass_import_get_error_line (Sass_Import_Entry);
-//ADDAPI size_t ADDCALL sass_import_get_error_column (Sass_Import_Entry);
-//ADDAPI const char* ADDCALL sass_import_get_error_message (Sass_Import_Entry);
-//
-//// Deallocator for associated memory (incl. entries)
-//ADDAPI void ADDCALL sass_delete_import_list (Sass_Import_List);
-//// Just in case we have some stray import structs
-//ADDAPI void ADDCALL sass_delete_import (Sass_Import_Entry);
-//
-//
-//
-//// Creators for sass function list and function descriptors
-//ADDAPI Sass_Function_List ADDCALL sass_make_function_list (size_t length);
-//ADDAPI Sass_Function_Entry ADDCALL sass_make_function (const char* signature, Sass_Function_Fn cb, void* cookie);
-//ADDAPI void ADDCALL sass_delete_function (Sass_Function_Entry entry);
-//ADDAPI void ADDCALL sass_delete_function_list (Sass_Function_List list);
-//
-//// Setters and getters for callbacks on function lists
-//ADDAPI Sass_Function_Entry ADDCALL sass_function_get_list_entry(Sass_Function_List list, size_t pos);
-//ADDAPI void ADDCALL sass_function_set_list_entry(Sass_Function_List list, size_t pos, Sass_Function_Entry cb);
-//
-//// Getters for custom function descriptors
-//ADDAPI const char* ADDCALL sass_function_get_signature (Sass_Function_Entry cb);
-//ADDAPI Sass_Function_Fn ADDCALL sass_function_get_function (Sass_Function_Entry cb);
-//ADDAPI void* ADDCALL sass_function_get_cookie (Sass_Function_Entry cb);
+use std::ffi::CString;
+use std::mem;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use libc;
+use c_api_helpers::*;
+use sass_compiler::SassCompiler;
+use environment::Environment;
+use parser::normalize_name;
+use sass_values::{value_from_c, value_to_c, SassValue};
+use values::Value;
+
+// Typedef helpers for import lists
+pub type SassImportEntry = *mut SassImport;
+pub type SassImportList = *mut SassImportEntry;
+// Typedef helpers for custom importer lists
+pub type SassImporterEntry = *mut SassImporter;
+pub type SassImporterList = *mut SassImporterEntry;
+// Typedef defining importer signature and return type
+pub type SassImporterFn = extern fn(url: *const c_char, cb: SassImporterEntry, compiler: *mut SassCompiler) -> SassImportList;
+
+// Typedef helpers for custom functions lists
+pub type SassFunctionEntry = *mut SassFunction;
+pub type SassFunctionList = *mut SassFunctionEntry;
+// Typedef defining function signature and return type
+pub type SassFunctionFn = extern fn(args: *const SassValue, cb: SassFunctionEntry, compiler: *mut SassCompiler) -> *mut SassValue;
+
+// Typedef helpers for callee lists
+pub type SassEnvFrame = *mut SassEnv;
+// Typedef helpers for callee lists
+pub type SassCalleeEntry = *mut SassCallee;
+
+// Type of function calls
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub enum SassCalleeType {
+    Mixin,
+    Function,
+    CFunction,
+}
+
+// The variables visible to a callee. The frame shares its scopes with
+// the caller, so variables set through it are seen by the stylesheet.
+#[derive(Debug)]
+pub struct SassEnv {
+    pub frame: Environment,
+}
+
+// Struct to hold an entry on the callee stack, describing a mixin or
+// function call and where it was made.
+#[derive(Debug)]
+pub struct SassCallee {
+    pub name: CString,
+    pub path: CString,
+    pub line: usize,
+    pub column: usize,
+    pub callee_type: SassCalleeType,
+    pub env: SassEnv,
+}
+
+// Struct to hold an import entry, either returned by a custom importer
+// or describing a file on the import stack. The source and srcmap are
+// owned C strings, freed when the entry is deleted. An unknown error
+// line or column is usize::MAX.
+#[derive(Debug)]
+#[repr(C)]
+pub struct SassImport {
+    pub imp_path: Option<CString>,
+    pub abs_path: Option<CString>,
+    pub source: *mut c_char,
+    pub srcmap: *mut c_char,
+    // error handling, set by an importer to abort the import
+    pub error: Option<CString>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Drop for SassImport {
+    fn drop(&mut self) {
+        unsafe {
+            libc::free(self.source as *mut libc::c_void);
+            libc::free(self.srcmap as *mut libc::c_void);
+        }
+    }
+}
+
+// Struct to hold custom importer callback
+#[derive(Debug)]
+#[repr(C)]
+pub struct SassImporter {
+    pub importer: SassImporterFn,
+    pub priority: f64,
+    pub cookie: *mut c_void,
+}
+
+// Struct to hold custom function callback
+#[derive(Debug)]
+#[repr(C)]
+pub struct SassFunction {
+    pub signature: CString,
+    pub function: SassFunctionFn,
+    pub cookie: *mut c_void,
+}
+
+/// Allocates a null-terminated list of `length` entries. The list is allocated
+/// with calloc because plugins hand their lists back to us to be freed.
+fn make_list<T>(length: usize) -> *mut *mut T {
+    unsafe { libc::calloc(length + 1, mem::size_of::<*mut T>()) as *mut *mut T }
+}
+
+/// Collects the entries of a null-terminated list, stopping at the first null.
+/// The list itself is not freed.
+pub fn list_to_vec<T>(list: *mut *mut T) -> Vec<*mut T> {
+    let mut entries = Vec::new();
+    if list.is_null() {
+        return entries;
+    }
+
+    unsafe {
+        let mut cur = list;
+        while !(*cur).is_null() {
+            entries.push(*cur);
+            cur = cur.offset(1);
+        }
+    }
+
+    entries
+}
+
+// Creator for sass custom importer return argument list
+#[no_mangle]
+pub extern fn sass_make_importer_list(length: usize) -> SassImporterList {
+    make_list(length)
+}
+
+// Safety: `list` is trusted to come from sass_make_importer_list with more
+// than `idx` entries. Like libsass, only null is checked.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern fn sass_importer_get_list_entry(list: SassImporterList, idx: usize) -> SassImporterEntry {
+    assert!(!list.is_null());
+    unsafe { *list.add(idx) }
+}
+
+// Safety: as for sass_importer_get_list_entry
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern fn sass_importer_set_list_entry(list: SassImporterList, idx: usize, entry: SassImporterEntry) {
+    assert!(!list.is_null());
+    unsafe { *list.add(idx) = entry; }
+}
+
+#[no_mangle]
+pub extern fn sass_delete_importer_list(list: SassImporterList) {
+    for entry in list_to_vec(list) {
+        sass_delete_importer(entry);
+    }
+    sass_free_list(list);
+}
+
+// Creators for custom importer callback (with some additional pointer)
+// The pointer is mostly used to store the callback into the actual binding
+#[no_mangle]
+pub extern fn sass_make_importer(importer: SassImporterFn, priority: f64, cookie: *mut c_void) -> SassImporterEntry {
+    box_to_raw_ptr(SassImporter { importer, priority, cookie })
+}
+
+// Getters for import function descriptors
+#[no_mangle]
+pub extern fn sass_importer_get_function(cb: SassImporterEntry) -> SassImporterFn {
+    let cb = ptr_to_ref(cb);
+    cb.importer
+}
+
+#[no_mangle]
+pub extern fn sass_importer_get_priority(cb: SassImporterEntry) -> f64 {
+    let cb = ptr_to_ref(cb);
+    cb.priority
+}
+
+#[no_mangle]
+pub extern fn sass_importer_get_cookie(cb: SassImporterEntry) -> *mut c_void {
+    let cb = ptr_to_ref(cb);
+    cb.cookie
+}
+
+// Deallocator for associated memory
+#[no_mangle]
+pub extern fn sass_delete_importer(cb: SassImporterEntry) {
+    drop_raw_ptr(cb);
+}
+
+// Creator for sass custom importer return argument list
+#[no_mangle]
+pub extern fn sass_make_import_list(length: usize) -> SassImportList {
+    make_list(length)
+}
+
+// Creator for a single import entry returned by the custom importer inside the list
+#[no_mangle]
+pub extern fn sass_make_import_entry(path: *const c_char, source: *mut c_char, srcmap: *mut c_char) -> SassImportEntry {
+    sass_make_import(path, path, source, srcmap)
+}
+
+/// Takes ownership of `source` and `srcmap`, which must have been
+/// allocated with `sass_alloc_memory` or `sass_copy_c_string`.
+#[no_mangle]
+pub extern fn sass_make_import(imp_path: *const c_char, abs_path: *const c_char, source: *mut c_char, srcmap: *mut c_char) -> SassImportEntry {
+    let imp_path = if imp_path.is_null() { None } else { Some(c_char_ptr_to_cstring(imp_path)) };
+    let abs_path = if abs_path.is_null() { None } else { Some(c_char_ptr_to_cstring(abs_path)) };
+    box_to_raw_ptr(SassImport { imp_path, abs_path, source, srcmap, error: None, line: usize::MAX, column: usize::MAX })
+}
+
+/// Sets an error message to abort the import and print out a message (the path
+/// from the existing object is used in the output). The line and column are
+/// one-based positions in the entry's source; pass 0 if they are not known.
+#[no_mangle]
+pub extern fn sass_import_set_error(import: SassImportEntry, message: *const c_char, line: usize, col: usize) -> SassImportEntry {
+    if import.is_null() {
+        return import;
+    }
+    let entry = ptr_to_ref(import);
+    entry.error = if message.is_null() { None } else { Some(c_char_ptr_to_cstring(message)) };
+    entry.line = if line == 0 { usize::MAX } else { line };
+    entry.column = if col == 0 { usize::MAX } else { col };
+    import
+}
+
+// Setters to insert an entry into the import list (you may also use [] access directly)
+// Since we are dealing with pointers they should have a guaranteed and fixed size
+// Safety: `list` is trusted to come from sass_make_import_list with more
+// than `idx` entries. Like libsass, only null is checked.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern fn sass_import_set_list_entry(list: SassImportList, idx: usize, entry: SassImportEntry) {
+    assert!(!list.is_null());
+    unsafe { *list.add(idx) = entry; }
+}
+
+// Safety: as for sass_import_set_list_entry
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern fn sass_import_get_list_entry(list: SassImportList, idx: usize) -> SassImportEntry {
+    assert!(!list.is_null());
+    unsafe { *list.add(idx) }
+}
+
+// Getters for callee entry
+#[no_mangle]
+pub extern fn sass_callee_get_name(entry: SassCalleeEntry) -> *const c_char {
+    let entry = ptr_to_ref(entry);
+    entry.name.as_ptr()
+}
+
+#[no_mangle]
+pub extern fn sass_callee_get_path(entry: SassCalleeEntry) -> *const c_char {
+    let entry = ptr_to_ref(entry);
+    entry.path.as_ptr()
+}
+
+#[no_mangle]
+pub extern fn sass_callee_get_line(entry: SassCalleeEntry) -> usize {
+    let entry = ptr_to_ref(entry);
+    entry.line
+}
+
+#[no_mangle]
+pub extern fn sass_callee_get_column(entry: SassCalleeEntry) -> usize {
+    let entry = ptr_to_ref(entry);
+    entry.column
+}
+
+#[no_mangle]
+pub extern fn sass_callee_get_type(entry: SassCalleeEntry) -> SassCalleeType {
+    let entry = ptr_to_ref(entry);
+    entry.callee_type
+}
+
+#[no_mangle]
+pub extern fn sass_callee_get_env(entry: SassCalleeEntry) -> SassEnvFrame {
+    let entry = ptr_to_ref(entry);
+    &mut entry.env
+}
+
+/// Variable names may be given with or without the leading `$`.
+fn env_variable_name(name: *const c_char) -> String {
+    let name = c_char_ptr_to_string(name);
+    normalize_name(name.trim_start_matches('$'))
+}
+
+fn optional_value(value: Option<Value>) -> *mut SassValue {
+    value.map_or(ptr::null_mut(), |v| value_to_c(&v))
+}
+
+// Getters and Setters for environments (lexical, local and global).
+// The getters return a new value owned by the caller, or null if the
+// variable does not exist. The setters copy the value.
+#[no_mangle]
+pub extern fn sass_env_get_lexical(env: SassEnvFrame, name: *const c_char) -> *mut SassValue {
+    let env = ptr_to_ref(env);
+    optional_value(env.frame.get_variable(&env_variable_name(name)))
+}
+
+#[no_mangle]
+pub extern fn sass_env_set_lexical(env: SassEnvFrame, name: *const c_char, val: *mut SassValue) {
+    let env = ptr_to_ref(env);
+    env.frame.set_variable(&env_variable_name(name), value_from_c(val), false);
+}
+
+#[no_mangle]
+pub extern fn sass_env_get_local(env: SassEnvFrame, name: *const c_char) -> *mut SassValue {
+    let env = ptr_to_ref(env);
+    optional_value(env.frame.get_local_variable(&env_variable_name(name)))
+}
+
+#[no_mangle]
+pub extern fn sass_env_set_local(env: SassEnvFrame, name: *const c_char, val: *mut SassValue) {
+    let env = ptr_to_ref(env);
+    env.frame.set_local_variable(&env_variable_name(name), value_from_c(val));
+}
+
+#[no_mangle]
+pub extern fn sass_env_get_global(env: SassEnvFrame, name: *const c_char) -> *mut SassValue {
+    let env = ptr_to_ref(env);
+    optional_value(env.frame.get_global_variable(&env_variable_name(name)))
+}
+
+#[no_mangle]
+pub extern fn sass_env_set_global(env: SassEnvFrame, name: *const c_char, val: *mut SassValue) {
+    let env = ptr_to_ref(env);
+    env.frame.set_global_variable(&env_variable_name(name), value_from_c(val));
+}
+
+fn optional_c_str(s: &Option<CString>) -> *const c_char {
+    match *s {
+        Some(ref s) => s.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+// Getters for import entry
+#[no_mangle]
+pub extern fn sass_import_get_imp_path(entry: SassImportEntry) -> *const c_char {
+    let entry = ptr_to_ref(entry);
+    optional_c_str(&entry.imp_path)
+}
+
+#[no_mangle]
+pub extern fn sass_import_get_abs_path(entry: SassImportEntry) -> *const c_char {
+    let entry = ptr_to_ref(entry);
+    optional_c_str(&entry.abs_path)
+}
+
+#[no_mangle]
+pub extern fn sass_import_get_source(entry: SassImportEntry) -> *const c_char {
+    let entry = ptr_to_ref(entry);
+    entry.source
+}
+
+#[no_mangle]
+pub extern fn sass_import_get_srcmap(entry: SassImportEntry) -> *const c_char {
+    let entry = ptr_to_ref(entry);
+    entry.srcmap
+}
+
+// Explicit functions to take ownership of these items
+// The property on our struct will be reset to NULL
+#[no_mangle]
+pub extern fn sass_import_take_source(entry: SassImportEntry) -> *mut c_char {
+    let entry = ptr_to_ref(entry);
+    mem::replace(&mut entry.source, ptr::null_mut())
+}
+
+#[no_mangle]
+pub extern fn sass_import_take_srcmap(entry: SassImportEntry) -> *mut c_char {
+    let entry = ptr_to_ref(entry);
+    mem::replace(&mut entry.srcmap, ptr::null_mut())
+}
+
+// Getters from import error entry
+#[no_mangle]
+pub extern fn sass_import_get_error_line(entry: SassImportEntry) -> usize {
+    let entry = ptr_to_ref(entry);
+    entry.line
+}
+
+#[no_mangle]
+pub extern fn sass_import_get_error_column(entry: SassImportEntry) -> usize {
+    let entry = ptr_to_ref(entry);
+    entry.column
+}
+
+#[no_mangle]
+pub extern fn sass_import_get_error_message(entry: SassImportEntry) -> *const c_char {
+    let entry = ptr_to_ref(entry);
+    optional_c_str(&entry.error)
+}
+
+// Deallocator for associated memory (incl. entries)
+#[no_mangle]
+pub extern fn sass_delete_import_list(list: SassImportList) {
+    for entry in list_to_vec(list) {
+        sass_delete_import(entry);
+    }
+    sass_free_list(list);
+}
+
+// Just in case we have some stray import structs
+#[no_mangle]
+pub extern fn sass_delete_import(entry: SassImportEntry) {
+    drop_raw_ptr(entry);
+}
+
+// Creators for sass function list and function descriptors
+#[no_mangle]
+pub extern fn sass_make_function_list(length: usize) -> SassFunctionList {
+    make_list(length)
+}
+
+#[no_mangle]
+pub extern fn sass_make_function(signature: *const c_char, cb: SassFunctionFn, cookie: *mut c_void) -> SassFunctionEntry {
+    let signature = c_char_ptr_to_cstring(signature);
+    box_to_raw_ptr(SassFunction { signature, function: cb, cookie })
+}
+
+#[no_mangle]
+pub extern fn sass_delete_function(entry: SassFunctionEntry) {
+    drop_raw_ptr(entry);
+}
+
+#[no_mangle]
+pub extern fn sass_delete_function_list(list: SassFunctionList) {
+    for entry in list_to_vec(list) {
+        sass_delete_function(entry);
+    }
+    sass_free_list(list);
+}
+
+// Setters and getters for callbacks on function lists
+// Safety: `list` is trusted to come from sass_make_function_list with more
+// than `pos` entries. Like libsass, only null is checked.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern fn sass_function_get_list_entry(list: SassFunctionList, pos: usize) -> SassFunctionEntry {
+    assert!(!list.is_null());
+    unsafe { *list.add(pos) }
+}
+
+// Safety: as for sass_function_get_list_entry
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern fn sass_function_set_list_entry(list: SassFunctionList, pos: usize, cb: SassFunctionEntry) {
+    assert!(!list.is_null());
+    unsafe { *list.add(pos) = cb; }
+}
+
+// Getters for custom function descriptors
+#[no_mangle]
+pub extern fn sass_function_get_signature(cb: SassFunctionEntry) -> *const c_char {
+    let cb = ptr_to_ref(cb);
+    cb.signature.as_ptr()
+}
+
+#[no_mangle]
+pub extern fn sass_function_get_function(cb: SassFunctionEntry) -> SassFunctionFn {
+    let cb = ptr_to_ref(cb);
+    cb.function
+}
+
+#[no_mangle]
+pub extern fn sass_function_get_cookie(cb: SassFunctionEntry) -> *mut c_void {
+    let cb = ptr_to_ref(cb);
+    cb.cookie
+}
+
+/// Frees the container of a list, but not the entries within it.
+pub fn sass_free_list<T>(list: *mut *mut T) {
+    if !list.is_null() {
+        unsafe { libc::free(list as *mut libc::c_void) };
+    }
+}
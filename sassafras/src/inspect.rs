@@ -0,0 +1,407 @@
+// FROM: src/inspect.cpp
+
+use color_maps;
+use sass_output_options::SassOutputStyle;
+use values::*;
+
+// Serializes values, either as CSS for declarations or, in inspect
+// mode, as the Sass representation used by inspect() and messages.
+#[derive(Debug, Copy, Clone)]
+pub struct Inspect {
+    pub compressed: bool,
+    pub precision: u8,
+    // inspect mode can print values that are not valid CSS, such as maps
+    pub inspect: bool,
+    // strings are written without quotes when interpolated
+    pub quote: bool,
+}
+
+impl Inspect {
+    pub fn new(style: SassOutputStyle, precision: u8) -> Self {
+        let compressed = matches!(style, SassOutputStyle::Compressed);
+        Inspect { compressed, precision, inspect: false, quote: true }
+    }
+
+    pub fn inspector(precision: u8) -> Self {
+        Inspect { compressed: false, precision, inspect: true, quote: true }
+    }
+
+    /// Serializes a value. The error is the message to report when
+    /// the value cannot be represented in CSS.
+    pub fn value(&self, value: &Value) -> Result<String, String> {
+        let mut buf = String::new();
+        self.write_value(&mut buf, value)?;
+        Ok(buf)
+    }
+
+    fn write_value(&self, buf: &mut String, value: &Value) -> Result<(), String> {
+        match *value {
+            Value::Null => {
+                if self.inspect {
+                    buf.push_str("null");
+                }
+            }
+            Value::Boolean(b) => buf.push_str(if b { "true" } else { "false" }),
+            Value::Number(ref n) => self.write_number(buf, n)?,
+            Value::Color(ref c) => self.write_color(buf, c),
+            Value::String(ref s) => {
+                if s.quoted && self.quote {
+                    buf.push_str(&quote(&s.text, None));
+                } else {
+                    buf.push_str(&s.text);
+                }
+            }
+            Value::List(ref l) => self.write_list(buf, l)?,
+            Value::Map(ref m) => {
+                if !self.inspect {
+                    return Err(format!("{} isn't a valid CSS value.", inspect_value(value, self.precision)));
+                }
+                buf.push('(');
+                for (i, (k, v)) in m.pairs().iter().enumerate() {
+                    if i > 0 {
+                        buf.push_str(", ");
+                    }
+                    self.write_map_element(buf, k)?;
+                    buf.push_str(": ");
+                    self.write_map_element(buf, v)?;
+                }
+                buf.push(')');
+            }
+            Value::Function(ref f) => {
+                if !self.inspect {
+                    return Err(format!("{} isn't a valid CSS value.", inspect_value(value, self.precision)));
+                }
+                buf.push_str(&format!("get-function({})", quote(f.name(), None)));
+            }
+            Value::Calculation(ref c) => self.write_calculation(buf, c)?,
+        }
+        Ok(())
+    }
+
+    fn write_calculation(&self, buf: &mut String, calc: &Calculation) -> Result<(), String> {
+        buf.push_str(&calc.name);
+        buf.push('(');
+        for (i, arg) in calc.args.iter().enumerate() {
+            if i > 0 {
+                buf.push_str(if self.compressed { "," } else { ", " });
+            }
+            self.write_calc_value(buf, arg)?;
+        }
+        buf.push(')');
+        Ok(())
+    }
+
+    /// Writes an argument of a calculation, with parentheses around the
+    /// operands that would otherwise bind differently.
+    fn write_calc_value(&self, buf: &mut String, value: &CalcValue) -> Result<(), String> {
+        match *value {
+            // An infinite or NaN operand is already within a calculation.
+            CalcValue::Number(ref n) if !n.value.is_finite() => self.write_non_finite(buf, n),
+            CalcValue::Number(ref n) => self.write_number(buf, n)?,
+            CalcValue::String(ref s) => buf.push_str(s),
+            CalcValue::Calculation(ref c) => self.write_calculation(buf, c)?,
+            CalcValue::Operation(ref operation) => {
+                let op = operation.op;
+                let parens_left = match operation.left {
+                    CalcValue::Operation(ref left) => left.op.precedence() < op.precedence(),
+                    _ => false,
+                };
+                let parens_right = match operation.right {
+                    CalcValue::Operation(ref right) => match op {
+                        CalcOperator::Divide => true,
+                        CalcOperator::Plus => false,
+                        _ => right.op.precedence() == 1,
+                    },
+                    _ => false,
+                };
+
+                self.write_calc_operand(buf, &operation.left, parens_left)?;
+                // `+` and `-` need the spaces around them even when compressed.
+                if !self.compressed || op.precedence() == 1 {
+                    buf.push(' ');
+                    buf.push_str(op.symbol());
+                    buf.push(' ');
+                } else {
+                    buf.push_str(op.symbol());
+                }
+                self.write_calc_operand(buf, &operation.right, parens_right)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_calc_operand(&self, buf: &mut String, value: &CalcValue, parens: bool) -> Result<(), String> {
+        if parens {
+            buf.push('(');
+        }
+        self.write_calc_value(buf, value)?;
+        if parens {
+            buf.push(')');
+        }
+        Ok(())
+    }
+
+    /// Writes a number, with the numbers either side of a slash if it
+    /// is written as one.
+    fn write_number(&self, buf: &mut String, n: &Number) -> Result<(), String> {
+        if let Some(ref slash) = n.as_slash {
+            self.write_number(buf, &slash.0)?;
+            buf.push('/');
+            return self.write_number(buf, &slash.1);
+        }
+        if !self.inspect && !n.has_valid_css_units() {
+            return Err(format!("{} isn't a valid CSS value.", inspect_value(&Value::Number(n.clone()), self.precision)));
+        }
+        if !n.value.is_finite() {
+            buf.push_str("calc(");
+            self.write_non_finite(buf, n);
+            buf.push(')');
+            return Ok(());
+        }
+        buf.push_str(&format_number(n.value, self.precision, self.compressed));
+        buf.push_str(&n.unit());
+        Ok(())
+    }
+
+    /// Writes an infinite or NaN number as the calculation operands CSS
+    /// uses for them, such as `infinity * 1px`.
+    fn write_non_finite(&self, buf: &mut String, n: &Number) {
+        buf.push_str(if n.value.is_nan() {
+            "NaN"
+        } else if n.value > 0.0 {
+            "infinity"
+        } else {
+            "-infinity"
+        });
+        let (times, divide) = if self.compressed { ("*1", "/1") } else { (" * 1", " / 1") };
+        for unit in &n.numerators {
+            buf.push_str(times);
+            buf.push_str(unit);
+        }
+        for unit in &n.denominators {
+            buf.push_str(divide);
+            buf.push_str(unit);
+        }
+    }
+
+    fn write_map_element(&self, buf: &mut String, value: &Value) -> Result<(), String> {
+        let needs_parens = match *value {
+            Value::List(ref l) => l.separator == ListSeparator::Comma && !l.bracketed,
+            _ => false,
+        };
+        if needs_parens {
+            buf.push('(');
+        }
+        self.write_value(buf, value)?;
+        if needs_parens {
+            buf.push(')');
+        }
+        Ok(())
+    }
+
+    fn write_list(&self, buf: &mut String, list: &List) -> Result<(), String> {
+        if list.bracketed {
+            buf.push('[');
+        } else if list.items.is_empty() {
+            if !self.inspect {
+                return Err("() isn't a valid CSS value.".to_string());
+            }
+            buf.push_str("()");
+            return Ok(());
+        }
+
+        let single_comma = self.inspect && list.items.len() == 1 && list.separator == ListSeparator::Comma;
+        if single_comma && !list.bracketed {
+            buf.push('(');
+        }
+
+        let separator = match list.separator {
+            ListSeparator::Comma => if self.compressed { "," } else { ", " },
+            ListSeparator::Slash => "/",
+            ListSeparator::Space | ListSeparator::Undecided => " ",
+        };
+
+        let mut first = true;
+        for item in &list.items {
+            if !self.inspect && item.is_blank() {
+                continue;
+            }
+            if !first {
+                buf.push_str(separator);
+            }
+            first = false;
+
+            let needs_parens = self.inspect && element_needs_parens(list.separator, item);
+            if needs_parens {
+                buf.push('(');
+            }
+            self.write_value(buf, item)?;
+            if needs_parens {
+                buf.push(')');
+            }
+        }
+
+        if single_comma {
+            buf.push(',');
+            if !list.bracketed {
+                buf.push(')');
+            }
+        }
+        if list.bracketed {
+            buf.push(']');
+        }
+        Ok(())
+    }
+
+    fn write_color(&self, buf: &mut String, color: &Color) {
+        let opaque = fuzzy_equals(color.alpha(), 1.0);
+        let (r, g, b) = (color.red(), color.green(), color.blue());
+
+        // In compressed mode, emit colors in the shortest representation possible.
+        if self.compressed && opaque {
+            let hex = hex_string(r, g, b, true);
+            match color_maps::color_to_name(r, g, b, 1.0) {
+                Some(name) if name.len() <= hex.len() => buf.push_str(name),
+                _ => buf.push_str(&hex),
+            }
+            return;
+        }
+
+        if let Some(ref format) = color.format {
+            match *format {
+                ColorFormat::Literal(ref text) => buf.push_str(text),
+                ColorFormat::RgbFunction => self.write_rgb(buf, color),
+            }
+        } else if let Some(name) = color_maps::color_to_name(r, g, b, color.alpha()) {
+            // Generated transparent colors are printed as rgba, to work
+            // around an IE bug.
+            if color.alpha() == 0.0 {
+                self.write_rgb(buf, color);
+            } else {
+                buf.push_str(name);
+            }
+        } else if opaque {
+            buf.push_str(&hex_string(r, g, b, false));
+        } else {
+            self.write_rgb(buf, color);
+        }
+    }
+
+    fn write_rgb(&self, buf: &mut String, color: &Color) {
+        let opaque = fuzzy_equals(color.alpha(), 1.0);
+        let sep = if self.compressed { "," } else { ", " };
+        buf.push_str(if opaque { "rgb(" } else { "rgba(" });
+        buf.push_str(&format!("{}{}{}{}{}", color.red(), sep, color.green(), sep, color.blue()));
+        if !opaque {
+            buf.push_str(sep);
+            buf.push_str(&format_number(color.alpha(), self.precision, self.compressed));
+        }
+        buf.push(')');
+    }
+}
+
+/// Serializes a value for inspect() and for messages. This never fails.
+pub fn inspect_value(value: &Value, precision: u8) -> String {
+    Inspect::inspector(precision).value(value).unwrap_or_default()
+}
+
+fn element_needs_parens(separator: ListSeparator, item: &Value) -> bool {
+    match *item {
+        Value::List(ref l) => {
+            if l.bracketed {
+                return false;
+            }
+            if l.items.len() < 2 {
+                return false;
+            }
+            match separator {
+                ListSeparator::Comma => l.separator == ListSeparator::Comma,
+                ListSeparator::Slash => l.separator == ListSeparator::Comma || l.separator == ListSeparator::Slash,
+                _ => l.separator != ListSeparator::Undecided,
+            }
+        }
+        _ => false,
+    }
+}
+
+fn hex_string(r: u8, g: u8, b: u8, allow_short: bool) -> String {
+    let short = allow_short && r % 17 == 0 && g % 17 == 0 && b % 17 == 0;
+    if short {
+        format!("#{:x}{:x}{:x}", r / 17, g / 17, b / 17)
+    } else {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+/// Formats a number with at most `precision` decimal places, without
+/// trailing zeros. In compressed mode the leading zero is dropped.
+pub fn format_number(value: f64, precision: u8, compressed: bool) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "Infinity".to_string() } else { "-Infinity".to_string() };
+    }
+
+    // Round half away from zero at the requested precision. Numbers near
+    // the largest double overflow when scaled, and have no decimals anyway.
+    let factor = 10f64.powi(i32::from(precision));
+    let scaled = value * factor;
+    let mut s = if scaled.is_finite() {
+        format!("{:.*}", precision as usize, fuzzy_round(scaled) / factor)
+    } else {
+        value.to_string()
+    };
+    if s.contains('.') {
+        let trimmed = s.trim_end_matches('0').trim_end_matches('.').len();
+        s.truncate(trimmed);
+    }
+    if s == "-0" {
+        s = "0".to_string();
+    }
+
+    if compressed {
+        if s.starts_with("0.") {
+            s.remove(0);
+        } else if s.starts_with("-0.") {
+            s.remove(1);
+        }
+    }
+
+    s
+}
+
+/// Quotes a string for output. If no quote character is given, double
+/// quotes are used unless the string contains them and no single quotes.
+pub fn quote(text: &str, quote_mark: Option<char>) -> String {
+    let q = quote_mark.unwrap_or_else(|| if text.contains('"') && !text.contains('\'') { '\'' } else { '"' });
+    let mut buf = String::with_capacity(text.len() + 2);
+    buf.push(q);
+
+    let chars: Vec<char> = text.chars().collect();
+    for (i, &c) in chars.iter().enumerate() {
+        match c {
+            '\\' => buf.push_str("\\\\"),
+            c if c == q => {
+                buf.push('\\');
+                buf.push(c);
+            }
+            '\n' | '\r' | '\x0C' => {
+                buf.push_str(&format!("\\{:x}", c as u32));
+                if chars.get(i + 1).map_or(false, |n| n.is_ascii_hexdigit() || *n == ' ' || *n == '\t') {
+                    buf.push(' ');
+                }
+            }
+            c if (c as u32) < 0x20 && c != '\t' || c as u32 == 0x7f => {
+                buf.push_str(&format!("\\{:x}", c as u32));
+                if chars.get(i + 1).map_or(false, |n| n.is_ascii_hexdigit() || *n == ' ' || *n == '\t') {
+                    buf.push(' ');
+                }
+            }
+            c => buf.push(c),
+        }
+    }
+
+    buf.push(q);
+    buf
+}
@@ -3,9 +3,37 @@
 
 extern crate libc;
 
+pub mod ast;
 pub mod base;
+pub mod bind;
 pub mod c_api_helpers;
+pub mod calculation;
+pub mod color_maps;
+pub mod context;
+pub mod css;
+pub mod deprecation;
+pub mod environment;
+pub mod error_handling;
+pub mod eval;
+pub mod expand;
+pub mod extend;
+pub mod fn_colors;
+pub mod fn_lists;
+pub mod fn_maps;
+pub mod fn_miscs;
+pub mod fn_numbers;
+pub mod fn_selectors;
+pub mod fn_strings;
+pub mod fn_utils;
 pub mod functions;
+pub mod inspect;
+pub mod logger;
+pub mod module;
+pub mod operators;
+pub mod output;
+pub mod parser;
+pub mod plugins;
+pub mod position;
 pub mod sass2scss;
 pub mod sass_compiler;
 pub mod sass_context;
@@ -14,6 +42,11 @@ pub mod sass_file_context;
 pub mod sass_inspect_options;
 pub mod sass_options;
 pub mod sass_output_options;
+pub mod sass_values;
+pub mod selector;
+pub mod selector_ops;
+pub mod units;
+pub mod values;
 
 /*
   #define IMPLEMENT_SASS_OPTION_ACCESSOR(type, option) \
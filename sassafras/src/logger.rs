@@ -0,0 +1,105 @@
+// FROM: src/logger.hpp
+
+use std::ffi::CString;
+use std::fmt;
+use std::os::raw::{c_char, c_void};
+use deprecation::Deprecation;
+use error_handling::{rel_path, traces_to_string, Backtrace};
+use position::ParserState;
+
+// The kind of message passed to a logger
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub enum SassLogLevel {
+    Debug,
+    Warning,
+    Error,
+    // a warning about a deprecated feature; the message starts with the
+    // id of the deprecation in brackets, such as "[slash-div] "
+    Deprecation,
+}
+
+// Typedef defining logger callback signature. The line and column are
+// 1-based, and the stack trace is formatted the way libsass prints it.
+pub type SassLoggerFn = extern fn(level: SassLogLevel, message: *const c_char, path: *const c_char, line: usize,
+                                  column: usize, stack_trace: *const c_char, cookie: *mut c_void);
+
+// Receives the messages of @debug, @warn and @error, and the warnings
+// of the compiler itself. The traces are the Sass stack, innermost
+// last, ending at the position of the message.
+pub trait Logger: fmt::Debug {
+    fn debug(&self, message: &str, pstate: &ParserState);
+
+    fn warn(&self, message: &str, pstate: &ParserState, traces: &[Backtrace]);
+
+    /// Called for uses of deprecated features that are neither fatal
+    /// nor silenced. By default they are passed on as warnings.
+    fn deprecation(&self, deprecation: Deprecation, message: &str, pstate: &ParserState, traces: &[Backtrace]) {
+        self.warn(&format!("[{}] {}", deprecation, message), pstate, traces);
+    }
+
+    /// Called for @error. The compile fails with the error as well, so
+    /// this does nothing unless a logger wants errors with its warnings.
+    fn error(&self, _message: &str, _pstate: &ParserState, _traces: &[Backtrace]) {}
+}
+
+// The default logger, which prints messages to stderr as libsass does.
+#[derive(Debug, Default)]
+pub struct StderrLogger;
+
+impl Logger for StderrLogger {
+    fn debug(&self, message: &str, pstate: &ParserState) {
+        eprintln!("{}:{} DEBUG: {}", rel_path(pstate.path()), pstate.line() + 1, message);
+    }
+
+    fn warn(&self, message: &str, _pstate: &ParserState, traces: &[Backtrace]) {
+        eprintln!("WARNING: {}\n{}", message, traces_to_string(traces, "         "));
+    }
+
+    fn deprecation(&self, deprecation: Deprecation, message: &str, _pstate: &ParserState, traces: &[Backtrace]) {
+        eprintln!("DEPRECATION WARNING [{}]: {}\n{}", deprecation, message, traces_to_string(traces, "         "));
+    }
+}
+
+// A logger that passes messages on to a callback registered through
+// the C API.
+#[derive(Debug)]
+pub struct CLogger {
+    pub callback: SassLoggerFn,
+    pub cookie: *mut c_void,
+}
+
+impl CLogger {
+    fn log(&self, level: SassLogLevel, message: &str, pstate: &ParserState, traces: &[Backtrace]) {
+        let message = c_string(message);
+        let path = c_string(&rel_path(pstate.path()));
+        let stack_trace = if traces.is_empty() { String::new() } else { traces_to_string(traces, "") };
+        let stack_trace = c_string(&stack_trace);
+        (self.callback)(level, message.as_ptr(), path.as_ptr(), pstate.line() + 1, pstate.column() + 1,
+                        stack_trace.as_ptr(), self.cookie);
+    }
+}
+
+impl Logger for CLogger {
+    fn debug(&self, message: &str, pstate: &ParserState) {
+        self.log(SassLogLevel::Debug, message, pstate, &[]);
+    }
+
+    fn warn(&self, message: &str, pstate: &ParserState, traces: &[Backtrace]) {
+        self.log(SassLogLevel::Warning, message, pstate, traces);
+    }
+
+    fn deprecation(&self, deprecation: Deprecation, message: &str, pstate: &ParserState, traces: &[Backtrace]) {
+        self.log(SassLogLevel::Deprecation, &format!("[{}] {}", deprecation, message), pstate, traces);
+    }
+
+    fn error(&self, message: &str, pstate: &ParserState, traces: &[Backtrace]) {
+        self.log(SassLogLevel::Error, message, pstate, traces);
+    }
+}
+
+// A C string of the text, with any null characters in it escaped as
+// `\0` so that the rest of the text is kept.
+fn c_string(text: &str) -> CString {
+    CString::new(text.replace('\0', "\\0")).expect("null characters have been escaped")
+}
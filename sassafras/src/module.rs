@@ -0,0 +1,220 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use ast::{Callable, ForwardRule, Mixin};
+use css::CssRef;
+use environment::Scope;
+use fn_colors;
+use fn_lists;
+use fn_maps;
+use fn_miscs;
+use fn_numbers;
+use fn_selectors;
+use fn_strings;
+use position::ParserState;
+use values::Value;
+
+// A stylesheet loaded by @use or @forward, once it has been evaluated.
+// Its members are the global variables, functions and mixins it
+// declares, and those of the modules it forwards.
+#[derive(Debug)]
+pub struct Module {
+    // the canonical url the module was loaded from
+    pub url: String,
+    // the global scopes of the module, shared with its environment so
+    // that assignments from outside are seen by its own members
+    variables: Scope<Value>,
+    functions: Scope<Callable>,
+    mixins: Scope<Mixin>,
+    forwarded: Vec<Forward>,
+    // the root of the CSS the stylesheet produced, which meta.load-css()
+    // adds again each time it loads the module; none for built-ins
+    pub css: Option<CssRef>,
+}
+
+// A module forwarded by a @forward, with its members filtered and
+// prefixed as the rule says.
+#[derive(Debug, Clone)]
+pub struct Forward {
+    pub module: Rc<Module>,
+    pub prefix: Option<String>,
+    pub shown: Option<Vec<String>>,
+    pub hidden: Vec<String>,
+}
+
+// The values a module is configured with by `with (...)`, which replace
+// its `!default` variables. Entries are removed as they are used, so
+// any that are left were not declared by the module.
+pub type Configuration = Rc<RefCell<HashMap<String, ConfiguredValue>>>;
+
+#[derive(Debug, Clone)]
+pub struct ConfiguredValue {
+    pub value: Value,
+    pub pstate: ParserState,
+}
+
+/// Whether a member can only be used within its own module. Names are
+/// normalized, so `_` has become `-`.
+pub fn is_private(name: &str) -> bool {
+    name.starts_with('-')
+}
+
+/// The namespace a @use gets without an `as` clause: the last component
+/// of its url, without extensions or a leading underscore.
+pub fn default_namespace(url: &str) -> String {
+    let basename = url.rsplit(&['/', ':'][..]).next().unwrap_or(url);
+    let basename = basename.split('.').next().unwrap_or(basename);
+    basename.trim_start_matches('_').to_string()
+}
+
+/// The built-in module at a `sass:` url, if there is one.
+pub fn builtin_module(url: &str, precision: u8) -> Option<Module> {
+    match url {
+        "sass:color" => Some(fn_colors::module()),
+        "sass:list" => Some(fn_lists::module()),
+        "sass:map" => Some(fn_maps::module()),
+        "sass:math" => Some(fn_numbers::module(precision)),
+        "sass:meta" => Some(fn_miscs::module()),
+        "sass:selector" => Some(fn_selectors::module()),
+        "sass:string" => Some(fn_strings::module()),
+        _ => None,
+    }
+}
+
+impl Module {
+    pub fn new(url: String, variables: Scope<Value>, functions: Scope<Callable>, mixins: Scope<Mixin>,
+               forwarded: Vec<Forward>) -> Self {
+        Module { url, variables, functions, mixins, forwarded, css: None }
+    }
+
+    /// Whether this is one of the `sass:` modules, whose variables
+    /// can't be assigned.
+    pub fn is_builtin(&self) -> bool {
+        self.url.starts_with("sass:")
+    }
+
+    pub fn get_variable(&self, name: &str) -> Option<Value> {
+        if is_private(name) {
+            return None;
+        }
+        if let Some(value) = self.variables.borrow().get(name) {
+            return Some(value.clone());
+        }
+        self.forwarded.iter()
+            .filter_map(|f| f.member_name(name, true).and_then(|name| f.module.get_variable(&name)))
+            .next()
+    }
+
+    /// Assigns a variable the module already has. Returns false if it
+    /// has none of that name.
+    pub fn set_variable(&self, name: &str, value: Value) -> bool {
+        if is_private(name) {
+            return false;
+        }
+        if let Some(existing) = self.variables.borrow_mut().get_mut(name) {
+            *existing = value;
+            return true;
+        }
+        for forward in &self.forwarded {
+            if let Some(inner) = forward.member_name(name, true) {
+                if forward.module.set_variable(&inner, value.clone()) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    pub fn get_function(&self, name: &str) -> Option<Callable> {
+        if is_private(name) {
+            return None;
+        }
+        if let Some(function) = self.functions.borrow().get(name) {
+            return Some(function.clone());
+        }
+        self.forwarded.iter()
+            .filter_map(|f| f.member_name(name, false).and_then(|name| f.module.get_function(&name)))
+            .next()
+    }
+
+    /// The public variables of the module, including those it forwards,
+    /// sorted by name.
+    pub fn variables(&self) -> Vec<(String, Value)> {
+        self.members(&self.variables, true, |module| module.variables())
+    }
+
+    /// The public functions of the module, including those it forwards,
+    /// sorted by name.
+    pub fn functions(&self) -> Vec<(String, Callable)> {
+        self.members(&self.functions, false, |module| module.functions())
+    }
+
+    fn members<T, F>(&self, scope: &Scope<T>, is_variable: bool, forwarded: F) -> Vec<(String, T)>
+        where T: Clone, F: Fn(&Module) -> Vec<(String, T)>
+    {
+        let mut members: Vec<(String, T)> = scope.borrow().iter()
+            .filter(|&(name, _)| !is_private(name))
+            .map(|(name, member)| (name.clone(), member.clone()))
+            .collect();
+        for forward in &self.forwarded {
+            for (name, member) in forwarded(&forward.module) {
+                let name = format!("{}{}", forward.prefix.as_ref().map_or("", |p| p.as_str()), name);
+                if forward.member_name(&name, is_variable).is_some() && !members.iter().any(|m| m.0 == name) {
+                    members.push((name, member));
+                }
+            }
+        }
+        members.sort_by(|a, b| a.0.cmp(&b.0));
+        members
+    }
+
+    pub fn get_mixin(&self, name: &str) -> Option<Mixin> {
+        if is_private(name) {
+            return None;
+        }
+        if let Some(mixin) = self.mixins.borrow().get(name) {
+            return Some(mixin.clone());
+        }
+        self.forwarded.iter()
+            .filter_map(|f| f.member_name(name, false).and_then(|name| f.module.get_mixin(&name)))
+            .next()
+    }
+}
+
+impl Forward {
+    pub fn new(module: Rc<Module>, rule: &ForwardRule) -> Self {
+        Forward {
+            module,
+            prefix: rule.prefix.clone(),
+            shown: rule.shown.clone(),
+            hidden: rule.hidden.clone(),
+        }
+    }
+
+    /// The name in the forwarded module of a member that is visible as
+    /// `name` through the @forward, or None if it is not visible.
+    pub fn member_name(&self, name: &str, is_variable: bool) -> Option<String> {
+        forwarded_name(&self.prefix, &self.shown, &self.hidden, name, is_variable)
+    }
+}
+
+/// Applies the prefix, `show` and `hide` of a @forward to the name a
+/// member is used by, giving its name in the forwarded module. The
+/// `show` and `hide` lists name members with their prefix.
+pub fn forwarded_name(prefix: &Option<String>, shown: &Option<Vec<String>>, hidden: &[String], name: &str,
+                      is_variable: bool) -> Option<String> {
+    let listed = if is_variable { format!("${}", name) } else { name.to_string() };
+    if hidden.contains(&listed) {
+        return None;
+    }
+    if let Some(ref shown) = *shown {
+        if !shown.contains(&listed) {
+            return None;
+        }
+    }
+    match *prefix {
+        Some(ref prefix) if name.starts_with(prefix.as_str()) => Some(name[prefix.len()..].to_string()),
+        Some(_) => None,
+        None => Some(name.to_string()),
+    }
+}
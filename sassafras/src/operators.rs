@@ -0,0 +1,183 @@
+// FROM: src/operators.cpp
+
+use inspect::{inspect_value, Inspect};
+use values::*;
+
+// Binary operators, in the order of the libsass Sass_OP enum.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BinaryOperator {
+    And,
+    Or,
+    Eq,
+    Neq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+impl BinaryOperator {
+    pub fn symbol(&self) -> &'static str {
+        match *self {
+            BinaryOperator::And => "and",
+            BinaryOperator::Or => "or",
+            BinaryOperator::Eq => "==",
+            BinaryOperator::Neq => "!=",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::Gte => ">=",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::Lte => "<=",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Sub => "-",
+            BinaryOperator::Mul => "*",
+            BinaryOperator::Div => "/",
+            BinaryOperator::Mod => "%",
+        }
+    }
+
+    /// Higher binds tighter.
+    pub fn precedence(&self) -> u8 {
+        match *self {
+            BinaryOperator::Or => 0,
+            BinaryOperator::And => 1,
+            BinaryOperator::Eq | BinaryOperator::Neq => 2,
+            BinaryOperator::Gt | BinaryOperator::Gte | BinaryOperator::Lt | BinaryOperator::Lte => 3,
+            BinaryOperator::Add | BinaryOperator::Sub => 4,
+            BinaryOperator::Mul | BinaryOperator::Div | BinaryOperator::Mod => 5,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum UnaryOperator {
+    Plus,
+    Minus,
+    Slash,
+    Not,
+}
+
+impl UnaryOperator {
+    pub fn symbol(&self) -> &'static str {
+        match *self {
+            UnaryOperator::Plus => "+",
+            UnaryOperator::Minus => "-",
+            UnaryOperator::Slash => "/",
+            UnaryOperator::Not => "not ",
+        }
+    }
+}
+
+/// The error for an operator that isn't defined for its operands.
+pub fn undefined_operation(op: BinaryOperator, left: &Value, right: &Value, precision: u8) -> String {
+    format!("Undefined operation: \"{} {} {}\".",
+            inspect_value(left, precision), op.symbol(), inspect_value(right, precision))
+}
+
+fn incompatible_units(left: &Number, right: &Number) -> String {
+    format!("Incompatible units: '{}' and '{}'.", right.unit(), left.unit())
+}
+
+/// Serializes an operand for string concatenation.
+fn to_css(value: &Value, precision: u8) -> Result<String, String> {
+    let inspect = Inspect { compressed: false, precision, inspect: false, quote: true };
+    match *value {
+        Value::String(ref s) => Ok(s.text.clone()),
+        ref other => inspect.value(other).or_else(|_| Ok(inspect_value(other, precision))),
+    }
+}
+
+/// Evaluates an arithmetic or relational operator. The logical operators
+/// and equality are handled by the evaluator, which short-circuits them.
+pub fn op(op: BinaryOperator, left: &Value, right: &Value, precision: u8) -> Result<Value, String> {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => number_op(op, l, r),
+        // Calculations are only combined inside calc().
+        (&Value::Calculation(_), _) | (_, &Value::Calculation(_)) => Err(undefined_operation(op, left, right, precision)),
+        _ => match op {
+            BinaryOperator::Add => {
+                let quoted = match (left, right) {
+                    (Value::String(s), _) => s.quoted,
+                    (_, Value::String(s)) => s.quoted,
+                    _ => false,
+                };
+                match (left, right) {
+                    (&Value::Number(_), &Value::Color(_)) | (&Value::Color(_), &Value::Number(_)) |
+                    (&Value::Color(_), &Value::Color(_)) => return Err(undefined_operation(op, left, right, precision)),
+                    _ => {}
+                }
+                let text = format!("{}{}", to_css(left, precision)?, to_css(right, precision)?);
+                Ok(Value::String(SassString { text, quoted }))
+            }
+            BinaryOperator::Sub | BinaryOperator::Div => {
+                match (left, right) {
+                    (&Value::Number(_), &Value::Color(_)) | (&Value::Color(_), &Value::Number(_)) |
+                    (&Value::Color(_), &Value::Color(_)) => return Err(undefined_operation(op, left, right, precision)),
+                    _ => {}
+                }
+                let text = format!("{}{}{}", to_css(left, precision)?, op.symbol(), to_css(right, precision)?);
+                Ok(Value::unquoted_string(text))
+            }
+            _ => Err(undefined_operation(op, left, right, precision)),
+        },
+    }
+}
+
+fn number_op(op: BinaryOperator, left: &Number, right: &Number) -> Result<Value, String> {
+    match op {
+        BinaryOperator::Mul => Ok(Value::Number(left.multiply(right))),
+        BinaryOperator::Div => Ok(Value::Number(left.divide(right))),
+        _ => {
+            // The other operators convert the right operand into the
+            // units of the left one, or take the units of the right one
+            // if the left is unitless.
+            let (l, r, numerators, denominators) = if left.is_unitless() {
+                (left.value, right.value, right.numerators.clone(), right.denominators.clone())
+            } else {
+                let r = right.coerce_value(&left.numerators, &left.denominators)
+                    .ok_or_else(|| incompatible_units(left, right))?;
+                (left.value, r, left.numerators.clone(), left.denominators.clone())
+            };
+
+            let number = |value| Ok(Value::Number(Number::with_units(value, numerators.clone(), denominators.clone())));
+            match op {
+                BinaryOperator::Add => number(l + r),
+                BinaryOperator::Sub => number(l - r),
+                BinaryOperator::Mod => {
+                    if r == 0.0 {
+                        return number(f64::NAN);
+                    }
+                    // The result takes the sign of the divisor.
+                    let m = l % r;
+                    number(if m != 0.0 && (m < 0.0) != (r < 0.0) { m + r } else { m })
+                }
+                BinaryOperator::Gt => Ok(Value::Boolean(l > r && !fuzzy_equals(l, r))),
+                BinaryOperator::Gte => Ok(Value::Boolean(l > r || fuzzy_equals(l, r))),
+                BinaryOperator::Lt => Ok(Value::Boolean(l < r && !fuzzy_equals(l, r))),
+                BinaryOperator::Lte => Ok(Value::Boolean(l < r || fuzzy_equals(l, r))),
+                _ => unreachable!("logical and equality operators are evaluated by the caller"),
+            }
+        }
+    }
+}
+
+/// Evaluates a unary operator.
+pub fn unary_op(op: UnaryOperator, operand: &Value, precision: u8) -> Result<Value, String> {
+    match (op, operand) {
+        (UnaryOperator::Not, _) => Ok(Value::Boolean(!operand.is_truthy())),
+        (UnaryOperator::Plus, &Value::Number(_)) => Ok(operand.clone()),
+        (UnaryOperator::Minus, Value::Number(n)) => {
+            let mut n = n.clone();
+            n.value = -n.value;
+            Ok(Value::Number(n))
+        }
+        (_, &Value::Color(_)) if op != UnaryOperator::Slash => {
+            Err(format!("Undefined operation: \"{}{}\".", op.symbol(), inspect_value(operand, precision)))
+        }
+        _ => Ok(Value::unquoted_string(format!("{}{}", op.symbol(), to_css(operand, precision)?))),
+    }
+}
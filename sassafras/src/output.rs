@@ -0,0 +1,433 @@
+// FROM: src/output.cpp, src/emitter.cpp
+
+use css::*;
+use error_handling::rel_path;
+use sass_output_options::SassOutputStyle;
+
+// Writes the CSS tree in one of the output styles.
+pub struct Output {
+    style: SassOutputStyle,
+    indent: String,
+    linefeed: String,
+    // whether to write the source line of each style rule before it
+    source_comments: bool,
+    buf: String,
+    // whether the last node written at the root was a style rule
+    last_was_rule: bool,
+}
+
+impl Output {
+    pub fn new(style: SassOutputStyle, indent: &str, linefeed: &str, source_comments: bool) -> Self {
+        Output {
+            style,
+            indent: indent.to_string(),
+            linefeed: linefeed.to_string(),
+            source_comments,
+            buf: String::new(),
+            last_was_rule: false,
+        }
+    }
+
+    fn compressed(&self) -> bool {
+        matches!(self.style, SassOutputStyle::Compressed)
+    }
+
+    fn compact(&self) -> bool {
+        matches!(self.style, SassOutputStyle::Compact)
+    }
+
+    /// Renders the whole tree, adding a charset if the output is not ASCII.
+    pub fn render(mut self, root: &CssRef) -> String {
+        for child in &root.borrow().children {
+            self.write_node(child, 0);
+        }
+
+        let mut css = self.buf;
+        if css.is_empty() {
+            return css;
+        }
+        if !css.ends_with(&self.linefeed) {
+            css.push_str(&self.linefeed);
+        }
+
+        if !css.is_ascii() {
+            if matches!(self.style, SassOutputStyle::Compressed) {
+                css.insert(0, '\u{FEFF}');
+            } else {
+                css.insert_str(0, &format!("@charset \"UTF-8\";{}", self.linefeed));
+            }
+        }
+        css
+    }
+
+    fn push_indent(&mut self, level: usize) {
+        match self.style {
+            SassOutputStyle::Compact | SassOutputStyle::Compressed => return,
+            _ => {}
+        }
+        for _ in 0..level {
+            let indent = self.indent.clone();
+            self.buf.push_str(&indent);
+        }
+    }
+
+    fn push_linefeed(&mut self) {
+        let linefeed = self.linefeed.clone();
+        self.buf.push_str(&linefeed);
+    }
+
+    /// The extra indentation of a rule in nested style, one level for
+    /// each parent in the source nesting that has declarations.
+    fn nesting_tabs(node: &CssNode) -> usize {
+        let mut tabs = 0;
+        let mut parent = node.nesting_parent.as_ref().and_then(|p| p.upgrade());
+        while let Some(p) = parent {
+            if p.borrow().has_declarations() {
+                tabs += 1;
+            }
+            parent = p.borrow().nesting_parent.as_ref().and_then(|p| p.upgrade());
+        }
+        tabs
+    }
+
+    fn write_node(&mut self, node: &CssRef, depth: usize) {
+        let node = node.borrow();
+        match node.kind {
+            CssKind::Root => {}
+            CssKind::StyleRule(ref selector) => {
+                if !node.has_declarations() || selector.is_invisible() {
+                    return;
+                }
+                let tabs = match self.style {
+                    SassOutputStyle::Nested => depth + Output::nesting_tabs(&node),
+                    _ => depth,
+                };
+                self.write_separator(depth, tabs);
+                if self.source_comments && !self.compressed() {
+                    self.write_source_comment(&node, tabs);
+                }
+                self.write_style_rule(&node, selector.to_css(self.compressed()), tabs);
+                if depth == 0 {
+                    self.last_was_rule = true;
+                }
+            }
+            CssKind::KeyframeBlock(ref selectors) => {
+                if node.is_invisible() {
+                    return;
+                }
+                self.write_separator(depth, depth);
+                let separator = if self.compressed() { "," } else { ", " };
+                self.write_style_rule(&node, selectors.join(separator), depth);
+            }
+            CssKind::MediaRule(_) | CssKind::SupportsRule(_) | CssKind::AtRule(_) => {
+                if node.is_invisible() {
+                    return;
+                }
+                let tabs = match self.style {
+                    SassOutputStyle::Nested => depth + Output::nesting_tabs(&node),
+                    _ => depth,
+                };
+                let childless = match node.kind {
+                    CssKind::AtRule(ref rule) => rule.childless,
+                    _ => false,
+                };
+                self.write_separator(depth, tabs);
+                let header = self.at_rule_header(&node.kind);
+                self.push_indent(tabs);
+                self.buf.push_str(&header);
+                if childless {
+                    self.buf.push(';');
+                } else {
+                    self.write_block(&node, tabs);
+                }
+                if depth == 0 {
+                    self.last_was_rule = !childless;
+                }
+            }
+            CssKind::Declaration(_) => {}
+            CssKind::Import(ref url) => {
+                if !self.buf.is_empty() && !self.compressed() {
+                    self.push_linefeed();
+                }
+                self.push_indent(depth);
+                self.buf.push_str("@import ");
+                self.buf.push_str(url);
+                self.buf.push(';');
+                if depth == 0 {
+                    self.last_was_rule = false;
+                }
+            }
+            CssKind::Comment(ref text) => {
+                self.write_separator(depth, depth);
+                self.push_indent(depth);
+                let column = node.pstate.as_ref().map_or(0, |p| p.column());
+                self.write_comment(text, column, depth);
+                if depth == 0 {
+                    self.last_was_rule = false;
+                }
+            }
+        }
+    }
+
+    /// Writes the libsass comment giving the line and file a style rule
+    /// came from, followed by the break before the rule itself.
+    fn write_source_comment(&mut self, node: &CssNode, tabs: usize) {
+        if let Some(ref pstate) = node.pstate {
+            self.push_indent(tabs);
+            self.buf.push_str(&format!("/* line {}, {} */", pstate.line() + 1, rel_path(pstate.path())));
+            if self.compact() {
+                self.buf.push(' ');
+            } else {
+                self.push_linefeed();
+            }
+        }
+    }
+
+    /// Writes the line break and blank line that separate a node
+    /// from the previous one.
+    fn write_separator(&mut self, depth: usize, tabs: usize) {
+        if self.buf.is_empty() || self.compressed() {
+            return;
+        }
+        // Compact output keeps a block's contents on one line.
+        if depth > 0 && self.compact() {
+            self.buf.push(' ');
+            return;
+        }
+        self.push_linefeed();
+        let blank_line = depth == 0 && self.last_was_rule && match self.style {
+            SassOutputStyle::Expanded => true,
+            _ => tabs == 0,
+        };
+        if blank_line {
+            self.push_linefeed();
+        }
+    }
+
+    fn write_style_rule(&mut self, node: &CssNode, selector: String, tabs: usize) {
+        let children: Vec<&CssRef> = node.children.iter().filter(|c| {
+            let c = c.borrow();
+            c.is_declaration() || c.is_comment()
+        }).collect();
+
+        self.push_indent(tabs);
+        match self.style {
+            SassOutputStyle::Compressed => {
+                self.buf.push_str(&selector);
+                self.buf.push('{');
+                let mut after_declaration = false;
+                for child in &children {
+                    let child = child.borrow();
+                    if child.is_declaration() {
+                        if after_declaration {
+                            self.buf.push(';');
+                        }
+                        after_declaration = true;
+                    }
+                    self.write_rule_child(&child, tabs + 1);
+                }
+                self.buf.push('}');
+            }
+            SassOutputStyle::Compact => {
+                self.buf.push_str(&selector.replace(",\n", ", "));
+                self.buf.push_str(" { ");
+                for child in &children {
+                    self.write_rule_child(&child.borrow(), tabs + 1);
+                    self.buf.push(' ');
+                }
+                self.buf.push('}');
+            }
+            SassOutputStyle::Expanded => {
+                self.write_multiline_selector(&selector, tabs);
+                self.buf.push_str(" {");
+                for child in &children {
+                    self.push_linefeed();
+                    self.push_indent(tabs + 1);
+                    self.write_rule_child(&child.borrow(), tabs + 1);
+                }
+                self.push_linefeed();
+                self.push_indent(tabs);
+                self.buf.push('}');
+            }
+            _ => {
+                self.write_multiline_selector(&selector, tabs);
+                self.buf.push_str(" {");
+                for child in &children {
+                    self.push_linefeed();
+                    self.push_indent(tabs + 1);
+                    self.write_rule_child(&child.borrow(), tabs + 1);
+                }
+                self.buf.push_str(" }");
+            }
+        }
+    }
+
+    /// Writes a declaration or comment within a style rule, with the `;`
+    /// after a declaration in all but compressed output.
+    fn write_rule_child(&mut self, node: &CssNode, tabs: usize) {
+        match node.kind {
+            CssKind::Comment(ref text) => {
+                let column = node.pstate.as_ref().map_or(0, |p| p.column());
+                self.write_comment(text, column, tabs);
+            }
+            _ => {
+                self.write_declaration(node, tabs);
+                if !self.compressed() {
+                    self.buf.push(';');
+                }
+            }
+        }
+    }
+
+    /// The text of an at-rule before its block, such as `@media print`.
+    fn at_rule_header(&self, kind: &CssKind) -> String {
+        match *kind {
+            CssKind::MediaRule(ref queries) => {
+                let separator = if self.compressed() { "," } else { ", " };
+                let queries: Vec<String> = queries.iter().map(|q| {
+                    let query = q.to_string();
+                    if self.compressed() { query.replace(": ", ":") } else { query }
+                }).collect();
+                format!("@media {}", queries.join(separator))
+            }
+            CssKind::SupportsRule(ref condition) => format!("@supports {}", condition),
+            CssKind::AtRule(ref rule) => match rule.value {
+                Some(ref value) => format!("@{} {}", rule.name, value),
+                None => format!("@{}", rule.name),
+            },
+            _ => unreachable!("not an at-rule"),
+        }
+    }
+
+    /// Writes the block of an at-rule: its declarations, and the rules
+    /// within it one level further in.
+    fn write_block(&mut self, node: &CssNode, tabs: usize) {
+        if node.children.iter().all(|c| c.borrow().is_invisible()) {
+            self.buf.push_str(if self.compressed() { "{}" } else { " {}" });
+            return;
+        }
+
+        self.buf.push_str(if self.compressed() { "{" } else { " {" });
+        let mut after_declaration = false;
+        for child in &node.children {
+            if child.borrow().is_declaration() {
+                match self.style {
+                    SassOutputStyle::Compressed => {
+                        if after_declaration {
+                            self.buf.push(';');
+                        }
+                    }
+                    SassOutputStyle::Compact => self.buf.push(' '),
+                    _ => {
+                        self.push_linefeed();
+                        self.push_indent(tabs + 1);
+                    }
+                }
+                self.write_declaration(&child.borrow(), tabs + 1);
+                if !self.compressed() {
+                    self.buf.push(';');
+                }
+                after_declaration = true;
+            } else if !child.borrow().is_invisible() {
+                if after_declaration && self.compressed() {
+                    self.buf.push(';');
+                }
+                self.write_node(child, tabs + 1);
+                after_declaration = false;
+            }
+        }
+
+        match self.style {
+            SassOutputStyle::Compressed => {}
+            SassOutputStyle::Expanded => {
+                self.push_linefeed();
+                self.push_indent(tabs);
+            }
+            _ => self.buf.push(' '),
+        }
+        self.buf.push('}');
+    }
+
+    /// Writes a selector, indenting the lines after any line breaks.
+    fn write_multiline_selector(&mut self, selector: &str, tabs: usize) {
+        for (i, line) in selector.split('\n').enumerate() {
+            if i > 0 {
+                self.push_linefeed();
+                self.push_indent(tabs);
+            }
+            self.buf.push_str(line);
+        }
+    }
+
+    /// Writes a declaration, which is indented `tabs` levels.
+    fn write_declaration(&mut self, node: &CssNode, tabs: usize) {
+        if let CssKind::Declaration(ref decl) = node.kind {
+            self.buf.push_str(&decl.name);
+            self.buf.push(':');
+            if decl.is_custom_property {
+                self.write_custom_property_value(&decl.value, tabs);
+                return;
+            }
+            if !self.compressed() {
+                self.buf.push(' ');
+            }
+            self.buf.push_str(&decl.value);
+            if decl.is_important {
+                self.buf.push_str(if self.compressed() { "!important" } else { " !important" });
+            }
+        }
+    }
+
+    /// Writes the value of a custom property as it was written. A value
+    /// on several lines is folded onto one in the single-line styles,
+    /// and otherwise keeps its lines' indentation relative to each other.
+    fn write_custom_property_value(&mut self, value: &str, tabs: usize) {
+        let mut lines = value.split('\n');
+        self.buf.push_str(lines.next().unwrap_or(""));
+        let rest: Vec<&str> = lines.collect();
+        if rest.is_empty() {
+            return;
+        }
+
+        match self.style {
+            SassOutputStyle::Compressed | SassOutputStyle::Compact => {
+                for line in rest {
+                    self.buf.push(' ');
+                    self.buf.push_str(line.trim_start());
+                }
+            }
+            _ => {
+                let indentation = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+                let minimum = rest.iter().filter(|l| !l.trim().is_empty()).map(|l| indentation(l)).min().unwrap_or(0);
+                for line in rest {
+                    self.push_linefeed();
+                    if !line.trim().is_empty() {
+                        self.push_indent(tabs);
+                        self.buf.push_str(&line[minimum..]);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes a comment as it was written. In the multi-line styles, the
+    /// lines after the first are moved by the change in the comment's
+    /// indentation, from `column` in the source to `tabs` levels.
+    fn write_comment(&mut self, text: &str, column: usize, tabs: usize) {
+        let mut lines = text.split('\n');
+        self.buf.push_str(lines.next().unwrap_or(""));
+        for line in lines {
+            self.push_linefeed();
+            match self.style {
+                SassOutputStyle::Compressed | SassOutputStyle::Compact => self.buf.push_str(line),
+                _ => {
+                    if !line.trim().is_empty() {
+                        let indentation = line.len() - line.trim_start_matches([' ', '\t']).len();
+                        self.push_indent(tabs);
+                        self.buf.push_str(&line[indentation.min(column)..]);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,2634 @@
+// FROM: src/parser.cpp
+
+use std::collections::HashSet;
+use std::rc::Rc;
+use ast::*;
+use deprecation::Deprecation;
+use error_handling::{SassError, SassResult};
+use module::default_namespace;
+use operators::{BinaryOperator, UnaryOperator};
+use position::{ParserState, SourceFile};
+use sass_options::SassDivision;
+use selector::unvendor;
+use values::*;
+
+// A recursive descent parser for the SCSS syntax.
+pub struct Parser {
+    source: Rc<SourceFile>,
+    pos: usize,
+    // keywords that end a space separated list, such as `to` in @for
+    stop_keywords: &'static [&'static str],
+    // whether the statements being parsed are the body of a @function
+    in_function: bool,
+    // whether `<` and `>` end an expression, as in media query ranges
+    stop_at_comparison: bool,
+    // whether `/` only separates values, as SassDivision::Slash asks
+    slash_separates: bool,
+    // whether the arguments of calc(), clamp(), min() or max() are being
+    // parsed, where `/` always divides
+    in_calculation: bool,
+    // the uses of deprecated syntax, reported once the file is parsed
+    deprecations: Vec<(Deprecation, String, ParserState)>,
+}
+
+// Collects text and interpolated expressions into an Interpolation.
+#[derive(Default)]
+struct InterpolationBuffer {
+    parts: Vec<InterpolationPart>,
+    text: String,
+}
+
+impl InterpolationBuffer {
+    fn push(&mut self, c: char) {
+        self.text.push(c);
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.text.push_str(s);
+    }
+
+    fn add_expression(&mut self, expr: Expression) {
+        if !self.text.is_empty() {
+            self.parts.push(InterpolationPart::Text(::std::mem::replace(&mut self.text, String::new())));
+        }
+        self.parts.push(InterpolationPart::Expression(expr));
+    }
+
+    fn add_interpolation(&mut self, interpolation: Interpolation) {
+        for part in interpolation.parts {
+            match part {
+                InterpolationPart::Text(text) => self.push_str(&text),
+                InterpolationPart::Expression(expr) => self.add_expression(expr),
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.parts.is_empty() && self.text.is_empty()
+    }
+
+    /// Removes trailing whitespace from the text written last.
+    fn trim_end(&mut self) {
+        let len = self.text.trim_end().len();
+        self.text.truncate(len);
+    }
+
+    fn finish(mut self, pstate: ParserState) -> Interpolation {
+        if !self.text.is_empty() {
+            self.parts.push(InterpolationPart::Text(self.text));
+        }
+        Interpolation { parts: self.parts, pstate }
+    }
+}
+
+pub fn is_name_start(c: char) -> bool {
+    c == '_' || c.is_ascii_alphabetic() || c as u32 >= 0x80
+}
+
+pub fn is_name(c: char) -> bool {
+    is_name_start(c) || c.is_ascii_digit() || c == '-'
+}
+
+/// Normalizes a variable, function or mixin name. Sass treats
+/// hyphens and underscores as the same character in names.
+pub fn normalize_name(name: &str) -> String {
+    name.replace('_', "-")
+}
+
+impl Parser {
+    pub fn new(source: Rc<SourceFile>) -> Self {
+        Parser {
+            source,
+            pos: 0,
+            stop_keywords: &[],
+            in_function: false,
+            stop_at_comparison: false,
+            slash_separates: false,
+            in_calculation: false,
+            deprecations: Vec::new(),
+        }
+    }
+
+    /// Parses `/` the way the division mode evaluates it. With
+    /// SassDivision::Slash it separates values rather than dividing
+    /// them, so it binds more loosely than arithmetic.
+    pub fn set_division(&mut self, division: SassDivision) {
+        self.slash_separates = division == SassDivision::Slash;
+    }
+
+    /// The uses of deprecated syntax found so far, with their messages.
+    pub fn take_deprecations(&mut self) -> Vec<(Deprecation, String, ParserState)> {
+        ::std::mem::replace(&mut self.deprecations, Vec::new())
+    }
+
+    pub fn parse(&mut self) -> SassResult<Stylesheet> {
+        let body = self.statements(true)?;
+        Ok(Stylesheet { body: Rc::new(body), source: self.source.clone() })
+    }
+
+    /// Parses the signature of a C function, such as `foo($a, $b: 1)`.
+    /// The name `*` declares a fallback for undefined functions.
+    pub fn parse_signature(&mut self) -> SassResult<(String, ArgumentDeclaration)> {
+        self.skip_ws();
+        let start = self.pos;
+        let name = if self.scan_char('*') {
+            "*".to_string()
+        } else {
+            let at = if self.scan_char('@') { "@" } else { "" };
+            format!("{}{}", at, normalize_name(&self.identifier()?))
+        };
+
+        self.skip_ws();
+        let args = if self.peek() == Some('(') {
+            self.argument_declaration()?
+        } else {
+            ArgumentDeclaration::empty(self.pstate(start))
+        };
+
+        self.skip_ws();
+        if self.peek().is_some() {
+            return Err(self.error("expected \"(\"."));
+        }
+        Ok((name, args))
+    }
+
+    // ---------------------------------------------------------------------
+    // Scanning helpers
+
+    fn src(&self) -> &str {
+        &self.source.contents
+    }
+
+    fn rest(&self) -> &str {
+        &self.source.contents[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    fn prev(&self) -> Option<char> {
+        self.src()[..self.pos].chars().next_back()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn looking_at(&self, s: &str) -> bool {
+        self.rest().starts_with(s)
+    }
+
+    fn looking_at_ignore_case(&self, s: &str) -> bool {
+        self.rest().get(..s.len()).map_or(false, |start| start.eq_ignore_ascii_case(s))
+    }
+
+    fn scan_char(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_char(&mut self, c: char) -> SassResult<()> {
+        if self.scan_char(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected \"{}\".", c)))
+        }
+    }
+
+    /// Scans a keyword, which must not be followed by a name character.
+    fn scan_keyword(&mut self, keyword: &str) -> bool {
+        if !self.looking_at_ignore_case(keyword) {
+            return false;
+        }
+        let after = self.rest()[keyword.len()..].chars().next();
+        if after.map_or(false, is_name) {
+            return false;
+        }
+        self.pos += keyword.len();
+        true
+    }
+
+    /// Whether a keyword is next, not followed by a name character.
+    fn looking_at_keyword(&self, keyword: &str) -> bool {
+        self.looking_at(keyword) && !self.rest()[keyword.len()..].chars().next().map_or(false, is_name)
+    }
+
+    fn pstate(&self, start: usize) -> ParserState {
+        ParserState::new(&self.source, start, self.pos - start)
+    }
+
+    fn error(&self, message: &str) -> Box<SassError> {
+        SassError::new(message, &ParserState::new(&self.source, self.pos, 0))
+    }
+
+    /// Skips whitespace and comments.
+    fn skip_ws(&mut self) {
+        loop {
+            self.skip_silent();
+            if !self.looking_at("/*") {
+                break;
+            }
+            self.pos += 2;
+            match self.rest().find("*/") {
+                Some(end) => self.pos += end + 2,
+                None => self.pos = self.src().len(),
+            }
+        }
+    }
+
+    /// Skips whitespace and `//` comments, stopping at a `/* */` comment
+    /// that may need to be kept.
+    fn skip_silent(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.next();
+                }
+                Some('/') if self.looking_at("//") => {
+                    while let Some(c) = self.peek() {
+                        if c == '\n' {
+                            break;
+                        }
+                        self.next();
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Reads a `/* */` comment, which may contain interpolation.
+    fn loud_comment(&mut self) -> SassResult<Statement> {
+        let start = self.pos;
+        self.pos += 2;
+        let mut buffer = InterpolationBuffer::default();
+        buffer.push_str("/*");
+        loop {
+            match self.peek() {
+                None => return Err(self.error("expected more input.")),
+                Some('*') if self.looking_at("*/") => {
+                    self.pos += 2;
+                    buffer.push_str("*/");
+                    break;
+                }
+                Some('#') if self.looking_at("#{") => {
+                    let expr = self.single_interpolation()?;
+                    buffer.add_expression(expr);
+                }
+                Some(c) => {
+                    self.next();
+                    buffer.push(c);
+                }
+            }
+        }
+        let pstate = self.pstate(start);
+        Ok(Statement::LoudComment(LoudComment { text: buffer.finish(pstate.clone()), pstate }))
+    }
+
+    fn at_statement_end(&self) -> bool {
+        matches!(self.peek(), None | Some(';') | Some('}'))
+    }
+
+    fn expect_statement_separator(&mut self) -> SassResult<()> {
+        self.skip_ws();
+        if self.scan_char(';') || self.at_statement_end() {
+            Ok(())
+        } else {
+            Err(self.error("expected \";\"."))
+        }
+    }
+
+    // ---------------------------------------------------------------------
+    // Statements
+
+    fn statements(&mut self, root: bool) -> SassResult<Vec<Statement>> {
+        let mut statements = Vec::new();
+        // @use and @forward must come before anything but variables
+        let mut allow_module_rules = root;
+        loop {
+            self.skip_silent();
+            match self.peek() {
+                None => {
+                    if root {
+                        break;
+                    }
+                    return Err(self.error("expected \"}\"."));
+                }
+                Some('}') => {
+                    if root {
+                        return Err(self.error("unmatched \"}\"."));
+                    }
+                    break;
+                }
+                Some(';') => {
+                    self.next();
+                }
+                Some('/') if self.looking_at("/*") => {
+                    let comment = self.loud_comment()?;
+                    // functions produce no CSS to write comments to
+                    if !self.in_function {
+                        statements.push(comment);
+                    }
+                }
+                Some('@') if root && (self.looking_at_keyword("@use") || self.looking_at_keyword("@forward")) => {
+                    let start = self.pos;
+                    self.next();
+                    let name = self.identifier()?;
+                    if !allow_module_rules {
+                        let message = format!("@{} rules must be written before any other rules.", name);
+                        return Err(SassError::new(message, &self.pstate(start)));
+                    }
+                    self.skip_ws();
+                    let statement = if name == "use" { self.use_rule(start)? } else { self.forward_rule(start)? };
+                    statements.push(statement);
+                }
+                Some(_) => {
+                    if let Some(statement) = self.statement()? {
+                        match statement {
+                            Statement::VariableDecl(_) => {}
+                            _ => allow_module_rules = false,
+                        }
+                        statements.push(statement);
+                    }
+                }
+            }
+        }
+        Ok(statements)
+    }
+
+    fn block(&mut self) -> SassResult<Block> {
+        self.expect_char('{')?;
+        let statements = self.statements(false)?;
+        self.expect_char('}')?;
+        Ok(Rc::new(statements))
+    }
+
+    fn statement(&mut self) -> SassResult<Option<Statement>> {
+        if self.in_function && !self.looking_at_function_child() {
+            return Err(self.error("Functions can only contain variable declarations and control directives."));
+        }
+        match self.peek() {
+            Some('$') => self.variable_declaration().map(Some),
+            Some(_) if self.looking_at_namespaced_variable() => self.variable_declaration().map(Some),
+            Some('@') => self.at_rule(),
+            // a custom property, even if its value has braces
+            Some('-') if self.looking_at("--") => self.declaration().map(Some),
+            _ => {
+                if self.looking_at_style_rule() && !self.looking_at_nested_property() {
+                    self.style_rule().map(Some)
+                } else {
+                    self.declaration().map(Some)
+                }
+            }
+        }
+    }
+
+    /// Whether the next statement may appear in a function body.
+    fn looking_at_function_child(&self) -> bool {
+        match self.peek() {
+            Some('$') => true,
+            Some('@') => {
+                let rest = &self.rest()[1..];
+                let len = rest.find(|c: char| !is_name(c)).unwrap_or(rest.len());
+                matches!(&rest[..len], "return" | "if" | "else" | "elseif" | "each" | "for" | "while" | "debug" | "warn" | "error")
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether a variable in another module, such as `ns.$x`, is next.
+    fn looking_at_namespaced_variable(&self) -> bool {
+        let rest = self.rest();
+        let len = rest.find(|c: char| !is_name(c)).unwrap_or(rest.len());
+        len > 0 && rest[len..].starts_with(".$")
+    }
+
+    fn variable_declaration(&mut self) -> SassResult<Statement> {
+        let start = self.pos;
+        let namespace = if self.peek() == Some('$') {
+            None
+        } else {
+            let namespace = self.identifier()?;
+            self.expect_char('.')?;
+            Some(namespace)
+        };
+        self.expect_char('$')?;
+        let name = normalize_name(&self.identifier()?);
+        self.skip_ws();
+        self.expect_char(':')?;
+        self.skip_ws();
+
+        let expr = self.expression()?;
+
+        let mut is_default = false;
+        let mut is_global = false;
+        loop {
+            self.skip_ws();
+            if !self.scan_char('!') {
+                break;
+            }
+            let flag_start = self.pos - 1;
+            self.skip_ws();
+            let flag = self.identifier()?;
+            let duplicate = match flag.as_str() {
+                "default" => ::std::mem::replace(&mut is_default, true),
+                "global" => {
+                    if namespace.is_some() {
+                        return Err(SassError::new("!global isn't allowed for variables in other modules.", &self.pstate(flag_start)));
+                    }
+                    ::std::mem::replace(&mut is_global, true)
+                }
+                _ => return Err(SassError::new("Invalid flag name.", &self.pstate(flag_start))),
+            };
+            if duplicate {
+                let message = format!("!{} should only be written once for each variable.\n\
+                                       This will be an error in Dart Sass 2.0.0.", flag);
+                self.deprecations.push((Deprecation::DuplicateVarFlags, message, self.pstate(flag_start)));
+            }
+        }
+
+        let pstate = self.pstate(start);
+        self.expect_statement_separator()?;
+        Ok(Statement::VariableDecl(VariableDecl { namespace, name, expr, is_default, is_global, pstate }))
+    }
+
+    fn use_rule(&mut self, start: usize) -> SassResult<Statement> {
+        let url = self.string()?;
+        self.skip_ws();
+
+        let namespace = if self.scan_keyword("as") {
+            self.skip_ws();
+            if self.scan_char('*') {
+                None
+            } else {
+                Some(self.identifier()?)
+            }
+        } else {
+            let namespace = default_namespace(&url);
+            let valid = {
+                let mut chars = namespace.chars();
+                chars.next().map_or(false, is_name_start) && chars.all(is_name)
+            };
+            if !valid {
+                let message = format!("The default namespace \"{}\" is not a valid Sass identifier.\n\n\
+                                       Recommendation: add an \"as\" clause to define an explicit namespace.", namespace);
+                return Err(SassError::new(message, &self.pstate(start)));
+            }
+            Some(namespace)
+        };
+        self.skip_ws();
+
+        let configuration = self.configuration(false)?;
+        let pstate = self.pstate(start);
+        self.expect_statement_separator()?;
+        Ok(Statement::Use(UseRule { url, namespace, configuration, pstate }))
+    }
+
+    fn forward_rule(&mut self, start: usize) -> SassResult<Statement> {
+        let url = self.string()?;
+        self.skip_ws();
+
+        let prefix = if self.scan_keyword("as") {
+            self.skip_ws();
+            let prefix = normalize_name(&self.identifier()?);
+            self.expect_char('*')?;
+            self.skip_ws();
+            Some(prefix)
+        } else {
+            None
+        };
+
+        let mut shown = None;
+        let mut hidden = Vec::new();
+        if self.scan_keyword("show") {
+            shown = Some(self.member_list()?);
+        } else if self.scan_keyword("hide") {
+            hidden = self.member_list()?;
+        }
+
+        let configuration = self.configuration(true)?;
+        let pstate = self.pstate(start);
+        self.expect_statement_separator()?;
+        Ok(Statement::Forward(ForwardRule { url, prefix, shown, hidden, configuration, pstate }))
+    }
+
+    /// Reads the members listed after `show` or `hide`, giving variables
+    /// a `$` to tell them from mixins and functions.
+    fn member_list(&mut self) -> SassResult<Vec<String>> {
+        let mut names = Vec::new();
+        loop {
+            self.skip_ws();
+            let name = if self.scan_char('$') {
+                format!("${}", normalize_name(&self.identifier()?))
+            } else {
+                normalize_name(&self.identifier()?)
+            };
+            names.push(name);
+            self.skip_ws();
+            if !self.scan_char(',') {
+                break;
+            }
+        }
+        Ok(names)
+    }
+
+    /// Reads `with ($name: value, ...)`. The values of a @forward may be
+    /// followed by `!default`.
+    fn configuration(&mut self, allow_guarded: bool) -> SassResult<Vec<ConfiguredVariable>> {
+        let mut variables = Vec::new();
+        if !self.scan_keyword("with") {
+            return Ok(variables);
+        }
+        self.skip_ws();
+        self.expect_char('(')?;
+
+        let mut names = HashSet::new();
+        loop {
+            self.skip_ws();
+            let start = self.pos;
+            self.expect_char('$')?;
+            let name = normalize_name(&self.identifier()?);
+            self.skip_ws();
+            self.expect_char(':')?;
+            self.skip_ws();
+            let expr = self.space_list()?;
+            self.skip_ws();
+
+            let mut is_guarded = false;
+            if allow_guarded && self.scan_char('!') {
+                if !self.scan_keyword("default") {
+                    return Err(self.error("Expected \"default\"."));
+                }
+                is_guarded = true;
+                self.skip_ws();
+            }
+
+            let pstate = self.pstate(start);
+            if !names.insert(name.clone()) {
+                return Err(SassError::new("The same variable may only be configured once.", &pstate));
+            }
+            variables.push(ConfiguredVariable { name, expr, is_guarded, pstate });
+
+            if !self.scan_char(',') {
+                break;
+            }
+            self.skip_ws();
+            if self.peek() == Some(')') {
+                break;
+            }
+        }
+
+        self.expect_char(')')?;
+        self.skip_ws();
+        Ok(variables)
+    }
+
+    fn at_rule(&mut self) -> SassResult<Option<Statement>> {
+        let start = self.pos;
+        self.expect_char('@')?;
+        let name = self.identifier()?;
+        self.skip_ws();
+
+        match name.as_str() {
+            "import" => self.import_rule(start).map(Some),
+            "mixin" => self.mixin_rule(start).map(Some),
+            "function" => self.function_rule(start).map(Some),
+            "return" => self.return_rule(start).map(Some),
+            "include" => self.include_rule(start).map(Some),
+            "extend" => self.extend_rule(start).map(Some),
+            "media" => self.media_rule(start).map(Some),
+            "supports" => self.supports_rule(start).map(Some),
+            "at-root" => self.at_root_rule(start).map(Some),
+            "content" => self.content_rule(start).map(Some),
+            "if" => self.if_rule(start).map(Some),
+            "each" => self.each_rule(start).map(Some),
+            "for" => self.for_rule(start).map(Some),
+            "while" => self.while_rule(start).map(Some),
+            "debug" => self.message_rule(start).map(|rule| Some(Statement::Debug(rule))),
+            "warn" => self.message_rule(start).map(|rule| Some(Statement::Warn(rule))),
+            "error" => self.message_rule(start).map(|rule| Some(Statement::Error(rule))),
+            "else" | "elseif" | "use" | "forward" => Err(SassError::new("This at-rule is not allowed here.", &self.pstate(start))),
+            "charset" => {
+                // We write our own @charset if the output needs one.
+                self.string()?;
+                self.expect_statement_separator()?;
+                Ok(None)
+            }
+            _ => self.unknown_at_rule(start, name).map(Some),
+        }
+    }
+
+    fn mixin_rule(&mut self, start: usize) -> SassResult<Statement> {
+        let name = normalize_name(&self.identifier()?);
+        self.skip_ws();
+        let args = if self.peek() == Some('(') {
+            self.argument_declaration()?
+        } else {
+            ArgumentDeclaration::empty(self.pstate(self.pos))
+        };
+        self.skip_ws();
+        let pstate = self.pstate(start);
+        let body = self.block()?;
+        Ok(Statement::MixinRule(Rc::new(MixinRule { name, args, body, pstate })))
+    }
+
+    fn function_rule(&mut self, start: usize) -> SassResult<Statement> {
+        let name_start = self.pos;
+        let name = normalize_name(&self.identifier()?);
+        match unvendor(&name).as_str() {
+            "calc" | "clamp" | "element" | "expression" | "url" | "and" | "or" | "not" => {
+                return Err(SassError::new("Invalid function name.", &self.pstate(name_start)));
+            }
+            _ => {}
+        }
+        self.skip_ws();
+        let args = self.argument_declaration()?;
+        self.skip_ws();
+        let pstate = self.pstate(start);
+
+        let in_function = ::std::mem::replace(&mut self.in_function, true);
+        let body = self.block();
+        self.in_function = in_function;
+        Ok(Statement::FunctionRule(Rc::new(FunctionRule { name, args, body: body?, pstate })))
+    }
+
+    fn return_rule(&mut self, start: usize) -> SassResult<Statement> {
+        if !self.in_function {
+            return Err(SassError::new("@return may only be used within a function.", &self.pstate(start)));
+        }
+        let value = self.expression()?;
+        let pstate = self.pstate(start);
+        self.expect_statement_separator()?;
+        Ok(Statement::Return(ReturnRule { value, pstate }))
+    }
+
+    /// Reads the expression of a @debug, @warn or @error.
+    fn message_rule(&mut self, start: usize) -> SassResult<MessageRule> {
+        let value = self.expression()?;
+        let pstate = self.pstate(start);
+        self.expect_statement_separator()?;
+        Ok(MessageRule { value, pstate })
+    }
+
+    fn extend_rule(&mut self, start: usize) -> SassResult<Statement> {
+        let selector = self.selector_text(&['!', ';', '}'])?;
+        let mut optional = false;
+        if self.scan_char('!') {
+            let flag_start = self.pos;
+            if self.identifier()? != "optional" {
+                return Err(SassError::new("Expected \"optional\".", &self.pstate(flag_start)));
+            }
+            optional = true;
+        }
+        let pstate = self.pstate(start);
+        self.expect_statement_separator()?;
+        Ok(Statement::Extend(ExtendRule { selector, optional, pstate }))
+    }
+
+    fn media_rule(&mut self, start: usize) -> SassResult<Statement> {
+        let query = self.media_query_list()?;
+        let pstate = self.pstate(start);
+        let body = self.block()?;
+        Ok(Statement::Media(MediaRule { query, body, pstate }))
+    }
+
+    /// Reads a comma separated list of media queries. The text is only
+    /// parsed enough to find the Sass expressions in it, and is parsed
+    /// again once they have been evaluated.
+    fn media_query_list(&mut self) -> SassResult<Interpolation> {
+        let start = self.pos;
+        let mut buffer = InterpolationBuffer::default();
+        loop {
+            self.skip_ws();
+            self.media_query(&mut buffer)?;
+            self.skip_ws();
+            if !self.scan_char(',') {
+                break;
+            }
+            buffer.push_str(", ");
+        }
+        Ok(buffer.finish(self.pstate(start)))
+    }
+
+    fn media_query(&mut self, buffer: &mut InterpolationBuffer) -> SassResult<()> {
+        if self.peek() != Some('(') {
+            let identifier = self.interpolated_identifier()?;
+            self.skip_ws();
+            let is_not = identifier.as_plain().map_or(false, |s| s.eq_ignore_ascii_case("not"));
+            buffer.add_interpolation(identifier);
+
+            if is_not && self.peek() == Some('(') {
+                // For example, `not (color)`.
+                buffer.push(' ');
+            } else if !self.looking_at_interpolated_identifier() {
+                // For example, `screen`.
+                return Ok(());
+            } else {
+                let identifier = self.interpolated_identifier()?;
+                self.skip_ws();
+                if identifier.as_plain().map_or(false, |s| s.eq_ignore_ascii_case("and")) {
+                    // For example, `screen and ...`.
+                    buffer.push_str(" and ");
+                } else {
+                    buffer.push(' ');
+                    buffer.add_interpolation(identifier);
+                    if self.scan_keyword("and") {
+                        // For example, `only screen and ...`.
+                        self.skip_ws();
+                        buffer.push_str(" and ");
+                    } else {
+                        // For example, `only screen`.
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        loop {
+            self.skip_ws();
+            self.media_feature(buffer)?;
+            self.skip_ws();
+            if !self.scan_keyword("and") {
+                break;
+            }
+            buffer.push_str(" and ");
+        }
+        Ok(())
+    }
+
+    /// Reads a parenthesized media feature such as `(min-width: $w)`, or
+    /// a range such as `(400px <= width < 700px)`.
+    fn media_feature(&mut self, buffer: &mut InterpolationBuffer) -> SassResult<()> {
+        if self.looking_at("#{") {
+            let expr = self.single_interpolation()?;
+            buffer.add_expression(expr);
+            return Ok(());
+        }
+
+        self.expect_char('(')?;
+        buffer.push('(');
+        self.skip_ws();
+        let expr = self.expression_until_comparison()?;
+        buffer.add_expression(expr);
+
+        if self.scan_char(':') {
+            self.skip_ws();
+            buffer.push_str(": ");
+            let expr = self.expression()?;
+            buffer.add_expression(expr);
+        } else if let Some(next) = self.peek().filter(|&c| c == '<' || c == '>' || c == '=') {
+            self.next();
+            buffer.push(' ');
+            buffer.push(next);
+            if next != '=' && self.scan_char('=') {
+                buffer.push('=');
+            }
+            buffer.push(' ');
+            self.skip_ws();
+            let expr = self.expression_until_comparison()?;
+            buffer.add_expression(expr);
+
+            // A second comparison in the same direction, for example
+            // `(400px < width < 700px)`.
+            if next != '=' && self.scan_char(next) {
+                buffer.push(' ');
+                buffer.push(next);
+                if self.scan_char('=') {
+                    buffer.push('=');
+                }
+                buffer.push(' ');
+                self.skip_ws();
+                let expr = self.expression_until_comparison()?;
+                buffer.add_expression(expr);
+            }
+        }
+
+        self.expect_char(')')?;
+        buffer.push(')');
+        Ok(())
+    }
+
+    fn expression_until_comparison(&mut self) -> SassResult<Expression> {
+        let stop_at_comparison = ::std::mem::replace(&mut self.stop_at_comparison, true);
+        let expr = self.expression();
+        self.stop_at_comparison = stop_at_comparison;
+        let expr = expr?;
+        self.skip_ws();
+        Ok(expr)
+    }
+
+    fn supports_rule(&mut self, start: usize) -> SassResult<Statement> {
+        let condition_start = self.pos;
+        let mut buffer = InterpolationBuffer::default();
+        self.supports_condition(&mut buffer)?;
+        let condition = buffer.finish(self.pstate(condition_start));
+        self.skip_ws();
+        let pstate = self.pstate(start);
+        let body = self.block()?;
+        Ok(Statement::Supports(SupportsRule { condition, body, pstate }))
+    }
+
+    /// Reads a condition such as `not (display: grid)` or
+    /// `(a: b) and (c: $d)`, evaluating declarations as Sass.
+    fn supports_condition(&mut self, buffer: &mut InterpolationBuffer) -> SassResult<()> {
+        if self.scan_keyword("not") {
+            self.skip_ws();
+            buffer.push_str("not ");
+            return self.supports_condition_in_parens(buffer);
+        }
+
+        self.supports_condition_in_parens(buffer)?;
+        self.skip_ws();
+        let operator = if self.looking_at_keyword("and") {
+            "and"
+        } else if self.looking_at_keyword("or") {
+            "or"
+        } else {
+            return Ok(());
+        };
+        while self.scan_keyword(operator) {
+            self.skip_ws();
+            buffer.push(' ');
+            buffer.push_str(operator);
+            buffer.push(' ');
+            self.supports_condition_in_parens(buffer)?;
+            self.skip_ws();
+        }
+        Ok(())
+    }
+
+    fn supports_condition_in_parens(&mut self, buffer: &mut InterpolationBuffer) -> SassResult<()> {
+        if self.looking_at("#{") {
+            let expr = self.single_interpolation()?;
+            buffer.add_expression(expr);
+            return Ok(());
+        }
+
+        if self.looking_at_interpolated_identifier() {
+            // A function such as `selector(...)`, whose argument is kept
+            // as written.
+            let name = self.interpolated_identifier()?;
+            buffer.add_interpolation(name);
+            self.expect_char('(')?;
+            buffer.push('(');
+            let text = self.balanced_text()?;
+            buffer.push_str(&text);
+            self.expect_char(')')?;
+            buffer.push(')');
+            return Ok(());
+        }
+
+        self.expect_char('(')?;
+        self.skip_ws();
+        buffer.push('(');
+        if self.peek() == Some('(') || self.looking_at_keyword("not") {
+            self.supports_condition(buffer)?;
+        } else {
+            let name = self.expression()?;
+            buffer.add_expression(name);
+            self.skip_ws();
+            self.expect_char(':')?;
+            self.skip_ws();
+            buffer.push_str(": ");
+            let value = self.expression()?;
+            buffer.add_expression(value);
+        }
+        self.skip_ws();
+        self.expect_char(')')?;
+        buffer.push(')');
+        Ok(())
+    }
+
+    /// Reads text up to the parenthesis that closes one just read.
+    fn balanced_text(&mut self) -> SassResult<String> {
+        let start = self.pos;
+        let mut depth = 0;
+        loop {
+            match self.peek() {
+                None => return Err(self.error("expected \")\".")),
+                Some(')') if depth == 0 => break,
+                Some('"') | Some('\'') => {
+                    self.string()?;
+                }
+                Some(c) => {
+                    self.next();
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Ok(self.src()[start..self.pos].to_string())
+    }
+
+    fn at_root_rule(&mut self, start: usize) -> SassResult<Statement> {
+        let query = if self.peek() == Some('(') {
+            Some(self.at_root_query()?)
+        } else {
+            None
+        };
+        self.skip_ws();
+        let pstate = self.pstate(start);
+        let body = if self.peek() == Some('{') {
+            self.block()?
+        } else {
+            // For example, `@at-root .a {...}`.
+            Rc::new(vec![self.style_rule()?])
+        };
+        Ok(Statement::AtRoot(AtRootRule { query, body, pstate }))
+    }
+
+    fn at_root_query(&mut self) -> SassResult<Interpolation> {
+        let start = self.pos;
+        let mut buffer = InterpolationBuffer::default();
+        self.expect_char('(')?;
+        buffer.push('(');
+        self.skip_ws();
+        let expr = self.expression()?;
+        buffer.add_expression(expr);
+        if self.scan_char(':') {
+            self.skip_ws();
+            buffer.push_str(": ");
+            let expr = self.expression()?;
+            buffer.add_expression(expr);
+        }
+        self.expect_char(')')?;
+        buffer.push(')');
+        Ok(buffer.finish(self.pstate(start)))
+    }
+
+    fn unknown_at_rule(&mut self, start: usize, name: String) -> SassResult<Statement> {
+        let value = if self.at_statement_end() || self.peek() == Some('{') {
+            None
+        } else {
+            Some(self.almost_any_value()?)
+        };
+        let pstate = self.pstate(start);
+        let body = if self.peek() == Some('{') {
+            Some(self.block()?)
+        } else {
+            self.expect_statement_separator()?;
+            None
+        };
+        Ok(Statement::AtRule(AtRule { name, value, body, pstate }))
+    }
+
+    /// Reads text that may contain interpolation up to the end of a
+    /// statement or the start of a block, such as an at-rule's prelude.
+    fn almost_any_value(&mut self) -> SassResult<Interpolation> {
+        let start = self.pos;
+        let mut buffer = InterpolationBuffer::default();
+        loop {
+            match self.peek() {
+                None | Some(';') | Some('{') | Some('}') => break,
+                Some('#') if self.looking_at("#{") => {
+                    let expr = self.single_interpolation()?;
+                    buffer.add_expression(expr);
+                }
+                Some('"') | Some('\'') => self.raw_string(&mut buffer)?,
+                Some('/') if self.looking_at("//") => self.skip_ws(),
+                Some(c) => {
+                    self.next();
+                    buffer.push(c);
+                }
+            }
+        }
+        buffer.trim_end();
+        Ok(buffer.finish(self.pstate(start)))
+    }
+
+    /// Reads a value of plain CSS tokens into `buffer`, as written apart
+    /// from interpolation, up to the end of the statement or a closing
+    /// bracket that wasn't opened in it. Brackets and strings in the
+    /// value must be balanced.
+    fn interpolated_declaration_value(&mut self, buffer: &mut InterpolationBuffer) -> SassResult<()> {
+        let mut brackets: Vec<char> = Vec::new();
+        loop {
+            match self.peek() {
+                None => match brackets.last() {
+                    Some(&closer) => return Err(self.error(&format!("expected \"{}\".", closer))),
+                    None => break,
+                },
+                Some('\\') => {
+                    self.next();
+                    buffer.push('\\');
+                    if let Some(c) = self.next() {
+                        buffer.push(c);
+                    }
+                }
+                Some('"') | Some('\'') => self.raw_string(buffer)?,
+                Some('#') if self.looking_at("#{") => {
+                    let expr = self.single_interpolation()?;
+                    buffer.add_expression(expr);
+                }
+                Some('/') if self.looking_at("/*") => {
+                    let start = self.pos;
+                    match self.rest().find("*/") {
+                        Some(end) => self.pos += end + 2,
+                        None => return Err(self.error("expected more input.")),
+                    }
+                    buffer.push_str(&self.src()[start..self.pos]);
+                }
+                Some(';') if brackets.is_empty() => break,
+                Some(c @ '(') | Some(c @ '[') | Some(c @ '{') => {
+                    self.next();
+                    buffer.push(c);
+                    brackets.push(match c {
+                        '(' => ')',
+                        '[' => ']',
+                        _ => '}',
+                    });
+                }
+                Some(c @ ')') | Some(c @ ']') | Some(c @ '}') => {
+                    if brackets.last() != Some(&c) {
+                        if brackets.is_empty() {
+                            break;
+                        }
+                        return Err(self.error(&format!("expected \"{}\".", brackets.last().unwrap())));
+                    }
+                    brackets.pop();
+                    self.next();
+                    buffer.push(c);
+                }
+                Some('\n') | Some('\r') => {
+                    // Runs of newlines are written as one.
+                    if !self.prev().map_or(false, |c| c == '\n' || c == '\r') {
+                        buffer.push('\n');
+                    }
+                    self.next();
+                }
+                Some(c) => {
+                    self.next();
+                    buffer.push(c);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn include_rule(&mut self, start: usize) -> SassResult<Statement> {
+        let mut name = self.identifier()?;
+        let mut namespace = None;
+        if self.scan_char('.') {
+            namespace = Some(name);
+            name = self.identifier()?;
+        }
+        self.skip_ws();
+        let args = if self.peek() == Some('(') {
+            self.argument_invocation()?
+        } else {
+            ArgumentInvocation::empty(self.pstate(self.pos))
+        };
+        let pstate = self.pstate(start);
+        self.skip_ws();
+
+        let content_start = self.pos;
+        let content_args = if self.scan_keyword("using") {
+            self.skip_ws();
+            let args = self.argument_declaration()?;
+            self.skip_ws();
+            Some(args)
+        } else {
+            None
+        };
+
+        let content = if self.peek() == Some('{') {
+            let args = content_args.unwrap_or_else(|| ArgumentDeclaration::empty(self.pstate(content_start)));
+            let body = self.block()?;
+            Some(Rc::new(ContentBlock { args, body, pstate: self.pstate(content_start) }))
+        } else if content_args.is_some() {
+            return Err(self.error("expected \"{\"."));
+        } else {
+            self.expect_statement_separator()?;
+            None
+        };
+
+        Ok(Statement::Include(IncludeRule { namespace, name, args, content, pstate }))
+    }
+
+    fn content_rule(&mut self, start: usize) -> SassResult<Statement> {
+        let args = if self.peek() == Some('(') {
+            self.argument_invocation()?
+        } else {
+            ArgumentInvocation::empty(self.pstate(self.pos))
+        };
+        let pstate = self.pstate(start);
+        self.expect_statement_separator()?;
+        Ok(Statement::Content(ContentRule { args, pstate }))
+    }
+
+    fn if_rule(&mut self, start: usize) -> SassResult<Statement> {
+        let condition = self.expression()?;
+        self.skip_ws();
+        let mut clauses = vec![(condition, self.block()?)];
+        let mut otherwise = None;
+
+        loop {
+            let before = self.pos;
+            self.skip_ws();
+            let at = self.pos;
+            if !self.scan_char('@') {
+                self.pos = before;
+                break;
+            }
+            // `@elseif` is an old spelling of `@else if`
+            if self.scan_keyword("elseif") {
+                let pstate = self.pstate(at);
+                self.deprecations.push((Deprecation::Elseif, "@elseif is deprecated and will not be supported in future Sass versions.\n\n\
+                                                               Recommendation: @else if".to_string(), pstate));
+                self.skip_ws();
+            } else if self.scan_keyword("else") {
+                self.skip_ws();
+                if !self.scan_keyword("if") {
+                    otherwise = Some(self.block()?);
+                    break;
+                }
+                self.skip_ws();
+            } else {
+                self.pos = before;
+                break;
+            }
+            let condition = self.expression()?;
+            self.skip_ws();
+            clauses.push((condition, self.block()?));
+        }
+
+        Ok(Statement::If(IfRule { clauses, otherwise, pstate: self.pstate(start) }))
+    }
+
+    fn loop_variable(&mut self) -> SassResult<String> {
+        self.expect_char('$')?;
+        let name = normalize_name(&self.identifier()?);
+        self.skip_ws();
+        Ok(name)
+    }
+
+    fn each_rule(&mut self, start: usize) -> SassResult<Statement> {
+        let mut variables = vec![self.loop_variable()?];
+        while self.scan_char(',') {
+            self.skip_ws();
+            variables.push(self.loop_variable()?);
+        }
+        if !self.scan_keyword("in") {
+            return Err(self.error("Expected \"in\"."));
+        }
+        self.skip_ws();
+        let list = self.expression()?;
+        self.skip_ws();
+        let pstate = self.pstate(start);
+        let body = self.block()?;
+        Ok(Statement::Each(EachRule { variables, list, body, pstate }))
+    }
+
+    fn for_rule(&mut self, start: usize) -> SassResult<Statement> {
+        let variable = self.loop_variable()?;
+        if !self.scan_keyword("from") {
+            return Err(self.error("Expected \"from\"."));
+        }
+        self.skip_ws();
+
+        let stop_keywords = ::std::mem::replace(&mut self.stop_keywords, &["through", "to"]);
+        let from = self.expression();
+        self.stop_keywords = stop_keywords;
+        let from = from?;
+        self.skip_ws();
+
+        let inclusive = if self.scan_keyword("through") {
+            true
+        } else if self.scan_keyword("to") {
+            false
+        } else {
+            return Err(self.error("Expected \"to\" or \"through\"."));
+        };
+        self.skip_ws();
+        let to = self.expression()?;
+        self.skip_ws();
+        let pstate = self.pstate(start);
+        let body = self.block()?;
+        Ok(Statement::For(ForRule { variable, from, to, inclusive, body, pstate }))
+    }
+
+    fn while_rule(&mut self, start: usize) -> SassResult<Statement> {
+        let condition = self.expression()?;
+        self.skip_ws();
+        let pstate = self.pstate(start);
+        let body = self.block()?;
+        Ok(Statement::While(WhileRule { condition, body, pstate }))
+    }
+
+    fn import_rule(&mut self, start: usize) -> SassResult<Statement> {
+        let mut imports = Vec::new();
+        loop {
+            self.skip_ws();
+            imports.push(self.import_argument()?);
+            self.skip_ws();
+            if !self.scan_char(',') {
+                break;
+            }
+        }
+
+        let pstate = self.pstate(start);
+        self.expect_statement_separator()?;
+        Ok(Statement::Import(Import { imports, pstate }))
+    }
+
+    fn import_argument(&mut self) -> SassResult<ImportArgument> {
+        let start = self.pos;
+        if self.looking_at_ignore_case("url(") {
+            let url = self.raw_url()?;
+            let modifiers = self.import_modifiers();
+            let pstate = self.pstate(start);
+            return Ok(ImportArgument::Static { url, modifiers, pstate });
+        }
+
+        let url = match self.interpolated_string()?.as_plain() {
+            Some(url) => url.to_string(),
+            None => {
+                // An interpolated URL is always a plain CSS import.
+                self.pos = start;
+                let mut buffer = InterpolationBuffer::default();
+                self.raw_string(&mut buffer)?;
+                let url = buffer.finish(self.pstate(start));
+                let modifiers = self.import_modifiers();
+                let pstate = self.pstate(start);
+                return Ok(ImportArgument::Static { url, modifiers, pstate });
+            }
+        };
+        let url_pstate = self.pstate(start);
+        let modifiers = self.import_modifiers();
+        let pstate = self.pstate(start);
+
+        if is_plain_import_url(&url) || modifiers.is_some() {
+            let quoted = ::inspect::quote(&url, None);
+            return Ok(ImportArgument::Static { url: Interpolation::plain(quoted, url_pstate), modifiers, pstate });
+        }
+
+        Ok(ImportArgument::Dynamic { url, pstate })
+    }
+
+    /// Reads media queries or other modifiers after a plain CSS import.
+    fn import_modifiers(&mut self) -> Option<Interpolation> {
+        self.skip_ws();
+        let start = self.pos;
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' | ';' | '}' if depth == 0 => break,
+                _ => {}
+            }
+            self.next();
+        }
+
+        let text = self.src()[start..self.pos].trim().to_string();
+        if text.is_empty() {
+            None
+        } else {
+            Some(Interpolation::plain(text, self.pstate(start)))
+        }
+    }
+
+    /// Decides whether the upcoming statement is a style rule, by looking
+    /// for a `{` before the end of the statement.
+    fn looking_at_style_rule(&self) -> bool {
+        let mut chars = self.rest().chars().peekable();
+        let mut depth = 0;
+        while let Some(c) = chars.next() {
+            match c {
+                '"' | '\'' => {
+                    while let Some(s) = chars.next() {
+                        if s == '\\' {
+                            chars.next();
+                        } else if s == c {
+                            break;
+                        }
+                    }
+                }
+                '/' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    let mut prev = ' ';
+                    for s in chars.by_ref() {
+                        if prev == '*' && s == '/' {
+                            break;
+                        }
+                        prev = s;
+                    }
+                }
+                '#' if chars.peek() == Some(&'{') => {
+                    // interpolation, whose braces don't start a block
+                    chars.next();
+                    let mut braces = 1;
+                    for s in chars.by_ref() {
+                        match s {
+                            '{' => braces += 1,
+                            '}' => braces -= 1,
+                            _ => {}
+                        }
+                        if braces == 0 {
+                            break;
+                        }
+                    }
+                }
+                '(' | '[' => depth += 1,
+                ')' | ']' => depth -= 1,
+                '{' if depth <= 0 => return true,
+                ';' | '}' if depth <= 0 => return false,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Whether the upcoming statement is a property with nested ones,
+    /// such as `font: {...}` or `margin: 0 {...}`, rather than a style
+    /// rule. A selector such as `a:hover {...}` has no whitespace after
+    /// its colon.
+    fn looking_at_nested_property(&self) -> bool {
+        let rest = self.rest();
+        let mut len = 0;
+        loop {
+            let name = &rest[len..];
+            if name.starts_with("#{") {
+                match name.find('}') {
+                    Some(end) => len += end + 1,
+                    None => return false,
+                }
+            } else {
+                match name.chars().next() {
+                    Some(c) if is_name(c) => len += c.len_utf8(),
+                    _ => break,
+                }
+            }
+        }
+        let after_name = rest[len..].trim_start();
+        if len == 0 || !after_name.starts_with(':') || after_name.starts_with("::") {
+            return false;
+        }
+        let value = &after_name[1..];
+        value.trim_start().starts_with('{') || value.starts_with(char::is_whitespace)
+    }
+
+    fn style_rule(&mut self) -> SassResult<Statement> {
+        let start = self.pos;
+        let selector = self.selector_text(&['{'])?;
+        let body = self.block()?;
+        Ok(Statement::StyleRule(StyleRule { selector, body, pstate: self.pstate(start) }))
+    }
+
+    /// Reads the text of a selector up to one of the `end` characters, or
+    /// the end of the file unless a block must follow. Comments are
+    /// dropped and runs of whitespace are collapsed. The selector is
+    /// parsed once any interpolation in it has been evaluated.
+    fn selector_text(&mut self, end: &[char]) -> SassResult<Interpolation> {
+        let start = self.pos;
+        let mut buffer = InterpolationBuffer::default();
+        loop {
+            match self.peek() {
+                None if end.contains(&'{') => return Err(self.error("expected \"{\".")),
+                None => break,
+                Some('#') if self.looking_at("#{") => {
+                    let expr = self.single_interpolation()?;
+                    buffer.add_expression(expr);
+                }
+                Some(c) if end.contains(&c) => break,
+                Some('"') | Some('\'') => self.raw_string(&mut buffer)?,
+                Some('/') if self.looking_at("/*") || self.looking_at("//") => {
+                    self.skip_ws();
+                    if !buffer.is_empty() {
+                        buffer.push(' ');
+                    }
+                }
+                Some('\\') => {
+                    self.next();
+                    buffer.push('\\');
+                    if let Some(c) = self.next() {
+                        buffer.push(c);
+                    }
+                }
+                Some(c) if c.is_whitespace() => {
+                    // Line breaks are kept, as they are preserved after
+                    // commas in the output.
+                    self.next();
+                    if buffer.is_empty() {
+                        continue;
+                    }
+                    if c == '\n' {
+                        if buffer.text.ends_with(' ') {
+                            buffer.text.pop();
+                        }
+                        buffer.push('\n');
+                    } else if !buffer.text.ends_with(' ') && !buffer.text.ends_with('\n') {
+                        buffer.push(' ');
+                    }
+                }
+                Some(c) => {
+                    self.next();
+                    buffer.push(c);
+                }
+            }
+        }
+
+        buffer.trim_end();
+        Ok(buffer.finish(self.pstate(start)))
+    }
+
+    fn declaration(&mut self) -> SassResult<Statement> {
+        let start = self.pos;
+        let name_start = self.pos;
+        let mut buffer = InterpolationBuffer::default();
+        while let Some(c) = self.peek() {
+            if c == '#' && self.looking_at("#{") {
+                let expr = self.single_interpolation()?;
+                buffer.add_expression(expr);
+                continue;
+            }
+            if c == ':' || c.is_whitespace() || c == ';' || c == '}' || c == '{' {
+                break;
+            }
+            if c == '/' && (self.looking_at("/*") || self.looking_at("//")) {
+                break;
+            }
+            buffer.push(c);
+            self.next();
+        }
+        if buffer.is_empty() {
+            return Err(self.error("expected \"}\"."));
+        }
+        let name = buffer.finish(self.pstate(name_start));
+
+        self.skip_ws();
+        self.expect_char(':')?;
+
+        if name.initial_plain().starts_with("--") {
+            // Custom property values are plain CSS tokens, apart from
+            // interpolation, and are kept with their whitespace.
+            let value_start = self.pos;
+            let mut buffer = InterpolationBuffer::default();
+            self.interpolated_declaration_value(&mut buffer)?;
+            buffer.trim_end();
+            let value = Expression::String(StringExpr { text: buffer.finish(self.pstate(value_start)), quoted: false });
+            let pstate = self.pstate(start);
+            self.expect_statement_separator()?;
+            return Ok(Statement::Declaration(Declaration {
+                name,
+                value: Some(value),
+                children: None,
+                is_important: false,
+                is_custom_property: true,
+                pstate,
+            }));
+        }
+
+        self.skip_ws();
+        if self.peek() == Some('{') {
+            let pstate = self.pstate(start);
+            let children = self.declaration_children()?;
+            return Ok(Statement::Declaration(Declaration {
+                name,
+                value: None,
+                children: Some(children),
+                is_important: false,
+                is_custom_property: false,
+                pstate,
+            }));
+        }
+
+        if self.at_statement_end() || self.peek() == Some('!') {
+            return Err(self.error("Expected expression."));
+        }
+        let mut value = self.expression()?;
+        let is_important = take_important(&mut value);
+        self.skip_ws();
+        let pstate = self.pstate(start);
+        let children = if self.peek() == Some('{') {
+            Some(self.declaration_children()?)
+        } else {
+            self.expect_statement_separator()?;
+            None
+        };
+        Ok(Statement::Declaration(Declaration {
+            name,
+            value: Some(value),
+            children,
+            is_important,
+            is_custom_property: false,
+            pstate,
+        }))
+    }
+
+    /// Parses the block of nested properties, which may also contain
+    /// variables and at-rules such as @include and @if.
+    fn declaration_children(&mut self) -> SassResult<Block> {
+        self.expect_char('{')?;
+        let mut children = Vec::new();
+        loop {
+            self.skip_silent();
+            match self.peek() {
+                None => return Err(self.error("expected \"}\".")),
+                Some('}') => break,
+                Some(';') => {
+                    self.next();
+                }
+                Some('/') if self.looking_at("/*") => children.push(self.loud_comment()?),
+                Some('$') => children.push(self.variable_declaration()?),
+                Some(_) if self.looking_at_namespaced_variable() => children.push(self.variable_declaration()?),
+                Some('@') => {
+                    if let Some(statement) = self.at_rule()? {
+                        children.push(statement);
+                    }
+                }
+                Some(_) => children.push(self.declaration()?),
+            }
+        }
+        self.expect_char('}')?;
+        Ok(Rc::new(children))
+    }
+
+    /// Whether `!important` is next, which may have whitespace after the
+    /// `!`.
+    fn looking_at_important(&self) -> bool {
+        self.peek() == Some('!') && self.rest()[1..].trim_start().get(..9).map_or(false, |s| s.eq_ignore_ascii_case("important"))
+    }
+
+    /// Reads `!important` as an unquoted string.
+    fn important_expression(&mut self) -> SassResult<Expression> {
+        let start = self.pos;
+        self.expect_char('!')?;
+        self.skip_ws();
+        if !self.scan_keyword("important") {
+            return Err(self.error("Expected \"important\"."));
+        }
+        let pstate = self.pstate(start);
+        Ok(Expression::String(StringExpr { text: Interpolation::plain("!important", pstate), quoted: false }))
+    }
+
+    // ---------------------------------------------------------------------
+    // Identifiers and strings
+
+    fn looking_at_identifier(&self) -> bool {
+        match self.peek() {
+            Some(c) if is_name_start(c) || c == '\\' => true,
+            Some('-') => match self.peek_at(1) {
+                Some(c) => is_name_start(c) || c == '-' || c == '\\',
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Reads `#{...}`, giving the interpolated expression.
+    fn single_interpolation(&mut self) -> SassResult<Expression> {
+        self.expect_char('#')?;
+        self.expect_char('{')?;
+        self.skip_ws();
+        let expr = self.expression()?;
+        self.skip_ws();
+        self.expect_char('}')?;
+        Ok(expr)
+    }
+
+    /// Whether an identifier, possibly starting with interpolation, is next.
+    fn looking_at_interpolated_identifier(&self) -> bool {
+        match self.peek() {
+            Some('#') => self.looking_at("#{"),
+            Some('-') => match self.peek_at(1) {
+                Some(c) => is_name_start(c) || c == '-' || c == '\\' || self.rest()[1..].starts_with("#{"),
+                None => false,
+            },
+            Some(c) => is_name_start(c) || c == '\\',
+            None => false,
+        }
+    }
+
+    /// Reads an identifier that may contain interpolation.
+    fn interpolated_identifier(&mut self) -> SassResult<Interpolation> {
+        let start = self.pos;
+        let mut buffer = InterpolationBuffer::default();
+        let mut text = String::new();
+        if self.scan_char('-') {
+            text.push('-');
+            if self.scan_char('-') {
+                text.push('-');
+            }
+        }
+        buffer.push_str(&text);
+
+        match self.peek() {
+            _ if text == "--" => {}
+            Some(c) if is_name_start(c) => {
+                self.next();
+                buffer.push(c);
+            }
+            Some('\\') => {
+                let mut escape = String::new();
+                self.escape(&mut escape);
+                buffer.push_str(&escape);
+            }
+            Some('#') if self.looking_at("#{") => {
+                let expr = self.single_interpolation()?;
+                buffer.add_expression(expr);
+            }
+            _ => return Err(self.error("Expected identifier.")),
+        }
+
+        loop {
+            match self.peek() {
+                Some('\\') => {
+                    let mut escape = String::new();
+                    self.escape(&mut escape);
+                    buffer.push_str(&escape);
+                }
+                Some('#') if self.looking_at("#{") => {
+                    let expr = self.single_interpolation()?;
+                    buffer.add_expression(expr);
+                }
+                Some(c) if is_name(c) => {
+                    self.next();
+                    buffer.push(c);
+                }
+                _ => break,
+            }
+        }
+        Ok(buffer.finish(self.pstate(start)))
+    }
+
+    /// Reads an identifier. Escapes are kept as written.
+    fn identifier(&mut self) -> SassResult<String> {
+        self.identifier_impl(false)
+    }
+
+    /// Reads a unit, which unlike an identifier does not swallow a
+    /// hyphen that is followed by a digit, as in `1px-2px`.
+    fn unit(&mut self) -> SassResult<String> {
+        self.identifier_impl(true)
+    }
+
+    fn identifier_impl(&mut self, unit: bool) -> SassResult<String> {
+        let mut text = String::new();
+        if self.scan_char('-') {
+            text.push('-');
+            if self.scan_char('-') {
+                text.push('-');
+                self.identifier_body(&mut text, unit);
+                return Ok(text);
+            }
+        }
+
+        match self.peek() {
+            Some(c) if is_name_start(c) => {
+                self.next();
+                text.push(c);
+            }
+            Some('\\') => self.escape(&mut text),
+            _ => return Err(self.error("Expected identifier.")),
+        }
+
+        self.identifier_body(&mut text, unit);
+        Ok(text)
+    }
+
+    fn identifier_body(&mut self, text: &mut String, unit: bool) {
+        while let Some(c) = self.peek() {
+            if c == '\\' {
+                self.escape(text);
+            } else if c == '-' && unit {
+                match self.peek_at(1) {
+                    Some(n) if n.is_ascii_digit() || n == '.' => break,
+                    _ => {
+                        self.next();
+                        text.push(c);
+                    }
+                }
+            } else if is_name(c) {
+                self.next();
+                text.push(c);
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn escape(&mut self, text: &mut String) {
+        self.next();
+        text.push('\\');
+        if let Some(c) = self.next() {
+            text.push(c);
+            if c.is_ascii_hexdigit() {
+                let mut count = 1;
+                while count < 6 && self.peek().map_or(false, |c| c.is_ascii_hexdigit()) {
+                    text.push(self.next().unwrap());
+                    count += 1;
+                }
+                if self.peek() == Some(' ') {
+                    self.next();
+                    text.push(' ');
+                }
+            }
+        }
+    }
+
+    /// Reads a quoted string and returns its unescaped contents.
+    fn string(&mut self) -> SassResult<String> {
+        let quote = match self.peek() {
+            Some(q) if q == '"' || q == '\'' => q,
+            _ => return Err(self.error("Expected string.")),
+        };
+        self.next();
+
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                None | Some('\n') => return Err(self.error(&format!("Expected {}.", quote))),
+                Some(c) if c == quote => break,
+                Some('\\') => self.string_escape(&mut text),
+                Some(c) => text.push(c),
+            }
+        }
+        Ok(text)
+    }
+
+    /// Reads a quoted string that may contain interpolation, giving its
+    /// unescaped contents.
+    fn interpolated_string(&mut self) -> SassResult<Interpolation> {
+        let start = self.pos;
+        let quote = match self.peek() {
+            Some(q) if q == '"' || q == '\'' => q,
+            _ => return Err(self.error("Expected string.")),
+        };
+        self.next();
+
+        let mut buffer = InterpolationBuffer::default();
+        loop {
+            match self.peek() {
+                None | Some('\n') => return Err(self.error(&format!("Expected {}.", quote))),
+                Some(c) if c == quote => {
+                    self.next();
+                    break;
+                }
+                Some('\\') => {
+                    self.next();
+                    let mut text = String::new();
+                    self.string_escape(&mut text);
+                    buffer.push_str(&text);
+                }
+                Some('#') if self.looking_at("#{") => {
+                    let expr = self.single_interpolation()?;
+                    buffer.add_expression(expr);
+                }
+                Some(c) => {
+                    self.next();
+                    buffer.push(c);
+                }
+            }
+        }
+        Ok(buffer.finish(self.pstate(start)))
+    }
+
+    /// Reads a quoted string as written, with its quotes and escapes,
+    /// into `buffer`. Interpolation in it is still evaluated.
+    fn raw_string(&mut self, buffer: &mut InterpolationBuffer) -> SassResult<()> {
+        let quote = match self.peek() {
+            Some(q) if q == '"' || q == '\'' => q,
+            _ => return Err(self.error("Expected string.")),
+        };
+        self.next();
+        buffer.push(quote);
+        loop {
+            match self.peek() {
+                None | Some('\n') => return Err(self.error(&format!("Expected {}.", quote))),
+                Some(c) if c == quote => {
+                    self.next();
+                    buffer.push(c);
+                    return Ok(());
+                }
+                Some('\\') => {
+                    self.next();
+                    buffer.push('\\');
+                    if let Some(c) = self.next() {
+                        buffer.push(c);
+                    }
+                }
+                Some('#') if self.looking_at("#{") => {
+                    let expr = self.single_interpolation()?;
+                    buffer.add_expression(expr);
+                }
+                Some(c) => {
+                    self.next();
+                    buffer.push(c);
+                }
+            }
+        }
+    }
+
+    fn string_escape(&mut self, text: &mut String) {
+        match self.peek() {
+            None => {}
+            Some('\n') => {
+                // a line continuation
+                self.next();
+            }
+            Some(c) if c.is_ascii_hexdigit() => {
+                let mut hex = String::new();
+                while hex.len() < 6 && self.peek().map_or(false, |c| c.is_ascii_hexdigit()) {
+                    hex.push(self.next().unwrap());
+                }
+                if self.peek().map_or(false, |c| c == ' ' || c == '\t' || c == '\n') {
+                    self.next();
+                }
+                let code = u32::from_str_radix(&hex, 16).unwrap_or(0xFFFD);
+                let c = if code == 0 { '\u{FFFD}' } else { ::std::char::from_u32(code).unwrap_or('\u{FFFD}') };
+                text.push(c);
+            }
+            Some(c) => {
+                self.next();
+                text.push(c);
+            }
+        }
+    }
+
+    /// Reads `url(...)` with an unquoted argument as plain text, apart
+    /// from any interpolation.
+    fn raw_url(&mut self) -> SassResult<Interpolation> {
+        let start = self.pos;
+        let mut buffer = InterpolationBuffer::default();
+        buffer.push_str(&self.src()[start..start + 4]);
+        self.pos += 4;
+        loop {
+            match self.peek() {
+                None => return Err(self.error("expected \")\".")),
+                Some(')') => {
+                    self.next();
+                    buffer.push(')');
+                    break;
+                }
+                Some('\\') => {
+                    self.next();
+                    buffer.push('\\');
+                    if let Some(c) = self.next() {
+                        buffer.push(c);
+                    }
+                }
+                Some('#') if self.looking_at("#{") => {
+                    let expr = self.single_interpolation()?;
+                    buffer.add_expression(expr);
+                }
+                Some('"') | Some('\'') => self.raw_string(&mut buffer)?,
+                Some(c) => {
+                    self.next();
+                    buffer.push(c);
+                }
+            }
+        }
+        Ok(buffer.finish(self.pstate(start)))
+    }
+
+    // ---------------------------------------------------------------------
+    // Expressions
+
+    /// Parses a full expression, which may be a comma separated list.
+    pub fn expression(&mut self) -> SassResult<Expression> {
+        let start = self.pos;
+        let first = self.space_list()?;
+        self.skip_ws();
+        if self.peek() != Some(',') {
+            return Ok(first);
+        }
+
+        let mut items = vec![first];
+        while self.scan_char(',') {
+            self.skip_ws();
+            if !self.looking_at_expression() {
+                // a trailing comma
+                break;
+            }
+            items.push(self.space_list()?);
+            self.skip_ws();
+        }
+
+        Ok(Expression::List(ListExpr { items, separator: ListSeparator::Comma, bracketed: false, pstate: self.pstate(start) }))
+    }
+
+    /// Whether the next character can start a single expression.
+    fn looking_at_expression(&self) -> bool {
+        match self.peek() {
+            None => false,
+            Some(c) => match c {
+                '$' | '"' | '\'' | '(' | '[' | '#' | '&' | '.' | '+' | '-' | '\\' => true,
+                '!' => self.looking_at_important(),
+                c => c.is_ascii_digit() || is_name_start(c),
+            },
+        }
+    }
+
+    /// Parses a space separated list, or a single expression.
+    fn space_list(&mut self) -> SassResult<Expression> {
+        let start = self.pos;
+        let first = self.binary(0)?;
+        let mut items = vec![first];
+        loop {
+            self.skip_ws();
+            if !self.looking_at_expression() || self.looking_at_keyword_operator() || self.looking_at("...") ||
+                self.stop_keywords.iter().any(|k| self.looking_at_keyword(k)) {
+                break;
+            }
+            items.push(self.binary(0)?);
+        }
+
+        if items.len() == 1 {
+            Ok(items.pop().unwrap())
+        } else {
+            Ok(Expression::List(ListExpr { items, separator: ListSeparator::Space, bracketed: false, pstate: self.pstate(start) }))
+        }
+    }
+
+    fn looking_at_keyword_operator(&self) -> bool {
+        ["and", "or"].iter().any(|k| self.looking_at_keyword(k))
+    }
+
+    /// Reads a binary operator if one is next.
+    fn binary_operator(&mut self) -> Option<BinaryOperator> {
+        let op = match self.peek()? {
+            '=' if self.looking_at("==") => (BinaryOperator::Eq, 2),
+            '!' if self.looking_at("!=") => (BinaryOperator::Neq, 2),
+            '<' | '>' if self.stop_at_comparison => return None,
+            '<' if self.looking_at("<=") => (BinaryOperator::Lte, 2),
+            '>' if self.looking_at(">=") => (BinaryOperator::Gte, 2),
+            '<' => (BinaryOperator::Lt, 1),
+            '>' => (BinaryOperator::Gt, 1),
+            '+' => (BinaryOperator::Add, 1),
+            '-' => {
+                // `1 -2` and `a -b` are lists, but `1-2` and `1 - 2` subtract.
+                let next = self.peek_at(1);
+                let whitespace_before = self.prev().map_or(true, |c| c.is_whitespace());
+                let number_next = next.map_or(false, |c| c.is_ascii_digit() || c == '.');
+                let identifier_next = next.map_or(false, |c| is_name_start(c) || c == '-' || c == '\\') ||
+                    self.rest()[1..].starts_with("#{");
+                if (number_next && whitespace_before) || identifier_next {
+                    return None;
+                }
+                (BinaryOperator::Sub, 1)
+            }
+            '*' => (BinaryOperator::Mul, 1),
+            '/' if !self.looking_at("//") && !self.looking_at("/*") => (BinaryOperator::Div, 1),
+            '%' => (BinaryOperator::Mod, 1),
+            'a' if self.looking_at_keyword_operator() && self.looking_at("and") => (BinaryOperator::And, 3),
+            'o' if self.looking_at_keyword_operator() && self.looking_at("or") => (BinaryOperator::Or, 2),
+            _ => return None,
+        };
+        self.pos += op.1;
+        Some(op.0)
+    }
+
+    /// How tightly an operator binds, higher binding tighter. A `/` that
+    /// only separates binds like the separator of a list: more loosely
+    /// than `+` and `-`, but more tightly than comparisons.
+    fn precedence(&self, op: BinaryOperator) -> u8 {
+        if op == BinaryOperator::Div && self.slash_separates && !self.in_calculation {
+            BinaryOperator::Add.precedence() * 2 - 1
+        } else {
+            op.precedence() * 2
+        }
+    }
+
+    /// Parses binary operations by precedence climbing.
+    fn binary(&mut self, min_precedence: u8) -> SassResult<Expression> {
+        let start = self.pos;
+        let mut left = self.unary()?;
+        loop {
+            self.skip_ws();
+            let before = self.pos;
+            let op = match self.binary_operator() {
+                Some(op) => op,
+                None => break,
+            };
+            if self.precedence(op) < min_precedence {
+                self.pos = before;
+                break;
+            }
+            // `$a -$b` subtracts, although it looks like a list.
+            let strict_unary = op == BinaryOperator::Sub && self.source.contents[..before].ends_with(char::is_whitespace) &&
+                !self.peek().map_or(true, char::is_whitespace);
+
+            self.skip_ws();
+            let right = self.binary(self.precedence(op) + 1)?;
+            if strict_unary {
+                let (left, right) = (left.pstate().text().to_string(), right.pstate().text().to_string());
+                let message = format!("This operation is parsed as:\n\n    {} - {}\n\n\
+                                       but you may have intended it to mean:\n\n    {} (-{})\n\n\
+                                       Add a space after - to clarify that it's meant to be a binary operation, or wrap\n\
+                                       it in parentheses to make it a unary operation. This will be an error in future\n\
+                                       versions of Sass.", left, right, left, right);
+                self.deprecations.push((Deprecation::StrictUnary, message, self.pstate(start)));
+            }
+            let allows_slash = op == BinaryOperator::Div && is_slash_operand(&left) && is_slash_operand(&right);
+            left = Expression::Binary(Box::new(BinaryExpr { op, left, right, allows_slash, pstate: self.pstate(start) }));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> SassResult<Expression> {
+        let start = self.pos;
+        let op = match self.peek() {
+            Some('+') if !self.peek_at(1).map_or(false, |c| c.is_ascii_digit() || c == '.') => Some(UnaryOperator::Plus),
+            Some('-') if !self.peek_at(1).map_or(false, |c| c.is_ascii_digit() || c == '.' || is_name_start(c) || c == '-' || c == '\\') &&
+                !self.rest()[1..].starts_with("#{") => Some(UnaryOperator::Minus),
+            Some('/') => Some(UnaryOperator::Slash),
+            _ => None,
+        };
+
+        if let Some(op) = op {
+            self.next();
+            self.skip_ws();
+            let operand = self.unary()?;
+            return Ok(Expression::Unary(Box::new(UnaryExpr { op, operand, pstate: self.pstate(start) })));
+        }
+
+        if self.scan_keyword("not") {
+            self.skip_ws();
+            let operand = self.unary()?;
+            return Ok(Expression::Unary(Box::new(UnaryExpr { op: UnaryOperator::Not, operand, pstate: self.pstate(start) })));
+        }
+
+        self.primary()
+    }
+
+    fn primary(&mut self) -> SassResult<Expression> {
+        let start = self.pos;
+        match self.peek() {
+            None => Err(self.error("Expected expression.")),
+            Some('(') => self.paren_expression(),
+            Some('[') => self.bracketed_list(),
+            Some('$') => {
+                self.next();
+                let name = normalize_name(&self.identifier()?);
+                Ok(Expression::Variable(VariableExpr { namespace: None, name, pstate: self.pstate(start) }))
+            }
+            Some('"') | Some('\'') => {
+                let text = self.interpolated_string()?;
+                Ok(Expression::String(StringExpr { text, quoted: true }))
+            }
+            Some('&') => {
+                self.next();
+                Ok(Expression::ParentSelector(self.pstate(start)))
+            }
+            Some('#') if self.looking_at("#{") => self.identifier_like(),
+            Some('#') => self.hex_color(),
+            Some('!') if self.looking_at_important() => self.important_expression(),
+            Some(c) if c.is_ascii_digit() || c == '.' || c == '+' || c == '-' && !self.looking_at_interpolated_identifier() => self.number(),
+            Some(_) if self.looking_at_interpolated_identifier() => self.identifier_like(),
+            Some(_) => Err(self.error("Expected expression.")),
+        }
+    }
+
+    fn paren_expression(&mut self) -> SassResult<Expression> {
+        let start = self.pos;
+        self.expect_char('(')?;
+        self.skip_ws();
+
+        if self.scan_char(')') {
+            return Ok(Expression::List(ListExpr {
+                items: vec![],
+                separator: ListSeparator::Undecided,
+                bracketed: false,
+                pstate: self.pstate(start),
+            }));
+        }
+
+        let first = self.space_list()?;
+        self.skip_ws();
+
+        if self.scan_char(':') {
+            return self.map(start, first);
+        }
+
+        if !self.scan_char(',') {
+            self.skip_ws();
+            self.expect_char(')')?;
+            return Ok(Expression::Paren(Box::new(first), self.pstate(start)));
+        }
+
+        let mut items = vec![first];
+        loop {
+            self.skip_ws();
+            if !self.looking_at_expression() {
+                break;
+            }
+            items.push(self.space_list()?);
+            self.skip_ws();
+            if !self.scan_char(',') {
+                break;
+            }
+        }
+
+        self.skip_ws();
+        self.expect_char(')')?;
+        Ok(Expression::List(ListExpr { items, separator: ListSeparator::Comma, bracketed: false, pstate: self.pstate(start) }))
+    }
+
+    fn map(&mut self, start: usize, first_key: Expression) -> SassResult<Expression> {
+        self.skip_ws();
+        let first_value = self.space_list()?;
+        let mut pairs = vec![(first_key, first_value)];
+
+        loop {
+            self.skip_ws();
+            if !self.scan_char(',') {
+                break;
+            }
+            self.skip_ws();
+            if !self.looking_at_expression() {
+                break;
+            }
+            let key = self.space_list()?;
+            self.skip_ws();
+            self.expect_char(':')?;
+            self.skip_ws();
+            let value = self.space_list()?;
+            pairs.push((key, value));
+        }
+
+        self.skip_ws();
+        self.expect_char(')')?;
+        Ok(Expression::Map(MapExpr { pairs, pstate: self.pstate(start) }))
+    }
+
+    fn bracketed_list(&mut self) -> SassResult<Expression> {
+        let start = self.pos;
+        self.expect_char('[')?;
+        self.skip_ws();
+
+        if self.scan_char(']') {
+            return Ok(Expression::List(ListExpr {
+                items: vec![],
+                separator: ListSeparator::Undecided,
+                bracketed: true,
+                pstate: self.pstate(start),
+            }));
+        }
+
+        let inner = self.expression()?;
+        self.skip_ws();
+        self.expect_char(']')?;
+        let pstate = self.pstate(start);
+
+        match inner {
+            Expression::List(mut list) if !list.bracketed => {
+                list.bracketed = true;
+                list.pstate = pstate;
+                Ok(Expression::List(list))
+            }
+            other => Ok(Expression::List(ListExpr {
+                items: vec![other],
+                separator: ListSeparator::Undecided,
+                bracketed: true,
+                pstate,
+            })),
+        }
+    }
+
+    fn hex_color(&mut self) -> SassResult<Expression> {
+        let start = self.pos;
+        self.expect_char('#')?;
+        let digits_start = self.pos;
+        while self.peek().map_or(false, |c| c.is_ascii_hexdigit()) {
+            self.next();
+        }
+        let digits = self.src()[digits_start..self.pos].to_string();
+        let followed_by_name = self.peek().map_or(false, is_name);
+
+        let parse = |s: &str| f64::from(u8::from_str_radix(s, 16).unwrap());
+        let double = |s: &str| parse(&format!("{}{}", s, s));
+        let color = match digits.len() {
+            _ if followed_by_name => None,
+            3 => Some(Color::rgba(double(&digits[0..1]), double(&digits[1..2]), double(&digits[2..3]), 1.0)),
+            4 => Some(Color::rgba(double(&digits[0..1]), double(&digits[1..2]), double(&digits[2..3]), double(&digits[3..4]) / 255.0)),
+            6 => Some(Color::rgba(parse(&digits[0..2]), parse(&digits[2..4]), parse(&digits[4..6]), 1.0)),
+            8 => Some(Color::rgba(parse(&digits[0..2]), parse(&digits[2..4]), parse(&digits[4..6]), parse(&digits[6..8]) / 255.0)),
+            _ => None,
+        };
+
+        match color {
+            Some(color) => {
+                let pstate = self.pstate(start);
+                let color = color.with_format(ColorFormat::Literal(pstate.text().to_string()));
+                Ok(Expression::Value(Value::Color(color), pstate))
+            }
+            None => {
+                // not a color, such as `#foo`, so read it as an unquoted string
+                self.identifier_body(&mut String::new(), false);
+                let pstate = self.pstate(start);
+                let text = pstate.text().to_string();
+                Ok(Expression::String(StringExpr { text: Interpolation::plain(text, pstate), quoted: false }))
+            }
+        }
+    }
+
+    fn number(&mut self) -> SassResult<Expression> {
+        let start = self.pos;
+        let negative = if self.scan_char('-') {
+            true
+        } else {
+            self.scan_char('+');
+            false
+        };
+
+        let digits_start = self.pos;
+        while self.peek().map_or(false, |c| c.is_ascii_digit()) {
+            self.next();
+        }
+        if self.peek() == Some('.') && self.peek_at(1).map_or(false, |c| c.is_ascii_digit()) {
+            self.next();
+            while self.peek().map_or(false, |c| c.is_ascii_digit()) {
+                self.next();
+            }
+        }
+        if self.pos == digits_start {
+            return Err(self.error("Expected number."));
+        }
+
+        // an exponent, but not the start of a unit such as `em`
+        if self.peek() == Some('e') || self.peek() == Some('E') {
+            let next = self.peek_at(1);
+            let after = self.peek_at(2);
+            let is_exponent = next.map_or(false, |c| c.is_ascii_digit()) ||
+                (next == Some('-') || next == Some('+')) && after.map_or(false, |c| c.is_ascii_digit());
+            if is_exponent {
+                self.next();
+                if self.peek() == Some('-') || self.peek() == Some('+') {
+                    self.next();
+                }
+                while self.peek().map_or(false, |c| c.is_ascii_digit()) {
+                    self.next();
+                }
+            }
+        }
+
+        let mut value: f64 = self.src()[digits_start..self.pos].parse()
+            .map_err(|_| SassError::new("Invalid number.", &self.pstate(start)))?;
+        if negative {
+            value = -value;
+        }
+
+        let unit = if self.scan_char('%') {
+            "%".to_string()
+        } else if self.looking_at_identifier() && !(self.peek() == Some('-') && self.peek_at(1) == Some('-')) {
+            self.unit()?
+        } else {
+            String::new()
+        };
+
+        Ok(Expression::Value(Value::Number(Number::with_unit(value, unit)), self.pstate(start)))
+    }
+
+    /// Parses an identifier, which may be a keyword, a color name,
+    /// a function call or an unquoted string.
+    fn identifier_like(&mut self) -> SassResult<Expression> {
+        let start = self.pos;
+
+        if self.looking_at_ignore_case("url(") {
+            let after = self.rest()[4..].trim_start().chars().next();
+            if after != Some('"') && after != Some('\'') && after != Some('$') {
+                let text = self.raw_url()?;
+                return Ok(Expression::String(StringExpr { text, quoted: false }));
+            }
+        }
+
+        let identifier = self.interpolated_identifier()?;
+        let name = match identifier.as_plain() {
+            Some(name) => name.to_string(),
+            None => {
+                if self.peek() == Some('(') {
+                    let args = self.argument_invocation()?;
+                    let pstate = self.pstate(start);
+                    return Ok(Expression::InterpolatedFunctionCall(InterpolatedFunctionCall { name: identifier, args, pstate }));
+                }
+                return Ok(Expression::String(StringExpr { text: identifier, quoted: false }));
+            }
+        };
+
+        if let Some(expr) = self.special_function(&name, start)? {
+            return Ok(expr);
+        }
+
+        if name.eq_ignore_ascii_case("alpha") && self.peek() == Some('(') {
+            if let Some(args) = self.microsoft_alpha_args()? {
+                return Ok(Expression::FunctionCall(FunctionCall { namespace: None, name, args, pstate: self.pstate(start) }));
+            }
+        }
+
+        if self.peek() == Some('(') {
+            let calculation = ["calc", "clamp", "min", "max"].iter().any(|n| name.eq_ignore_ascii_case(n));
+            let outer = ::std::mem::replace(&mut self.in_calculation, calculation);
+            let args = self.argument_invocation();
+            self.in_calculation = outer;
+            let args = args?;
+            if name == "if" {
+                return Ok(Expression::If(IfExpr { args, pstate: self.pstate(start) }));
+            }
+            return Ok(Expression::FunctionCall(FunctionCall { namespace: None, name, args, pstate: self.pstate(start) }));
+        }
+
+        if self.peek() == Some('.') && self.peek_at(1) != Some('.') {
+            if let Some(expr) = self.namespaced_expression(&name, start)? {
+                return Ok(expr);
+            }
+        }
+
+        let pstate = self.pstate(start);
+        match name.as_str() {
+            "true" => return Ok(Expression::Value(Value::Boolean(true), pstate)),
+            "false" => return Ok(Expression::Value(Value::Boolean(false), pstate)),
+            "null" => return Ok(Expression::Value(Value::Null, pstate)),
+            _ => {}
+        }
+
+        if let Some(color) = Value::from_color_name(&name) {
+            return Ok(Expression::Value(color, pstate));
+        }
+
+        Ok(Expression::String(StringExpr { text: Interpolation::plain(name, pstate), quoted: false }))
+    }
+
+    /// Parses a call of a function whose arguments are plain CSS, such as
+    /// `element(#id)`, `-webkit-calc(...)` or `progid:...(...)`, as text in
+    /// which only interpolation is evaluated. Gives None, having read
+    /// nothing more, if it isn't one.
+    fn special_function(&mut self, name: &str, start: usize) -> SassResult<Option<Expression>> {
+        let normalized = unvendor(name);
+        let mut buffer = InterpolationBuffer::default();
+        buffer.push_str(name);
+        match normalized.as_str() {
+            // calc() itself is a calculation, but prefixed it's kept as written
+            "calc" if normalized.len() != name.len() => {}
+            "element" | "expression" => {}
+            "progid" => {
+                if !self.scan_char(':') {
+                    return Ok(None);
+                }
+                buffer.push(':');
+                while let Some(c) = self.peek() {
+                    if !c.is_ascii_alphabetic() && c != '.' {
+                        break;
+                    }
+                    self.next();
+                    buffer.push(c);
+                }
+                if self.peek() != Some('(') {
+                    return Err(self.error("expected \"(\"."));
+                }
+            }
+            _ => return Ok(None),
+        }
+        if !self.scan_char('(') {
+            return Ok(None);
+        }
+        buffer.push('(');
+        self.interpolated_declaration_value(&mut buffer)?;
+        self.expect_char(')')?;
+        buffer.push(')');
+        let text = buffer.finish(self.pstate(start));
+        Ok(Some(Expression::String(StringExpr { text, quoted: false })))
+    }
+
+    /// Parses a member of another module, such as `ns.$x` or `ns.f()`,
+    /// after the namespace. Gives None, having read nothing more, if it
+    /// is something else.
+    fn namespaced_expression(&mut self, namespace: &str, start: usize) -> SassResult<Option<Expression>> {
+        let before = self.pos;
+        self.expect_char('.')?;
+        if self.scan_char('$') {
+            let name = normalize_name(&self.identifier()?);
+            let pstate = self.pstate(start);
+            return Ok(Some(Expression::Variable(VariableExpr { namespace: Some(namespace.to_string()), name, pstate })));
+        }
+
+        if self.looking_at_identifier() {
+            let name = self.identifier()?;
+            if self.peek() == Some('(') {
+                let args = self.argument_invocation()?;
+                let pstate = self.pstate(start);
+                return Ok(Some(Expression::FunctionCall(FunctionCall { namespace: Some(namespace.to_string()), name, args, pstate })));
+            }
+        }
+        self.pos = before;
+        Ok(None)
+    }
+
+    /// Parses the parameters of a mixin or function, with optional
+    /// defaults and a final `$rest...` parameter.
+    fn argument_declaration(&mut self) -> SassResult<ArgumentDeclaration> {
+        let start = self.pos;
+        self.expect_char('(')?;
+        let mut params: Vec<Parameter> = Vec::new();
+        let mut rest = None;
+
+        loop {
+            self.skip_ws();
+            if self.peek() == Some(')') {
+                break;
+            }
+
+            let param_start = self.pos;
+            self.expect_char('$')?;
+            let name = normalize_name(&self.identifier()?);
+            self.skip_ws();
+
+            if self.looking_at("...") {
+                self.pos += 3;
+                self.skip_ws();
+                rest = Some(name);
+                break;
+            }
+
+            let default = if self.scan_char(':') {
+                self.skip_ws();
+                Some(self.space_list()?)
+            } else {
+                None
+            };
+            if params.iter().any(|p| p.name == name) {
+                return Err(SassError::new("Duplicate argument.", &self.pstate(param_start)));
+            }
+            params.push(Parameter { name, default, pstate: self.pstate(param_start) });
+
+            self.skip_ws();
+            if !self.scan_char(',') {
+                break;
+            }
+        }
+
+        self.skip_ws();
+        self.expect_char(')')?;
+        Ok(ArgumentDeclaration { params, rest, pstate: self.pstate(start) })
+    }
+
+    fn argument_invocation(&mut self) -> SassResult<ArgumentInvocation> {
+        let start = self.pos;
+        self.expect_char('(')?;
+        let mut positional = Vec::new();
+        let mut named: Vec<(String, Expression)> = Vec::new();
+        let mut rest = None;
+        let mut keyword_rest = None;
+
+        loop {
+            self.skip_ws();
+            if self.peek() == Some(')') {
+                break;
+            }
+
+            let arg_start = self.pos;
+            let mut keyword = None;
+            if self.peek() == Some('$') {
+                self.next();
+                let name = normalize_name(&self.identifier()?);
+                self.skip_ws();
+                if self.scan_char(':') {
+                    keyword = Some(name);
+                } else {
+                    self.pos = arg_start;
+                }
+            }
+
+            self.skip_ws();
+            let expr = self.space_list()?;
+            self.skip_ws();
+            if keyword.is_none() && self.looking_at("...") {
+                self.pos += 3;
+                if rest.is_none() {
+                    rest = Some(Box::new(expr));
+                } else if keyword_rest.is_none() {
+                    keyword_rest = Some(Box::new(expr));
+                } else {
+                    return Err(self.error("expected \")\"."));
+                }
+                self.skip_ws();
+                if !self.scan_char(',') {
+                    break;
+                }
+                continue;
+            }
+            if rest.is_some() {
+                return Err(SassError::new("Only keyword arguments may follow variable arguments.", &self.pstate(arg_start)));
+            }
+
+            match keyword {
+                Some(name) => {
+                    if named.iter().any(|(n, _)| *n == name) {
+                        return Err(SassError::new("Duplicate argument.", &self.pstate(arg_start)));
+                    }
+                    named.push((name, expr));
+                }
+                None => {
+                    if !named.is_empty() {
+                        return Err(SassError::new("Positional arguments must come before keyword arguments.", &self.pstate(arg_start)));
+                    }
+                    positional.push(expr);
+                }
+            }
+
+            self.skip_ws();
+            if !self.scan_char(',') {
+                break;
+            }
+        }
+
+        self.skip_ws();
+        self.expect_char(')')?;
+        Ok(ArgumentInvocation { positional, named, rest, keyword_rest, pstate: self.pstate(start) })
+    }
+
+    /// Parses the arguments of the proprietary Microsoft alpha() filter,
+    /// such as `(opacity=50)`, each as an unquoted `name=value` string.
+    /// Gives None, without consuming anything, for ordinary arguments.
+    fn microsoft_alpha_args(&mut self) -> SassResult<Option<ArgumentInvocation>> {
+        let start = self.pos;
+        self.expect_char('(')?;
+        let mut positional = Vec::new();
+        loop {
+            self.skip_ws();
+            let arg_start = self.pos;
+            if !self.looking_at_identifier() {
+                self.pos = start;
+                return Ok(None);
+            }
+            let name = self.identifier()?;
+            self.skip_ws();
+            if self.peek() != Some('=') || self.looking_at("==") {
+                self.pos = start;
+                return Ok(None);
+            }
+            self.next();
+            self.skip_ws();
+
+            let mut buffer = InterpolationBuffer::default();
+            buffer.push_str(&name);
+            buffer.push('=');
+            buffer.add_expression(self.binary(0)?);
+            positional.push(Expression::String(StringExpr { text: buffer.finish(self.pstate(arg_start)), quoted: false }));
+
+            self.skip_ws();
+            if !self.scan_char(',') {
+                break;
+            }
+        }
+
+        self.expect_char(')')?;
+        Ok(Some(ArgumentInvocation { positional, named: vec![], rest: None, keyword_rest: None, pstate: self.pstate(start) }))
+    }
+}
+
+/// Removes a trailing `!important` from the value of a declaration,
+/// as in `a, b !important`, giving whether there was one.
+fn take_important(expr: &mut Expression) -> bool {
+    let single = match *expr {
+        Expression::List(ref mut list) if !list.bracketed => match list.separator {
+            ListSeparator::Comma => return list.items.last_mut().map_or(false, take_important),
+            ListSeparator::Space if list.items.last().map_or(false, is_important) => {
+                list.items.pop();
+                if list.items.len() != 1 {
+                    return true;
+                }
+                list.items.pop().unwrap()
+            }
+            _ => return false,
+        },
+        _ => return false,
+    };
+    *expr = single;
+    true
+}
+
+fn is_important(expr: &Expression) -> bool {
+    match *expr {
+        Expression::String(ref s) => !s.quoted && s.text.as_plain() == Some("!important"),
+        _ => false,
+    }
+}
+
+/// Whether a `/` between these operands may be printed as a slash.
+fn is_slash_operand(expr: &Expression) -> bool {
+    match *expr {
+        Expression::Value(Value::Number(_), _) => true,
+        Expression::Binary(ref b) => b.allows_slash,
+        _ => false,
+    }
+}
+
+/// Whether an imported url is plain CSS rather than a Sass file.
+pub fn is_plain_import_url(url: &str) -> bool {
+    url.ends_with(".css") || url.starts_with("http://") || url.starts_with("https://") || url.starts_with("//")
+}
@@ -0,0 +1,191 @@
+// FROM: src/plugins.cpp
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use functions::*;
+use base::LIBSASS_VERSION;
+use c_api_helpers::path_to_cstring;
+use libc;
+use libc::c_void;
+use logger::Logger;
+use position::{ParserState, SourceFile};
+
+#[cfg(target_os = "macos")]
+const PLUGIN_EXTENSION: &str = "dylib";
+#[cfg(all(unix, not(target_os = "macos")))]
+const PLUGIN_EXTENSION: &str = "so";
+#[cfg(windows)]
+const PLUGIN_EXTENSION: &str = "dll";
+
+type PluginVersionFn = extern fn() -> *const c_char;
+type PluginLoadFunctionsFn = extern fn() -> SassFunctionList;
+type PluginLoadImportersFn = extern fn() -> SassImporterList;
+
+// Native plugins found in the plugin paths. The libraries stay loaded
+// for as long as this struct is alive, because the functions and
+// importers they returned point into them.
+#[derive(Default, Debug)]
+pub struct Plugins {
+    // the directories already searched, which are not loaded twice
+    paths: Vec<PathBuf>,
+    handles: Vec<*mut c_void>,
+    pub functions: Vec<SassFunctionEntry>,
+    pub importers: Vec<SassImporterEntry>,
+    pub headers: Vec<SassImporterEntry>,
+}
+
+impl Plugins {
+    /// Loads every shared object in `path`. Plugins that fail to load are
+    /// reported as warnings to `logger`. Returns the number of plugins
+    /// that were loaded successfully, which is 0 if the directory was
+    /// already loaded.
+    pub fn load_plugins<P: AsRef<Path>>(&mut self, path: P, logger: &dyn Logger) -> usize {
+        let path = path.as_ref();
+        if self.paths.iter().any(|p| p == path) {
+            return 0;
+        }
+        self.paths.push(path.to_path_buf());
+
+        let entries = match path.read_dir() {
+            Ok(entries) => entries,
+            Err(_) => return 0,
+        };
+
+        let mut loaded = 0;
+        for entry in entries.filter_map(|e| e.ok()) {
+            let plugin = entry.path();
+            let is_plugin = plugin.extension().map_or(false, |e| e == PLUGIN_EXTENSION);
+            if !is_plugin {
+                continue;
+            }
+            match self.load_plugin(&plugin) {
+                Ok(()) => loaded += 1,
+                Err(message) => {
+                    let source = SourceFile::new(plugin.to_string_lossy(), "");
+                    logger.warn(&message, &ParserState::new(&source, 0, 0), &[]);
+                }
+            }
+        }
+
+        loaded
+    }
+
+    /// Loads one specific plugin, or says why it could not be loaded.
+    #[cfg(unix)]
+    pub fn load_plugin(&mut self, path: &Path) -> Result<(), String> {
+        let cpath = path_to_cstring(path);
+        let plugin = unsafe { libc::dlopen(cpath.as_ptr(), libc::RTLD_LAZY) };
+        if plugin.is_null() {
+            return Err(format!("failed loading plugin <{}>{}", path.display(), dlerror()));
+        }
+
+        // try to load initial function to query libsass version support
+        let plugin_version = match load_fn(plugin, "libsass_get_version") {
+            Some(f) => unsafe { ::std::mem::transmute::<*mut c_void, PluginVersionFn>(f) },
+            None => {
+                let message = format!("failed loading 'libsass_get_version' in <{}>{}", path.display(), dlerror());
+                unsafe { libc::dlclose(plugin) };
+                return Err(message);
+            }
+        };
+
+        // get the libsass version of the plugin
+        let their_version = plugin_version();
+        let their_version = if their_version.is_null() {
+            "[na]".into()
+        } else {
+            unsafe { CStr::from_ptr(their_version) }.to_string_lossy()
+        };
+        if !compatibility(&their_version) {
+            unsafe { libc::dlclose(plugin) };
+            return Err(format!("skipping plugin <{}>: built for libsass {}, but this is libsass {}",
+                               path.display(), their_version, LIBSASS_VERSION.trim_end_matches('\0')));
+        }
+
+        if let Some(f) = load_fn(plugin, "libsass_load_functions") {
+            let load = unsafe { ::std::mem::transmute::<*mut c_void, PluginLoadFunctionsFn>(f) };
+            let fns = load();
+            self.functions.extend(list_to_vec(fns));
+            // only delete the container, items not yet
+            sass_free_list(fns);
+        }
+
+        if let Some(f) = load_fn(plugin, "libsass_load_importers") {
+            let load = unsafe { ::std::mem::transmute::<*mut c_void, PluginLoadImportersFn>(f) };
+            let imps = load();
+            self.importers.extend(list_to_vec(imps));
+            sass_free_list(imps);
+        }
+
+        if let Some(f) = load_fn(plugin, "libsass_load_headers") {
+            let load = unsafe { ::std::mem::transmute::<*mut c_void, PluginLoadImportersFn>(f) };
+            let imps = load();
+            self.headers.extend(list_to_vec(imps));
+            sass_free_list(imps);
+        }
+
+        self.handles.push(plugin);
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    pub fn load_plugin(&mut self, path: &Path) -> Result<(), String> {
+        Err(format!("failed loading plugin <{}>: plugins are not supported on Windows yet", path.display()))
+    }
+}
+
+impl Drop for Plugins {
+    fn drop(&mut self) {
+        // The entries point into the libraries, so they go first.
+        for &entry in &self.functions {
+            sass_delete_function(entry);
+        }
+        for &entry in self.importers.iter().chain(self.headers.iter()) {
+            sass_delete_importer(entry);
+        }
+        #[cfg(unix)]
+        for &handle in &self.handles {
+            unsafe { libc::dlclose(handle) };
+        }
+    }
+}
+
+// check if plugin is compatible with this version
+// plugins may be linked static against libsass
+// we try to be compatible between major versions
+fn compatibility(their_version: &str) -> bool {
+    let our_version = LIBSASS_VERSION.trim_end_matches('\0');
+
+    // first check if anyone has an unknown version
+    if their_version == "[na]" || our_version == "[na]" {
+        return false;
+    }
+
+    // find the position of the second dot
+    let second_dot = our_version.match_indices('.').nth(1).map(|(pos, _)| pos);
+    match second_dot {
+        // otherwise only compare up to the second dot (major versions)
+        Some(pos) => their_version.get(..pos) == our_version.get(..pos),
+        // if we do not have two dots we fallback to compare complete string
+        None => their_version == our_version,
+    }
+}
+
+#[cfg(unix)]
+fn load_fn(plugin: *mut c_void, name: &str) -> Option<*mut c_void> {
+    let name = CString::new(name).expect("Symbol names do not contain nulls");
+    let f = unsafe { libc::dlsym(plugin, name.as_ptr()) };
+    if f.is_null() { None } else { Some(f) }
+}
+
+// The reason dlopen or dlsym last failed, as a suffix for a message.
+#[cfg(unix)]
+fn dlerror() -> String {
+    let err = unsafe { libc::dlerror() };
+    if err.is_null() {
+        String::new()
+    } else {
+        format!(": {}", unsafe { CStr::from_ptr(err) }.to_string_lossy())
+    }
+}
@@ -0,0 +1,89 @@
+// FROM: src/position.hpp
+
+use std::fmt;
+use std::rc::Rc;
+
+// A stylesheet (file, string or importer result) handed to the parser.
+pub struct SourceFile {
+    pub path: String,
+    pub contents: String,
+    // byte offsets of the start of each line
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new<P: Into<String>, S: Into<String>>(path: P, contents: S) -> Rc<SourceFile> {
+        let contents = contents.into();
+        let mut line_starts = vec![0];
+        line_starts.extend(contents.match_indices('\n').map(|(i, _)| i + 1));
+        Rc::new(SourceFile { path: path.into(), contents, line_starts })
+    }
+
+    /// Returns the zero-based line and column (in chars) of a byte offset.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let start = self.line_starts[line];
+        let column = self.contents[start..offset].chars().count();
+        (line, column)
+    }
+
+    /// Returns the byte offset of a zero-based line and column (in chars),
+    /// clamped to the end of the line.
+    pub fn offset(&self, line: usize, column: usize) -> usize {
+        let line = line.min(self.line_starts.len() - 1);
+        let start = self.line_starts[line];
+        let text = self.line_text(line);
+        start + text.char_indices().nth(column).map_or(text.len(), |(i, _)| i)
+    }
+
+    /// Returns the text of a zero-based line, without the line ending.
+    pub fn line_text(&self, line: usize) -> &str {
+        let start = self.line_starts[line];
+        let end = self.line_starts.get(line + 1).cloned().unwrap_or(self.contents.len());
+        self.contents[start..end].trim_end_matches(['\n', '\r'])
+    }
+}
+
+impl fmt::Debug for SourceFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SourceFile({})", self.path)
+    }
+}
+
+// Where in the source an AST node came from.
+#[derive(Debug, Clone)]
+pub struct ParserState {
+    pub source: Rc<SourceFile>,
+    // byte offset of the start of the node
+    pub offset: usize,
+    // length of the node in bytes
+    pub length: usize,
+}
+
+impl ParserState {
+    pub fn new(source: &Rc<SourceFile>, offset: usize, length: usize) -> Self {
+        ParserState { source: source.clone(), offset, length }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.source.path
+    }
+
+    // zero-based
+    pub fn line(&self) -> usize {
+        self.source.line_col(self.offset).0
+    }
+
+    // zero-based
+    pub fn column(&self) -> usize {
+        self.source.line_col(self.offset).1
+    }
+
+    /// The source text the node was parsed from.
+    pub fn text(&self) -> &str {
+        &self.source.contents[self.offset..self.offset + self.length]
+    }
+}
@@ -1,5 +1,14 @@
-use sass_context::SassContext;
-use c_api_helpers::ptr_to_ref;
+use std::ffi::CString;
+use std::os::raw::c_int;
+use std::ptr;
+use std::rc::Rc;
+use ast::Stylesheet;
+use context::{Context, Resource};
+use expand::Expand;
+use functions::{SassCalleeEntry, SassImportEntry};
+use output::Output;
+use sass_context::{SassContext, SassInputStyle};
+use c_api_helpers::*;
 use sass_options::SassOptions;
 
 // Compiler states
@@ -18,11 +27,38 @@ pub struct SassCompiler {
     // progress status
     state: SassCompilerState,
     // original c context
-    c_ctx: SassContext,
+    c_ctx: *mut SassContext,
     // Sass::Context
-    //cpp_ctx: Context,
-    // Sass::Block
-    //root: Block_Obj
+    pub cpp_ctx: Context,
+    // the loaded and parsed entry stylesheet
+    entry: Option<Resource>,
+    root: Option<Rc<Stylesheet>>,
+}
+
+/// Creates a compiler for a context. The source string is only given
+/// for data contexts.
+pub fn sass_prepare_context(c_ctx: *mut SassContext, source_string: Option<String>) -> *mut SassCompiler {
+    let ctx = ptr_to_ref(c_ctx);
+    ctx.reset_results();
+
+    let compiler = box_to_raw_ptr(SassCompiler {
+        state: SassCompilerState::Created,
+        c_ctx,
+        cpp_ctx: Context::new(ctx, source_string),
+        entry: None,
+        root: None,
+    });
+    ptr_to_ref(compiler).cpp_ctx.c_compiler = compiler;
+    compiler
+}
+
+/// Runs all compilation steps and stores the result on the context.
+pub fn sass_compile_context(c_ctx: *mut SassContext, source_string: Option<String>) -> c_int {
+    let compiler = sass_prepare_context(c_ctx, source_string);
+    sass_compiler_parse(compiler);
+    sass_compiler_execute(compiler);
+    sass_delete_compiler(compiler);
+    ptr_to_ref(c_ctx).error_status
 }
 
 //
@@ -40,118 +76,152 @@ pub extern fn sass_compiler_get_state(compiler: *mut SassCompiler) -> SassCompil
 #[no_mangle]
 pub extern fn sass_compiler_get_context(compiler: *mut SassCompiler) -> *mut SassContext {
     let compiler = ptr_to_ref(compiler);
-    &mut compiler.c_ctx
+    compiler.c_ctx
 }
 
 #[no_mangle]
 pub extern fn sass_compiler_get_options(compiler: *mut SassCompiler) -> *mut SassOptions {
     let compiler = ptr_to_ref(compiler);
-    &mut compiler.c_ctx.options
+    &mut ptr_to_ref(compiler.c_ctx).options
 }
 
+// Getters for the import stack. The entry file is at the bottom of
+// the stack and the file currently being evaluated is on top.
 #[no_mangle]
-pub extern fn sass_compiler_get_stack_size(compiler: *mut SassCompiler) -> usize {
+pub extern fn sass_compiler_get_import_stack_size(compiler: *mut SassCompiler) -> usize {
     let compiler = ptr_to_ref(compiler);
-    //compiler.cpp_ctx..import_stack.size()
-    0
+    compiler.cpp_ctx.import_stack.borrow().len()
 }
 
 #[no_mangle]
-pub extern fn sass_compiler_get_last_import(compiler: *mut SassCompiler) -> usize {
+pub extern fn sass_compiler_get_stack_size(compiler: *mut SassCompiler) -> usize {
+    sass_compiler_get_import_stack_size(compiler)
+}
+
+#[no_mangle]
+pub extern fn sass_compiler_get_last_import(compiler: *mut SassCompiler) -> SassImportEntry {
     let compiler = ptr_to_ref(compiler);
-    //compiler.cpp_ctx.import_stack.back() // the last element, should be a SassImportEntry,
-    // which is not defined yet. It is a function pointer.
-    // SassImportEntry
-    0
+    compiler.cpp_ctx.import_stack.borrow().last().cloned().unwrap_or(ptr::null_mut())
 }
 
 #[no_mangle]
-pub extern fn sass_compiler_get_import_entry(compiler: *mut SassCompiler) -> usize {
+pub extern fn sass_compiler_get_import_entry(compiler: *mut SassCompiler, idx: usize) -> SassImportEntry {
     let compiler = ptr_to_ref(compiler);
-    // return compiler->cpp_ctx->import_stack[idx]; // SassImportEntry
-    0
+    compiler.cpp_ctx.import_stack.borrow().get(idx).cloned().unwrap_or(ptr::null_mut())
 }
 
+// Getters for the callee stack, with the innermost call on top.
 #[no_mangle]
 pub extern fn sass_compiler_get_callee_stack_size(compiler: *mut SassCompiler) -> usize {
     let compiler = ptr_to_ref(compiler);
-    // return compiler->cpp_ctx->callee_stack.size();
-    0
+    compiler.cpp_ctx.callee_stack.borrow().len()
+}
+
+#[no_mangle]
+pub extern fn sass_compiler_get_last_callee(compiler: *mut SassCompiler) -> SassCalleeEntry {
+    let compiler = ptr_to_ref(compiler);
+    compiler.cpp_ctx.callee_stack.borrow().last().cloned().unwrap_or(ptr::null_mut())
+}
+
+#[no_mangle]
+pub extern fn sass_compiler_get_callee_entry(compiler: *mut SassCompiler, idx: usize) -> SassCalleeEntry {
+    let compiler = ptr_to_ref(compiler);
+    compiler.cpp_ctx.callee_stack.borrow().get(idx).cloned().unwrap_or(ptr::null_mut())
 }
 
+// Execute the different compilation steps individually
+// Usefull if you only want to query the included files
 #[no_mangle]
-pub extern fn sass_compiler_get_last_callee(compiler: *mut SassCompiler) -> usize {
-    // should return a SassCalleeEntry
+pub extern fn sass_compiler_parse(compiler: *mut SassCompiler) -> c_int {
+    if compiler.is_null() {
+        return 1;
+    }
     let compiler = ptr_to_ref(compiler);
-    // return &compiler->cpp_ctx->callee_stack.back();
-    0
+    match compiler.state {
+        SassCompilerState::Parsed => return 0,
+        SassCompilerState::Executed => return -1,
+        SassCompilerState::Created => {}
+    }
+    let c_ctx = ptr_to_ref(compiler.c_ctx);
+    if c_ctx.error_status != 0 {
+        return c_ctx.error_status;
+    }
+
+    compiler.state = SassCompilerState::Parsed;
+    let entry = match compiler.cpp_ctx.load_entry() {
+        Ok(entry) => entry,
+        Err(msg) => {
+            c_ctx.set_internal_error(&msg);
+            return c_ctx.error_status;
+        }
+    };
+
+    match compiler.cpp_ctx.parse(&entry) {
+        Ok(root) => {
+            compiler.entry = Some(entry);
+            compiler.root = Some(root);
+            0
+        }
+        Err(e) => {
+            c_ctx.set_error(&e);
+            c_ctx.error_status
+        }
+    }
 }
 
 #[no_mangle]
-pub extern fn sass_compiler_get_callee_entry(compiler: *mut SassCompiler, idx: usize) -> usize {
-    // should return a SassCalleeEntry
+pub extern fn sass_compiler_execute(compiler: *mut SassCompiler) -> c_int {
+    if compiler.is_null() {
+        return 1;
+    }
     let compiler = ptr_to_ref(compiler);
-    // return &compiler->cpp_ctx->callee_stack[idx];
-    0
-}
-
-/*
-//// Execute the different compilation steps individually
-//// Usefull if you only want to query the included files
-  int ADDCALL sass_compiler_parse(struct Sass_Compiler* compiler)
-  {
-    if (compiler == 0) return 1;
-    if (compiler->state == SASS_COMPILER_PARSED) return 0;
-    if (compiler->state != SASS_COMPILER_CREATED) return -1;
-    if (compiler->c_ctx == NULL) return 1;
-    if (compiler->cpp_ctx == NULL) return 1;
-    if (compiler->c_ctx->error_status)
-      return compiler->c_ctx->error_status;
-    // parse the context we have set up (file or data)
-    compiler->root = sass_parse_block(compiler);
-    // success
-    return 0;
-  }
-*/
-
-/*
-  int ADDCALL sass_compiler_execute(struct Sass_Compiler* compiler)
-  {
-    if (compiler == 0) return 1;
-    if (compiler->state == SASS_COMPILER_EXECUTED) return 0;
-    if (compiler->state != SASS_COMPILER_PARSED) return -1;
-    if (compiler->c_ctx == NULL) return 1;
-    if (compiler->cpp_ctx == NULL) return 1;
-    if (compiler->root.isNull()) return 1;
-    if (compiler->c_ctx->error_status)
-      return compiler->c_ctx->error_status;
-    compiler->state = SASS_COMPILER_EXECUTED;
-    Context* cpp_ctx = compiler->cpp_ctx;
-    Block_Obj root = compiler->root;
-    // compile the parsed root block
-    try { compiler->c_ctx->output_string = cpp_ctx->render(root); }
-    // pass catched errors to generic error handler
-    catch (...) { return handle_errors(compiler->c_ctx) | 1; }
-    // generate source map json and store on context
-    compiler->c_ctx->source_map_string = cpp_ctx->render_srcmap();
-    // success
-    return 0;
-  }
-*/
-
-/*
-//// Release all memory allocated with the compiler
-//// This does _not_ include any contexts or options
-void ADDCALL sass_delete_compiler (struct Sass_Compiler* compiler)
-  {
-    if (compiler == 0) {
-      return;
+    match compiler.state {
+        SassCompilerState::Executed => return 0,
+        SassCompilerState::Created => return -1,
+        SassCompilerState::Parsed => {}
+    }
+    let c_ctx = ptr_to_ref(compiler.c_ctx);
+    if c_ctx.error_status != 0 {
+        return c_ctx.error_status;
     }
-    Context* cpp_ctx = compiler->cpp_ctx;
-    if (cpp_ctx) delete(cpp_ctx);
-    compiler->cpp_ctx = NULL;
-    compiler->c_ctx = NULL;
-    compiler->root = NULL;
-    free(compiler);
-  }
-*/
\ No newline at end of file
+    let (entry, root) = match (compiler.entry.as_ref(), compiler.root.as_ref()) {
+        (Some(entry), Some(root)) => (entry, root),
+        _ => return 1,
+    };
+
+    compiler.state = SassCompilerState::Executed;
+    let cpp_ctx = &compiler.cpp_ctx;
+    let result = Expand::new(cpp_ctx).expand_root(entry, root);
+
+    // the entry of a data context is not a file
+    let skip_entry = c_ctx.context_type == SassInputStyle::Data;
+    c_ctx.set_included_files(cpp_ctx.included_files(skip_entry));
+
+    match result {
+        Ok(css) => {
+            let output = Output::new(cpp_ctx.output_style, &cpp_ctx.indent, &cpp_ctx.linefeed, cpp_ctx.source_comments).render(&css);
+            match CString::new(output) {
+                Ok(output) => {
+                    c_ctx.output_string = Some(output);
+                    0
+                }
+                Err(_) => {
+                    // a C string ends at the first null character
+                    c_ctx.set_internal_error("The output contains a null character");
+                    c_ctx.error_status
+                }
+            }
+        }
+        Err(e) => {
+            c_ctx.set_error(&e);
+            c_ctx.error_status
+        }
+    }
+}
+
+// Release all memory allocated with the compiler
+// This does _not_ include any contexts or options
+#[no_mangle]
+pub extern fn sass_delete_compiler(compiler: *mut SassCompiler) {
+    drop_raw_ptr(compiler);
+}
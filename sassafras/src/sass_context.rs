@@ -1,9 +1,13 @@
 use sass_options::SassOptions;
-use std::path::PathBuf;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::ptr;
 use c_api_helpers::ptr_to_ref;
+use base::sass_copy_c_string;
+use error_handling::{rel_path, traces_to_string, SassError};
 
 // input behaviours
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 #[repr(C)]
 pub enum SassInputStyle {
     Null,
@@ -25,49 +29,263 @@ pub struct SassContext {
     // store context type info
     pub context_type: SassInputStyle,
     // generated output data
-    pub output_string: String,
+    pub output_string: Option<CString>,
     // generated source map json
-    pub source_map_string: String,
+    pub source_map_string: Option<CString>,
 
     // error status
     pub error_status: i32,
-    pub error_json: String,
-    pub error_text: String,
-    pub error_message: String,
+    pub error_json: Option<CString>,
+    pub error_text: Option<CString>,
+    pub error_message: Option<CString>,
 
     // error position
-    pub error_file: PathBuf,
+    pub error_file: Option<CString>,
     pub error_line: usize,
     pub error_column: usize,
-    pub error_src: String,
+    pub error_src: Option<CString>,
 
     // report imported files
-    pub included_files: Vec<PathBuf>,
-}
-
-//// Getters for Sass_Context values
-//ADDAPI const char* ADDCALL sass_context_get_output_string (struct Sass_Context* ctx);
-//ADDAPI int ADDCALL sass_context_get_error_status (struct Sass_Context* ctx);
-//ADDAPI const char* ADDCALL sass_context_get_error_json (struct Sass_Context* ctx);
-//ADDAPI const char* ADDCALL sass_context_get_error_text (struct Sass_Context* ctx);
-//ADDAPI const char* ADDCALL sass_context_get_error_message (struct Sass_Context* ctx);
-//ADDAPI const char* ADDCALL sass_context_get_error_file (struct Sass_Context* ctx);
-//ADDAPI const char* ADDCALL sass_context_get_error_src (struct Sass_Context* ctx);
-//ADDAPI size_t ADDCALL sass_context_get_error_line (struct Sass_Context* ctx);
-//ADDAPI size_t ADDCALL sass_context_get_error_column (struct Sass_Context* ctx);
-//ADDAPI const char* ADDCALL sass_context_get_source_map_string (struct Sass_Context* ctx);
-//ADDAPI char** ADDCALL sass_context_get_included_files (struct Sass_Context* ctx);
-
-//// Calculate the size of the stored null terminated array
-//ADDAPI size_t ADDCALL sass_context_get_included_files_size (struct Sass_Context* ctx);
-
-//// Take ownership of memory (value on context is set to 0)
-//ADDAPI char* ADDCALL sass_context_take_error_json (struct Sass_Context* ctx);
-//ADDAPI char* ADDCALL sass_context_take_error_text (struct Sass_Context* ctx);
-//ADDAPI char* ADDCALL sass_context_take_error_message (struct Sass_Context* ctx);
-//ADDAPI char* ADDCALL sass_context_take_error_file (struct Sass_Context* ctx);
-//ADDAPI char* ADDCALL sass_context_take_output_string (struct Sass_Context* ctx);
-//ADDAPI char* ADDCALL sass_context_take_source_map_string (struct Sass_Context* ctx);
+    pub included_files: Vec<CString>,
+    // null terminated array pointing into included_files
+    included_files_array: Vec<*const c_char>,
+}
+
+impl SassContext {
+    /// Clears the results of any previous compilation.
+    pub fn reset_results(&mut self) {
+        self.output_string = None;
+        self.source_map_string = None;
+        self.error_status = 0;
+        self.error_json = None;
+        self.error_text = None;
+        self.error_message = None;
+        self.error_file = None;
+        self.error_line = 0;
+        self.error_column = 0;
+        self.error_src = None;
+        self.set_included_files(Vec::new());
+    }
+
+    pub fn set_included_files(&mut self, files: Vec<String>) {
+        self.included_files = files.into_iter().filter_map(|f| CString::new(f).ok()).collect();
+        self.included_files_array = self.included_files.iter().map(|f| f.as_ptr()).collect();
+        self.included_files_array.push(ptr::null());
+    }
+
+    /// Stores a Sass error on the context, formatted the way sassc prints it.
+    pub fn set_error(&mut self, e: &SassError) {
+        let prefix = "Error";
+        let mut msg = format!("{}: ", prefix);
+        let mut got_newline = false;
+        for c in e.message.chars() {
+            if c == '\r' || c == '\n' {
+                got_newline = true;
+            } else if got_newline {
+                msg.push_str(&" ".repeat(prefix.len() + 2));
+                got_newline = false;
+            }
+            msg.push(c);
+        }
+        if !got_newline {
+            msg.push('\n');
+        }
+
+        let path = e.pstate.path();
+        if e.traces.is_empty() {
+            msg.push_str(&format!("{} on line {} of {}\n", " ".repeat(prefix.len() + 2), e.pstate.line() + 1, rel_path(path)));
+        } else {
+            msg.push_str(&traces_to_string(&e.traces, "        "));
+        }
+
+        // show the offending line with a marker below the column
+        let line = e.pstate.line();
+        let column = e.pstate.column();
+        let text: Vec<char> = e.pstate.source.line_text(line).chars().collect();
+        let max_chars = 76;
+        let left_chars = if column > text.len() { column } else { 42 };
+        let move_in = column.saturating_sub(left_chars);
+        let shorten = if text.len() > max_chars + move_in { text.len() - move_in - max_chars } else { 0 };
+        let excerpt: String = text.iter().skip(move_in).take(text.len().saturating_sub(move_in + shorten)).collect();
+        msg.push_str(&format!(">> {}\n", excerpt));
+        msg.push_str(&format!("   {}^\n", "-".repeat(column - move_in)));
+
+        let json = format!("{{\n  \"status\": 1,\n  \"file\": {},\n  \"line\": {},\n  \"column\": {},\n  \"message\": {},\n  \"formatted\": {}\n}}",
+                           json_string(path), line + 1, column + 1, json_string(&e.message), json_string(&msg));
+
+        self.error_json = CString::new(json).ok();
+        self.error_message = CString::new(msg).ok();
+        self.error_text = CString::new(e.message.clone()).ok();
+        self.error_status = 1;
+        self.error_file = CString::new(path).ok();
+        self.error_line = line + 1;
+        self.error_column = column + 1;
+        self.error_src = CString::new(e.pstate.source.contents.clone()).ok();
+        self.output_string = None;
+        self.source_map_string = None;
+    }
+
+    /// Stores an error that has no source position, such as a missing input file.
+    pub fn set_internal_error(&mut self, message: &str) {
+        let msg = format!("Internal Error: {}\n", message);
+        let json = format!("{{\n  \"status\": 1,\n  \"message\": {},\n  \"formatted\": {}\n}}",
+                           json_string(message), json_string(&msg));
+
+        self.error_json = CString::new(json).ok();
+        self.error_message = CString::new(msg).ok();
+        self.error_text = CString::new(message).ok();
+        self.error_status = 1;
+        self.output_string = None;
+        self.source_map_string = None;
+    }
+}
+
+/// Quotes a string for JSON output.
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn optional_c_str(s: &Option<CString>) -> *const c_char {
+    match *s {
+        Some(ref s) => s.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+fn take_c_str(s: &mut Option<CString>) -> *mut c_char {
+    let ptr = sass_copy_c_string(optional_c_str(s));
+    *s = None;
+    ptr
+}
+
+// Getters for Sass_Context values
+#[no_mangle]
+pub extern fn sass_context_get_output_string(ctx: *mut SassContext) -> *const c_char {
+    let ctx = ptr_to_ref(ctx);
+    optional_c_str(&ctx.output_string)
+}
+
+#[no_mangle]
+pub extern fn sass_context_get_error_status(ctx: *mut SassContext) -> i32 {
+    let ctx = ptr_to_ref(ctx);
+    ctx.error_status
+}
+
+#[no_mangle]
+pub extern fn sass_context_get_error_json(ctx: *mut SassContext) -> *const c_char {
+    let ctx = ptr_to_ref(ctx);
+    optional_c_str(&ctx.error_json)
+}
+
+#[no_mangle]
+pub extern fn sass_context_get_error_text(ctx: *mut SassContext) -> *const c_char {
+    let ctx = ptr_to_ref(ctx);
+    optional_c_str(&ctx.error_text)
+}
+
+#[no_mangle]
+pub extern fn sass_context_get_error_message(ctx: *mut SassContext) -> *const c_char {
+    let ctx = ptr_to_ref(ctx);
+    optional_c_str(&ctx.error_message)
+}
+
+#[no_mangle]
+pub extern fn sass_context_get_error_file(ctx: *mut SassContext) -> *const c_char {
+    let ctx = ptr_to_ref(ctx);
+    optional_c_str(&ctx.error_file)
+}
+
+#[no_mangle]
+pub extern fn sass_context_get_error_src(ctx: *mut SassContext) -> *const c_char {
+    let ctx = ptr_to_ref(ctx);
+    optional_c_str(&ctx.error_src)
+}
+
+#[no_mangle]
+pub extern fn sass_context_get_error_line(ctx: *mut SassContext) -> usize {
+    let ctx = ptr_to_ref(ctx);
+    ctx.error_line
+}
+
+#[no_mangle]
+pub extern fn sass_context_get_error_column(ctx: *mut SassContext) -> usize {
+    let ctx = ptr_to_ref(ctx);
+    ctx.error_column
+}
+
+#[no_mangle]
+pub extern fn sass_context_get_source_map_string(ctx: *mut SassContext) -> *const c_char {
+    let ctx = ptr_to_ref(ctx);
+    optional_c_str(&ctx.source_map_string)
+}
+
+#[no_mangle]
+pub extern fn sass_context_get_included_files(ctx: *mut SassContext) -> *const *const c_char {
+    let ctx = ptr_to_ref(ctx);
+    if ctx.included_files_array.is_empty() {
+        ctx.included_files_array.push(ptr::null());
+    }
+    ctx.included_files_array.as_ptr()
+}
+
+// Calculate the size of the stored null terminated array
+#[no_mangle]
+pub extern fn sass_context_get_included_files_size(ctx: *mut SassContext) -> usize {
+    let ctx = ptr_to_ref(ctx);
+    ctx.included_files.len()
+}
+
+// Take ownership of memory (value on context is set to 0)
+#[no_mangle]
+pub extern fn sass_context_take_error_json(ctx: *mut SassContext) -> *mut c_char {
+    let ctx = ptr_to_ref(ctx);
+    take_c_str(&mut ctx.error_json)
+}
+
+#[no_mangle]
+pub extern fn sass_context_take_error_text(ctx: *mut SassContext) -> *mut c_char {
+    let ctx = ptr_to_ref(ctx);
+    take_c_str(&mut ctx.error_text)
+}
+
+#[no_mangle]
+pub extern fn sass_context_take_error_message(ctx: *mut SassContext) -> *mut c_char {
+    let ctx = ptr_to_ref(ctx);
+    take_c_str(&mut ctx.error_message)
+}
+
+#[no_mangle]
+pub extern fn sass_context_take_error_file(ctx: *mut SassContext) -> *mut c_char {
+    let ctx = ptr_to_ref(ctx);
+    take_c_str(&mut ctx.error_file)
+}
+
+#[no_mangle]
+pub extern fn sass_context_take_output_string(ctx: *mut SassContext) -> *mut c_char {
+    let ctx = ptr_to_ref(ctx);
+    take_c_str(&mut ctx.output_string)
+}
+
+#[no_mangle]
+pub extern fn sass_context_take_source_map_string(ctx: *mut SassContext) -> *mut c_char {
+    let ctx = ptr_to_ref(ctx);
+    take_c_str(&mut ctx.source_map_string)
+}
+
 //ADDAPI char** ADDCALL sass_context_take_included_files (struct Sass_Context* ctx);
 
 #[no_mangle]
@@ -1,7 +1,14 @@
 use sass_context::SassContext;
 use c_api_helpers::ptr_to_ref;
 use sass_options::SassOptions;
-use c_api_helpers::drop_raw_ptr;
+use c_api_helpers::*;
+use sass_compiler::{sass_compile_context, sass_prepare_context, SassCompiler};
+use sass_context::SassInputStyle;
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+use libc;
 
 // struct for data compilation
 #[derive(Debug, Default)]
@@ -12,14 +19,71 @@ pub struct SassDataContext {
     srcmap_string: String,
 }
 
+/// Takes ownership of `source_string`, which must have been allocated
+/// with `sass_alloc_memory` or `sass_copy_c_string`.
+// Safety: as in libsass, `source_string` is trusted to be null or a
+// null-terminated string from the sass allocator.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern fn sass_make_data_context(source_string: *mut c_char) -> *mut SassDataContext {
+    let mut ctx = SassDataContext::default();
+    ctx.context.context_type = SassInputStyle::Data;
+    ctx.context.options.init();
+
+    if source_string.is_null() {
+        ctx.context.set_internal_error("Data context created without a source string");
+    } else {
+        ctx.source_string = unsafe { CStr::from_ptr(source_string) }.to_string_lossy().into_owned();
+        unsafe { libc::free(source_string as *mut libc::c_void) };
+        if ctx.source_string.is_empty() {
+            ctx.context.set_internal_error("Data context created with empty source string");
+        }
+    }
+
+    box_to_raw_ptr(ctx)
+}
+
+#[no_mangle]
+pub extern fn sass_compile_data_context(data_ctx: *mut SassDataContext) -> c_int {
+    if data_ctx.is_null() {
+        return 1;
+    }
+    let ctx = ptr_to_ref(data_ctx);
+    if ctx.context.error_status != 0 {
+        return ctx.context.error_status;
+    }
+
+    // load plugins and register custom behaviors
+    ctx.context.options.load_plugins();
+    let source = ctx.source_string.clone();
+    sass_compile_context(&mut ctx.context, Some(source))
+}
+
+#[no_mangle]
+pub extern fn sass_make_data_compiler(data_ctx: *mut SassDataContext) -> *mut SassCompiler {
+    if data_ctx.is_null() {
+        return ptr::null_mut();
+    }
+    let ctx = ptr_to_ref(data_ctx);
+    ctx.context.options.load_plugins();
+    let source = ctx.source_string.clone();
+    sass_prepare_context(&mut ctx.context, Some(source))
+}
+
 #[no_mangle]
 pub extern fn sass_data_context_get_options(data_ctx: *mut SassDataContext) -> *mut SassOptions {
     let ctx = ptr_to_ref(data_ctx);
     &mut ctx.context.options
 }
 
-//ADDAPI void ADDCALL sass_data_context_set_options (struct Sass_Data_Context* data_ctx, struct Sass_Options* opt)
-// { copy_options(ctx, opt); }
+#[no_mangle]
+pub extern fn sass_data_context_set_options(data_ctx: *mut SassDataContext, options: *mut SassOptions) {
+    // Moves the options into the context, leaving defaults behind so that
+    // the caller can still safely call sass_delete_options.
+    let ctx = ptr_to_ref(data_ctx);
+    let options = ptr_to_ref(options);
+    ctx.context.options = mem::replace(options, SassOptions::default());
+}
 
 #[no_mangle]
 pub extern fn sass_data_context_get_context(data_ctx: *mut SassDataContext) -> *mut SassContext {
@@ -31,48 +95,3 @@ pub extern fn sass_data_context_get_context(data_ctx: *mut SassDataContext) -> *
 pub extern fn sass_delete_data_context(data_ctx: *mut SassDataContext) {
     drop_raw_ptr(data_ctx);
 }
-
-/*
-struct Sass_Compiler* ADDCALL sass_make_data_compiler (struct Sass_Data_Context* data_ctx)
-{
-if (data_ctx == 0) return 0;
-Context* cpp_ctx = new Data_Context(*data_ctx);
-return sass_prepare_context(data_ctx, cpp_ctx);
-}
-*/
-
-/*
-//ADDAPI struct Sass_Data_Context* ADDCALL sass_make_data_context (char* source_string);
-  Sass_Data_Context* ADDCALL sass_make_data_context(char* source_string)
-  {
-    struct Sass_Data_Context* ctx = (struct Sass_Data_Context*) calloc(1, sizeof(struct Sass_Data_Context));
-    if (ctx == 0) { std::cerr << "Error allocating memory for data context" << std::endl; return 0; }
-    ctx->type = SASS_CONTEXT_DATA;
-    init_options(ctx);
-    try {
-      if (source_string == 0) { throw(std::runtime_error("Data context created without a source string")); }
-      if (*source_string == 0) { throw(std::runtime_error("Data context created with empty source string")); }
-      ctx->source_string = source_string;
-    } catch (...) {
-      handle_errors(ctx);
-    }
-    return ctx;
-  }
-*/
-
-/*//ADDAPI int ADDCALL sass_compile_data_context (struct Sass_Data_Context* ctx);
-  int ADDCALL sass_compile_data_context(Sass_Data_Context* data_ctx)
-  {
-    if (data_ctx == 0) return 1;
-    if (data_ctx->error_status)
-      return data_ctx->error_status;
-    try {
-      if (data_ctx->source_string == 0) { throw(std::runtime_error("Data context has no source string")); }
-      // empty source string is a valid case, even if not really usefull (different than with file context)
-      // if (*data_ctx->source_string == 0) { throw(std::runtime_error("Data context has empty source string")); }
-    }
-    catch (...) { return handle_errors(data_ctx) | 1; }
-    Context* cpp_ctx = new Data_Context(*data_ctx);
-    return sass_compile_context(data_ctx, cpp_ctx);
-  }
-*/
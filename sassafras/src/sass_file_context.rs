@@ -5,6 +5,9 @@ use c_api_helpers::*;
 use std::path::PathBuf;
 use sass_options::SassOptions;
 use std::os::raw::c_int;
+use std::mem;
+use std::ptr;
+use sass_compiler::{sass_compile_context, sass_prepare_context, SassCompiler};
 
 // struct for file compilation
 #[derive(Default, Debug)]
@@ -45,31 +48,33 @@ pub extern fn sass_make_file_context(input_path: *const c_char) -> *mut SassFile
 // Call the compilation step for the specific context
 #[no_mangle]
 pub extern fn sass_compile_file_context(file_ctx: *mut SassFileContext) -> c_int {
+    if file_ctx.is_null() {
+        return 1;
+    }
     let ctx = ptr_to_ref(file_ctx);
+    if ctx.context.error_status != 0 {
+        return ctx.context.error_status;
+    }
+    if ctx.context.options.input_path.as_os_str().is_empty() {
+        ctx.context.set_internal_error("File context has empty input path");
+        return 1;
+    }
 
-//    if (file_ctx == 0) return 1;
-//    if (file_ctx->error_status)
-//    return file_ctx->error_status;
-//    try {
-//        if (file_ctx->input_path == 0) { throw(std::runtime_error("File context has no input path")); }
-//    if (*file_ctx->input_path == 0) { throw(std::runtime_error("File context has empty input path")); }
-//    }
-//    catch (...) { return handle_errors(file_ctx) | 1; }
-//    Context* cpp_ctx = new File_Context(*file_ctx);
-//    return sass_compile_context(file_ctx, cpp_ctx);
-    1
+    // load plugins and register custom behaviors
+    ctx.context.options.load_plugins();
+    sass_compile_context(&mut ctx.context, None)
 }
 
-/*
-//// Create a sass compiler instance for more control
-  struct Sass_Compiler* ADDCALL sass_make_file_compiler (struct Sass_File_Context* file_ctx)
-  {
-    if (file_ctx == 0) return 0;
-    Context* cpp_ctx = new File_Context(*file_ctx);
-    return sass_prepare_context(file_ctx, cpp_ctx);
-  }
-
-*/
+// Create a sass compiler instance for more control
+#[no_mangle]
+pub extern fn sass_make_file_compiler(file_ctx: *mut SassFileContext) -> *mut SassCompiler {
+    if file_ctx.is_null() {
+        return ptr::null_mut();
+    }
+    let ctx = ptr_to_ref(file_ctx);
+    ctx.context.options.load_plugins();
+    sass_prepare_context(&mut ctx.context, None)
+}
 
 #[no_mangle]
 pub extern fn sass_delete_file_context(ptr: *mut SassFileContext) {
@@ -90,7 +95,9 @@ pub extern fn sass_file_context_get_options(file_ctx: *mut SassFileContext) -> *
 
 #[no_mangle]
 pub extern fn sass_file_context_set_options(file_ctx: *mut SassFileContext, options: *mut SassOptions) {
-    // { copy_options(ctx, opt); }
+    // Moves the options into the context, leaving defaults behind so that
+    // the caller can still safely call sass_delete_options.
     let ctx = ptr_to_ref(file_ctx);
-    ctx.context.options = ptr_to(options);
+    let options = ptr_to_ref(options);
+    ctx.context.options = mem::replace(options, SassOptions::default());
 }
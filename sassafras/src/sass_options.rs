@@ -1,7 +1,31 @@
 use std::path::PathBuf;
+use std::rc::Rc;
 use sass_output_options::{SassOutputStyle, SassOutputOptions};
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_void};
 use c_api_helpers::*;
+use deprecation::Deprecation;
+use functions::*;
+use logger::{CLogger, Logger, SassLoggerFn, StderrLogger};
+use plugins::Plugins;
+use std::env;
+
+// How `/` between two values is evaluated
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub enum SassDivision {
+    // divides, except between literal numbers in plain CSS such as
+    // `font: 12px/1.5`, where the slash is kept
+    Legacy,
+    // never divides but separates the values, as plain CSS does;
+    // math.div() is used for division
+    Slash,
+}
+
+impl Default for SassDivision {
+    fn default() -> Self {
+        SassDivision::Legacy
+    }
+}
 
 // sass config options structure
 #[derive(Default, Debug)]
@@ -62,14 +86,37 @@ pub struct SassOptions {
     // Directly inserted in source maps
     pub source_map_root: String,
 
+    // How `/` is evaluated, by libsass rules or as a separator only
+    pub division: SassDivision,
+
+    // Seed for random(), so that builds are reproducible. Without
+    // one the sequence is seeded from the clock.
+    pub random_seed: Option<u64>,
+
     // Custom functions that can be called from sccs code
-    //c_functions: Sass_Function_List,
+    pub c_functions: Vec<SassFunctionEntry>,
 
     // List of custom importers
-    //c_importers: Sass_Importer_List,
+    pub c_importers: Vec<SassImporterEntry>,
 
     // List of custom headers
-    //c_headers: Sass_Importer_List
+    pub c_headers: Vec<SassImporterEntry>,
+
+    // Receives @debug, @warn and @error messages; they are
+    // printed to stderr if this is not set
+    pub logger: Option<Rc<dyn Logger>>,
+
+    // Deprecations whose warnings are errors instead, and those whose
+    // warnings are not reported at all
+    fatal_deprecations: Vec<Deprecation>,
+    silence_deprecations: Vec<Deprecation>,
+
+    // Don't report deprecation warnings for stylesheets loaded from the
+    // include paths, which are dependencies the user can't fix
+    pub quiet_deps: bool,
+
+    // Native plugins loaded from the plugin paths
+    plugins: Plugins,
 }
 
 impl SassOptions {
@@ -99,18 +146,94 @@ impl SassOptions {
         }
     }
 
+    /// The include paths, from both `include_path` and `include_paths`.
+    pub fn include_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = env::split_paths(&self.include_path)
+            .filter(|p| !p.as_os_str().is_empty())
+            .collect();
+        paths.extend(self.include_paths.iter().cloned());
+        paths
+    }
+
+    /// The additional file extensions to try when resolving imports.
+    pub fn extensions(&self) -> &[PathBuf] {
+        &self.extensions
+    }
+
+    pub fn push_fatal_deprecation(&mut self, deprecation: Deprecation) {
+        if !self.fatal_deprecations.contains(&deprecation) {
+            self.fatal_deprecations.push(deprecation);
+        }
+    }
+
+    pub fn push_silence_deprecation(&mut self, deprecation: Deprecation) {
+        if !self.silence_deprecations.contains(&deprecation) {
+            self.silence_deprecations.push(deprecation);
+        }
+    }
+
+    pub fn fatal_deprecations(&self) -> &[Deprecation] {
+        &self.fatal_deprecations
+    }
+
+    pub fn silence_deprecations(&self) -> &[Deprecation] {
+        &self.silence_deprecations
+    }
+
     pub fn push_plugin_path<P: Into<PathBuf>>(&mut self, path: P) {
         let path = path.into();
         if !self.plugin_paths.contains(&path) {
             self.plugin_paths.push(path);
         }
     }
+
+    /// Loads the native plugins from `plugin_path` and `plugin_paths`.
+    /// Each directory is only loaded once, however often the options
+    /// are compiled with.
+    pub fn load_plugins(&mut self) {
+        let mut paths: Vec<PathBuf> = env::split_paths(&self.plugin_path)
+            .filter(|p| !p.as_os_str().is_empty())
+            .collect();
+        paths.extend(self.plugin_paths.iter().cloned());
+
+        let logger = self.logger();
+        for path in paths {
+            self.plugins.load_plugins(path, &*logger);
+        }
+    }
+
+    /// The logger set through the C API, or one that prints to stderr.
+    pub fn logger(&self) -> Rc<dyn Logger> {
+        self.logger.clone().unwrap_or_else(|| Rc::new(StderrLogger))
+    }
+
+    /// The custom functions followed by those the plugins provide.
+    pub fn functions(&self) -> Vec<SassFunctionEntry> {
+        self.c_functions.iter().chain(self.plugins.functions.iter()).cloned().collect()
+    }
+
+    /// The custom importers followed by those the plugins provide.
+    pub fn importers(&self) -> Vec<SassImporterEntry> {
+        self.c_importers.iter().chain(self.plugins.importers.iter()).cloned().collect()
+    }
+
+    /// The custom headers followed by those the plugins provide.
+    pub fn headers(&self) -> Vec<SassImporterEntry> {
+        self.c_headers.iter().chain(self.plugins.headers.iter()).cloned().collect()
+    }
 }
 
-// For debugging, to show that something is actually dropped.
 impl Drop for SassOptions {
     fn drop(&mut self) {
-        //println!("Dropping Sass_Options `{:#?}`", self);
+        // Deallocate custom functions, headers and importers. The plugins
+        // field is dropped after this, which frees their own entries and
+        // unloads the plugin libraries.
+        for &entry in &self.c_functions {
+            sass_delete_function(entry);
+        }
+        for &entry in self.c_importers.iter().chain(self.c_headers.iter()) {
+            sass_delete_importer(entry);
+        }
     }
 }
 
@@ -192,6 +315,61 @@ pub extern fn sass_option_set_source_map_file(options_ptr: *mut SassOptions, sou
     options.source_map_file = pb;
 }
 
+#[no_mangle]
+pub extern fn sass_option_set_division(options_ptr: *mut SassOptions, division: SassDivision) {
+    let options = ptr_to_ref(options_ptr);
+    options.division = division;
+}
+
+#[no_mangle]
+pub extern fn sass_option_set_random_seed(options_ptr: *mut SassOptions, random_seed: u64) {
+    let options = ptr_to_ref(options_ptr);
+    options.random_seed = Some(random_seed);
+}
+
+// Sets the callback that receives @debug, @warn and @error messages,
+// with a cookie passed back to it. A null callback restores the
+// default of printing them to stderr.
+#[no_mangle]
+pub extern fn sass_option_set_logger(options_ptr: *mut SassOptions, callback: Option<SassLoggerFn>, cookie: *mut c_void) {
+    let options = ptr_to_ref(options_ptr);
+    options.logger = callback.map(|callback| Rc::new(CLogger { callback, cookie }) as Rc<dyn Logger>);
+}
+
+// Makes the warnings of a deprecation, given by its id such as
+// "slash-div", into errors. Returns false if the id is unknown.
+#[no_mangle]
+pub extern fn sass_option_push_fatal_deprecation(options_ptr: *mut SassOptions, id: *const c_char) -> bool {
+    let options = ptr_to_ref(options_ptr);
+    match Deprecation::from_id(&c_char_ptr_to_string(id)) {
+        Some(deprecation) => {
+            options.push_fatal_deprecation(deprecation);
+            true
+        }
+        None => false,
+    }
+}
+
+// Stops the warnings of a deprecation, given by its id, from being
+// reported. Returns false if the id is unknown.
+#[no_mangle]
+pub extern fn sass_option_push_silence_deprecation(options_ptr: *mut SassOptions, id: *const c_char) -> bool {
+    let options = ptr_to_ref(options_ptr);
+    match Deprecation::from_id(&c_char_ptr_to_string(id)) {
+        Some(deprecation) => {
+            options.push_silence_deprecation(deprecation);
+            true
+        }
+        None => false,
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_option_set_quiet_deps(options_ptr: *mut SassOptions, quiet_deps: bool) {
+    let options = ptr_to_ref(options_ptr);
+    options.quiet_deps = quiet_deps;
+}
+
 #[no_mangle]
 pub extern fn sass_option_get_precision(options_ptr: *mut SassOptions) -> u8 {
     let options = ptr_to_ref(options_ptr);
@@ -204,6 +382,24 @@ pub extern fn sass_option_get_output_style(options_ptr: *mut SassOptions) -> Sas
     options.output_options.inspect_options.output_style
 }
 
+#[no_mangle]
+pub extern fn sass_option_get_division(options_ptr: *mut SassOptions) -> SassDivision {
+    let options = ptr_to_ref(options_ptr);
+    options.division
+}
+
+#[no_mangle]
+pub extern fn sass_option_get_random_seed(options_ptr: *mut SassOptions) -> u64 {
+    let options = ptr_to_ref(options_ptr);
+    options.random_seed.unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern fn sass_option_get_quiet_deps(options_ptr: *mut SassOptions) -> bool {
+    let options = ptr_to_ref(options_ptr);
+    options.quiet_deps
+}
+
 #[no_mangle]
 pub extern fn sass_option_get_source_comments(options_ptr: *mut SassOptions) -> bool {
     let options = ptr_to_ref(options_ptr);
@@ -315,14 +511,14 @@ pub extern fn sass_option_set_input_path(options_ptr: *mut SassOptions, input_pa
 pub extern fn sass_option_set_plugin_path(options_ptr: *mut SassOptions, plugin_path: *const c_char) {
     let options = ptr_to_ref(options_ptr);
     let pb = c_char_ptr_to_pathbuf(plugin_path);
-    options.output_path = pb;
+    options.plugin_path = pb;
 }
 
 #[no_mangle]
 pub extern fn sass_option_set_include_path(options_ptr: *mut SassOptions, include_path: *const c_char) {
     let options = ptr_to_ref(options_ptr);
     let pb = c_char_ptr_to_pathbuf(include_path);
-    options.output_path = pb;
+    options.include_path = pb;
 }
 
 #[no_mangle]
@@ -332,25 +528,37 @@ pub extern fn sass_option_set_source_map_root(options_ptr: *mut SassOptions, sou
     options.output_path = pb;
 }
 
+/// Takes ownership of the entries in the list and frees the list itself.
 #[no_mangle]
-pub extern fn sass_option_set_c_headers(options_ptr: *mut SassOptions, c_headers: *const c_char) {
-    // c_headers: SassImporterList (yes this is right)
+pub extern fn sass_option_set_c_headers(options_ptr: *mut SassOptions, c_headers: SassImporterList) {
     let options = ptr_to_ref(options_ptr);
+    for entry in options.c_headers.drain(..) {
+        sass_delete_importer(entry);
+    }
+    options.c_headers = list_to_vec(c_headers);
+    sass_free_list(c_headers);
 }
 
+/// Takes ownership of the entries in the list and frees the list itself.
 #[no_mangle]
-pub extern fn sass_option_set_c_importers(options_ptr: *mut SassOptions, c_importers: *const c_char) {
-    // c_importers: SassImporterList
+pub extern fn sass_option_set_c_importers(options_ptr: *mut SassOptions, c_importers: SassImporterList) {
     let options = ptr_to_ref(options_ptr);
-//    let pb = c_char_ptr_to_pathbuf(output_path);
-//    options.output_path = pb;
+    for entry in options.c_importers.drain(..) {
+        sass_delete_importer(entry);
+    }
+    options.c_importers = list_to_vec(c_importers);
+    sass_free_list(c_importers);
 }
 
+/// Takes ownership of the entries in the list and frees the list itself.
 #[no_mangle]
-pub extern fn sass_option_set_c_functions(options_ptr: *mut SassOptions, c_functions: *const c_char) {
+pub extern fn sass_option_set_c_functions(options_ptr: *mut SassOptions, c_functions: SassFunctionList) {
     let options = ptr_to_ref(options_ptr);
-//    let pb = c_char_ptr_to_pathbuf(output_path);
-//    options.output_path = pb;
+    for entry in options.c_functions.drain(..) {
+        sass_delete_function(entry);
+    }
+    options.c_functions = list_to_vec(c_functions);
+    sass_free_list(c_functions);
 }
 
 #[no_mangle]
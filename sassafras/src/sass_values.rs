@@ -0,0 +1,631 @@
+// FROM: src/sass_values.cpp, include/sass/values.h
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::ptr;
+use libc;
+use c_api_helpers::*;
+use inspect::{inspect_value, Inspect};
+use operators::{self, BinaryOperator};
+use values::*;
+
+// The precision used when values are operated on or printed through
+// the C API, as in libsass.
+const C_API_PRECISION: u8 = 5;
+
+// Type for Sass values
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub enum SassTag {
+    Boolean,
+    Number,
+    Color,
+    String,
+    List,
+    Map,
+    Null,
+    Error,
+    Warning,
+}
+
+// Tags for denoting Sass list separators
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub enum SassSeparator {
+    Comma,
+    Space,
+    // only used internally to represent a hash map before evaluation
+    // otherwise we would be too early to check for duplicate keys
+    Hash,
+}
+
+// Value Operators
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub enum SassOp {
+    And, Or,                   // logical connectives
+    Eq, Neq, Gt, Gte, Lt, Lte, // arithmetic relations
+    Add, Sub, Mul, Div, Mod,   // arithmetic functions
+    NumOps,                    // so we know how big to make the op table
+}
+
+// A value passed to or returned from a custom function. Lists and maps
+// own the values in them, which are freed with the container. Entries
+// that have not been set yet are null.
+#[derive(Debug)]
+pub enum SassValue {
+    Null,
+    Boolean(bool),
+    Number { value: f64, unit: CString },
+    Color { r: f64, g: f64, b: f64, a: f64 },
+    String { value: CString, quoted: bool },
+    List { separator: SassSeparator, is_bracketed: bool, values: Vec<*mut SassValue> },
+    Map { keys: Vec<*mut SassValue>, values: Vec<*mut SassValue> },
+    Error(CString),
+    Warning(CString),
+}
+
+impl Drop for SassValue {
+    fn drop(&mut self) {
+        match *self {
+            SassValue::List { ref values, .. } => {
+                for &value in values {
+                    drop_raw_ptr(value);
+                }
+            }
+            SassValue::Map { ref keys, ref values } => {
+                for &value in keys.iter().chain(values) {
+                    drop_raw_ptr(value);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Converts a value to a newly allocated C value, owned by the caller.
+pub fn value_to_c(value: &Value) -> *mut SassValue {
+    let c_value = match *value {
+        Value::Null => SassValue::Null,
+        Value::Boolean(b) => SassValue::Boolean(b),
+        Value::Number(ref n) => SassValue::Number { value: n.value, unit: to_cstring(&n.unit()) },
+        Value::Color(ref c) => SassValue::Color {
+            r: f64::from(c.red()),
+            g: f64::from(c.green()),
+            b: f64::from(c.blue()),
+            a: c.alpha(),
+        },
+        Value::String(ref s) => SassValue::String { value: to_cstring(&s.text), quoted: s.quoted },
+        Value::List(ref l) => SassValue::List {
+            separator: if l.separator == ListSeparator::Comma { SassSeparator::Comma } else { SassSeparator::Space },
+            is_bracketed: l.bracketed,
+            values: l.items.iter().map(value_to_c).collect(),
+        },
+        Value::Map(ref m) => SassValue::Map {
+            keys: m.pairs().iter().map(|(k, _)| value_to_c(k)).collect(),
+            values: m.pairs().iter().map(|(_, v)| value_to_c(v)).collect(),
+        },
+        // The C API has no function values.
+        Value::Function(_) => SassValue::Null,
+        // Nor calculations, which are passed as the CSS they produce.
+        Value::Calculation(_) => {
+            let css = inspect_value(value, C_API_PRECISION);
+            SassValue::String { value: to_cstring(&css), quoted: false }
+        }
+    };
+    box_to_raw_ptr(c_value)
+}
+
+/// Converts a C value back to a value. Null pointers, errors and
+/// warnings become null; callers check for errors before converting.
+// Safety: `value` is trusted to be null or to come from the C value API.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn value_from_c(value: *const SassValue) -> Value {
+    if value.is_null() {
+        return Value::Null;
+    }
+
+    match *unsafe { &*value } {
+        SassValue::Null | SassValue::Error(_) | SassValue::Warning(_) => Value::Null,
+        SassValue::Boolean(b) => Value::Boolean(b),
+        SassValue::Number { value, ref unit } => Value::Number(parse_unit(value, &unit.to_string_lossy())),
+        SassValue::Color { r, g, b, a } => Value::Color(Color::rgba(r, g, b, a)),
+        SassValue::String { ref value, quoted } => {
+            Value::String(SassString { text: value.to_string_lossy().into_owned(), quoted })
+        }
+        SassValue::List { separator, is_bracketed, ref values } => {
+            let separator = match separator {
+                SassSeparator::Comma => ListSeparator::Comma,
+                _ => ListSeparator::Space,
+            };
+            let items = values.iter().map(|&v| value_from_c(v)).collect();
+            Value::List(List::new(items, separator, is_bracketed))
+        }
+        SassValue::Map { ref keys, ref values } => {
+            let mut map = SassMap::new();
+            for (&k, &v) in keys.iter().zip(values) {
+                map.insert(value_from_c(k), value_from_c(v));
+            }
+            Value::Map(map)
+        }
+    }
+}
+
+/// Parses a unit string such as `px*em/s` back into a number.
+fn parse_unit(value: f64, unit: &str) -> Number {
+    let split = |units: &str| -> Vec<String> {
+        units.split('*').filter(|u| !u.is_empty()).map(|u| u.to_string()).collect()
+    };
+    match unit.find('/') {
+        Some(pos) => Number::with_units(value, split(&unit[..pos]), split(&unit[pos + 1..])),
+        None => Number::with_units(value, split(unit), vec![]),
+    }
+}
+
+fn to_cstring(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).unwrap_or_default()
+}
+
+/// Takes ownership of a malloc'd C string, as the setters in the C API do.
+fn take_c_string(s: *mut c_char) -> CString {
+    if s.is_null() {
+        return CString::default();
+    }
+    let copy = c_char_ptr_to_cstring(s);
+    unsafe { libc::free(s as *mut libc::c_void) };
+    copy
+}
+
+fn value_ref<'a>(v: *const SassValue) -> &'a mut SassValue {
+    ptr_to_ref(v as *mut SassValue)
+}
+
+// Creator functions for all value types
+#[no_mangle]
+pub extern fn sass_make_null() -> *mut SassValue {
+    box_to_raw_ptr(SassValue::Null)
+}
+
+#[no_mangle]
+pub extern fn sass_make_boolean(val: bool) -> *mut SassValue {
+    box_to_raw_ptr(SassValue::Boolean(val))
+}
+
+#[no_mangle]
+pub extern fn sass_make_string(val: *const c_char) -> *mut SassValue {
+    box_to_raw_ptr(SassValue::String { value: c_char_ptr_to_cstring(val), quoted: false })
+}
+
+#[no_mangle]
+pub extern fn sass_make_qstring(val: *const c_char) -> *mut SassValue {
+    box_to_raw_ptr(SassValue::String { value: c_char_ptr_to_cstring(val), quoted: true })
+}
+
+#[no_mangle]
+pub extern fn sass_make_number(val: f64, unit: *const c_char) -> *mut SassValue {
+    let unit = if unit.is_null() { CString::default() } else { c_char_ptr_to_cstring(unit) };
+    box_to_raw_ptr(SassValue::Number { value: val, unit })
+}
+
+#[no_mangle]
+pub extern fn sass_make_color(r: f64, g: f64, b: f64, a: f64) -> *mut SassValue {
+    box_to_raw_ptr(SassValue::Color { r, g, b, a })
+}
+
+#[no_mangle]
+pub extern fn sass_make_list(len: usize, sep: SassSeparator, is_bracketed: bool) -> *mut SassValue {
+    box_to_raw_ptr(SassValue::List { separator: sep, is_bracketed, values: vec![ptr::null_mut(); len] })
+}
+
+#[no_mangle]
+pub extern fn sass_make_map(len: usize) -> *mut SassValue {
+    box_to_raw_ptr(SassValue::Map { keys: vec![ptr::null_mut(); len], values: vec![ptr::null_mut(); len] })
+}
+
+#[no_mangle]
+pub extern fn sass_make_error(msg: *const c_char) -> *mut SassValue {
+    box_to_raw_ptr(SassValue::Error(c_char_ptr_to_cstring(msg)))
+}
+
+#[no_mangle]
+pub extern fn sass_make_warning(msg: *const c_char) -> *mut SassValue {
+    box_to_raw_ptr(SassValue::Warning(c_char_ptr_to_cstring(msg)))
+}
+
+// Generic destructor function for all types
+// Will release memory of all associated Sass_Values
+// Means we will delete recursively for lists and maps
+#[no_mangle]
+pub extern fn sass_delete_value(val: *mut SassValue) {
+    drop_raw_ptr(val);
+}
+
+// Make a deep cloned copy of the given sass value
+#[no_mangle]
+pub extern fn sass_clone_value(val: *const SassValue) -> *mut SassValue {
+    let clone_all = |values: &[*mut SassValue]| -> Vec<*mut SassValue> {
+        values.iter().map(|&v| if v.is_null() { v } else { sass_clone_value(v) }).collect()
+    };
+
+    let clone = match *value_ref(val) {
+        SassValue::Null => SassValue::Null,
+        SassValue::Boolean(b) => SassValue::Boolean(b),
+        SassValue::Number { value, ref unit } => SassValue::Number { value, unit: unit.clone() },
+        SassValue::Color { r, g, b, a } => SassValue::Color { r, g, b, a },
+        SassValue::String { ref value, quoted } => SassValue::String { value: value.clone(), quoted },
+        SassValue::List { separator, is_bracketed, ref values } => {
+            SassValue::List { separator, is_bracketed, values: clone_all(values) }
+        }
+        SassValue::Map { ref keys, ref values } => SassValue::Map { keys: clone_all(keys), values: clone_all(values) },
+        SassValue::Error(ref msg) => SassValue::Error(msg.clone()),
+        SassValue::Warning(ref msg) => SassValue::Warning(msg.clone()),
+    };
+    box_to_raw_ptr(clone)
+}
+
+// Execute an operation for two Sass_Values and return the result as a Sass_Value too
+#[no_mangle]
+pub extern fn sass_value_op(op: SassOp, a: *const SassValue, b: *const SassValue) -> *mut SassValue {
+    let left = value_from_c(a);
+    let right = value_from_c(b);
+
+    let op = match op {
+        SassOp::And => return value_to_c(if left.is_truthy() { &right } else { &left }),
+        SassOp::Or => return value_to_c(if left.is_truthy() { &left } else { &right }),
+        SassOp::Eq => return sass_make_boolean(left == right),
+        SassOp::Neq => return sass_make_boolean(left != right),
+        SassOp::Gt => BinaryOperator::Gt,
+        SassOp::Gte => BinaryOperator::Gte,
+        SassOp::Lt => BinaryOperator::Lt,
+        SassOp::Lte => BinaryOperator::Lte,
+        SassOp::Add => BinaryOperator::Add,
+        SassOp::Sub => BinaryOperator::Sub,
+        SassOp::Mul => BinaryOperator::Mul,
+        SassOp::Div => BinaryOperator::Div,
+        SassOp::Mod => BinaryOperator::Mod,
+        SassOp::NumOps => return box_to_raw_ptr(SassValue::Error(to_cstring("invalid operator"))),
+    };
+
+    match operators::op(op, &left, &right, C_API_PRECISION) {
+        Ok(value) => value_to_c(&value),
+        Err(msg) => box_to_raw_ptr(SassValue::Error(to_cstring(&msg))),
+    }
+}
+
+// Stringify a Sass_Values and also return the result as a Sass_Value (of type STRING)
+#[no_mangle]
+pub extern fn sass_value_stringify(a: *const SassValue, compressed: bool, precision: i32) -> *mut SassValue {
+    let value = value_from_c(a);
+    let inspect = Inspect { compressed, precision: precision.clamp(0, 255) as u8, inspect: false, quote: true };
+    let text = inspect.value(&value)
+        .unwrap_or_else(|_| Inspect { inspect: true, ..inspect }.value(&value).unwrap_or_default());
+    box_to_raw_ptr(SassValue::String { value: to_cstring(&text), quoted: true })
+}
+
+// Return the sass tag for a generic sass value
+// Check is needed before accessing specific values!
+#[no_mangle]
+pub extern fn sass_value_get_tag(v: *const SassValue) -> SassTag {
+    match *value_ref(v) {
+        SassValue::Null => SassTag::Null,
+        SassValue::Boolean(_) => SassTag::Boolean,
+        SassValue::Number { .. } => SassTag::Number,
+        SassValue::Color { .. } => SassTag::Color,
+        SassValue::String { .. } => SassTag::String,
+        SassValue::List { .. } => SassTag::List,
+        SassValue::Map { .. } => SassTag::Map,
+        SassValue::Error(_) => SassTag::Error,
+        SassValue::Warning(_) => SassTag::Warning,
+    }
+}
+
+// Check value to be of a specific type
+// Can also be used before accessing properties!
+#[no_mangle]
+pub extern fn sass_value_is_null(v: *const SassValue) -> bool {
+    sass_value_get_tag(v) == SassTag::Null
+}
+
+#[no_mangle]
+pub extern fn sass_value_is_number(v: *const SassValue) -> bool {
+    sass_value_get_tag(v) == SassTag::Number
+}
+
+#[no_mangle]
+pub extern fn sass_value_is_string(v: *const SassValue) -> bool {
+    sass_value_get_tag(v) == SassTag::String
+}
+
+#[no_mangle]
+pub extern fn sass_value_is_boolean(v: *const SassValue) -> bool {
+    sass_value_get_tag(v) == SassTag::Boolean
+}
+
+#[no_mangle]
+pub extern fn sass_value_is_color(v: *const SassValue) -> bool {
+    sass_value_get_tag(v) == SassTag::Color
+}
+
+#[no_mangle]
+pub extern fn sass_value_is_list(v: *const SassValue) -> bool {
+    sass_value_get_tag(v) == SassTag::List
+}
+
+#[no_mangle]
+pub extern fn sass_value_is_map(v: *const SassValue) -> bool {
+    sass_value_get_tag(v) == SassTag::Map
+}
+
+#[no_mangle]
+pub extern fn sass_value_is_error(v: *const SassValue) -> bool {
+    sass_value_get_tag(v) == SassTag::Error
+}
+
+#[no_mangle]
+pub extern fn sass_value_is_warning(v: *const SassValue) -> bool {
+    sass_value_get_tag(v) == SassTag::Warning
+}
+
+// Getters and setters for Sass_Number
+#[no_mangle]
+pub extern fn sass_number_get_value(v: *const SassValue) -> f64 {
+    match *value_ref(v) {
+        SassValue::Number { value, .. } => value,
+        _ => 0.0,
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_number_set_value(v: *mut SassValue, value: f64) {
+    if let SassValue::Number { value: ref mut old, .. } = *value_ref(v) {
+        *old = value;
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_number_get_unit(v: *const SassValue) -> *const c_char {
+    match *value_ref(v) {
+        SassValue::Number { ref unit, .. } => unit.as_ptr(),
+        _ => ptr::null(),
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_number_set_unit(v: *mut SassValue, unit: *mut c_char) {
+    if let SassValue::Number { unit: ref mut old, .. } = *value_ref(v) {
+        *old = take_c_string(unit);
+    }
+}
+
+// Getters and setters for Sass_String
+#[no_mangle]
+pub extern fn sass_string_get_value(v: *const SassValue) -> *const c_char {
+    match *value_ref(v) {
+        SassValue::String { ref value, .. } => value.as_ptr(),
+        _ => ptr::null(),
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_string_set_value(v: *mut SassValue, value: *mut c_char) {
+    if let SassValue::String { value: ref mut old, .. } = *value_ref(v) {
+        *old = take_c_string(value);
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_string_is_quoted(v: *const SassValue) -> bool {
+    match *value_ref(v) {
+        SassValue::String { quoted, .. } => quoted,
+        _ => false,
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_string_set_quoted(v: *mut SassValue, quoted: bool) {
+    if let SassValue::String { quoted: ref mut old, .. } = *value_ref(v) {
+        *old = quoted;
+    }
+}
+
+// Getters and setters for Sass_Boolean
+#[no_mangle]
+pub extern fn sass_boolean_get_value(v: *const SassValue) -> bool {
+    match *value_ref(v) {
+        SassValue::Boolean(value) => value,
+        _ => false,
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_boolean_set_value(v: *mut SassValue, value: bool) {
+    if let SassValue::Boolean(ref mut old) = *value_ref(v) {
+        *old = value;
+    }
+}
+
+// Getters and setters for Sass_Color
+fn color_channel<'a>(v: *const SassValue, channel: usize) -> Option<&'a mut f64> {
+    match *value_ref(v) {
+        SassValue::Color { ref mut r, ref mut g, ref mut b, ref mut a } => {
+            Some(match channel { 0 => r, 1 => g, 2 => b, _ => a })
+        }
+        _ => None,
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_color_get_r(v: *const SassValue) -> f64 {
+    color_channel(v, 0).map_or(0.0, |c| *c)
+}
+
+#[no_mangle]
+pub extern fn sass_color_set_r(v: *mut SassValue, r: f64) {
+    if let Some(c) = color_channel(v, 0) { *c = r; }
+}
+
+#[no_mangle]
+pub extern fn sass_color_get_g(v: *const SassValue) -> f64 {
+    color_channel(v, 1).map_or(0.0, |c| *c)
+}
+
+#[no_mangle]
+pub extern fn sass_color_set_g(v: *mut SassValue, g: f64) {
+    if let Some(c) = color_channel(v, 1) { *c = g; }
+}
+
+#[no_mangle]
+pub extern fn sass_color_get_b(v: *const SassValue) -> f64 {
+    color_channel(v, 2).map_or(0.0, |c| *c)
+}
+
+#[no_mangle]
+pub extern fn sass_color_set_b(v: *mut SassValue, b: f64) {
+    if let Some(c) = color_channel(v, 2) { *c = b; }
+}
+
+#[no_mangle]
+pub extern fn sass_color_get_a(v: *const SassValue) -> f64 {
+    color_channel(v, 3).map_or(0.0, |c| *c)
+}
+
+#[no_mangle]
+pub extern fn sass_color_set_a(v: *mut SassValue, a: f64) {
+    if let Some(c) = color_channel(v, 3) { *c = a; }
+}
+
+// Getter for the number of items in list
+#[no_mangle]
+pub extern fn sass_list_get_length(v: *const SassValue) -> usize {
+    match *value_ref(v) {
+        SassValue::List { ref values, .. } => values.len(),
+        _ => 0,
+    }
+}
+
+// Getters and setters for Sass_List
+#[no_mangle]
+pub extern fn sass_list_get_separator(v: *const SassValue) -> SassSeparator {
+    match *value_ref(v) {
+        SassValue::List { separator, .. } => separator,
+        _ => SassSeparator::Space,
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_list_set_separator(v: *mut SassValue, value: SassSeparator) {
+    if let SassValue::List { ref mut separator, .. } = *value_ref(v) {
+        *separator = value;
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_list_get_is_bracketed(v: *const SassValue) -> bool {
+    match *value_ref(v) {
+        SassValue::List { is_bracketed, .. } => is_bracketed,
+        _ => false,
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_list_set_is_bracketed(v: *mut SassValue, value: bool) {
+    if let SassValue::List { ref mut is_bracketed, .. } = *value_ref(v) {
+        *is_bracketed = value;
+    }
+}
+
+// Getters and setters for Sass_List values
+#[no_mangle]
+pub extern fn sass_list_get_value(v: *const SassValue, i: usize) -> *mut SassValue {
+    match *value_ref(v) {
+        SassValue::List { ref values, .. } => values.get(i).cloned().unwrap_or(ptr::null_mut()),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// The list takes ownership of the value. Any value already at that
+/// position is not freed, as in libsass.
+#[no_mangle]
+pub extern fn sass_list_set_value(v: *mut SassValue, i: usize, value: *mut SassValue) {
+    if let SassValue::List { ref mut values, .. } = *value_ref(v) {
+        if i < values.len() {
+            values[i] = value;
+        }
+    }
+}
+
+// Getter for the number of items in map
+#[no_mangle]
+pub extern fn sass_map_get_length(v: *const SassValue) -> usize {
+    match *value_ref(v) {
+        SassValue::Map { ref keys, .. } => keys.len(),
+        _ => 0,
+    }
+}
+
+// Getters and setters for Sass_Map keys and values
+#[no_mangle]
+pub extern fn sass_map_get_key(v: *const SassValue, i: usize) -> *mut SassValue {
+    match *value_ref(v) {
+        SassValue::Map { ref keys, .. } => keys.get(i).cloned().unwrap_or(ptr::null_mut()),
+        _ => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_map_set_key(v: *mut SassValue, i: usize, key: *mut SassValue) {
+    if let SassValue::Map { ref mut keys, .. } = *value_ref(v) {
+        if i < keys.len() {
+            keys[i] = key;
+        }
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_map_get_value(v: *const SassValue, i: usize) -> *mut SassValue {
+    match *value_ref(v) {
+        SassValue::Map { ref values, .. } => values.get(i).cloned().unwrap_or(ptr::null_mut()),
+        _ => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_map_set_value(v: *mut SassValue, i: usize, value: *mut SassValue) {
+    if let SassValue::Map { ref mut values, .. } = *value_ref(v) {
+        if i < values.len() {
+            values[i] = value;
+        }
+    }
+}
+
+// Getters and setters for Sass_Error
+#[no_mangle]
+pub extern fn sass_error_get_message(v: *const SassValue) -> *const c_char {
+    match *value_ref(v) {
+        SassValue::Error(ref msg) => msg.as_ptr(),
+        _ => ptr::null(),
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_error_set_message(v: *mut SassValue, msg: *mut c_char) {
+    if let SassValue::Error(ref mut old) = *value_ref(v) {
+        *old = take_c_string(msg);
+    }
+}
+
+// Getters and setters for Sass_Warning
+#[no_mangle]
+pub extern fn sass_warning_get_message(v: *const SassValue) -> *const c_char {
+    match *value_ref(v) {
+        SassValue::Warning(ref msg) => msg.as_ptr(),
+        _ => ptr::null(),
+    }
+}
+
+#[no_mangle]
+pub extern fn sass_warning_set_message(v: *mut SassValue, msg: *mut c_char) {
+    if let SassValue::Warning(ref mut old) = *value_ref(v) {
+        *old = take_c_string(msg);
+    }
+}
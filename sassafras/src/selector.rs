@@ -0,0 +1,845 @@
+// FROM: src/ast_selectors.hpp, src/parser_selectors.cpp
+
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+use parser::{is_name, is_name_start};
+use values::{List, ListSeparator, Value};
+
+// A comma separated list of selectors, such as `a, .b > c`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SelectorList {
+    pub components: Vec<ComplexSelector>,
+}
+
+// A sequence of compound selectors separated by combinators.
+#[derive(Debug, Clone)]
+pub struct ComplexSelector {
+    pub components: Vec<ComplexComponent>,
+    // whether the selector was preceded by a newline in the source
+    pub line_break: bool,
+}
+
+// Line breaks are only formatting, so they don't affect equality.
+impl PartialEq for ComplexSelector {
+    fn eq(&self, other: &ComplexSelector) -> bool {
+        self.components == other.components
+    }
+}
+
+impl Eq for ComplexSelector {}
+
+impl Hash for ComplexSelector {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.components.hash(state);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ComplexComponent {
+    Compound(CompoundSelector),
+    Combinator(Combinator),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Combinator {
+    // `>`
+    Child,
+    // `+`
+    NextSibling,
+    // `~`
+    FollowingSibling,
+}
+
+impl Combinator {
+    pub fn symbol(&self) -> &'static str {
+        match *self {
+            Combinator::Child => ">",
+            Combinator::NextSibling => "+",
+            Combinator::FollowingSibling => "~",
+        }
+    }
+}
+
+// A sequence of simple selectors with no combinators, such as `a.b:hover`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CompoundSelector {
+    pub components: Vec<SimpleSelector>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SimpleSelector {
+    // `*`, possibly with a namespace such as `ns|*`
+    Universal(Option<String>),
+    // an element name, possibly with a namespace
+    Type(String),
+    Class(String),
+    Id(String),
+    // `%name`, which is never emitted
+    Placeholder(String),
+    // `[name op value modifier]`
+    Attribute(AttributeSelector),
+    Pseudo(PseudoSelector),
+    // `&`, possibly followed by a suffix as in `&-foo`
+    Parent(Option<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AttributeSelector {
+    pub name: String,
+    pub op: Option<String>,
+    pub value: Option<String>,
+    pub modifier: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PseudoSelector {
+    pub name: String,
+    // `::before` rather than `:hover`
+    pub is_element: bool,
+    // the unparsed argument, for pseudos that do not take selectors
+    pub argument: Option<String>,
+    // the argument of selector pseudos such as `:not()`
+    pub selector: Option<Box<SelectorList>>,
+}
+
+// Pseudo classes whose argument is itself a selector.
+const SELECTOR_PSEUDOS: &[&str] = &[
+    "not", "is", "matches", "where", "any", "-moz-any", "-webkit-any", "current",
+    "has", "host", "host-context", "slotted",
+];
+
+/// A name in lower case without any vendor prefix such as `-moz-`.
+pub fn unvendor(name: &str) -> String {
+    let lower = name.to_ascii_lowercase();
+    if let Some(rest) = lower.strip_prefix('-') {
+        if let Some(idx) = rest.find('-') {
+            return rest[idx + 1..].to_string();
+        }
+    }
+    lower
+}
+
+impl PseudoSelector {
+    /// The name without any vendor prefix.
+    pub fn normalized_name(&self) -> String {
+        unvendor(&self.name)
+    }
+
+    /// A copy of this pseudo with a different selector argument.
+    pub fn with_selector(&self, selector: SelectorList) -> Self {
+        PseudoSelector { selector: Some(Box::new(selector)), ..self.clone() }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Parsing
+
+struct SelectorParser<'a> {
+    text: &'a str,
+    pos: usize,
+    allow_parent: bool,
+}
+
+/// Parses a selector list. The error is a message without position,
+/// which the caller reports at the selector.
+pub fn parse_selector(text: &str, allow_parent: bool) -> Result<SelectorList, String> {
+    let mut parser = SelectorParser { text, pos: 0, allow_parent };
+    let list = parser.selector_list()?;
+    parser.skip_ws();
+    if parser.pos < text.len() {
+        return Err(parser.expected("selector"));
+    }
+    Ok(list)
+}
+
+/// Parses a compound selector such as the target of an `@extend`.
+pub fn parse_compound_selector(text: &str) -> Result<CompoundSelector, String> {
+    let mut parser = SelectorParser { text, pos: 0, allow_parent: false };
+    parser.skip_ws();
+    let compound = parser.compound_selector()?;
+    parser.skip_ws();
+    if parser.pos < text.len() {
+        return Err(parser.expected("selector"));
+    }
+    Ok(compound)
+}
+
+impl<'a> SelectorParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_ws(&mut self) -> bool {
+        let mut newline = false;
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            if c == '\n' {
+                newline = true;
+            }
+            self.next();
+        }
+        newline
+    }
+
+    fn expected(&self, what: &str) -> String {
+        let before = &self.text[..self.pos];
+        format!("Invalid CSS after \"{}\": expected {}, was \"{}\"", before.trim_start(), what, self.rest())
+    }
+
+    fn selector_list(&mut self) -> Result<SelectorList, String> {
+        let mut components = Vec::new();
+        let mut line_break = self.skip_ws();
+        loop {
+            let mut complex = self.complex_selector()?;
+            complex.line_break = line_break;
+            components.push(complex);
+            self.skip_ws();
+            if self.peek() != Some(',') {
+                break;
+            }
+            self.next();
+            line_break = self.skip_ws();
+            // allow a trailing comma
+            if self.peek().is_none() || self.peek() == Some(')') {
+                break;
+            }
+        }
+        Ok(SelectorList { components })
+    }
+
+    fn complex_selector(&mut self) -> Result<ComplexSelector, String> {
+        let mut components = Vec::new();
+        loop {
+            self.skip_ws();
+            let combinator = match self.peek() {
+                Some('>') => Some(Combinator::Child),
+                Some('+') => Some(Combinator::NextSibling),
+                Some('~') => Some(Combinator::FollowingSibling),
+                _ => None,
+            };
+            if let Some(combinator) = combinator {
+                self.next();
+                components.push(ComplexComponent::Combinator(combinator));
+                continue;
+            }
+
+            match self.peek() {
+                None | Some(',') | Some(')') | Some('{') => break,
+                _ => {}
+            }
+            components.push(ComplexComponent::Compound(self.compound_selector()?));
+        }
+
+        if components.is_empty() {
+            return Err(self.expected("selector"));
+        }
+        Ok(ComplexSelector { components, line_break: false })
+    }
+
+    fn compound_selector(&mut self) -> Result<CompoundSelector, String> {
+        let mut components = vec![self.simple_selector(true)?];
+        loop {
+            match self.peek() {
+                Some('*') | Some('.') | Some('#') | Some('%') | Some(':') | Some('[') | Some('&') => {}
+                Some(c) if is_name_start(c) || c == '\\' || c == '-' || c == '|' => {}
+                _ => break,
+            }
+            components.push(self.simple_selector(false)?);
+        }
+        Ok(CompoundSelector { components })
+    }
+
+    fn simple_selector(&mut self, first: bool) -> Result<SimpleSelector, String> {
+        match self.peek() {
+            Some('&') => {
+                if !first {
+                    return Err("\"&\" may only used at the beginning of a compound selector.".to_string());
+                }
+                if !self.allow_parent {
+                    return Err("Parent selectors aren't allowed here.".to_string());
+                }
+                self.next();
+                let suffix = if self.peek().map_or(false, |c| is_name(c) || c == '\\') {
+                    Some(self.name_body())
+                } else {
+                    None
+                };
+                Ok(SimpleSelector::Parent(suffix))
+            }
+            Some('.') => {
+                self.next();
+                Ok(SimpleSelector::Class(self.identifier()?))
+            }
+            Some('#') => {
+                self.next();
+                Ok(SimpleSelector::Id(self.identifier()?))
+            }
+            Some('%') => {
+                self.next();
+                Ok(SimpleSelector::Placeholder(self.identifier()?))
+            }
+            Some('[') => self.attribute_selector(),
+            Some(':') => self.pseudo_selector(),
+            Some('*') | Some('|') => self.type_or_universal(),
+            Some(c) if is_name_start(c) || c == '\\' || c == '-' => self.type_or_universal(),
+            _ => Err(self.expected("selector")),
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(c) if is_name_start(c) || c == '\\' || c == '-' => Ok(self.name_body()),
+            _ => Err(self.expected("identifier")),
+        }
+    }
+
+    fn name_body(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c == '\\' {
+                self.next();
+                name.push('\\');
+                if let Some(escaped) = self.next() {
+                    name.push(escaped);
+                }
+            } else if is_name(c) {
+                self.next();
+                name.push(c);
+            } else {
+                break;
+            }
+        }
+        name
+    }
+
+    fn type_or_universal(&mut self) -> Result<SimpleSelector, String> {
+        let mut text = String::new();
+        if self.peek() == Some('*') {
+            self.next();
+            text.push('*');
+        } else if self.peek() != Some('|') {
+            text.push_str(&self.identifier()?);
+        }
+
+        if self.peek() == Some('|') && self.peek_at(1) != Some('=') {
+            self.next();
+            let namespace = text;
+            if self.peek() == Some('*') {
+                self.next();
+                return Ok(SimpleSelector::Universal(Some(namespace)));
+            }
+            let name = self.identifier()?;
+            return Ok(SimpleSelector::Type(format!("{}|{}", namespace, name)));
+        }
+
+        if text == "*" {
+            Ok(SimpleSelector::Universal(None))
+        } else {
+            Ok(SimpleSelector::Type(text))
+        }
+    }
+
+    fn attribute_selector(&mut self) -> Result<SimpleSelector, String> {
+        self.next();
+        self.skip_ws();
+
+        let mut name = String::new();
+        if self.peek() == Some('*') || self.peek() == Some('|') {
+            if self.peek() == Some('*') {
+                self.next();
+                name.push('*');
+            }
+            if self.peek() == Some('|') {
+                self.next();
+                name.push('|');
+            }
+        }
+        name.push_str(&self.identifier()?);
+        if self.peek() == Some('|') && self.peek_at(1) != Some('=') {
+            self.next();
+            name.push('|');
+            name.push_str(&self.identifier()?);
+        }
+        self.skip_ws();
+
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(SimpleSelector::Attribute(AttributeSelector { name, op: None, value: None, modifier: None }));
+        }
+
+        let op = match self.peek() {
+            Some('=') => "=",
+            Some(c) if "~|^$*".contains(c) && self.peek_at(1) == Some('=') => &self.rest()[..2],
+            _ => return Err(self.expected("\"]\"")),
+        }.to_string();
+        self.pos += op.len();
+        self.skip_ws();
+
+        let value = match self.peek() {
+            Some(q) if q == '"' || q == '\'' => {
+                let start = self.pos;
+                self.next();
+                loop {
+                    match self.next() {
+                        None => return Err(self.expected(&q.to_string())),
+                        Some('\\') => {
+                            self.next();
+                        }
+                        Some(c) if c == q => break,
+                        Some(_) => {}
+                    }
+                }
+                self.text[start..self.pos].to_string()
+            }
+            _ => self.identifier()?,
+        };
+        self.skip_ws();
+
+        let modifier = if self.peek().map_or(false, |c| c.is_ascii_alphabetic()) {
+            let modifier = self.next().unwrap().to_string();
+            self.skip_ws();
+            Some(modifier)
+        } else {
+            None
+        };
+
+        if self.next() != Some(']') {
+            return Err(self.expected("\"]\""));
+        }
+        Ok(SimpleSelector::Attribute(AttributeSelector { name, op: Some(op), value: Some(value), modifier }))
+    }
+
+    fn pseudo_selector(&mut self) -> Result<SimpleSelector, String> {
+        self.next();
+        let is_element = if self.peek() == Some(':') {
+            self.next();
+            true
+        } else {
+            false
+        };
+        let name = self.identifier()?;
+
+        if self.peek() != Some('(') {
+            return Ok(SimpleSelector::Pseudo(PseudoSelector { name, is_element, argument: None, selector: None }));
+        }
+        self.next();
+        self.skip_ws();
+
+        let normalized = unvendor(&name);
+        let mut argument = None;
+        let mut selector = None;
+        if !is_element && SELECTOR_PSEUDOS.contains(&normalized.as_str()) {
+            selector = Some(Box::new(self.selector_list()?));
+        } else if !is_element && (normalized == "nth-child" || normalized == "nth-last-child") {
+            let arg = self.raw_argument();
+            match arg.find(" of ") {
+                Some(idx) => {
+                    let inner = parse_selector(&arg[idx + 4..], self.allow_parent)?;
+                    argument = Some(arg[..idx].trim().to_string());
+                    selector = Some(Box::new(inner));
+                }
+                None => argument = Some(arg),
+            }
+        } else {
+            argument = Some(self.raw_argument());
+        }
+
+        self.skip_ws();
+        if self.next() != Some(')') {
+            return Err(self.expected("\")\""));
+        }
+        Ok(SimpleSelector::Pseudo(PseudoSelector { name, is_element, argument, selector }))
+    }
+
+    /// Reads a pseudo argument up to the matching close paren, collapsing whitespace.
+    fn raw_argument(&mut self) -> String {
+        let mut depth = 0;
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => break,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            self.next();
+            if c.is_whitespace() {
+                if !text.ends_with(' ') {
+                    text.push(' ');
+                }
+            } else {
+                text.push(c);
+            }
+        }
+        text.trim().to_string()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Parent selector resolution
+
+impl SelectorList {
+    pub fn contains_parent_selector(&self) -> bool {
+        self.components.iter().any(|c| c.contains_parent_selector())
+    }
+
+    /// Replaces `&` with the parent selector, or prepends the parent to
+    /// each selector if there is no `&` and `implicit_parent` is set.
+    pub fn resolve_parent_selectors(&self, parent: Option<&SelectorList>, implicit_parent: bool) -> Result<SelectorList, String> {
+        let parent = match parent {
+            Some(parent) => parent,
+            None => {
+                if self.contains_parent_selector() {
+                    return Err("Base-level rules cannot contain the parent-selector-referencing character '&'.".to_string());
+                }
+                return Ok(self.clone());
+            }
+        };
+
+        let mut result = Vec::new();
+        for complex in &self.components {
+            if !complex.contains_parent_selector() {
+                if !implicit_parent {
+                    result.push(complex.clone());
+                    continue;
+                }
+                for parent_complex in &parent.components {
+                    let mut components = parent_complex.components.clone();
+                    components.extend(complex.components.iter().cloned());
+                    result.push(ComplexSelector { components, line_break: complex.line_break || parent_complex.line_break });
+                }
+                continue;
+            }
+
+            // Each compound containing `&` multiplies the selector by the parents.
+            let mut prefixes: Vec<ComplexSelector> = vec![ComplexSelector { components: vec![], line_break: complex.line_break }];
+            for component in &complex.components {
+                match *component {
+                    ComplexComponent::Compound(ref compound) => match compound.resolve_parent(parent)? {
+                        None => {
+                            let compound = compound.resolve_nested_parents(parent)?;
+                            for prefix in &mut prefixes {
+                                prefix.components.push(ComplexComponent::Compound(compound.clone()));
+                            }
+                        }
+                        Some(resolved) => {
+                            let mut next = Vec::new();
+                            for prefix in &prefixes {
+                                for parent_complex in &resolved {
+                                    let mut components = prefix.components.clone();
+                                    components.extend(parent_complex.components.iter().cloned());
+                                    next.push(ComplexSelector {
+                                        components,
+                                        line_break: prefix.line_break || parent_complex.line_break,
+                                    });
+                                }
+                            }
+                            prefixes = next;
+                        }
+                    },
+                    ComplexComponent::Combinator(_) => {
+                        for prefix in &mut prefixes {
+                            prefix.components.push(component.clone());
+                        }
+                    }
+                }
+            }
+            result.extend(prefixes);
+        }
+
+        Ok(SelectorList { components: result })
+    }
+
+    /// Whether every selector in the list contains a placeholder, so that
+    /// the rule should not be emitted.
+    /// The selector as a SassScript value, a comma separated list of
+    /// space separated lists of unquoted strings.
+    pub fn to_value(&self) -> Value {
+        let complexes = self.components.iter().map(|complex| {
+            let parts = complex.components.iter().map(|component| match *component {
+                ComplexComponent::Compound(ref compound) => Value::unquoted_string(compound.to_string()),
+                ComplexComponent::Combinator(combinator) => Value::unquoted_string(combinator.symbol()),
+            }).collect();
+            Value::List(List::new(parts, ListSeparator::Space, false))
+        }).collect();
+        Value::List(List::new(complexes, ListSeparator::Comma, false))
+    }
+
+    pub fn is_invisible(&self) -> bool {
+        self.components.iter().all(|c| c.is_invisible())
+    }
+}
+
+impl ComplexSelector {
+    pub fn contains_parent_selector(&self) -> bool {
+        self.components.iter().any(|component| match *component {
+            ComplexComponent::Compound(ref compound) => compound.contains_parent_selector(),
+            ComplexComponent::Combinator(_) => false,
+        })
+    }
+
+    pub fn is_invisible(&self) -> bool {
+        self.components.iter().any(|component| match *component {
+            ComplexComponent::Compound(ref compound) => compound.is_invisible(),
+            ComplexComponent::Combinator(_) => false,
+        })
+    }
+
+    /// The compound selectors, ignoring combinators.
+    pub fn compounds(&self) -> Vec<&CompoundSelector> {
+        self.components.iter().filter_map(|component| match *component {
+            ComplexComponent::Compound(ref compound) => Some(compound),
+            ComplexComponent::Combinator(_) => None,
+        }).collect()
+    }
+}
+
+impl CompoundSelector {
+    pub fn contains_parent_selector(&self) -> bool {
+        self.components.iter().any(|simple| match *simple {
+            SimpleSelector::Parent(_) => true,
+            SimpleSelector::Pseudo(ref pseudo) => pseudo.selector.as_ref().map_or(false, |s| s.contains_parent_selector()),
+            _ => false,
+        })
+    }
+
+    pub fn is_invisible(&self) -> bool {
+        self.components.iter().any(|simple| match *simple {
+            SimpleSelector::Placeholder(_) => true,
+            SimpleSelector::Pseudo(ref pseudo) => {
+                pseudo.normalized_name() != "not" && pseudo.selector.as_ref().map_or(false, |s| s.is_invisible())
+            }
+            _ => false,
+        })
+    }
+
+    /// Resolves selector pseudo arguments such as `:not(&)`.
+    fn resolve_nested_parents(&self, parent: &SelectorList) -> Result<CompoundSelector, String> {
+        let mut components = Vec::with_capacity(self.components.len());
+        for simple in &self.components {
+            match *simple {
+                SimpleSelector::Pseudo(ref pseudo) if pseudo.selector.as_ref().map_or(false, |s| s.contains_parent_selector()) => {
+                    let inner = pseudo.selector.as_ref().unwrap().resolve_parent_selectors(Some(parent), false)?;
+                    let mut pseudo = pseudo.clone();
+                    pseudo.selector = Some(Box::new(inner));
+                    components.push(SimpleSelector::Pseudo(pseudo));
+                }
+                ref other => components.push(other.clone()),
+            }
+        }
+        Ok(CompoundSelector { components })
+    }
+
+    /// If this compound starts with `&`, returns the parent selectors with
+    /// the rest of this compound appended to each of them.
+    fn resolve_parent(&self, parent: &SelectorList) -> Result<Option<Vec<ComplexSelector>>, String> {
+        let suffix = match self.components.first() {
+            Some(SimpleSelector::Parent(suffix)) => suffix.clone(),
+            _ => return Ok(None),
+        };
+        let rest = CompoundSelector { components: self.components[1..].to_vec() }.resolve_nested_parents(parent)?;
+
+        let mut result = Vec::new();
+        for parent_complex in &parent.components {
+            let mut components = parent_complex.components.clone();
+            let last = match components.pop() {
+                Some(ComplexComponent::Compound(compound)) => compound,
+                _ => return Err(format!("Parent \"{}\" is incompatible with this selector.", parent_complex)),
+            };
+
+            let mut last = last.components;
+            if let Some(ref suffix) = suffix {
+                let appended = match last.pop() {
+                    Some(SimpleSelector::Type(name)) => SimpleSelector::Type(name + suffix),
+                    Some(SimpleSelector::Class(name)) => SimpleSelector::Class(name + suffix),
+                    Some(SimpleSelector::Id(name)) => SimpleSelector::Id(name + suffix),
+                    Some(SimpleSelector::Placeholder(name)) => SimpleSelector::Placeholder(name + suffix),
+                    Some(SimpleSelector::Pseudo(ref pseudo)) if pseudo.argument.is_none() && pseudo.selector.is_none() => {
+                        let mut pseudo = pseudo.clone();
+                        pseudo.name.push_str(suffix);
+                        SimpleSelector::Pseudo(pseudo)
+                    }
+                    _ => {
+                        return Err(format!("Invalid parent selector for \"{}\": \"{}\"", self, parent_complex));
+                    }
+                };
+                last.push(appended);
+            }
+            last.extend(rest.components.iter().cloned());
+            components.push(ComplexComponent::Compound(CompoundSelector { components: last }));
+            result.push(ComplexSelector { components, line_break: parent_complex.line_break });
+        }
+        Ok(Some(result))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Serialization
+
+impl SelectorList {
+    /// Serializes the list, on one line per selector where the source
+    /// had line breaks, unless compressed. Selectors with placeholders
+    /// are left out.
+    pub fn to_css(&self, compressed: bool) -> String {
+        let mut buf = String::new();
+        let visible = self.components.iter().filter(|complex| !complex.is_invisible());
+        for (i, complex) in visible.enumerate() {
+            if i > 0 {
+                if compressed {
+                    buf.push(',');
+                } else if complex.line_break {
+                    buf.push_str(",\n");
+                } else {
+                    buf.push_str(", ");
+                }
+            }
+            complex.write(&mut buf, compressed);
+        }
+        buf
+    }
+}
+
+impl ComplexSelector {
+    fn write(&self, buf: &mut String, compressed: bool) {
+        let mut first = true;
+        for component in &self.components {
+            match *component {
+                ComplexComponent::Combinator(combinator) => {
+                    if compressed {
+                        buf.push_str(combinator.symbol());
+                    } else {
+                        if !first {
+                            buf.push(' ');
+                        }
+                        buf.push_str(combinator.symbol());
+                    }
+                }
+                ComplexComponent::Compound(ref compound) => {
+                    let after_combinator = matches!(buf.chars().next_back(), Some('>') | Some('+') | Some('~'));
+                    if !first && (!compressed || !after_combinator) {
+                        buf.push(' ');
+                    }
+                    compound.write(buf, compressed);
+                }
+            }
+            first = false;
+        }
+    }
+}
+
+impl CompoundSelector {
+    fn write(&self, buf: &mut String, compressed: bool) {
+        for simple in &self.components {
+            simple.write(buf, compressed);
+        }
+    }
+}
+
+impl SimpleSelector {
+    fn write(&self, buf: &mut String, compressed: bool) {
+        match *self {
+            SimpleSelector::Universal(None) => buf.push('*'),
+            SimpleSelector::Universal(Some(ref ns)) => {
+                let _ = write!(buf, "{}|*", ns);
+            }
+            SimpleSelector::Type(ref name) => buf.push_str(name),
+            SimpleSelector::Class(ref name) => {
+                let _ = write!(buf, ".{}", name);
+            }
+            SimpleSelector::Id(ref name) => {
+                let _ = write!(buf, "#{}", name);
+            }
+            SimpleSelector::Placeholder(ref name) => {
+                let _ = write!(buf, "%{}", name);
+            }
+            SimpleSelector::Attribute(ref attr) => {
+                buf.push('[');
+                buf.push_str(&attr.name);
+                if let Some(ref op) = attr.op {
+                    buf.push_str(op);
+                }
+                if let Some(ref value) = attr.value {
+                    buf.push_str(value);
+                }
+                if let Some(ref modifier) = attr.modifier {
+                    buf.push(' ');
+                    buf.push_str(modifier);
+                }
+                buf.push(']');
+            }
+            SimpleSelector::Pseudo(ref pseudo) => {
+                buf.push(':');
+                if pseudo.is_element {
+                    buf.push(':');
+                }
+                buf.push_str(&pseudo.name);
+                if pseudo.argument.is_none() && pseudo.selector.is_none() {
+                    return;
+                }
+                buf.push('(');
+                if let Some(ref argument) = pseudo.argument {
+                    buf.push_str(argument);
+                    if pseudo.selector.is_some() {
+                        buf.push_str(" of ");
+                    }
+                }
+                if let Some(ref selector) = pseudo.selector {
+                    buf.push_str(&selector.to_css(compressed).replace(",\n", ", "));
+                }
+                buf.push(')');
+            }
+            SimpleSelector::Parent(ref suffix) => {
+                buf.push('&');
+                if let Some(ref suffix) = *suffix {
+                    buf.push_str(suffix);
+                }
+            }
+        }
+    }
+}
+
+impl ::std::fmt::Display for SelectorList {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "{}", self.to_css(false).replace(",\n", ", "))
+    }
+}
+
+impl ::std::fmt::Display for ComplexSelector {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        let mut buf = String::new();
+        self.write(&mut buf, false);
+        write!(f, "{}", buf)
+    }
+}
+
+impl ::std::fmt::Display for CompoundSelector {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        let mut buf = String::new();
+        self.write(&mut buf, false);
+        write!(f, "{}", buf)
+    }
+}
+
+impl ::std::fmt::Display for SimpleSelector {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        let mut buf = String::new();
+        self.write(&mut buf, false);
+        write!(f, "{}", buf)
+    }
+}
@@ -0,0 +1,944 @@
+// FROM: src/ast_sel_super.cpp, src/ast_sel_unify.cpp, src/ast_sel_weave.cpp
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::slice;
+use selector::*;
+
+// Selector pseudo classes that match an element matching any of their
+// arguments, so that `:is(.a)` is a subselector of `.a`.
+const SUBSELECTOR_PSEUDOS: &[&str] = &["is", "matches", "where", "any", "nth-child", "nth-last-child"];
+
+// The components of a complex selector.
+pub type Components = Vec<ComplexComponent>;
+
+fn compound(components: Vec<SimpleSelector>) -> ComplexComponent {
+    ComplexComponent::Compound(CompoundSelector { components })
+}
+
+fn as_compound(component: &ComplexComponent) -> Option<&CompoundSelector> {
+    match *component {
+        ComplexComponent::Compound(ref compound) => Some(compound),
+        ComplexComponent::Combinator(_) => None,
+    }
+}
+
+fn as_combinator(component: &ComplexComponent) -> Option<Combinator> {
+    match *component {
+        ComplexComponent::Combinator(combinator) => Some(combinator),
+        ComplexComponent::Compound(_) => None,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Specificity
+
+// The specificity of a class, attribute or pseudo class selector.
+const SIMPLE_SPECIFICITY: u64 = 1000;
+
+impl SimpleSelector {
+    pub fn min_specificity(&self) -> u64 {
+        self.specificity().0
+    }
+
+    pub fn max_specificity(&self) -> u64 {
+        self.specificity().1
+    }
+
+    fn specificity(&self) -> (u64, u64) {
+        match *self {
+            SimpleSelector::Universal(_) => (0, 0),
+            SimpleSelector::Type(_) => (1, 1),
+            SimpleSelector::Id(_) => (SIMPLE_SPECIFICITY * SIMPLE_SPECIFICITY, SIMPLE_SPECIFICITY * SIMPLE_SPECIFICITY),
+            SimpleSelector::Pseudo(ref pseudo) => {
+                if pseudo.is_element {
+                    return (1, 1);
+                }
+                let selector = match pseudo.selector {
+                    Some(ref selector) => selector,
+                    None => return (SIMPLE_SPECIFICITY, SIMPLE_SPECIFICITY),
+                };
+                if pseudo.normalized_name() == "not" {
+                    selector.components.iter().fold((0, 0), |(min, max), complex| {
+                        (cmp::max(min, complex.min_specificity()), cmp::max(max, complex.max_specificity()))
+                    })
+                } else {
+                    // higher than any real selector's specificity
+                    let start = SIMPLE_SPECIFICITY.pow(3);
+                    selector.components.iter().fold((start, 0), |(min, max), complex| {
+                        (cmp::min(min, complex.min_specificity()), cmp::max(max, complex.max_specificity()))
+                    })
+                }
+            }
+            _ => (SIMPLE_SPECIFICITY, SIMPLE_SPECIFICITY),
+        }
+    }
+}
+
+impl CompoundSelector {
+    pub fn min_specificity(&self) -> u64 {
+        self.components.iter().map(|s| s.min_specificity()).sum()
+    }
+
+    pub fn max_specificity(&self) -> u64 {
+        self.components.iter().map(|s| s.max_specificity()).sum()
+    }
+}
+
+impl ComplexSelector {
+    pub fn min_specificity(&self) -> u64 {
+        self.compounds().iter().map(|c| c.min_specificity()).sum()
+    }
+
+    pub fn max_specificity(&self) -> u64 {
+        self.compounds().iter().map(|c| c.max_specificity()).sum()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Superselectors
+
+impl SelectorList {
+    /// Whether every element matched by `other` is also matched by this.
+    pub fn is_superselector(&self, other: &SelectorList) -> bool {
+        list_is_superselector(&self.components, &other.components)
+    }
+}
+
+impl ComplexSelector {
+    pub fn is_superselector(&self, other: &ComplexSelector) -> bool {
+        complex_is_superselector(&self.components, &other.components)
+    }
+}
+
+impl CompoundSelector {
+    pub fn is_superselector(&self, other: &CompoundSelector) -> bool {
+        compound_is_superselector(self, other, &[])
+    }
+}
+
+pub fn list_is_superselector(list1: &[ComplexSelector], list2: &[ComplexSelector]) -> bool {
+    list2.iter().all(|complex2| list1.iter().any(|complex1| complex1.is_superselector(complex2)))
+}
+
+/// Like `complex_is_superselector`, but for the parents of compound
+/// selectors that are known to match the same element.
+fn complex_is_parent_superselector(complex1: &[ComplexComponent], complex2: &[ComplexComponent]) -> bool {
+    if complex1.first().and_then(as_combinator).is_some() || complex2.first().and_then(as_combinator).is_some() {
+        return false;
+    }
+    if complex1.len() > complex2.len() {
+        return false;
+    }
+    let base = compound(vec![SimpleSelector::Placeholder("<temp>".to_string())]);
+    let mut with_base1 = complex1.to_vec();
+    with_base1.push(base.clone());
+    let mut with_base2 = complex2.to_vec();
+    with_base2.push(base);
+    complex_is_superselector(&with_base1, &with_base2)
+}
+
+pub fn complex_is_superselector(complex1: &[ComplexComponent], complex2: &[ComplexComponent]) -> bool {
+    // Selectors with trailing combinators are neither superselectors
+    // nor subselectors.
+    match (complex1.last(), complex2.last()) {
+        (Some(&ComplexComponent::Compound(_)), Some(&ComplexComponent::Compound(_))) => {}
+        _ => return false,
+    }
+
+    let mut i1 = 0;
+    let mut i2 = 0;
+    loop {
+        let remaining1 = complex1.len() - i1;
+        let remaining2 = complex2.len() - i2;
+        if remaining1 == 0 || remaining2 == 0 {
+            return false;
+        }
+
+        // More complex selectors are never superselectors of less
+        // complex ones.
+        if remaining1 > remaining2 {
+            return false;
+        }
+
+        // Selectors with leading combinators are neither superselectors
+        // nor subselectors.
+        let compound1 = match as_compound(&complex1[i1]) {
+            Some(compound) => compound,
+            None => return false,
+        };
+        if as_combinator(&complex2[i2]).is_some() {
+            return false;
+        }
+
+        if remaining1 == 1 {
+            let last2 = as_compound(complex2.last().unwrap()).unwrap();
+            return compound_is_superselector(compound1, last2, &complex2[i2..complex2.len() - 1]);
+        }
+
+        // Find the first index where `complex2[i2..after]` is a
+        // subselector of `compound1`. This stops before the end, since
+        // the rest of `complex1` still has to match something.
+        let mut after = i2 + 1;
+        while after < complex2.len() {
+            if let Some(compound2) = as_compound(&complex2[after - 1]) {
+                if compound_is_superselector(compound1, compound2, &complex2[i2..after - 1]) {
+                    break;
+                }
+            }
+            after += 1;
+        }
+        if after == complex2.len() {
+            return false;
+        }
+
+        let combinator1 = as_combinator(&complex1[i1 + 1]);
+        let combinator2 = as_combinator(&complex2[after]);
+        if let Some(combinator1) = combinator1 {
+            let combinator2 = match combinator2 {
+                Some(combinator2) => combinator2,
+                None => return false,
+            };
+
+            // `.foo ~ .bar` is a superselector of `.foo + .bar`, but
+            // otherwise the combinators must match.
+            if combinator1 == Combinator::FollowingSibling {
+                if combinator2 == Combinator::Child {
+                    return false;
+                }
+            } else if combinator2 != combinator1 {
+                return false;
+            }
+
+            // `.foo > .baz` is not a superselector of `.foo > .bar > .baz`
+            // or `.foo > .bar .baz`, despite `.baz` being a superselector
+            // of `.bar > .baz` and `.bar .baz`. Same for `+` and `~`.
+            if remaining1 == 3 && remaining2 > 3 {
+                return false;
+            }
+
+            i1 += 2;
+            i2 = after + 1;
+        } else if let Some(combinator2) = combinator2 {
+            if combinator2 != Combinator::Child {
+                return false;
+            }
+            i1 += 1;
+            i2 = after + 1;
+        } else {
+            i1 += 1;
+            i2 = after;
+        }
+    }
+}
+
+/// Whether `compound1` matches every element `compound2` matches.
+/// `parents` are the components before `compound2`, if any.
+pub fn compound_is_superselector(compound1: &CompoundSelector, compound2: &CompoundSelector, parents: &[ComplexComponent]) -> bool {
+    // Every simple selector in `compound1` must have a matching simple
+    // selector in `compound2`.
+    for simple1 in &compound1.components {
+        match *simple1 {
+            SimpleSelector::Pseudo(ref pseudo) if pseudo.selector.is_some() => {
+                if !selector_pseudo_is_superselector(pseudo, compound2, parents) {
+                    return false;
+                }
+            }
+            _ => {
+                if !simple_is_superselector_of_compound(simple1, compound2) {
+                    return false;
+                }
+            }
+        }
+    }
+
+    // `compound1` can't be a superselector of a selector with
+    // pseudo-elements that it doesn't share.
+    for simple2 in &compound2.components {
+        if let SimpleSelector::Pseudo(ref pseudo) = *simple2 {
+            if pseudo.is_element && pseudo.selector.is_none() && !simple_is_superselector_of_compound(simple2, compound1) {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+fn simple_is_superselector_of_compound(simple: &SimpleSelector, compound: &CompoundSelector) -> bool {
+    compound.components.iter().any(|their_simple| {
+        if simple == their_simple {
+            return true;
+        }
+
+        // Some selector pseudo classes can match normal selectors.
+        let pseudo = match *their_simple {
+            SimpleSelector::Pseudo(ref pseudo) => pseudo,
+            _ => return false,
+        };
+        let selector = match pseudo.selector {
+            Some(ref selector) => selector,
+            None => return false,
+        };
+        if !SUBSELECTOR_PSEUDOS.contains(&pseudo.normalized_name().as_str()) {
+            return false;
+        }
+        selector.components.iter().all(|complex| {
+            complex.components.len() == 1 && as_compound(&complex.components[0]).map_or(false, |c| c.components.contains(simple))
+        })
+    })
+}
+
+fn selector_pseudo_is_superselector(pseudo1: &PseudoSelector, compound2: &CompoundSelector, parents: &[ComplexComponent]) -> bool {
+    let selector1 = pseudo1.selector.as_ref().unwrap();
+    let args = |is_class: bool| -> Vec<&SelectorList> {
+        compound2.components.iter().filter_map(|simple| match *simple {
+            SimpleSelector::Pseudo(ref pseudo) if pseudo.is_element != is_class && pseudo.name == pseudo1.name => {
+                pseudo.selector.as_deref()
+            }
+            _ => None,
+        }).collect()
+    };
+
+    match pseudo1.normalized_name().as_str() {
+        "is" | "matches" | "where" | "any" => {
+            let mut with_compound = parents.to_vec();
+            with_compound.push(ComplexComponent::Compound(compound2.clone()));
+            args(true).iter().any(|selector2| selector1.is_superselector(selector2)) ||
+                selector1.components.iter().any(|complex1| complex_is_superselector(&complex1.components, &with_compound))
+        }
+        "has" | "host" | "host-context" => args(true).iter().any(|selector2| selector1.is_superselector(selector2)),
+        "slotted" => args(false).iter().any(|selector2| selector1.is_superselector(selector2)),
+        "not" => selector1.components.iter().all(|complex| {
+            compound2.components.iter().any(|simple2| match *simple2 {
+                SimpleSelector::Type(_) => complex.components.last().and_then(as_compound).map_or(false, |compound1| {
+                    compound1.components.iter().any(|simple1| match *simple1 {
+                        SimpleSelector::Type(_) => simple1 != simple2,
+                        _ => false,
+                    })
+                }),
+                SimpleSelector::Id(_) => complex.components.last().and_then(as_compound).map_or(false, |compound1| {
+                    compound1.components.iter().any(|simple1| match *simple1 {
+                        SimpleSelector::Id(_) => simple1 != simple2,
+                        _ => false,
+                    })
+                }),
+                SimpleSelector::Pseudo(ref pseudo2) if pseudo2.name == pseudo1.name => match pseudo2.selector {
+                    Some(ref selector2) => list_is_superselector(&selector2.components, slice::from_ref(complex)),
+                    None => false,
+                },
+                _ => false,
+            })
+        }),
+        "current" => args(true).iter().any(|selector2| **selector1 == **selector2),
+        "nth-child" | "nth-last-child" => compound2.components.iter().any(|simple2| match *simple2 {
+            SimpleSelector::Pseudo(ref pseudo2) => {
+                pseudo2.name == pseudo1.name && pseudo2.argument == pseudo1.argument &&
+                    pseudo2.selector.as_ref().map_or(false, |selector2| selector1.is_superselector(selector2))
+            }
+            _ => false,
+        }),
+        _ => false,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Unification
+
+impl SimpleSelector {
+    /// Unifies this with a compound selector, giving a compound that
+    /// matches only elements both match, or None if there are none.
+    pub fn unify(&self, compound: &[SimpleSelector]) -> Option<Vec<SimpleSelector>> {
+        match *self {
+            SimpleSelector::Universal(ref namespace) => {
+                match compound.first() {
+                    Some(first @ &SimpleSelector::Universal(_)) | Some(first @ &SimpleSelector::Type(_)) => {
+                        let unified = unify_universal_and_element(self, first)?;
+                        let mut result = vec![unified];
+                        result.extend(compound[1..].iter().cloned());
+                        return Some(result);
+                    }
+                    _ => {}
+                }
+                if namespace.is_some() && namespace.as_ref().map(|s| s.as_str()) != Some("*") {
+                    let mut result = vec![self.clone()];
+                    result.extend(compound.iter().cloned());
+                    return Some(result);
+                }
+                if !compound.is_empty() {
+                    return Some(compound.to_vec());
+                }
+                Some(vec![self.clone()])
+            }
+            SimpleSelector::Type(_) => {
+                match compound.first() {
+                    Some(first @ &SimpleSelector::Universal(_)) | Some(first @ &SimpleSelector::Type(_)) => {
+                        let unified = unify_universal_and_element(self, first)?;
+                        let mut result = vec![unified];
+                        result.extend(compound[1..].iter().cloned());
+                        Some(result)
+                    }
+                    _ => {
+                        let mut result = vec![self.clone()];
+                        result.extend(compound.iter().cloned());
+                        Some(result)
+                    }
+                }
+            }
+            SimpleSelector::Id(_) => {
+                let other_id = compound.iter().any(|simple| match *simple {
+                    SimpleSelector::Id(_) => simple != self,
+                    _ => false,
+                });
+                if other_id {
+                    return None;
+                }
+                self.unify_default(compound)
+            }
+            SimpleSelector::Pseudo(ref pseudo) => {
+                if let Some(unified) = self.unify_with_universal(compound) {
+                    return unified;
+                }
+                if compound.contains(self) {
+                    return Some(compound.to_vec());
+                }
+
+                let mut result = Vec::with_capacity(compound.len() + 1);
+                let mut added_this = false;
+                for simple in compound {
+                    if let SimpleSelector::Pseudo(ref other) = *simple {
+                        if other.is_element {
+                            // A compound selector may only contain one
+                            // pseudo-element.
+                            if pseudo.is_element {
+                                return None;
+                            }
+                            // Pseudo classes come before pseudo-elements.
+                            result.push(self.clone());
+                            added_this = true;
+                        }
+                    }
+                    result.push(simple.clone());
+                }
+                if !added_this {
+                    result.push(self.clone());
+                }
+                Some(result)
+            }
+            _ => self.unify_default(compound),
+        }
+    }
+
+    /// Unifies with a compound that is just a universal selector, which
+    /// takes care of namespaces.
+    fn unify_with_universal(&self, compound: &[SimpleSelector]) -> Option<Option<Vec<SimpleSelector>>> {
+        if compound.len() == 1 {
+            if let SimpleSelector::Universal(_) = compound[0] {
+                return Some(compound[0].unify(slice::from_ref(self)));
+            }
+        }
+        None
+    }
+
+    fn unify_default(&self, compound: &[SimpleSelector]) -> Option<Vec<SimpleSelector>> {
+        if let Some(unified) = self.unify_with_universal(compound) {
+            return unified;
+        }
+        if compound.contains(self) {
+            return Some(compound.to_vec());
+        }
+
+        // Pseudo selectors always come last.
+        let mut result = Vec::with_capacity(compound.len() + 1);
+        let mut added_this = false;
+        for simple in compound {
+            if !added_this {
+                if let SimpleSelector::Pseudo(_) = *simple {
+                    result.push(self.clone());
+                    added_this = true;
+                }
+            }
+            result.push(simple.clone());
+        }
+        if !added_this {
+            result.push(self.clone());
+        }
+        Some(result)
+    }
+}
+
+/// Splits a type or universal selector into its namespace and name.
+fn namespace_and_name(simple: &SimpleSelector) -> (Option<String>, Option<String>) {
+    match *simple {
+        SimpleSelector::Universal(ref namespace) => (namespace.clone(), None),
+        SimpleSelector::Type(ref name) => match name.find('|') {
+            Some(i) => (Some(name[..i].to_string()), Some(name[i + 1..].to_string())),
+            None => (None, Some(name.clone())),
+        },
+        _ => (None, None),
+    }
+}
+
+/// Unifies two type or universal selectors.
+fn unify_universal_and_element(selector1: &SimpleSelector, selector2: &SimpleSelector) -> Option<SimpleSelector> {
+    let (namespace1, name1) = namespace_and_name(selector1);
+    let (namespace2, name2) = namespace_and_name(selector2);
+
+    let namespace = if namespace1 == namespace2 || namespace2.as_deref() == Some("*") {
+        namespace1
+    } else if namespace1.as_deref() == Some("*") {
+        namespace2
+    } else {
+        return None;
+    };
+
+    let name = if name1 == name2 || name2.is_none() {
+        name1
+    } else if name1.is_none() || name1.as_deref() == Some("*") {
+        name2
+    } else {
+        return None;
+    };
+
+    Some(match name {
+        None => SimpleSelector::Universal(namespace),
+        Some(name) => match namespace {
+            Some(namespace) => SimpleSelector::Type(format!("{}|{}", namespace, name)),
+            None => SimpleSelector::Type(name),
+        },
+    })
+}
+
+pub fn unify_compound(compound1: &[SimpleSelector], compound2: &[SimpleSelector]) -> Option<CompoundSelector> {
+    let mut result = compound2.to_vec();
+    for simple in compound1 {
+        result = simple.unify(&result)?;
+    }
+    Some(CompoundSelector { components: result })
+}
+
+/// Unifies complex selectors, giving the selectors that match only
+/// elements all of them match. The last components must be compound.
+pub fn unify_complex(complexes: Vec<Components>) -> Option<Vec<Components>> {
+    if complexes.len() == 1 {
+        return Some(complexes);
+    }
+
+    let mut unified_base: Option<Vec<SimpleSelector>> = None;
+    for complex in &complexes {
+        let base = complex.last().and_then(as_compound)?;
+        unified_base = Some(match unified_base {
+            None => base.components.clone(),
+            Some(mut unified) => {
+                for simple in &base.components {
+                    unified = simple.unify(&unified)?;
+                }
+                unified
+            }
+        });
+    }
+
+    let mut without_bases: Vec<Components> = complexes.iter().map(|c| c[..c.len() - 1].to_vec()).collect();
+    without_bases.last_mut().unwrap().push(compound(unified_base.unwrap()));
+    Some(weave(without_bases))
+}
+
+// ---------------------------------------------------------------------------
+// Weaving
+
+/// Expands complex selectors so that each is a descendant of the ones
+/// before it, with all the ways their parents can be interleaved.
+///
+/// For example, `.a .b` and `.c .d` weave to `.a .c .b .d` and
+/// `.c .a .b .d`: the last compound of each selector stays last.
+pub fn weave(complexes: Vec<Components>) -> Vec<Components> {
+    let mut iter = complexes.into_iter();
+    let mut prefixes: Vec<Components> = match iter.next() {
+        Some(first) => vec![first],
+        None => return vec![],
+    };
+
+    for mut complex in iter {
+        let target = match complex.pop() {
+            Some(target) => target,
+            None => continue,
+        };
+        if complex.is_empty() {
+            for prefix in &mut prefixes {
+                prefix.push(target.clone());
+            }
+            continue;
+        }
+
+        let mut new_prefixes = Vec::new();
+        for prefix in &prefixes {
+            if let Some(parent_prefixes) = weave_parents(prefix, &complex) {
+                for mut parent_prefix in parent_prefixes {
+                    parent_prefix.push(target.clone());
+                    new_prefixes.push(parent_prefix);
+                }
+            }
+        }
+        prefixes = new_prefixes;
+    }
+
+    prefixes
+}
+
+/// Interleaves the parents of two complex selectors, giving every way
+/// they can be merged while keeping the order of each.
+fn weave_parents(parents1: &[ComplexComponent], parents2: &[ComplexComponent]) -> Option<Vec<Components>> {
+    let mut queue1: VecDeque<ComplexComponent> = parents1.iter().cloned().collect();
+    let mut queue2: VecDeque<ComplexComponent> = parents2.iter().cloned().collect();
+
+    let initial_combinators = merge_initial_combinators(&mut queue1, &mut queue2)?;
+    let final_combinators = merge_final_combinators(&mut queue1, &mut queue2, VecDeque::new())?;
+
+    // Make sure there's at most one `:root` in the output.
+    let root1 = first_if_root(&mut queue1);
+    let root2 = first_if_root(&mut queue2);
+    match (root1, root2) {
+        (Some(root1), Some(root2)) => {
+            let root = unify_compound(&root1.components, &root2.components)?;
+            queue1.push_front(ComplexComponent::Compound(root.clone()));
+            queue2.push_front(ComplexComponent::Compound(root));
+        }
+        (Some(root1), None) => queue2.push_front(ComplexComponent::Compound(root1)),
+        (None, Some(root2)) => queue1.push_front(ComplexComponent::Compound(root2)),
+        (None, None) => {}
+    }
+
+    let mut groups1 = group_selectors(queue1);
+    let mut groups2 = group_selectors(queue2);
+    let lcs = longest_common_subsequence(&Vec::from(groups2.clone()), &Vec::from(groups1.clone()), |group1, group2| {
+        if group1 == group2 {
+            return Some(group1.clone());
+        }
+        if as_compound(&group1[0]).is_none() || as_compound(&group2[0]).is_none() {
+            return None;
+        }
+        if complex_is_parent_superselector(group1, group2) {
+            return Some(group2.clone());
+        }
+        if complex_is_parent_superselector(group2, group1) {
+            return Some(group1.clone());
+        }
+        if !must_unify(group1, group2) {
+            return None;
+        }
+
+        let unified = unify_complex(vec![group1.clone(), group2.clone()])?;
+        if unified.len() > 1 {
+            return None;
+        }
+        unified.into_iter().next()
+    });
+
+    let mut choices: Vec<Vec<Components>> = vec![vec![initial_combinators]];
+    for group in lcs {
+        let chunks = chunks(&mut groups1, &mut groups2, |sequence| {
+            sequence.front().map_or(true, |first| complex_is_parent_superselector(first, &group))
+        });
+        choices.push(chunks.into_iter().map(|chunk| chunk.into_iter().flatten().collect()).collect());
+        choices.push(vec![group]);
+        groups1.pop_front();
+        groups2.pop_front();
+    }
+    let chunks = chunks(&mut groups1, &mut groups2, |sequence| sequence.is_empty());
+    choices.push(chunks.into_iter().map(|chunk| chunk.into_iter().flatten().collect()).collect());
+    choices.extend(final_combinators);
+
+    let choices: Vec<Vec<Components>> = choices.into_iter().filter(|choice| !choice.is_empty()).collect();
+    Some(paths(&choices).into_iter().map(|path| path.into_iter().flatten().collect()).collect())
+}
+
+fn first_if_root(queue: &mut VecDeque<ComplexComponent>) -> Option<CompoundSelector> {
+    let is_root = match queue.front() {
+        Some(ComplexComponent::Compound(compound)) => has_root(compound),
+        _ => false,
+    };
+    if !is_root {
+        return None;
+    }
+    match queue.pop_front() {
+        Some(ComplexComponent::Compound(compound)) => Some(compound),
+        _ => None,
+    }
+}
+
+fn has_root(compound: &CompoundSelector) -> bool {
+    compound.components.iter().any(|simple| match *simple {
+        SimpleSelector::Pseudo(ref pseudo) => !pseudo.is_element && pseudo.normalized_name() == "root",
+        _ => false,
+    })
+}
+
+/// Removes and merges the leading combinators of both selectors. One
+/// sequence of combinators must contain the other.
+fn merge_initial_combinators(components1: &mut VecDeque<ComplexComponent>, components2: &mut VecDeque<ComplexComponent>) -> Option<Components> {
+    let take = |components: &mut VecDeque<ComplexComponent>| {
+        let mut combinators = Vec::new();
+        while let Some(combinator) = components.front().and_then(as_combinator) {
+            components.pop_front();
+            combinators.push(combinator);
+        }
+        combinators
+    };
+    let combinators1 = take(components1);
+    let combinators2 = take(components2);
+
+    let lcs = longest_common_subsequence(&combinators1, &combinators2, |a, b| if a == b { Some(*a) } else { None });
+    let merged = if lcs == combinators1 {
+        combinators2
+    } else if lcs == combinators2 {
+        combinators1
+    } else {
+        return None;
+    };
+    Some(merged.into_iter().map(ComplexComponent::Combinator).collect())
+}
+
+/// Removes and merges the trailing combinators of both selectors, along
+/// with the compounds before them. Each element of the result is a set
+/// of alternatives.
+fn merge_final_combinators(components1: &mut VecDeque<ComplexComponent>, components2: &mut VecDeque<ComplexComponent>,
+                           mut result: VecDeque<Vec<Components>>) -> Option<VecDeque<Vec<Components>>> {
+    let ends_with_combinator = |components: &VecDeque<ComplexComponent>| components.back().and_then(as_combinator).is_some();
+    if !ends_with_combinator(components1) && !ends_with_combinator(components2) {
+        return Some(result);
+    }
+
+    let take = |components: &mut VecDeque<ComplexComponent>| {
+        let mut combinators = Vec::new();
+        while let Some(combinator) = components.back().and_then(as_combinator) {
+            components.pop_back();
+            combinators.push(combinator);
+        }
+        combinators
+    };
+    let combinators1 = take(components1);
+    let combinators2 = take(components2);
+
+    if combinators1.len() > 1 || combinators2.len() > 1 {
+        // Multiple combinators are unusual. If one sequence contains the
+        // other, use that, otherwise give up.
+        let lcs = longest_common_subsequence(&combinators1, &combinators2, |a, b| if a == b { Some(*a) } else { None });
+        let merged = if lcs == combinators1 {
+            combinators2
+        } else if lcs == combinators2 {
+            combinators1
+        } else {
+            return None;
+        };
+        result.push_front(vec![merged.into_iter().rev().map(ComplexComponent::Combinator).collect()]);
+        return Some(result);
+    }
+
+    let pop_compound = |components: &mut VecDeque<ComplexComponent>| match components.pop_back() {
+        Some(ComplexComponent::Compound(compound)) => Some(compound),
+        _ => None,
+    };
+    let with = |compound: &CompoundSelector, combinator: Combinator| {
+        vec![ComplexComponent::Compound(compound.clone()), ComplexComponent::Combinator(combinator)]
+    };
+
+    match (combinators1.first().cloned(), combinators2.first().cloned()) {
+        (Some(combinator1), Some(combinator2)) => {
+            let compound1 = pop_compound(components1)?;
+            let compound2 = pop_compound(components2)?;
+            use selector::Combinator::*;
+
+            if combinator1 == FollowingSibling && combinator2 == FollowingSibling {
+                if compound1.is_superselector(&compound2) {
+                    result.push_front(vec![with(&compound2, FollowingSibling)]);
+                } else if compound2.is_superselector(&compound1) {
+                    result.push_front(vec![with(&compound1, FollowingSibling)]);
+                } else {
+                    let mut first = with(&compound1, FollowingSibling);
+                    first.extend(with(&compound2, FollowingSibling));
+                    let mut second = with(&compound2, FollowingSibling);
+                    second.extend(with(&compound1, FollowingSibling));
+                    let mut choices = vec![first, second];
+                    if let Some(unified) = unify_compound(&compound1.components, &compound2.components) {
+                        choices.push(with(&unified, FollowingSibling));
+                    }
+                    result.push_front(choices);
+                }
+            } else if (combinator1 == FollowingSibling && combinator2 == NextSibling) ||
+                (combinator1 == NextSibling && combinator2 == FollowingSibling) {
+                let (following, next) = if combinator1 == FollowingSibling { (&compound1, &compound2) } else { (&compound2, &compound1) };
+                if following.is_superselector(next) {
+                    result.push_front(vec![with(next, NextSibling)]);
+                } else {
+                    let mut first = with(following, FollowingSibling);
+                    first.extend(with(next, NextSibling));
+                    let mut choices = vec![first];
+                    if let Some(unified) = unify_compound(&compound1.components, &compound2.components) {
+                        choices.push(with(&unified, NextSibling));
+                    }
+                    result.push_front(choices);
+                }
+            } else if combinator1 == Child && (combinator2 == NextSibling || combinator2 == FollowingSibling) {
+                result.push_front(vec![with(&compound2, combinator2)]);
+                components1.push_back(ComplexComponent::Compound(compound1));
+                components1.push_back(ComplexComponent::Combinator(Child));
+            } else if combinator2 == Child && (combinator1 == NextSibling || combinator1 == FollowingSibling) {
+                result.push_front(vec![with(&compound1, combinator1)]);
+                components2.push_back(ComplexComponent::Compound(compound2));
+                components2.push_back(ComplexComponent::Combinator(Child));
+            } else if combinator1 == combinator2 {
+                let unified = unify_compound(&compound1.components, &compound2.components)?;
+                result.push_front(vec![with(&unified, combinator1)]);
+            } else {
+                return None;
+            }
+
+            merge_final_combinators(components1, components2, result)
+        }
+        (Some(combinator1), None) => {
+            if combinator1 == Combinator::Child {
+                let superselector = match (components2.back().and_then(as_compound), components1.back().and_then(as_compound)) {
+                    (Some(last2), Some(last1)) => last2.is_superselector(last1),
+                    _ => false,
+                };
+                if superselector {
+                    components2.pop_back();
+                }
+            }
+            let compound1 = pop_compound(components1)?;
+            result.push_front(vec![with(&compound1, combinator1)]);
+            merge_final_combinators(components1, components2, result)
+        }
+        (None, Some(combinator2)) => {
+            if combinator2 == Combinator::Child {
+                let superselector = match (components1.back().and_then(as_compound), components2.back().and_then(as_compound)) {
+                    (Some(last1), Some(last2)) => last1.is_superselector(last2),
+                    _ => false,
+                };
+                if superselector {
+                    components1.pop_back();
+                }
+            }
+            let compound2 = pop_compound(components2)?;
+            result.push_front(vec![with(&compound2, combinator2)]);
+            merge_final_combinators(components1, components2, result)
+        }
+        (None, None) => Some(result),
+    }
+}
+
+/// Whether two groups contain a unique simple selector in common, such
+/// as an id, so that they can only match the same element.
+fn must_unify(complex1: &[ComplexComponent], complex2: &[ComplexComponent]) -> bool {
+    let is_unique = |simple: &SimpleSelector| match *simple {
+        SimpleSelector::Id(_) => true,
+        SimpleSelector::Pseudo(ref pseudo) => pseudo.is_element,
+        _ => false,
+    };
+    let unique: Vec<&SimpleSelector> = complex1.iter().filter_map(as_compound)
+        .flat_map(|compound| compound.components.iter().filter(|s| is_unique(s)))
+        .collect();
+    if unique.is_empty() {
+        return false;
+    }
+    complex2.iter().filter_map(as_compound).any(|compound| {
+        compound.components.iter().any(|simple| is_unique(simple) && unique.contains(&simple))
+    })
+}
+
+/// Takes the initial parts of both queues until `done` holds for each,
+/// giving the ways they can be combined: one after the other, in either
+/// order.
+fn chunks<T: Clone, F>(queue1: &mut VecDeque<T>, queue2: &mut VecDeque<T>, done: F) -> Vec<Vec<T>>
+    where F: Fn(&VecDeque<T>) -> bool
+{
+    let mut chunk1 = Vec::new();
+    while !done(queue1) {
+        chunk1.push(queue1.pop_front().unwrap());
+    }
+    let mut chunk2 = Vec::new();
+    while !done(queue2) {
+        chunk2.push(queue2.pop_front().unwrap());
+    }
+
+    match (chunk1.is_empty(), chunk2.is_empty()) {
+        (true, true) => vec![],
+        (true, false) => vec![chunk2],
+        (false, true) => vec![chunk1],
+        (false, false) => {
+            let mut first = chunk1.clone();
+            first.extend(chunk2.iter().cloned());
+            let mut second = chunk2;
+            second.extend(chunk1);
+            vec![first, second]
+        }
+    }
+}
+
+/// Every way of picking one option from each choice, in order.
+pub fn paths<T: Clone>(choices: &[Vec<T>]) -> Vec<Vec<T>> {
+    choices.iter().fold(vec![vec![]], |paths, choice| {
+        choice.iter().flat_map(|option| {
+            paths.iter().map(move |path| {
+                let mut path = path.clone();
+                path.push(option.clone());
+                path
+            })
+        }).collect()
+    })
+}
+
+/// Splits the components into groups of compound selectors joined by
+/// combinators, such as `a > b` in `a > b c`.
+fn group_selectors(complex: VecDeque<ComplexComponent>) -> VecDeque<Components> {
+    let mut groups: VecDeque<Components> = VecDeque::new();
+    for component in complex {
+        let joins = match groups.back() {
+            Some(group) => as_combinator(group.last().unwrap()).is_some() || as_combinator(&component).is_some(),
+            None => false,
+        };
+        if joins {
+            groups.back_mut().unwrap().push(component);
+        } else {
+            groups.push_back(vec![component]);
+        }
+    }
+    groups
+}
+
+/// The longest common subsequence of two lists, where `select` gives
+/// the element to use if two elements match.
+pub fn longest_common_subsequence<T: Clone, F>(list1: &[T], list2: &[T], select: F) -> Vec<T>
+    where F: Fn(&T, &T) -> Option<T>
+{
+    let mut lengths = vec![vec![0usize; list2.len() + 1]; list1.len() + 1];
+    let mut selections: Vec<Vec<Option<T>>> = vec![vec![None; list2.len()]; list1.len()];
+
+    for i in 0..list1.len() {
+        for j in 0..list2.len() {
+            let selection = select(&list1[i], &list2[j]);
+            lengths[i + 1][j + 1] = if selection.is_none() {
+                cmp::max(lengths[i + 1][j], lengths[i][j + 1])
+            } else {
+                lengths[i][j] + 1
+            };
+            selections[i][j] = selection;
+        }
+    }
+
+    let mut result = Vec::new();
+    let (mut i, mut j) = (list1.len(), list2.len());
+    while i > 0 && j > 0 {
+        if let Some(ref selection) = selections[i - 1][j - 1] {
+            result.push(selection.clone());
+            i -= 1;
+            j -= 1;
+        } else if lengths[i][j - 1] > lengths[i - 1][j] {
+            j -= 1;
+        } else {
+            i -= 1;
+        }
+    }
+    result.reverse();
+    result
+}
@@ -0,0 +1,81 @@
+// FROM: src/units.cpp
+
+// The unit classes that can be converted into each other.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UnitClass {
+    Length,
+    Angle,
+    Time,
+    Frequency,
+    Resolution,
+    Incommensurable,
+}
+
+// Each convertible unit with its class and its size relative to
+// the canonical unit of that class (px, deg, s, Hz, dppx).
+const CONVERSIONS: &[(&str, UnitClass, f64)] = &[
+    ("px", UnitClass::Length, 1.0),
+    ("in", UnitClass::Length, 96.0),
+    ("cm", UnitClass::Length, 96.0 / 2.54),
+    ("mm", UnitClass::Length, 96.0 / 25.4),
+    ("q", UnitClass::Length, 96.0 / 101.6),
+    ("pt", UnitClass::Length, 96.0 / 72.0),
+    ("pc", UnitClass::Length, 96.0 / 6.0),
+    ("deg", UnitClass::Angle, 1.0),
+    ("grad", UnitClass::Angle, 0.9),
+    ("rad", UnitClass::Angle, 180.0 / ::std::f64::consts::PI),
+    ("turn", UnitClass::Angle, 360.0),
+    ("s", UnitClass::Time, 1.0),
+    ("ms", UnitClass::Time, 0.001),
+    ("hz", UnitClass::Frequency, 1.0),
+    ("khz", UnitClass::Frequency, 1000.0),
+    ("dppx", UnitClass::Resolution, 1.0),
+    ("dpi", UnitClass::Resolution, 1.0 / 96.0),
+    ("dpcm", UnitClass::Resolution, 2.54 / 96.0),
+    ("x", UnitClass::Resolution, 1.0),
+];
+
+fn lookup(unit: &str) -> Option<(UnitClass, f64)> {
+    let lower = unit.to_ascii_lowercase();
+    CONVERSIONS.iter()
+        .find(|&&(u, _, _)| u == lower)
+        .map(|&(_, class, factor)| (class, factor))
+}
+
+pub fn unit_class(unit: &str) -> UnitClass {
+    lookup(unit).map_or(UnitClass::Incommensurable, |(class, _)| class)
+}
+
+/// Returns the factor to multiply a value in unit `from` by
+/// to get the value in unit `to`, if the units are compatible.
+pub fn conversion_factor(from: &str, to: &str) -> Option<f64> {
+    if from == to {
+        return Some(1.0);
+    }
+
+    match (lookup(from), lookup(to)) {
+        (Some((from_class, from_factor)), Some((to_class, to_factor))) if from_class == to_class => {
+            Some(from_factor / to_factor)
+        }
+        _ => None,
+    }
+}
+
+/// Formats a unit as numerators and denominators, e.g. `px*em/s`.
+pub fn unit_string(numerators: &[String], denominators: &[String]) -> String {
+    let mut s = numerators.join("*");
+    if !denominators.is_empty() {
+        if numerators.is_empty() && denominators.len() == 1 {
+            s.push_str(&denominators[0]);
+            s.push_str("^-1");
+        } else if numerators.is_empty() {
+            s.push('(');
+            s.push_str(&denominators.join("*"));
+            s.push_str(")^-1");
+        } else {
+            s.push('/');
+            s.push_str(&denominators.join("*"));
+        }
+    }
+    s
+}
@@ -1,127 +1,653 @@
-//// Forward declaration
-//union Sass_Value;
-//
-//// Type for Sass values
-//enum Sass_Tag {
-//    SASS_BOOLEAN,
-//    SASS_NUMBER,
-//    SASS_COLOR,
-//    SASS_STRING,
-//    SASS_LIST,
-//    SASS_MAP,
-//    SASS_NULL,
-//    SASS_ERROR,
-//    SASS_WARNING
-//};
-//
-//// Tags for denoting Sass list separators
-//enum Sass_Separator {
-//    SASS_COMMA,
-//    SASS_SPACE,
-//    // only used internally to represent a hash map before evaluation
-//    // otherwise we would be too early to check for duplicate keys
-//    SASS_HASH
-//};
-//
-//// Value Operators
-//enum Sass_OP {
-//    AND, OR,                   // logical connectives
-//    EQ, NEQ, GT, GTE, LT, LTE, // arithmetic relations
-//    ADD, SUB, MUL, DIV, MOD,   // arithmetic functions
-//    NUM_OPS                    // so we know how big to make the op table
-//};
-//
-//// Creator functions for all value types
-//ADDAPI union Sass_Value* ADDCALL sass_make_null    (void);
-//ADDAPI union Sass_Value* ADDCALL sass_make_boolean (bool val);
-//ADDAPI union Sass_Value* ADDCALL sass_make_string  (const char* val);
-//ADDAPI union Sass_Value* ADDCALL sass_make_qstring (const char* val);
-//ADDAPI union Sass_Value* ADDCALL sass_make_number  (double val, const char* unit);
-//ADDAPI union Sass_Value* ADDCALL sass_make_color   (double r, double g, double b, double a);
-//ADDAPI union Sass_Value* ADDCALL sass_make_list    (size_t len, enum Sass_Separator sep, bool is_bracketed);
-//ADDAPI union Sass_Value* ADDCALL sass_make_map     (size_t len);
-//ADDAPI union Sass_Value* ADDCALL sass_make_error   (const char* msg);
-//ADDAPI union Sass_Value* ADDCALL sass_make_warning (const char* msg);
-//
-//// Generic destructor function for all types
-//// Will release memory of all associated Sass_Values
-//// Means we will delete recursively for lists and maps
-//ADDAPI void ADDCALL sass_delete_value (union Sass_Value* val);
-//
-//// Make a deep cloned copy of the given sass value
-//ADDAPI union Sass_Value* ADDCALL sass_clone_value (const union Sass_Value* val);
-//
-//// Execute an operation for two Sass_Values and return the result as a Sass_Value too
-//ADDAPI union Sass_Value* ADDCALL sass_value_op (enum Sass_OP op, const union Sass_Value* a, const union Sass_Value* b);
-//
-//// Stringify a Sass_Values and also return the result as a Sass_Value (of type STRING)
-//ADDAPI union Sass_Value* ADDCALL sass_value_stringify (const union Sass_Value* a, bool compressed, int precision);
-//
-//// Return the sass tag for a generic sass value
-//// Check is needed before accessing specific values!
-//ADDAPI enum Sass_Tag ADDCALL sass_value_get_tag (const union Sass_Value* v);
-//
-//// Check value to be of a specific type
-//// Can also be used before accessing properties!
-//ADDAPI bool ADDCALL sass_value_is_null (const union Sass_Value* v);
-//ADDAPI bool ADDCALL sass_value_is_number (const union Sass_Value* v);
-//ADDAPI bool ADDCALL sass_value_is_string (const union Sass_Value* v);
-//ADDAPI bool ADDCALL sass_value_is_boolean (const union Sass_Value* v);
-//ADDAPI bool ADDCALL sass_value_is_color (const union Sass_Value* v);
-//ADDAPI bool ADDCALL sass_value_is_list (const union Sass_Value* v);
-//ADDAPI bool ADDCALL sass_value_is_map (const union Sass_Value* v);
-//ADDAPI bool ADDCALL sass_value_is_error (const union Sass_Value* v);
-//ADDAPI bool ADDCALL sass_value_is_warning (const union Sass_Value* v);
-//
-//// Getters and setters for Sass_Number
-//ADDAPI double ADDCALL sass_number_get_value (const union Sass_Value* v);
-//ADDAPI void ADDCALL sass_number_set_value (union Sass_Value* v, double value);
-//ADDAPI const char* ADDCALL sass_number_get_unit (const union Sass_Value* v);
-//ADDAPI void ADDCALL sass_number_set_unit (union Sass_Value* v, char* unit);
-//
-//// Getters and setters for Sass_String
-//ADDAPI const char* ADDCALL sass_string_get_value (const union Sass_Value* v);
-//ADDAPI void ADDCALL sass_string_set_value (union Sass_Value* v, char* value);
-//ADDAPI bool ADDCALL sass_string_is_quoted(const union Sass_Value* v);
-//ADDAPI void ADDCALL sass_string_set_quoted(union Sass_Value* v, bool quoted);
-//
-//// Getters and setters for Sass_Boolean
-//ADDAPI bool ADDCALL sass_boolean_get_value (const union Sass_Value* v);
-//ADDAPI void ADDCALL sass_boolean_set_value (union Sass_Value* v, bool value);
-//
-//// Getters and setters for Sass_Color
-//ADDAPI double ADDCALL sass_color_get_r (const union Sass_Value* v);
-//ADDAPI void ADDCALL sass_color_set_r (union Sass_Value* v, double r);
-//ADDAPI double ADDCALL sass_color_get_g (const union Sass_Value* v);
-//ADDAPI void ADDCALL sass_color_set_g (union Sass_Value* v, double g);
-//ADDAPI double ADDCALL sass_color_get_b (const union Sass_Value* v);
-//ADDAPI void ADDCALL sass_color_set_b (union Sass_Value* v, double b);
-//ADDAPI double ADDCALL sass_color_get_a (const union Sass_Value* v);
-//ADDAPI void ADDCALL sass_color_set_a (union Sass_Value* v, double a);
-//
-//// Getter for the number of items in list
-//ADDAPI size_t ADDCALL sass_list_get_length (const union Sass_Value* v);
-//// Getters and setters for Sass_List
-//ADDAPI enum Sass_Separator ADDCALL sass_list_get_separator (const union Sass_Value* v);
-//ADDAPI void ADDCALL sass_list_set_separator (union Sass_Value* v, enum Sass_Separator value);
-//ADDAPI bool ADDCALL sass_list_get_is_bracketed (const union Sass_Value* v);
-//ADDAPI void ADDCALL sass_list_set_is_bracketed (union Sass_Value* v, bool value);
-//// Getters and setters for Sass_List values
-//ADDAPI union Sass_Value* ADDCALL sass_list_get_value (const union Sass_Value* v, size_t i);
-//ADDAPI void ADDCALL sass_list_set_value (union Sass_Value* v, size_t i, union Sass_Value* value);
-//
-//// Getter for the number of items in map
-//ADDAPI size_t ADDCALL sass_map_get_length (const union Sass_Value* v);
-//// Getters and setters for Sass_Map keys and values
-//ADDAPI union Sass_Value* ADDCALL sass_map_get_key (const union Sass_Value* v, size_t i);
-//ADDAPI void ADDCALL sass_map_set_key (union Sass_Value* v, size_t i, union Sass_Value*);
-//ADDAPI union Sass_Value* ADDCALL sass_map_get_value (const union Sass_Value* v, size_t i);
-//ADDAPI void ADDCALL sass_map_set_value (union Sass_Value* v, size_t i, union Sass_Value*);
-//
-//// Getters and setters for Sass_Error
-//ADDAPI char* ADDCALL sass_error_get_message (const union Sass_Value* v);
-//ADDAPI void ADDCALL sass_error_set_message (union Sass_Value* v, char* msg);
-//
-//// Getters and setters for Sass_Warning
-//ADDAPI char* ADDCALL sass_warning_get_message (const union Sass_Value* v);
-//ADDAPI void ADDCALL sass_warning_set_message (union Sass_Value* v, char* msg);
+// FROM: src/ast_values.hpp
+
+use std::f64;
+use ast::Callable;
+use color_maps;
+use units::*;
+
+// Two numbers closer than this are considered equal.
+pub const NUMBER_EPSILON: f64 = 1e-11;
+
+pub fn fuzzy_equals(a: f64, b: f64) -> bool {
+    (a - b).abs() < NUMBER_EPSILON
+}
+
+/// Rounds `.5` away from zero, unlike the banker's rounding used by
+/// some formatting routines.
+pub fn fuzzy_round(a: f64) -> f64 {
+    if a > 0.0 {
+        if (a % 1.0) >= 0.5 - NUMBER_EPSILON { a.ceil() } else { a.floor() }
+    } else if (a % 1.0) <= -0.5 + NUMBER_EPSILON {
+        a.floor()
+    } else {
+        a.ceil()
+    }
+}
+
+/// Returns the value as an integer if it is (fuzzily) one.
+pub fn fuzzy_as_int(a: f64) -> Option<i64> {
+    let rounded = a.round();
+    if fuzzy_equals(a, rounded) { Some(rounded as i64) } else { None }
+}
+
+// How the items of a list are separated.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ListSeparator {
+    Space,
+    Comma,
+    Slash,
+    // a list with zero or one items that has not had a separator set
+    Undecided,
+}
+
+impl ListSeparator {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ListSeparator::Comma => "comma",
+            ListSeparator::Slash => "slash",
+            ListSeparator::Space | ListSeparator::Undecided => "space",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Number {
+    pub value: f64,
+    pub numerators: Vec<String>,
+    pub denominators: Vec<String>,
+    // the numbers either side of a `/` that plain CSS keeps, such as in
+    // `font: 12px/1.5`; the number is written that way until it is used
+    pub as_slash: Option<Box<(Number, Number)>>,
+}
+
+impl Number {
+    pub fn new(value: f64) -> Self {
+        Number { value, numerators: vec![], denominators: vec![], as_slash: None }
+    }
+
+    pub fn with_unit<S: Into<String>>(value: f64, unit: S) -> Self {
+        let unit = unit.into();
+        let numerators = if unit.is_empty() { vec![] } else { vec![unit] };
+        Number { value, numerators, denominators: vec![], as_slash: None }
+    }
+
+    pub fn with_units(value: f64, numerators: Vec<String>, denominators: Vec<String>) -> Self {
+        Number { value, numerators, denominators, as_slash: None }
+    }
+
+    /// Divides two numbers, remembering them so that the result is
+    /// written as `numerator/denominator`.
+    pub fn slash(numerator: Number, denominator: Number) -> Number {
+        let mut number = numerator.divide(&denominator);
+        number.as_slash = Some(Box::new((numerator, denominator)));
+        number
+    }
+
+    /// This number, written as a number rather than with a slash.
+    pub fn without_slash(&self) -> Number {
+        Number { as_slash: None, ..self.clone() }
+    }
+
+    pub fn is_unitless(&self) -> bool {
+        self.numerators.is_empty() && self.denominators.is_empty()
+    }
+
+    /// Whether this number has exactly the single unit given.
+    pub fn has_unit(&self, unit: &str) -> bool {
+        self.denominators.is_empty() && self.numerators.len() == 1 && self.numerators[0] == unit
+    }
+
+    pub fn unit(&self) -> String {
+        unit_string(&self.numerators, &self.denominators)
+    }
+
+    pub fn is_int(&self) -> bool {
+        fuzzy_as_int(self.value).is_some()
+    }
+
+    /// Whether the units are valid in plain CSS, i.e. at most a single numerator.
+    pub fn has_valid_css_units(&self) -> bool {
+        self.numerators.len() <= 1 && self.denominators.is_empty()
+    }
+
+    /// Returns this number's value converted to the given units, or None if
+    /// the units are not compatible. Unitless numbers are compatible with
+    /// any units.
+    pub fn coerce_value(&self, numerators: &[String], denominators: &[String]) -> Option<f64> {
+        if self.is_unitless() || (numerators.is_empty() && denominators.is_empty()) {
+            return Some(self.value);
+        }
+        if self.numerators.len() != numerators.len() || self.denominators.len() != denominators.len() {
+            return None;
+        }
+
+        let mut value = self.value;
+        let mut remaining: Vec<&String> = numerators.iter().collect();
+        for unit in &self.numerators {
+            let pos = remaining.iter().position(|other| conversion_factor(unit, other).is_some())?;
+            value *= conversion_factor(unit, remaining[pos]).unwrap();
+            remaining.remove(pos);
+        }
+
+        let mut remaining: Vec<&String> = denominators.iter().collect();
+        for unit in &self.denominators {
+            let pos = remaining.iter().position(|other| conversion_factor(unit, other).is_some())?;
+            value /= conversion_factor(unit, remaining[pos]).unwrap();
+            remaining.remove(pos);
+        }
+
+        Some(value)
+    }
+
+    /// Like coerce_value, but unitless numbers are only compatible with
+    /// other unitless numbers.
+    pub fn convert_value(&self, numerators: &[String], denominators: &[String]) -> Option<f64> {
+        if self.is_unitless() != (numerators.is_empty() && denominators.is_empty()) {
+            return None;
+        }
+        self.coerce_value(numerators, denominators)
+    }
+
+    pub fn is_comparable_to(&self, other: &Number) -> bool {
+        self.coerce_value(&other.numerators, &other.denominators).is_some()
+    }
+
+    /// Multiplies two numbers, cancelling out compatible units.
+    pub fn multiply(&self, other: &Number) -> Number {
+        let mut numerators = self.numerators.clone();
+        numerators.extend(other.numerators.iter().cloned());
+        let mut denominators = self.denominators.clone();
+        denominators.extend(other.denominators.iter().cloned());
+        Number::simplify(self.value * other.value, numerators, denominators)
+    }
+
+    /// Divides two numbers, cancelling out compatible units.
+    pub fn divide(&self, other: &Number) -> Number {
+        let mut numerators = self.numerators.clone();
+        numerators.extend(other.denominators.iter().cloned());
+        let mut denominators = self.denominators.clone();
+        denominators.extend(other.numerators.iter().cloned());
+        Number::simplify(self.value / other.value, numerators, denominators)
+    }
+
+    fn simplify(mut value: f64, numerators: Vec<String>, mut denominators: Vec<String>) -> Number {
+        let mut kept = Vec::new();
+        for unit in numerators {
+            let pos = denominators.iter().position(|d| conversion_factor(&unit, d).is_some());
+            match pos {
+                Some(pos) => {
+                    value *= conversion_factor(&unit, &denominators[pos]).unwrap();
+                    denominators.remove(pos);
+                }
+                None => kept.push(unit),
+            }
+        }
+        Number { value, numerators: kept, denominators, as_slash: None }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Number) -> bool {
+        match self.convert_value(&other.numerators, &other.denominators) {
+            Some(value) => fuzzy_equals(value, other.value),
+            None => false,
+        }
+    }
+}
+
+// RGB channels are stored rounded to integers, so that colors compare
+// and print the same way as in dart-sass.
+#[derive(Debug, Clone)]
+pub struct Color {
+    red: u8,
+    green: u8,
+    blue: u8,
+    // hue, saturation and lightness if the color was created from them
+    hsl: Option<(f64, f64, f64)>,
+    alpha: f64,
+    // How the color was written, kept so that colors that pass through
+    // unchanged are printed the same way.
+    pub format: Option<ColorFormat>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorFormat {
+    // the original spelling of a literal, such as `#FFF` or `RED`
+    Literal(String),
+    // made by rgb() or rgba(), and printed as a call to them
+    RgbFunction,
+}
+
+impl Color {
+    /// Creates a color from RGB channels, which are rounded and clamped.
+    pub fn rgba(red: f64, green: f64, blue: f64, alpha: f64) -> Self {
+        Color {
+            red: clamp_channel(red),
+            green: clamp_channel(green),
+            blue: clamp_channel(blue),
+            hsl: None,
+            alpha: clamp_alpha(alpha),
+            format: None,
+        }
+    }
+
+    /// Creates a color from a hue in degrees and saturation and lightness
+    /// percentages between 0 and 100.
+    pub fn hsla(hue: f64, saturation: f64, lightness: f64, alpha: f64) -> Self {
+        let hue = ((hue % 360.0) + 360.0) % 360.0;
+        let saturation = saturation.clamp(0.0, 100.0);
+        let lightness = lightness.clamp(0.0, 100.0);
+
+        let s = saturation / 100.0;
+        let l = lightness / 100.0;
+        let h = hue / 360.0;
+        let m2 = if l <= 0.5 { l * (s + 1.0) } else { l + s - l * s };
+        let m1 = l * 2.0 - m2;
+
+        Color {
+            red: clamp_channel(hue_to_rgb(m1, m2, h + 1.0 / 3.0) * 255.0),
+            green: clamp_channel(hue_to_rgb(m1, m2, h) * 255.0),
+            blue: clamp_channel(hue_to_rgb(m1, m2, h - 1.0 / 3.0) * 255.0),
+            hsl: Some((hue, saturation, lightness)),
+            alpha: clamp_alpha(alpha),
+            format: None,
+        }
+    }
+
+    /// Creates a color from a hue in degrees and whiteness and blackness
+    /// percentages between 0 and 100. Whiteness and blackness that add up
+    /// to more than 100 are scaled down in proportion.
+    pub fn hwba(hue: f64, whiteness: f64, blackness: f64, alpha: f64) -> Self {
+        let h = (((hue % 360.0) + 360.0) % 360.0) / 360.0;
+        let mut w = whiteness / 100.0;
+        let mut b = blackness / 100.0;
+        let sum = w + b;
+        if sum > 1.0 {
+            w /= sum;
+            b /= sum;
+        }
+        let factor = 1.0 - w - b;
+        let channel = |hue: f64| (hue_to_rgb(0.0, 1.0, hue) * factor + w) * 255.0;
+        Color::rgba(channel(h + 1.0 / 3.0), channel(h), channel(h - 1.0 / 3.0), alpha)
+    }
+
+    pub fn with_format(mut self, format: ColorFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    pub fn red(&self) -> u8 { self.red }
+    pub fn green(&self) -> u8 { self.green }
+    pub fn blue(&self) -> u8 { self.blue }
+    pub fn alpha(&self) -> f64 { self.alpha }
+
+    pub fn hue(&self) -> f64 { self.hsl().0 }
+    pub fn saturation(&self) -> f64 { self.hsl().1 }
+    pub fn lightness(&self) -> f64 { self.hsl().2 }
+
+    pub fn whiteness(&self) -> f64 {
+        f64::from(self.red.min(self.green).min(self.blue)) / 255.0 * 100.0
+    }
+
+    pub fn blackness(&self) -> f64 {
+        100.0 - f64::from(self.red.max(self.green).max(self.blue)) / 255.0 * 100.0
+    }
+
+    /// Returns hue, saturation and lightness, computing them from the RGB
+    /// channels if the color was not created from HSL.
+    pub fn hsl(&self) -> (f64, f64, f64) {
+        if let Some(hsl) = self.hsl {
+            return hsl;
+        }
+
+        let r = f64::from(self.red) / 255.0;
+        let g = f64::from(self.green) / 255.0;
+        let b = f64::from(self.blue) / 255.0;
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+
+        let mut hue = if max == min {
+            0.0
+        } else if max == r {
+            60.0 * (g - b) / delta
+        } else if max == g {
+            60.0 * (b - r) / delta + 120.0
+        } else {
+            60.0 * (r - g) / delta + 240.0
+        };
+        hue = ((hue % 360.0) + 360.0) % 360.0;
+
+        let lightness = 50.0 * (max + min);
+        let saturation = if max == min {
+            0.0
+        } else if lightness < 50.0 {
+            100.0 * delta / (max + min)
+        } else {
+            100.0 * delta / (2.0 - max - min)
+        };
+
+        (hue, saturation, lightness)
+    }
+
+    /// Returns a copy with a different alpha channel.
+    pub fn with_alpha(&self, alpha: f64) -> Color {
+        Color { alpha: clamp_alpha(alpha), format: None, ..self.clone() }
+    }
+}
+
+impl PartialEq for Color {
+    fn eq(&self, other: &Color) -> bool {
+        self.red == other.red && self.green == other.green && self.blue == other.blue &&
+            fuzzy_equals(self.alpha, other.alpha)
+    }
+}
+
+fn clamp_channel(value: f64) -> u8 {
+    fuzzy_round(value).clamp(0.0, 255.0) as u8
+}
+
+fn clamp_alpha(value: f64) -> f64 {
+    value.clamp(0.0, 1.0)
+}
+
+fn hue_to_rgb(m1: f64, m2: f64, mut hue: f64) -> f64 {
+    if hue < 0.0 { hue += 1.0; }
+    if hue > 1.0 { hue -= 1.0; }
+
+    if hue < 1.0 / 6.0 {
+        m1 + (m2 - m1) * hue * 6.0
+    } else if hue < 1.0 / 2.0 {
+        m2
+    } else if hue < 2.0 / 3.0 {
+        m1 + (m2 - m1) * (2.0 / 3.0 - hue) * 6.0
+    } else {
+        m1
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SassString {
+    pub text: String,
+    pub quoted: bool,
+}
+
+impl SassString {
+    pub fn quoted<S: Into<String>>(text: S) -> Self {
+        SassString { text: text.into(), quoted: true }
+    }
+
+    pub fn unquoted<S: Into<String>>(text: S) -> Self {
+        SassString { text: text.into(), quoted: false }
+    }
+}
+
+impl PartialEq for SassString {
+    fn eq(&self, other: &SassString) -> bool {
+        self.text == other.text
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct List {
+    pub items: Vec<Value>,
+    pub separator: ListSeparator,
+    pub bracketed: bool,
+    // the keyword arguments, if this is the argument list of a rest parameter
+    pub keywords: Option<SassMap>,
+}
+
+impl List {
+    pub fn new(items: Vec<Value>, separator: ListSeparator, bracketed: bool) -> Self {
+        List { items, separator, bracketed, keywords: None }
+    }
+
+    /// The value of a `$args...` parameter: the extra positional
+    /// arguments, and any keyword arguments that matched no parameter.
+    pub fn arglist(items: Vec<Value>, separator: ListSeparator, keywords: SassMap) -> Self {
+        List { items, separator, bracketed: false, keywords: Some(keywords) }
+    }
+
+    pub fn empty() -> Self {
+        List::new(vec![], ListSeparator::Undecided, false)
+    }
+}
+
+impl PartialEq for List {
+    fn eq(&self, other: &List) -> bool {
+        self.bracketed == other.bracketed &&
+            (self.separator == other.separator || self.items.len() <= 1 && other.items.len() <= 1) &&
+            self.items == other.items
+    }
+}
+
+// An ordered map. Keys are compared with Sass equality, so this is a
+// simple list of pairs rather than a hash map.
+#[derive(Debug, Clone, Default)]
+pub struct SassMap {
+    pairs: Vec<(Value, Value)>,
+}
+
+impl SassMap {
+    pub fn new() -> Self {
+        SassMap { pairs: vec![] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    pub fn get(&self, key: &Value) -> Option<&Value> {
+        self.pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn contains_key(&self, key: &Value) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Sets the value for a key, keeping the position of an existing key.
+    pub fn insert(&mut self, key: Value, value: Value) {
+        match self.pairs.iter().position(|(k, _)| *k == key) {
+            Some(pos) => self.pairs[pos].1 = value,
+            None => self.pairs.push((key, value)),
+        }
+    }
+
+    pub fn remove(&mut self, key: &Value) -> Option<Value> {
+        let pos = self.pairs.iter().position(|(k, _)| k == key)?;
+        Some(self.pairs.remove(pos).1)
+    }
+
+    pub fn pairs(&self) -> &[(Value, Value)] {
+        &self.pairs
+    }
+
+    pub fn keys(&self) -> Vec<Value> {
+        self.pairs.iter().map(|(k, _)| k.clone()).collect()
+    }
+
+    pub fn values(&self) -> Vec<Value> {
+        self.pairs.iter().map(|(_, v)| v.clone()).collect()
+    }
+
+    /// The map as a comma separated list of `key value` pairs.
+    pub fn to_list(&self) -> List {
+        let items = self.pairs.iter()
+            .map(|(k, v)| Value::List(List::new(vec![k.clone(), v.clone()], ListSeparator::Space, false)))
+            .collect();
+        List::new(items, ListSeparator::Comma, false)
+    }
+}
+
+impl PartialEq for SassMap {
+    fn eq(&self, other: &SassMap) -> bool {
+        self.len() == other.len() &&
+            self.pairs.iter().all(|(k, v)| other.get(k).map_or(false, |ov| ov == v))
+    }
+}
+
+// An arithmetic operator in a calculation.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CalcOperator {
+    Plus,
+    Minus,
+    Times,
+    Divide,
+}
+
+impl CalcOperator {
+    pub fn symbol(&self) -> &'static str {
+        match *self {
+            CalcOperator::Plus => "+",
+            CalcOperator::Minus => "-",
+            CalcOperator::Times => "*",
+            CalcOperator::Divide => "/",
+        }
+    }
+
+    pub fn precedence(&self) -> u8 {
+        match *self {
+            CalcOperator::Plus | CalcOperator::Minus => 1,
+            CalcOperator::Times | CalcOperator::Divide => 2,
+        }
+    }
+}
+
+// An argument of a calculation, or an operand in one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcValue {
+    Number(Number),
+    // unquoted text, such as `var(--x)` or the result of interpolation
+    String(String),
+    Operation(Box<CalcOperation>),
+    Calculation(Calculation),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalcOperation {
+    pub op: CalcOperator,
+    pub left: CalcValue,
+    pub right: CalcValue,
+}
+
+// A call to calc(), clamp(), min() or max() that can't be simplified
+// to a number until the browser knows the sizes involved, such as
+// `calc(100% - 10px)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calculation {
+    pub name: String,
+    pub args: Vec<CalcValue>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Number(Number),
+    Color(Color),
+    String(SassString),
+    List(List),
+    Map(SassMap),
+    Function(Callable),
+    Calculation(Calculation),
+}
+
+impl Value {
+    pub fn unquoted_string<S: Into<String>>(text: S) -> Value {
+        Value::String(SassString::unquoted(text))
+    }
+
+    pub fn quoted_string<S: Into<String>>(text: S) -> Value {
+        Value::String(SassString::quoted(text))
+    }
+
+    pub fn number(value: f64) -> Value {
+        Value::Number(Number::new(value))
+    }
+
+    pub fn is_truthy(&self) -> bool {
+        !matches!(*self, Value::Null | Value::Boolean(false))
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(*self, Value::Null)
+    }
+
+    /// Whether the value produces no output, so that a declaration
+    /// with this value can be left out.
+    pub fn is_blank(&self) -> bool {
+        match *self {
+            Value::Null => true,
+            Value::String(ref s) => !s.quoted && s.text.is_empty(),
+            Value::List(ref l) => !l.bracketed && l.items.iter().all(|i| i.is_blank()),
+            _ => false,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            Value::Null => "null",
+            Value::Boolean(_) => "bool",
+            Value::Number(_) => "number",
+            Value::Color(_) => "color",
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Function(_) => "function",
+            Value::Calculation(_) => "calculation",
+        }
+    }
+
+    /// Every value can be treated as a list. Maps are lists of pairs,
+    /// and other values are single-element lists.
+    pub fn as_list(&self) -> Vec<Value> {
+        match *self {
+            Value::List(ref l) => l.items.clone(),
+            Value::Map(ref m) => m.to_list().items,
+            ref other => vec![other.clone()],
+        }
+    }
+
+    /// The value as it is used in Sass rather than written in CSS, so
+    /// that a number such as `12px/1.5` becomes the result of dividing.
+    pub fn without_slash(self) -> Value {
+        match self {
+            Value::Number(ref n) if n.as_slash.is_some() => Value::Number(n.without_slash()),
+            value => value,
+        }
+    }
+
+    /// The value as a map. An empty list is also an empty map.
+    pub fn try_map(&self) -> Option<SassMap> {
+        match *self {
+            Value::Map(ref m) => Some(m.clone()),
+            Value::List(ref l) if l.items.is_empty() => Some(SassMap::new()),
+            _ => None,
+        }
+    }
+
+    pub fn separator(&self) -> ListSeparator {
+        match *self {
+            Value::List(ref l) => l.separator,
+            Value::Map(ref m) if !m.is_empty() => ListSeparator::Comma,
+            _ => ListSeparator::Undecided,
+        }
+    }
+
+    pub fn is_bracketed(&self) -> bool {
+        match *self {
+            Value::List(ref l) => l.bracketed,
+            _ => false,
+        }
+    }
+
+    pub fn from_color_name(name: &str) -> Option<Value> {
+        color_maps::name_to_color(name)
+            .map(|(r, g, b, a)| Value::Color(Color::rgba(f64::from(r), f64::from(g), f64::from(b), a).with_format(ColorFormat::Literal(name.to_string()))))
+    }
+}
+
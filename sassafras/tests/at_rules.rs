@@ -0,0 +1,43 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+
+#[test]
+fn supports_in_rules_bubbles_to_the_top() {
+    let css = compile(".a {\n  @supports (display: grid) {\n    display: grid;\n  }\n}\n");
+    assert_eq!(css.unwrap(), "@supports (display: grid) {\n  .a {\n    display: grid;\n  }\n}\n");
+}
+
+#[test]
+fn at_root_leaves_the_parent_selectors() {
+    let css = compile(".a {\n  .b {\n    @at-root .c {\n      d: e;\n    }\n  }\n}\n");
+    assert_eq!(css.unwrap(), ".c {\n  d: e;\n}\n");
+}
+
+#[test]
+fn at_root_without_media_leaves_the_query() {
+    let css = compile("@media print {\n  .a {\n    @at-root (without: media) {\n      b: c;\n    }\n  }\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  b: c;\n}\n");
+}
+
+#[test]
+fn keyframe_selectors_are_kept() {
+    let css = compile("@keyframes spin {\n  from {\n    a: b;\n  }\n  50% {\n    a: c;\n  }\n  to {\n    a: d;\n  }\n}\n");
+    assert_eq!(css.unwrap(), "@keyframes spin {\n  from {\n    a: b;\n  }\n  50% {\n    a: c;\n  }\n  to {\n    a: d;\n  }\n}\n");
+}
+
+#[test]
+fn font_face_and_property_keep_their_declarations() {
+    let css = compile("@font-face {\n  font-family: x;\n  src: url(x.woff);\n}\n\
+                       @property --x {\n  syntax: \"<length>\";\n  inherits: false;\n}\n");
+    assert_eq!(css.unwrap(), "@font-face {\n  font-family: x;\n  src: url(x.woff);\n}\n\n\
+                              @property --x {\n  syntax: \"<length>\";\n  inherits: false;\n}\n");
+}
+
+#[test]
+fn unknown_rules_pass_through_with_an_interpolated_prelude() {
+    let css = compile("$n: foo;\n@unknown #{$n} bar {\n  a: b;\n}\n@layer base {\n  .a {\n    b: c;\n  }\n}\n");
+    assert_eq!(css.unwrap(), "@unknown foo bar {\n  a: b;\n}\n\n@layer base {\n  .a {\n    b: c;\n  }\n}\n");
+}
@@ -0,0 +1,49 @@
+extern crate sassafras;
+
+mod common;
+
+use std::ffi::CString;
+use sassafras::functions::*;
+use sassafras::sass_compiler::*;
+use sassafras::sass_values::*;
+use common::*;
+
+/// Returns the callee stack as an unquoted string.
+extern "C" fn trace(_args: *const SassValue, _cb: SassFunctionEntry, compiler: *mut SassCompiler) -> *mut SassValue {
+    let size = sass_compiler_get_callee_stack_size(compiler);
+    let callees: Vec<String> = (0..size).map(|i| {
+        let callee = sass_compiler_get_callee_entry(compiler, i);
+        format!("{}:{:?}@{}", c_str(sass_callee_get_name(callee)), sass_callee_get_type(callee), sass_callee_get_line(callee))
+    }).collect();
+    let text = CString::new(callees.join(",")).unwrap();
+    sass_make_string(text.as_ptr())
+}
+
+/// Reads a variable from the caller's scope and writes one local and
+/// one global variable.
+extern "C" fn lookup(args: *const SassValue, _cb: SassFunctionEntry, compiler: *mut SassCompiler) -> *mut SassValue {
+    let env = sass_callee_get_env(sass_compiler_get_last_callee(compiler));
+    let name = sass_string_get_value(sass_list_get_value(args, 0));
+    let value = sass_env_get_lexical(env, name);
+
+    let unit = CString::new("px").unwrap();
+    sass_env_set_local(env, CString::new("from-c").unwrap().as_ptr(), sass_make_number(3.0, unit.as_ptr()));
+    sass_env_set_global(env, CString::new("touched").unwrap().as_ptr(), sass_make_boolean(true));
+    value
+}
+
+#[test]
+fn c_functions_see_the_callee_stack() {
+    let source = "@function outer() {\n  @return trace();\n}\n\
+                  @mixin m {\n  a: outer();\n}\n\
+                  .x {\n  @include m;\n}\n";
+    let css = compile_with(source, |options| set_functions(options, &[("trace()", trace)]));
+    assert_eq!(css.unwrap(), ".x {\n  a: m:Mixin@8,outer:Function@5,trace:CFunction@2;\n}\n");
+}
+
+#[test]
+fn c_functions_read_and_write_variables_in_the_callers_scope() {
+    let source = ".a {\n  $local: 5px;\n  b: lookup(\"local\");\n  c: $touched;\n  d: $from-c;\n}\n";
+    let css = compile_with(source, |options| set_functions(options, &[("lookup($name)", lookup)]));
+    assert_eq!(css.unwrap(), ".a {\n  b: 5px;\n  c: true;\n  d: 3px;\n}\n");
+}
@@ -0,0 +1,32 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+
+#[test]
+fn calculations_of_compatible_numbers_are_simplified() {
+    let css = compile(".a {\n  e: calc(1px + 2px);\n  g: min(1px, 2px) max(1px, 3em);\n  h: clamp(1px, 2px, 3px);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  e: 3px;\n  g: 1px max(1px, 3em);\n  h: 2px;\n}\n");
+}
+
+#[test]
+fn other_calculations_keep_their_evaluated_sass() {
+    let css = compile("$gutter: 10px;\n.a {\n  d: calc(100% - #{$gutter});\n  f: calc(100% - $gutter);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  d: calc(100% - 10px);\n  f: calc(100% - 10px);\n}\n");
+}
+
+#[test]
+fn infinity_and_nan_are_written_as_css_keywords() {
+    let css = compile(".a {\n  a: calc(1px / 0);\n  b: calc(-1px / 0);\n  c: calc(var(--x) + 1px / 0);\n  d: calc(0px / 0);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  a: calc(infinity * 1px);\n  b: calc(-infinity * 1px);\n  \
+                              c: calc(var(--x) + infinity * 1px);\n  d: calc(NaN * 1px);\n}\n");
+}
+
+#[test]
+fn special_functions_pass_through() {
+    let css = compile(".a {\n  i: var(--y, 1px) env(safe-area-inset-top);\n  j: url(a.png) element(#x);\n  c: expression(1 + 1);\n  \
+                       b: progid:DXImageTransform.Microsoft.gradient(startColorstr=#1, endColorstr=#2);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  i: var(--y, 1px) env(safe-area-inset-top);\n  j: url(a.png) element(#x);\n  c: expression(1 + 1);\n  \
+                              b: progid:DXImageTransform.Microsoft.gradient(startColorstr=#1, endColorstr=#2);\n}\n");
+}
@@ -0,0 +1,40 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+
+#[test]
+fn color_module_adjusts_scales_and_changes() {
+    let css = compile("@use \"sass:color\";\n.a {\n  a: color.scale(#6b717f, $lightness: 30%);\n\
+                       b: color.adjust(#6b717f, $hue: 60deg, $alpha: -0.4);\n  c: color.change(red, $blue: 100);\n\
+                       d: color.mix(#036, #d2e1dd, 75%);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  a: #969ba7;\n  b: rgba(121, 107, 127, 0.6);\n  c: #ff0064;\n  d: #355f84;\n}\n");
+}
+
+#[test]
+fn derived_colors_and_channels() {
+    let css = compile("@use \"sass:color\";\n.a {\n  e: color.complement(#6b717f) color.invert(#b37399) color.grayscale(#6b717f);\n\
+                       f: color.ie-hex-str(rgba(255, 0, 0, 0.5));\n\
+                       g: color.red(#abc) color.hue(hsl(120, 50%, 50%)) color.alpha(rgba(0, 0, 0, 0.3));\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  e: #7f796b #4c8c66 #757575;\n  f: #80FF0000;\n  g: 170 120deg 0.3;\n}\n");
+}
+
+#[test]
+fn constructors_accept_the_space_separated_syntax() {
+    let css = compile(".a {\n  b: rgb(10 20 30 / 50%) hsl(120deg 100% 25%);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  b: rgba(10, 20, 30, 0.5) green;\n}\n");
+}
+
+#[test]
+fn legacy_helpers_match_dart_sass() {
+    let css = compile(".a {\n  b: lighten(#800, 20%) darken(#b37399, 20%) saturate(#9e3f3f, 10%) desaturate(#9e3f3f, 10%);\n\
+                       c: opacify(rgba(0, 0, 0, 0.5), 0.2) transparentize(black, 0.3);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  b: #ee0000 #7c4465 #a93434 #934a4a;\n  c: rgba(0, 0, 0, 0.7) rgba(0, 0, 0, 0.7);\n}\n");
+}
+
+#[test]
+fn microsoft_alpha_filter_passes_through() {
+    let css = compile("$o: 20;\n.a {\n  b: alpha(opacity=50);\n  c: alpha(opacity=$o, style = 2);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  b: alpha(opacity=50);\n  c: alpha(opacity=20, style=2);\n}\n");
+}
@@ -0,0 +1,26 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+use sassafras::sass_options::*;
+use sassafras::sass_output_options::SassOutputStyle;
+
+const SOURCE: &str = "/* loud #{1 + 1} */\n// silent\n.a {\n  /* inner */\n  b: c; // trailing\n}\n/*! preserved */\n";
+
+#[test]
+fn loud_comments_are_kept_and_silent_ones_dropped() {
+    assert_eq!(compile(SOURCE).unwrap(), "/* loud 2 */\n.a {\n  /* inner */\n  b: c;\n}\n\n/*! preserved */\n");
+}
+
+#[test]
+fn compressed_keeps_only_preserved_comments() {
+    let css = compile_with(SOURCE, |options| sass_option_set_output_style(options, SassOutputStyle::Compressed));
+    assert_eq!(css.unwrap(), ".a{b:c}/*! preserved */\n");
+}
+
+#[test]
+fn source_comments_give_the_line_of_each_rule() {
+    let css = compile_with(SOURCE, |options| sass_option_set_source_comments(options, true));
+    assert_eq!(css.unwrap(), "/* loud 2 */\n/* line 3, stdin */\n.a {\n  /* inner */\n  b: c;\n}\n\n/*! preserved */\n");
+}
@@ -0,0 +1,141 @@
+// Helpers shared by the integration tests, which compile through the
+// same C API that sassc and the other libsass clients use.
+#![allow(dead_code)]
+
+use std::env;
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::os::raw::{c_char, c_void};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use sassafras::base::sass_copy_c_string;
+use sassafras::functions::*;
+use sassafras::logger::SassLogLevel;
+use sassafras::sass_context::*;
+use sassafras::sass_data_context::*;
+use sassafras::sass_file_context::*;
+use sassafras::sass_options::*;
+use sassafras::sass_output_options::SassOutputStyle;
+
+/// Compiles a stylesheet in the expanded style, giving the CSS or the
+/// error message.
+pub fn compile(source: &str) -> Result<String, String> {
+    compile_with(source, |_| {})
+}
+
+/// Compiles a stylesheet in the expanded style after `configure` has
+/// set any other options.
+pub fn compile_with<F: FnOnce(*mut SassOptions)>(source: &str, configure: F) -> Result<String, String> {
+    compile_data(source, configure, result_of)
+}
+
+/// Compiles a stylesheet that should fail, giving the error message
+/// and the line and column it points at.
+pub fn compile_error_with<F: FnOnce(*mut SassOptions)>(source: &str, configure: F) -> (String, usize, usize) {
+    compile_data(source, configure, |ctx| {
+        assert!(sass_context_get_error_status(ctx) != 0, "expected an error");
+        (c_str(sass_context_get_error_text(ctx)), sass_context_get_error_line(ctx), sass_context_get_error_column(ctx))
+    })
+}
+
+fn compile_data<F, R, T>(source: &str, configure: F, result: R) -> T
+    where F: FnOnce(*mut SassOptions), R: FnOnce(*mut SassContext) -> T {
+    let source = CString::new(source).unwrap();
+    let data_ctx = sass_make_data_context(sass_copy_c_string(source.as_ptr()));
+    let options = sass_data_context_get_options(data_ctx);
+    sass_option_set_output_style(options, SassOutputStyle::Expanded);
+    configure(options);
+
+    sass_compile_data_context(data_ctx);
+    let result = result(sass_data_context_get_context(data_ctx));
+    sass_delete_data_context(data_ctx);
+    result
+}
+
+/// Compiles a file in the expanded style after `configure` has set any
+/// other options.
+pub fn compile_file<F: FnOnce(*mut SassOptions)>(path: &Path, configure: F) -> Result<String, String> {
+    let path = CString::new(path.to_string_lossy().into_owned()).unwrap();
+    let file_ctx = sass_make_file_context(path.as_ptr());
+    let options = sass_file_context_get_options(file_ctx);
+    sass_option_set_output_style(options, SassOutputStyle::Expanded);
+    configure(options);
+
+    sass_compile_file_context(file_ctx);
+    let result = result_of(sass_file_context_get_context(file_ctx));
+    sass_delete_file_context(file_ctx);
+    result
+}
+
+fn result_of(ctx: *mut SassContext) -> Result<String, String> {
+    if sass_context_get_error_status(ctx) != 0 {
+        Err(c_str(sass_context_get_error_text(ctx)))
+    } else {
+        Ok(c_str(sass_context_get_output_string(ctx)))
+    }
+}
+
+pub fn c_str(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned()
+}
+
+/// Creates an empty directory for a test's files.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join("sassafras-tests").join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes the files of a test, given as (relative path, contents).
+pub fn write_files(dir: &Path, files: &[(&str, &str)]) {
+    for &(name, contents) in files {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(path, contents).unwrap();
+    }
+}
+
+/// Sets a single custom importer on the options.
+pub fn set_importer(options: *mut SassOptions, importer: SassImporterFn) {
+    let list = sass_make_importer_list(1);
+    sass_importer_set_list_entry(list, 0, sass_make_importer(importer, 0.0, ptr::null_mut()));
+    sass_option_set_c_importers(options, list);
+}
+
+/// An import list with one entry holding `source`, as an importer
+/// returns for a virtual file.
+pub fn import_source(url: &str, source: &str) -> SassImportList {
+    let url = CString::new(url).unwrap();
+    let source = CString::new(source).unwrap();
+    let list = sass_make_import_list(1);
+    sass_import_set_list_entry(list, 0, sass_make_import_entry(url.as_ptr(), sass_copy_c_string(source.as_ptr()), ptr::null_mut()));
+    list
+}
+
+/// Sets custom functions on the options, given as (signature, function).
+pub fn set_functions(options: *mut SassOptions, functions: &[(&str, SassFunctionFn)]) {
+    let list = sass_make_function_list(functions.len());
+    for (i, &(signature, function)) in functions.iter().enumerate() {
+        let signature = CString::new(signature).unwrap();
+        sass_function_set_list_entry(list, i, sass_make_function(signature.as_ptr(), function, ptr::null_mut()));
+    }
+    sass_option_set_c_functions(options, list);
+}
+
+/// Collects the messages the compiler logs, each as "<level> <message>
+/// at <line>:<column>" followed by its stack trace.
+pub fn set_logs(options: *mut SassOptions, logs: &mut Vec<String>) {
+    sass_option_set_logger(options, Some(log_message), logs as *mut Vec<String> as *mut c_void);
+}
+
+extern "C" fn log_message(level: SassLogLevel, message: *const c_char, _path: *const c_char, line: usize,
+                          column: usize, stack_trace: *const c_char, cookie: *mut c_void) {
+    let logs = unsafe { &mut *(cookie as *mut Vec<String>) };
+    logs.push(format!("{:?} {} at {}:{}\n{}", level, c_str(message), line, column, c_str(stack_trace)));
+}
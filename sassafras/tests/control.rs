@@ -0,0 +1,57 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+
+#[test]
+fn if_else_if_and_else() {
+    let css = compile("@mixin size($n) {\n  @if $n > 10 {\n    size: big;\n  } @else if $n > 5 {\n    size: medium;\n  } @else {\n    size: small;\n  }\n}\n\
+                       .a {\n  @include size(20);\n}\n.b {\n  @include size(7);\n}\n.c {\n  @include size(1);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  size: big;\n}\n\n.b {\n  size: medium;\n}\n\n.c {\n  size: small;\n}\n");
+}
+
+#[test]
+fn only_false_and_null_are_falsy() {
+    let css = compile(".x {\n  @each $v in 0, \"\", (), false, null {\n    @if $v {\n      v: truthy;\n    } @else {\n      v: falsy;\n    }\n  }\n}\n");
+    assert_eq!(css.unwrap(), ".x {\n  v: truthy;\n  v: truthy;\n  v: truthy;\n  v: falsy;\n  v: falsy;\n}\n");
+}
+
+#[test]
+fn each_destructures_maps_and_lists() {
+    let css = compile("$sizes: (sm: 1px, lg: 2px);\n@each $name, $size in $sizes {\n  .m-#{$name} {\n    margin: $size;\n  }\n}\n\
+                       @each $a, $b in (1 2, 3) {\n  .p {\n    a: $a;\n    b: inspect($b);\n  }\n}\n");
+    assert_eq!(css.unwrap(), ".m-sm {\n  margin: 1px;\n}\n\n.m-lg {\n  margin: 2px;\n}\n\n\
+                              .p {\n  a: 1;\n  b: 2;\n}\n\n.p {\n  a: 3;\n  b: null;\n}\n");
+}
+
+#[test]
+fn for_through_and_to() {
+    let css = compile(".a {\n  @for $i from 1 through 3 {\n    through: $i;\n  }\n  @for $i from 3 to 1 {\n    to: $i;\n  }\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  through: 1;\n  through: 2;\n  through: 3;\n  to: 3;\n  to: 2;\n}\n");
+}
+
+#[test]
+fn while_loops_in_functions() {
+    let css = compile("@function halve($n) {\n  $steps: 0;\n  @while $n > 1 {\n    $n: $n / 2;\n    $steps: $steps + 1;\n  }\n  @return $steps;\n}\n\
+                       .a {\n  b: halve(16);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  b: 4;\n}\n");
+}
+
+#[test]
+fn loop_variables_are_scoped_to_the_loop() {
+    let css = compile("$i: outer;\n@for $i from 1 through 2 {\n}\n.a {\n  i: $i;\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  i: outer;\n}\n");
+}
+
+#[test]
+fn if_function_only_evaluates_the_chosen_branch() {
+    let css = compile(".a {\n  b: if(true, null, 1);\n  c: if(false, $undefined, 2);\n  d: if($condition: null, $if-true: x, $if-false: y);\n  e: if((false, 1, 2)...);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  c: 2;\n  d: y;\n  e: 2;\n}\n");
+}
+
+#[test]
+fn if_function_checks_its_arguments() {
+    assert_eq!(compile(".a {\n  b: if(true, 1);\n}\n").unwrap_err(), "Function if is missing argument $if-false.");
+    assert_eq!(compile(".a {\n  b: if(true, 1, 2, 3);\n}\n").unwrap_err(), "wrong number of arguments (4 for 3) for `if'");
+}
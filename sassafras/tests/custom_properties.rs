@@ -0,0 +1,23 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+
+#[test]
+fn values_are_kept_as_written() {
+    let css = compile(":root {\n  --x: 1px  solid;\n  --e: calc( 1px+2px );\n  --f: \"str\";\n}\n");
+    assert_eq!(css.unwrap(), ":root {\n  --x: 1px  solid;\n  --e: calc( 1px+2px );\n  --f: \"str\";\n}\n");
+}
+
+#[test]
+fn nested_brackets_and_json_survive() {
+    let css = compile(":root {\n  --brand: { \"a\": [1, 2], \"b\": {\"c\": 3} };\n}\n");
+    assert_eq!(css.unwrap(), ":root {\n  --brand: { \"a\": [1, 2], \"b\": {\"c\": 3} };\n}\n");
+}
+
+#[test]
+fn only_interpolation_is_evaluated() {
+    let css = compile("$c: red;\n:root {\n  --c: #{$c};\n  --d: $c;\n}\n");
+    assert_eq!(css.unwrap(), ":root {\n  --c: red;\n  --d: $c;\n}\n");
+}
@@ -0,0 +1,67 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+use sassafras::sass_options::*;
+use std::ffi::CString;
+
+const SLASH_DIV: &str = ".a {\n  b: (4px/2);\n}\n";
+
+#[test]
+fn deprecations_are_logged_with_their_category() {
+    let mut logs = Vec::new();
+    let css = compile_with(SLASH_DIV, |options| set_logs(options, &mut logs));
+    assert_eq!(css.unwrap(), ".a {\n  b: 2px;\n}\n");
+    assert_eq!(logs.len(), 1);
+    assert!(logs[0].starts_with("Deprecation [slash-div] Using / for division outside of calc() is deprecated"), "{}", logs[0]);
+    assert!(logs[0].contains("Recommendation: math.div(4px, 2) or calc(4px / 2) at 2:7\n"), "{}", logs[0]);
+}
+
+#[test]
+fn fatal_deprecations_fail_the_compile() {
+    let id = CString::new("slash-div").unwrap();
+    let err = compile_with(SLASH_DIV, |options| assert!(sass_option_push_fatal_deprecation(options, id.as_ptr()))).unwrap_err();
+    assert!(err.ends_with("This is only an error because you've set the slash-div deprecation to be fatal.\n\
+                           Remove this setting if you need to keep using this feature."), "{}", err);
+}
+
+#[test]
+fn silenced_deprecations_are_not_logged() {
+    let id = CString::new("slash-div").unwrap();
+    let mut logs = Vec::new();
+    let css = compile_with(SLASH_DIV, |options| {
+        assert!(sass_option_push_silence_deprecation(options, id.as_ptr()));
+        set_logs(options, &mut logs);
+    });
+    assert_eq!(css.unwrap(), ".a {\n  b: 2px;\n}\n");
+    assert!(logs.is_empty(), "{:?}", logs);
+}
+
+#[test]
+fn unknown_categories_are_rejected() {
+    let id = CString::new("no-such-thing").unwrap();
+    compile_with(SLASH_DIV, |options| assert!(!sass_option_push_fatal_deprecation(options, id.as_ptr()))).unwrap();
+}
+
+#[test]
+fn quiet_deps_hides_warnings_from_include_paths() {
+    let dir = temp_dir("deprecations-quiet-deps");
+    write_files(&dir, &[
+        ("lib/_dep.scss", "@function half($x) {\n  @return $x/2;\n}\n"),
+        ("main.scss", "@use \"dep\";\n.a {\n  b: dep.half(4px);\n}\n"),
+    ]);
+    let lib = CString::new(dir.join("lib").to_string_lossy().into_owned()).unwrap();
+    let compile_main = |quiet| {
+        let mut logs = Vec::new();
+        let css = compile_file(&dir.join("main.scss"), |options| {
+            sass_option_push_include_path(options, lib.as_ptr());
+            sass_option_set_quiet_deps(options, quiet);
+            set_logs(options, &mut logs);
+        });
+        assert_eq!(css.unwrap(), ".a {\n  b: 2px;\n}\n");
+        logs
+    };
+    assert_eq!(compile_main(false).len(), 1);
+    assert!(compile_main(true).is_empty());
+}
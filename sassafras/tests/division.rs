@@ -0,0 +1,46 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+use sassafras::sass_options::*;
+
+const SOURCE: &str = "$a: 10px;\n$b: 2;\n.a {\n  font: 12px/1.5 serif;\n  b: $a / $b;\n  c: (10px/2);\n  d: #{$a}/#{$b};\n}\n";
+
+#[test]
+fn legacy_division_keeps_plain_css_slashes() {
+    assert_eq!(compile(SOURCE).unwrap(), ".a {\n  font: 12px/1.5 serif;\n  b: 5px;\n  c: 5px;\n  d: 10px/2;\n}\n");
+}
+
+#[test]
+fn slash_division_only_separates() {
+    let css = compile_with(SOURCE, |options| sass_option_set_division(options, SassDivision::Slash));
+    assert_eq!(css.unwrap(), ".a {\n  font: 12px/1.5 serif;\n  b: 10px/2;\n  c: 10px/2;\n  d: 10px/2;\n}\n");
+}
+
+#[test]
+fn slash_division_binds_more_loosely_than_arithmetic() {
+    let source = ".a {\n  b: 4/2 + 1;\n  c: 1 + 4/2;\n  d: 2 * 3/4 - 1;\n  e: 4/2 == 4/2;\n  f: calc(4px/2 + 1px);\n}\n";
+    let css = compile_with(source, |options| sass_option_set_division(options, SassDivision::Slash));
+    assert_eq!(css.unwrap(), ".a {\n  b: 4/3;\n  c: 5/2;\n  d: 6/3;\n  e: true;\n  f: 3px;\n}\n");
+}
+
+#[test]
+fn slash_division_does_no_arithmetic_with_slashes() {
+    let err = compile_with(".a {\n  b: 1 + (4/2);\n}\n", |options| sass_option_set_division(options, SassDivision::Slash));
+    assert_eq!(err.unwrap_err(), "Undefined operation: \"1 + 4/2\".");
+}
+
+#[test]
+fn math_div_and_list_slash_work_in_either_mode() {
+    let source = "@use \"sass:math\";\n@use \"sass:list\";\n.a {\n  b: math.div(10px, 4);\n  c: list.slash(1px, 2px);\n  d: math.div(6px, 2px);\n}\n";
+    let expected = ".a {\n  b: 2.5px;\n  c: 1px/2px;\n  d: 3;\n}\n";
+    assert_eq!(compile(source).unwrap(), expected);
+    assert_eq!(compile_with(source, |options| sass_option_set_division(options, SassDivision::Slash)).unwrap(), expected);
+}
+
+#[test]
+fn dividing_into_complex_units_is_not_css() {
+    let err = compile("@use \"sass:math\";\n.a {\n  b: math.div(1, 2px);\n}\n").unwrap_err();
+    assert_eq!(err, "0.5px^-1 isn't a valid CSS value.");
+}
@@ -0,0 +1,38 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+
+#[test]
+fn placeholders_are_extended_and_left_out() {
+    let css = compile("%button {\n  padding: 1px;\n}\n.save {\n  @extend %button;\n  color: green;\n}\n");
+    assert_eq!(css.unwrap(), ".save {\n  padding: 1px;\n}\n\n.save {\n  color: green;\n}\n");
+}
+
+#[test]
+fn extended_selectors_are_unified() {
+    let css = compile(".a.b {\n  x: y;\n}\n.c > .a {\n  z: w;\n}\nspan.d {\n  @extend .a;\n}\n");
+    assert_eq!(css.unwrap(), ".a.b, span.b.d {\n  x: y;\n}\n\n.c > .a, .c > span.d {\n  z: w;\n}\n");
+}
+
+#[test]
+fn selectors_in_pseudo_classes_are_extended() {
+    let css = compile(":not(.a) {\n  x: y;\n}\n.b {\n  @extend .a;\n}\n");
+    assert_eq!(css.unwrap(), ":not(.a):not(.b) {\n  x: y;\n}\n");
+}
+
+#[test]
+fn missing_targets_are_errors_unless_optional() {
+    let css = compile(".a {\n  @extend .missing !optional;\n  b: c;\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  b: c;\n}\n");
+
+    let error = compile(".a {\n  @extend .missing;\n}\n").unwrap_err();
+    assert_eq!(error, "The target selector was not found.\nUse \"@extend .missing !optional\" to avoid this error.");
+}
+
+#[test]
+fn extending_across_media_is_an_error() {
+    let error = compile(".a {\n  x: y;\n}\n@media print {\n  .b {\n    @extend .a;\n  }\n}\n").unwrap_err();
+    assert_eq!(error, "You may not @extend selectors across media queries.");
+}
@@ -0,0 +1,30 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+
+#[test]
+fn user_functions_are_called_from_expressions() {
+    let css = compile("@function double($n, $by: 2) {\n  @return $n * $by;\n}\n.a {\n  b: double(3px) + double(1px, $by: 3);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  b: 9px;\n}\n");
+}
+
+#[test]
+fn functions_can_be_found_and_called_indirectly() {
+    let css = compile("@function add($a, $b) {\n  @return $a + $b;\n}\n\
+                       .a {\n  exists: function-exists(add);\n  missing: function-exists(nope);\n  call: call(get-function(add), 1, 2);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  exists: true;\n  missing: false;\n  call: 3;\n}\n");
+}
+
+#[test]
+fn functions_must_return() {
+    let error = compile("@function f() {\n  $x: 1;\n}\n.a {\n  b: f();\n}\n").unwrap_err();
+    assert_eq!(error, "Function f finished without @return");
+}
+
+#[test]
+fn functions_can_only_contain_some_statements() {
+    let error = compile("@function f() {\n  color: red;\n  @return 1;\n}\n").unwrap_err();
+    assert_eq!(error, "Functions can only contain variable declarations and control directives.");
+}
@@ -0,0 +1,112 @@
+extern crate sassafras;
+
+mod common;
+
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::ptr;
+use sassafras::functions::*;
+use sassafras::sass_compiler::*;
+use common::*;
+
+thread_local! {
+    // what the probe importer saw on the import stack, one line per call
+    static SEEN: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Records the import stack for urls starting with "probe" and returns
+/// an empty stylesheet for them.
+extern "C" fn probe(url: *const c_char, _cb: SassImporterEntry, compiler: *mut SassCompiler) -> SassImportList {
+    let url = c_str(url);
+    if !url.starts_with("probe") {
+        return ptr::null_mut();
+    }
+
+    let size = sass_compiler_get_import_stack_size(compiler);
+    let files: Vec<String> = (0..size).map(|i| {
+        let entry = sass_compiler_get_import_entry(compiler, i);
+        let abs_path = c_str(sass_import_get_abs_path(entry));
+        abs_path.rsplit('/').next().unwrap().to_string()
+    }).collect();
+    let last = sass_compiler_get_last_import(compiler);
+    let seen = format!("{} from {}: {} [{}]", url, c_str(sass_import_get_imp_path(last)), size, files.join(", "));
+    SEEN.with(|s| s.borrow_mut().push(seen));
+    import_source(&url, ".probe { a: b; }")
+}
+
+#[test]
+fn importers_see_the_file_that_imports_them() {
+    let dir = temp_dir("importers-stack");
+    write_files(&dir, &[
+        ("main.scss", "@import \"probe-1\";\n@import \"sub\";\n"),
+        ("_sub.scss", "@import \"probe-2\";\n"),
+    ]);
+
+    SEEN.with(|s| s.borrow_mut().clear());
+    let css = compile_file(&dir.join("main.scss"), |options| set_importer(options, probe));
+    assert!(css.is_ok(), "{:?}", css);
+
+    let seen = SEEN.with(|s| s.borrow().clone());
+    let main = dir.join("main.scss").to_string_lossy().into_owned();
+    assert_eq!(seen, vec![
+        format!("probe-1 from {}: 1 [main.scss]", main),
+        "probe-2 from sub: 2 [main.scss, _sub.scss]".to_string(),
+    ]);
+}
+
+#[test]
+fn import_entries_expose_the_source_of_the_importing_file() {
+    extern "C" fn source_probe(url: *const c_char, _cb: SassImporterEntry, compiler: *mut SassCompiler) -> SassImportList {
+        let source = c_str(sass_import_get_source(sass_compiler_get_last_import(compiler)));
+        import_source(&c_str(url), &format!(".source {{ length: {}; }}", source.len()))
+    }
+
+    let source = "@import \"virtual\";";
+    let css = compile_with(source, |options| set_importer(options, source_probe));
+    assert_eq!(css.unwrap(), format!(".source {{\n  length: {};\n}}\n", source.len()));
+}
+
+#[test]
+fn css_imports_are_moved_before_other_css() {
+    let css = compile("/* top */\n.p {\n  a: b;\n}\n@import url(bar);\n.q {\n  c: d;\n}\n@import \"baz.css\" screen;\n");
+    assert_eq!(css.unwrap(), "/* top */\n@import url(bar);\n@import \"baz.css\" screen;\n.p {\n  a: b;\n}\n\n.q {\n  c: d;\n}\n");
+}
+
+#[test]
+fn output_with_a_null_character_is_an_error() {
+    let dir = temp_dir("importers-null-output");
+    write_files(&dir, &[("main.scss", "/* a\0b */\n")]);
+    assert_eq!(compile_file(&dir.join("main.scss"), |_| {}).unwrap_err(), "The output contains a null character");
+}
+
+/// Fails every import with an error at line 2, column 6 of the source
+/// it returns.
+extern "C" fn failing_at(url: *const c_char, _cb: SassImporterEntry, _compiler: *mut SassCompiler) -> SassImportList {
+    let list = import_source(&c_str(url), "a {\n  b: c d;\n}");
+    let message = CString::new("malformed virtual file").unwrap();
+    sass_import_set_error(sass_import_get_list_entry(list, 0), message.as_ptr(), 2, 6);
+    list
+}
+
+/// Fails every import without giving a position.
+extern "C" fn failing(url: *const c_char, _cb: SassImporterEntry, _compiler: *mut SassCompiler) -> SassImportList {
+    let url = CString::new(c_str(url)).unwrap();
+    let list = sass_make_import_list(1);
+    let entry = sass_make_import_entry(url.as_ptr(), ptr::null_mut(), ptr::null_mut());
+    let message = CString::new("permission denied").unwrap();
+    sass_import_set_list_entry(list, 0, sass_import_set_error(entry, message.as_ptr(), usize::MAX, usize::MAX));
+    list
+}
+
+#[test]
+fn importer_errors_point_at_the_import() {
+    let error = compile_error_with("a { b: c; }\n  @import \"locked\";\n", |options| set_importer(options, failing));
+    assert_eq!(error, ("permission denied".to_string(), 2, 11));
+}
+
+#[test]
+fn importer_errors_with_a_position_point_into_the_imported_source() {
+    let error = compile_error_with("@import \"broken\";\n", |options| set_importer(options, failing_at));
+    assert_eq!(error, ("malformed virtual file".to_string(), 2, 6));
+}
@@ -0,0 +1,35 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+
+#[test]
+fn parent_selector_can_be_interpolated() {
+    let css = compile(".block {\n  #{&}__element {\n    a: b;\n  }\n  @at-root #{&}__other {\n    c: d;\n  }\n}\n");
+    assert_eq!(css.unwrap(), ".block .block__element {\n  a: b;\n}\n\n.block__other {\n  c: d;\n}\n");
+}
+
+#[test]
+fn suffixes_attach_to_the_parent() {
+    let css = compile(".block {\n  &--mod {\n    c: d;\n  }\n}\n");
+    assert_eq!(css.unwrap(), ".block--mod {\n  c: d;\n}\n");
+}
+
+#[test]
+fn property_names_and_values_are_interpolated() {
+    let css = compile("$p: margin;\n$s: top;\n.a {\n  #{$p}-#{$s}: 1px;\n  width: calc(100% - #{$s});\n  content: \"a #{1 + 2} b\";\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  margin-top: 1px;\n  width: calc(100% - top);\n  content: \"a 3 b\";\n}\n");
+}
+
+#[test]
+fn interpolated_selectors_are_reparsed() {
+    let css = compile("$sel: \".x, .y\";\n#{$sel} {\n  a: b;\n}\n");
+    assert_eq!(css.unwrap(), ".x, .y {\n  a: b;\n}\n");
+}
+
+#[test]
+fn null_and_quoted_strings_interpolate_bare() {
+    let css = compile(".a {\n  b: #{null}c;\n  d: \"#{\"quoted\"}\";\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  b: c;\n  d: \"quoted\";\n}\n");
+}
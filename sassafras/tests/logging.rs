@@ -0,0 +1,57 @@
+extern crate sassafras;
+
+mod common;
+
+use std::env;
+use std::process::Command;
+use common::*;
+
+#[test]
+fn debug_and_warn_go_to_the_logger() {
+    let mut logs = Vec::new();
+    let css = compile_with("@function f() {\n  @warn \"careful\";\n  @return 1;\n}\n.a {\n  b: f();\n}\n@debug 1 + 1;\n",
+                           |options| set_logs(options, &mut logs));
+    assert_eq!(css.unwrap(), ".a {\n  b: 1;\n}\n");
+    assert_eq!(logs, vec![
+        "Warning careful at 2:3\non line 2:3 of stdin, in function `f`\nfrom line 6:6 of stdin\n".to_string(),
+        "Debug 2 at 8:1\n".to_string(),
+    ]);
+}
+
+#[test]
+fn error_is_logged_and_fails_the_compile() {
+    let mut logs = Vec::new();
+    let (message, line, column) = compile_error_with("@mixin m {\n  @error \"bad #{1 + 1}\";\n}\n.a {\n  @include m;\n}\n",
+                                                     |options| set_logs(options, &mut logs));
+    assert_eq!((message.as_str(), line, column), ("bad 2", 2, 3));
+    assert_eq!(logs, vec!["Error bad 2 at 2:3\non line 2:3 of stdin, in mixin `m`\nfrom line 5:3 of stdin\n".to_string()]);
+}
+
+#[test]
+fn null_characters_in_messages_are_escaped() {
+    let dir = temp_dir("logging-null");
+    write_files(&dir, &[("main.scss", "@warn \"a\0b\";\n")]);
+    let mut logs = Vec::new();
+    compile_file(&dir.join("main.scss"), |options| set_logs(options, &mut logs)).unwrap();
+    assert_eq!(logs.len(), 1);
+    assert!(logs[0].starts_with("Warning a\\0b at 1:1\n"), "{}", logs[0]);
+}
+
+#[test]
+fn messages_reach_stderr_without_a_logger() {
+    // The compile runs in a copy of this test in a child process, so
+    // that what it prints to stderr can be read here.
+    if env::var_os("SASSAFRAS_LOGGING_CHILD").is_some() {
+        let css = compile(".a {\n  @warn \"to stderr\";\n  b: c;\n}\n");
+        assert_eq!(css.unwrap(), ".a {\n  b: c;\n}\n");
+        return;
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["messages_reach_stderr_without_a_logger", "--exact", "--nocapture"])
+        .env("SASSAFRAS_LOGGING_CHILD", "1")
+        .output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("WARNING: to stderr\n         on line 2:3 of stdin\n"), "{}", stderr);
+}
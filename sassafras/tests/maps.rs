@@ -0,0 +1,40 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+
+const TOKENS: &str = "@use \"sass:map\";\n@use \"sass:meta\";\n$m: (a: 1, b: (c: 2));\n";
+
+#[test]
+fn nested_maps_are_read_with_key_paths() {
+    let css = compile(&format!("{}.a {{\n  a: map.get($m, b, c) map.get($m, z);\n  b: map.has-key($m, b, c) map.has-key($m, b, z);\n}}\n", TOKENS));
+    assert_eq!(css.unwrap(), ".a {\n  a: 2;\n  b: true false;\n}\n");
+}
+
+#[test]
+fn nested_maps_are_updated_with_key_paths() {
+    let css = compile(&format!("{}.a {{\n  c: meta.inspect(map.set($m, b, d, 3));\n  d: meta.inspect(map.merge($m, b, (e: 4)));\n\
+                                e: meta.inspect(map.deep-merge($m, (b: (e: 4))));\n  f: meta.inspect(map.deep-remove($m, b, c));\n}}\n", TOKENS));
+    assert_eq!(css.unwrap(), ".a {\n  c: (a: 1, b: (c: 2, d: 3));\n  d: (a: 1, b: (c: 2, e: 4));\n  \
+                              e: (a: 1, b: (c: 2, e: 4));\n  f: (a: 1, b: ());\n}\n");
+}
+
+#[test]
+fn maps_keep_their_order() {
+    let css = compile(&format!("{}.a {{\n  d: meta.inspect(map.merge($m, (a: 9)));\n  e: meta.inspect(map.remove($m, a));\n\
+                                f: map.keys($m);\n  g: map.values((x: 1, y: 2));\n}}\n", TOKENS));
+    assert_eq!(css.unwrap(), ".a {\n  d: (a: 9, b: (c: 2));\n  e: (b: (c: 2));\n  f: a, b;\n  g: 1, 2;\n}\n");
+}
+
+#[test]
+fn legacy_globals_are_available() {
+    let css = compile("$m: (a: 1, b: 2);\n.a {\n  b: map-get($m, b) map-has-key($m, c);\n  c: map-keys(map-merge($m, (c: 3)));\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  b: 2 false;\n  c: a, b, c;\n}\n");
+}
+
+#[test]
+fn maps_are_not_css() {
+    assert_eq!(compile("@use \"sass:map\";\n.a {\n  b: map.get(1, a);\n}\n").unwrap_err(), "$map: 1 is not a map.");
+    assert_eq!(compile(".a {\n  b: (a: 1);\n}\n").unwrap_err(), "(a: 1) isn't a valid CSS value.");
+}
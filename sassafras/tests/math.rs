@@ -0,0 +1,51 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+use sassafras::sass_options::*;
+
+#[test]
+fn math_functions_keep_units() {
+    let css = compile("@use \"sass:math\";\n.a {\n  a: math.div(10px, 4);\n  b: math.percentage(0.5);\n\
+                       c: math.round(2.5) math.ceil(1.2) math.floor(-1.2) math.abs(-3px);\n  d: math.max(1px, 2px, 3px);\n\
+                       e: math.clamp(0, 5, 3);\n  f: math.sqrt(16) math.pow(2, 10) math.hypot(3, 4);\n\
+                       g: math.sin(90deg) math.cos(0);\n  h: math.compatible(1in, 1cm) math.is-unitless(1px);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  a: 2.5px;\n  b: 50%;\n  c: 3 2 -2 3px;\n  d: 3px;\n  e: 3;\n  f: 4 1024 5;\n  g: 1 1;\n  h: true false;\n}\n");
+}
+
+#[test]
+fn numbers_are_written_with_the_precision() {
+    let css = compile("@use \"sass:math\";\n.a {\n  b: math.div(1, 3);\n  c: 1in + 1cm;\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  b: 0.33333;\n  c: 1.3937in;\n}\n");
+
+    let css = compile_with("@use \"sass:math\";\n.a {\n  b: math.div(1, 3);\n}\n", |options| sass_option_set_precision(options, 2));
+    assert_eq!(css.unwrap(), ".a {\n  b: 0.33;\n}\n");
+}
+
+#[test]
+fn units_are_checked() {
+    assert_eq!(compile(".a {\n  b: 1px + 1s;\n}\n").unwrap_err(), "Incompatible units: 's' and 'px'.");
+    assert_eq!(compile("@use \"sass:math\";\n.a {\n  b: math.sqrt(1px);\n}\n").unwrap_err(), "$number: Expected 1px to have no units.");
+}
+
+#[test]
+fn the_largest_number_is_written_in_full() {
+    let css = compile("@use \"sass:math\";\n.a {\n  b: math.$max-number;\n}\n").unwrap();
+    assert!(css.starts_with(".a {\n  b: 17976931348623157"), "{}", css);
+    assert!(css.ends_with("0000;\n}\n") && !css.contains("inf"), "{}", css);
+}
+
+#[test]
+fn infinity_and_nan_are_written_as_calculations() {
+    let css = compile("@use \"sass:math\";\n.a {\n  b: math.log(0);\n  c: math.div(0, 0);\n  d: math.div(1%, 0);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  b: calc(-infinity);\n  c: calc(NaN);\n  d: calc(infinity * 1%);\n}\n");
+}
+
+#[test]
+fn random_is_reproducible_with_a_seed() {
+    let source = "@use \"sass:math\";\n.a {\n  b: math.random();\n  c: math.random(100);\n}\n";
+    let first = compile_with(source, |options| sass_option_set_random_seed(options, 7)).unwrap();
+    let second = compile_with(source, |options| sass_option_set_random_seed(options, 7)).unwrap();
+    assert_eq!(first, second);
+}
@@ -0,0 +1,31 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+
+#[test]
+fn media_in_rules_bubbles_to_the_top() {
+    let css = compile(".a {\n  x: y;\n  @media print {\n    z: w;\n  }\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  x: y;\n}\n\n@media print {\n  .a {\n    z: w;\n  }\n}\n");
+}
+
+#[test]
+fn nested_media_queries_are_merged() {
+    let css = compile("@media screen {\n  .a {\n    @media (min-width: 10px) {\n      @media (max-width: 20px) {\n        b: c;\n      }\n    }\n  }\n}\n");
+    assert_eq!(css.unwrap(), "@media screen and (min-width: 10px) and (max-width: 20px) {\n  .a {\n    b: c;\n  }\n}\n");
+}
+
+#[test]
+fn queries_that_cannot_merge_are_dropped() {
+    let css = compile("@media print {\n  @media screen {\n    .a {\n      b: c;\n    }\n  }\n}\n");
+    assert_eq!(css.unwrap(), "");
+}
+
+#[test]
+fn queries_allow_expressions_and_ranges() {
+    let css = compile("$bp: 600px;\n@media (min-width: $bp + 1) and (#{\"max\"}-width: 900px) {\n  .a {\n    b: c;\n  }\n}\n\
+                       @media (400px <= width <= 700px) {\n  .b {\n    c: d;\n  }\n}\n");
+    assert_eq!(css.unwrap(), "@media (min-width: 601px) and (max-width: 900px) {\n  .a {\n    b: c;\n  }\n}\n\n\
+                              @media (400px <= width <= 700px) {\n  .b {\n    c: d;\n  }\n}\n");
+}
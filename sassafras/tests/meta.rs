@@ -0,0 +1,69 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+use std::path::PathBuf;
+
+const BASE: &str = "$c: 0 !default;\n.base {\n  c: $c;\n}\n@media print {\n  .m {\n    d: e;\n  }\n}\n%ph {\n  p: q;\n}\n";
+
+/// Writes the module `_base.scss` and `main.scss` into a fresh
+/// directory, giving the path of the main file.
+fn with_base(test: &str, main: &str) -> PathBuf {
+    let dir = temp_dir(test);
+    write_files(&dir, &[("_base.scss", BASE), ("main.scss", main)]);
+    dir.join("main.scss")
+}
+
+#[test]
+fn introspection_functions() {
+    let css = compile("@use \"sass:meta\";\n$g: 1;\n@function f($a) {\n  @return $a * 2;\n}\n@mixin m {\n  x: y;\n}\n\
+                       .a {\n  a: meta.type-of(1px) meta.type-of(\"s\") meta.type-of((a: 1)) meta.type-of(null);\n  \
+                       b: meta.inspect((1, 2)) meta.inspect(null);\n  \
+                       c: meta.variable-exists(g) meta.global-variable-exists(nope) meta.function-exists(f) meta.mixin-exists(m);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  a: number string map null;\n  b: 1, 2 null;\n  c: true false true true;\n}\n");
+}
+
+#[test]
+fn functions_can_be_called_by_reference() {
+    let css = compile("@use \"sass:meta\";\n@function f($a) {\n  @return $a * 2;\n}\n.a {\n  d: meta.call(meta.get-function(f), 3);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  d: 6;\n}\n");
+}
+
+#[test]
+fn mixins_see_their_content_and_keywords() {
+    let css = compile("@use \"sass:meta\";\n@mixin m {\n  @if meta.content-exists() {\n    @content;\n  } @else {\n    none: yes;\n  }\n}\n\
+                       @mixin kw($args...) {\n  k: meta.inspect(meta.keywords($args));\n}\n\
+                       .a {\n  @include m;\n  @include m {\n    some: content;\n  }\n  @include kw($x: 1, $y: 2);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  none: yes;\n  some: content;\n  k: (x: 1, y: 2);\n}\n");
+}
+
+#[test]
+fn module_variables_are_listed() {
+    let main = with_base("meta-module-variables", "@use \"sass:meta\";\n@use \"base\";\n.a {\n  v: meta.inspect(meta.module-variables(base));\n}\n");
+    let css = compile_file(&main, |_| {}).unwrap();
+    assert!(css.ends_with(".a {\n  v: (\"c\": 0);\n}\n"), "{}", css);
+}
+
+#[test]
+fn load_css_nests_the_module_css() {
+    let main = with_base("meta-load-css", "@use \"sass:meta\";\n.x {\n  @include meta.load-css(\"base\", $with: (c: 5));\n}\n");
+    assert_eq!(compile_file(&main, |_| {}).unwrap(),
+               ".x .base {\n  c: 5;\n}\n\n@media print {\n  .x .m {\n    d: e;\n  }\n}\n");
+}
+
+#[test]
+fn load_css_reuses_a_loaded_module() {
+    let main = with_base("meta-load-css-again", "@use \"sass:meta\";\n@include meta.load-css(\"base\", $with: (c: 5));\n\
+                                                 .x {\n  @include meta.load-css(\"base\");\n}\n.y {\n  @extend %ph;\n}\n");
+    assert_eq!(compile_file(&main, |_| {}).unwrap(),
+               ".base {\n  c: 5;\n}\n\n@media print {\n  .m {\n    d: e;\n  }\n}\n\n.y {\n  p: q;\n}\n\n\
+                .x .base {\n  c: 5;\n}\n\n@media print {\n  .x .m {\n    d: e;\n  }\n}\n\n.x .y {\n  p: q;\n}\n");
+}
+
+#[test]
+fn load_css_cannot_configure_a_loaded_module() {
+    let main = with_base("meta-load-css-configured", "@use \"sass:meta\";\n@use \"base\" with ($c: 2);\n\
+                                                      @include meta.load-css(\"base\", $with: (c: 6));\n");
+    assert_eq!(compile_file(&main, |_| {}).unwrap_err(), "This module was already loaded, so it can't be configured using \"with\".");
+}
@@ -0,0 +1,45 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+
+#[test]
+fn positional_keyword_and_default_arguments() {
+    let css = compile("@mixin box($width, $height: $width, $color: red) {\n  width: $width;\n  height: $height;\n  color: $color;\n}\n\
+                       .a {\n  @include box(10px, $color: blue);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  width: 10px;\n  height: 10px;\n  color: blue;\n}\n");
+}
+
+#[test]
+fn rest_arguments_and_splats() {
+    let css = compile("@mixin shadows($first, $rest...) {\n  first: $first;\n  rest: $rest;\n  keywords: inspect(keywords($rest));\n}\n\
+                       $list: 2px 3px;\n\
+                       .a {\n  @include shadows(1px, $list..., (x: 4px)...);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  first: 1px;\n  rest: 2px 3px;\n  keywords: (x: 4px);\n}\n");
+}
+
+#[test]
+fn map_splats_pass_keyword_arguments() {
+    let css = compile("@mixin pair($a, $b) {\n  a: $a;\n  b: $b;\n}\n.x {\n  @include pair((b: 2, a: 1)...);\n}\n");
+    assert_eq!(css.unwrap(), ".x {\n  a: 1;\n  b: 2;\n}\n");
+}
+
+#[test]
+fn content_blocks_with_arguments() {
+    let css = compile("@mixin hover {\n  &:hover {\n    @content(red);\n  }\n}\n\
+                       .a {\n  @include hover using ($color) {\n    color: $color;\n  }\n}\n");
+    assert_eq!(css.unwrap(), ".a:hover {\n  color: red;\n}\n");
+}
+
+#[test]
+fn missing_arguments_are_errors() {
+    let error = compile("@mixin m($a) {\n  a: $a;\n}\n.x {\n  @include m;\n}\n").unwrap_err();
+    assert_eq!(error, "Mixin m is missing argument $a.");
+}
+
+#[test]
+fn unknown_keyword_arguments_are_errors() {
+    let error = compile("@mixin m($a) {\n  a: $a;\n}\n.x {\n  @include m(1, $b: 2);\n}\n").unwrap_err();
+    assert_eq!(error, "Mixin m has no parameter named $b");
+}
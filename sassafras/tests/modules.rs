@@ -0,0 +1,59 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+use std::path::PathBuf;
+
+const LIB: &str = "$color: red !default;\n$-secret: 1;\n@mixin m {\n  c: $color;\n}\n\
+                   @function double($x) {\n  @return $x * 2;\n}\n.lib {\n  loaded: yes;\n}\n";
+
+/// Writes the shared library module and `main.scss` into a fresh
+/// directory, giving the path of the main file.
+fn with_lib(test: &str, main: &str) -> PathBuf {
+    let dir = temp_dir(test);
+    write_files(&dir, &[
+        ("_lib.scss", LIB),
+        ("_fwd.scss", "@forward \"lib\" as lib-* hide lib-double;\n"),
+        ("main.scss", main),
+    ]);
+    dir.join("main.scss")
+}
+
+#[test]
+fn use_configures_and_loads_a_module_once() {
+    let main = with_lib("modules-once", "@use \"lib\" with ($color: blue);\n@use \"lib\" as l2;\n\
+                                         .a {\n  @include lib.m;\n  d: lib.double(2px);\n  e: l2.$color;\n}\n");
+    assert_eq!(compile_file(&main, |_| {}).unwrap(),
+               ".lib {\n  loaded: yes;\n}\n\n.a {\n  c: blue;\n  d: 4px;\n  e: blue;\n}\n");
+}
+
+#[test]
+fn use_as_star_drops_the_namespace() {
+    let main = with_lib("modules-star", "@use \"lib\" as *;\n.b {\n  d: double(3);\n}\n");
+    assert_eq!(compile_file(&main, |_| {}).unwrap(), ".lib {\n  loaded: yes;\n}\n\n.b {\n  d: 6;\n}\n");
+}
+
+#[test]
+fn forward_adds_a_prefix() {
+    let main = with_lib("modules-forward", "@use \"fwd\";\n.c {\n  x: fwd.$lib-color;\n}\n");
+    assert_eq!(compile_file(&main, |_| {}).unwrap(), ".lib {\n  loaded: yes;\n}\n\n.c {\n  x: red;\n}\n");
+}
+
+#[test]
+fn forward_hides_members() {
+    let main = with_lib("modules-hide", "@use \"fwd\";\n.c {\n  x: fwd.lib-double(1);\n}\n");
+    assert_eq!(compile_file(&main, |_| {}).unwrap_err(), "Undefined function.");
+}
+
+#[test]
+fn private_members_are_not_visible() {
+    let main = with_lib("modules-private", "@use \"lib\";\n.c {\n  x: lib.$-secret;\n}\n");
+    assert_eq!(compile_file(&main, |_| {}).unwrap_err(), "Private members can't be accessed from outside their modules.");
+}
+
+#[test]
+fn a_loaded_module_cannot_be_configured() {
+    let main = with_lib("modules-configured", "@use \"lib\";\n@use \"lib\" with ($color: blue);\n");
+    assert_eq!(compile_file(&main, |_| {}).unwrap_err(), "This module was already loaded, so it can't be configured using \"with\".");
+}
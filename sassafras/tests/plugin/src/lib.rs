@@ -0,0 +1,68 @@
+// A native plugin for tests/plugins.rs. It links sassafras statically,
+// as a libsass plugin may link libsass, and provides one function, one
+// importer and one header through the libsass plugin entry points.
+
+extern crate sassafras;
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::ptr;
+use sassafras::base::{libsass_version, sass_copy_c_string};
+use sassafras::functions::*;
+use sassafras::sass_compiler::SassCompiler;
+use sassafras::sass_values::*;
+
+#[no_mangle]
+pub extern "C" fn libsass_get_version() -> *const c_char {
+    libsass_version()
+}
+
+#[no_mangle]
+pub extern "C" fn libsass_load_functions() -> SassFunctionList {
+    let list = sass_make_function_list(1);
+    let signature = CString::new("plugin-answer()").unwrap();
+    sass_function_set_list_entry(list, 0, sass_make_function(signature.as_ptr(), plugin_answer, ptr::null_mut()));
+    list
+}
+
+#[no_mangle]
+pub extern "C" fn libsass_load_importers() -> SassImporterList {
+    importer_list(import_plugin_url)
+}
+
+#[no_mangle]
+pub extern "C" fn libsass_load_headers() -> SassImporterList {
+    importer_list(import_header)
+}
+
+extern "C" fn plugin_answer(_args: *const SassValue, _cb: SassFunctionEntry, _compiler: *mut SassCompiler) -> *mut SassValue {
+    let unit = CString::new("").unwrap();
+    sass_make_number(42.0, unit.as_ptr())
+}
+
+// Imports "plugin:<name>" as a rule with the class <name>.
+extern "C" fn import_plugin_url(url: *const c_char, _cb: SassImporterEntry, _compiler: *mut SassCompiler) -> SassImportList {
+    let url = unsafe { ::std::ffi::CStr::from_ptr(url) }.to_string_lossy().into_owned();
+    if !url.starts_with("plugin:") {
+        return ptr::null_mut();
+    }
+    import_list(&url, &format!(".{} {{\n  from: plugin;\n}}\n", &url["plugin:".len()..]))
+}
+
+extern "C" fn import_header(_url: *const c_char, _cb: SassImporterEntry, _compiler: *mut SassCompiler) -> SassImportList {
+    import_list("plugin-header", "$plugin-header: yes;\n")
+}
+
+fn importer_list(importer: SassImporterFn) -> SassImporterList {
+    let list = sass_make_importer_list(1);
+    sass_importer_set_list_entry(list, 0, sass_make_importer(importer, 0.0, ptr::null_mut()));
+    list
+}
+
+fn import_list(path: &str, source: &str) -> SassImportList {
+    let path = CString::new(path).unwrap();
+    let source = CString::new(source).unwrap();
+    let list = sass_make_import_list(1);
+    sass_import_set_list_entry(list, 0, sass_make_import_entry(path.as_ptr(), sass_copy_c_string(source.as_ptr()), ptr::null_mut()));
+    list
+}
@@ -0,0 +1,96 @@
+extern crate sassafras;
+
+mod common;
+
+use std::env;
+use std::ffi::CString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::ptr;
+use sassafras::base::libsass_version;
+use sassafras::functions::*;
+use sassafras::sass_compiler::SassCompiler;
+use sassafras::sass_options::*;
+use sassafras::sass_values::*;
+use common::*;
+
+extern "C" fn one(_args: *const SassValue, _cb: SassFunctionEntry, _compiler: *mut SassCompiler) -> *mut SassValue {
+    sass_make_number(1.0, CString::new("").unwrap().as_ptr())
+}
+
+extern "C" fn two(_args: *const SassValue, _cb: SassFunctionEntry, _compiler: *mut SassCompiler) -> *mut SassValue {
+    sass_make_number(2.0, CString::new("").unwrap().as_ptr())
+}
+
+fn function_list(function: SassFunctionFn) -> SassFunctionList {
+    let list = sass_make_function_list(1);
+    let signature = CString::new("value()").unwrap();
+    sass_function_set_list_entry(list, 0, sass_make_function(signature.as_ptr(), function, ptr::null_mut()));
+    list
+}
+
+/// Builds the plugin in tests/plugin and copies it into a fresh
+/// directory, giving the directory.
+fn plugin_dir(test: &str) -> PathBuf {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/plugin/Cargo.toml");
+    let target = Path::new(env!("CARGO_TARGET_TMPDIR")).join("plugin");
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let output = Command::new(cargo)
+        .args(["build", "--quiet", "--manifest-path"]).arg(&manifest)
+        .arg("--target-dir").arg(&target)
+        .output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let name = format!("{}sassafras_test_plugin.{}", env::consts::DLL_PREFIX, env::consts::DLL_EXTENSION);
+    let dir = temp_dir(test);
+    fs::copy(target.join("debug").join(&name), dir.join(&name)).unwrap();
+    dir
+}
+
+#[test]
+fn reports_a_libsass_3_6_version_to_plugins() {
+    assert!(c_str(libsass_version()).starts_with("3.6."));
+}
+
+#[test]
+fn files_that_are_not_plugins_are_skipped() {
+    let dir = temp_dir("plugins-not-a-library");
+    write_files(&dir, &[("broken.so", "not a shared object"), ("readme.txt", "ignored")]);
+    let path = CString::new(dir.to_string_lossy().into_owned()).unwrap();
+
+    let mut logs = Vec::new();
+    let css = compile_with("a { b: c; }", |options| {
+        sass_option_push_plugin_path(options, path.as_ptr());
+        set_logs(options, &mut logs);
+    });
+    assert_eq!(css.unwrap(), "a {\n  b: c;\n}\n");
+    assert_eq!(logs.len(), 1);
+    assert!(logs[0].starts_with(&format!("Warning failed loading plugin <{}>: ", dir.join("broken.so").display())), "{}", logs[0]);
+}
+
+#[test]
+fn a_plugin_registers_its_functions_importers_and_headers() {
+    let dir = plugin_dir("plugins-fixture");
+    let path = CString::new(dir.to_string_lossy().into_owned()).unwrap();
+
+    let css = compile_with("@import \"plugin:imported\";\na {\n  b: plugin-answer();\n  c: $plugin-header;\n}\n",
+                           |options| sass_option_push_plugin_path(options, path.as_ptr()));
+    assert_eq!(css.unwrap(), ".imported {\n  from: plugin;\n}\n\na {\n  b: 42;\n  c: yes;\n}\n");
+}
+
+#[test]
+fn a_missing_plugin_path_is_ignored() {
+    let path = CString::new("/no/such/plugin/dir").unwrap();
+    let css = compile_with("a { b: c; }", |options| sass_option_set_plugin_path(options, path.as_ptr()));
+    assert_eq!(css.unwrap(), "a {\n  b: c;\n}\n");
+}
+
+#[test]
+fn setting_the_functions_again_replaces_them() {
+    let css = compile_with("a { b: value(); }", |options| {
+        sass_option_set_c_functions(options, function_list(one));
+        sass_option_set_c_functions(options, function_list(two));
+    });
+    assert_eq!(css.unwrap(), "a {\n  b: 2;\n}\n");
+}
@@ -0,0 +1,39 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+use sassafras::sass_options::*;
+use sassafras::sass_output_options::SassOutputStyle;
+
+#[test]
+fn nested_properties_are_prefixed() {
+    let css = compile(".a {\n  font: {\n    family: x;\n    size: 2px;\n  }\n  margin: 0 {\n    left: 1px;\n  }\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  font-family: x;\n  font-size: 2px;\n  margin: 0;\n  margin-left: 1px;\n}\n");
+}
+
+#[test]
+fn important_is_spaced_for_the_style() {
+    let source = ".a {\n  b: c !important;\n  d: e!important;\n}\n";
+    assert_eq!(compile(source).unwrap(), ".a {\n  b: c !important;\n  d: e !important;\n}\n");
+    let css = compile_with(source, |options| sass_option_set_output_style(options, SassOutputStyle::Compressed));
+    assert_eq!(css.unwrap(), ".a{b:c!important;d:e!important}\n");
+}
+
+#[test]
+fn null_and_empty_values_are_left_out() {
+    let css = compile(".a {\n  b: c;\n  d: null;\n  e: \"\";\n  f: unquote(\"\");\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  b: c;\n  e: \"\";\n}\n");
+}
+
+#[test]
+fn default_and_global_flags() {
+    let css = compile("$x: 1 !default;\n$x: 2 !default;\n.a {\n  $y: 3 !global;\n  b: $x;\n}\n.b {\n  c: $y;\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  b: 1;\n}\n\n.b {\n  c: 3;\n}\n");
+}
+
+#[test]
+fn misused_flags_are_errors() {
+    assert_eq!(compile("$z: 1 !foo;\n").unwrap_err(), "Invalid flag name.");
+    assert_eq!(compile(".a {\n  b: 1 !default;\n}\n").unwrap_err(), "expected \";\".");
+}
@@ -0,0 +1,39 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+
+#[test]
+fn selectors_are_nested_appended_and_unified() {
+    let css = compile("@use \"sass:selector\";\n.p {\n  a: selector.nest(\".a\", \"&:hover\", \".b\");\n  b: selector.append(\".a\", \"__b\", \".c\");\n  \
+                       c: selector.unify(\".a.b\", \"span.c\");\n  d: selector.is-superselector(\".a\", \".a.b\") selector.is-superselector(\".a.b\", \".a\");\n}\n");
+    assert_eq!(css.unwrap(), ".p {\n  a: .a:hover .b;\n  b: .a__b.c;\n  c: span.a.b.c;\n  d: true false;\n}\n");
+}
+
+#[test]
+fn selectors_are_parsed_into_values() {
+    let css = compile("@use \"sass:selector\";\n@use \"sass:meta\";\n.p {\n  e: selector.simple-selectors(\"a.b:hover[x=y]\");\n  \
+                       f: meta.inspect(selector.parse(\".a .b, .c\"));\n  g: &;\n}\n");
+    assert_eq!(css.unwrap(), ".p {\n  e: a, .b, :hover, [x=y];\n  f: .a .b, .c;\n  g: .p;\n}\n");
+}
+
+#[test]
+fn extend_needs_every_target_of_a_compound() {
+    let css = compile("@use \"sass:selector\";\n.p {\n  a: selector.extend(\".a.b\", \".a.b\", \".c\");\n  b: selector.extend(\".a\", \".a.b\", \".c\");\n}\n");
+    assert_eq!(css.unwrap(), ".p {\n  a: .a.b, .c;\n  b: .a;\n}\n");
+}
+
+#[test]
+fn extend_reaches_into_pseudo_selector_arguments() {
+    let css = compile("@use \"sass:selector\";\n.p {\n  a: selector.extend(\".a:is(.b)\", \".b\", \".c\");\n  \
+                       b: selector.extend(\".a:not(.b)\", \".b\", \".c\");\n}\n");
+    assert_eq!(css.unwrap(), ".p {\n  a: .a:is(.b, .c);\n  b: .a:not(.b):not(.c);\n}\n");
+}
+
+#[test]
+fn replace_reaches_into_pseudo_selector_arguments() {
+    let css = compile("@use \"sass:selector\";\n.p {\n  a: selector.replace(\".a:not(.b)\", \".b\", \".c\");\n  \
+                       b: selector.replace(\":is(.x, .b) .y\", \".b\", \".c\");\n}\n");
+    assert_eq!(css.unwrap(), ".p {\n  a: .a:not(.c);\n  b: :is(.x, .c) .y;\n}\n");
+}
@@ -0,0 +1,34 @@
+extern crate sassafras;
+
+mod common;
+
+use common::*;
+
+#[test]
+fn string_functions_are_unicode_aware() {
+    let css = compile("@use \"sass:string\";\n.a {\n  a: string.quote(abc) string.unquote(\"a b\");\n\
+                       b: string.index(\"héllo\", \"l\") string.insert(\"abcd\", \"X\", -2) string.length(\"日本語\");\n\
+                       c: string.slice(\"héllo\", 2, -2) string.to-upper-case(\"abc\") string.to-lower-case(ABC);\n\
+                       d: string.slice(\"abc\", 5);\n}\n");
+    assert_eq!(css.unwrap(), "@charset \"UTF-8\";\n.a {\n  a: \"abc\" a b;\n  b: 3 \"abcXd\" 3;\n  c: \"éll\" \"ABC\" abc;\n  d: \"\";\n}\n");
+}
+
+#[test]
+fn unique_ids_differ() {
+    let css = compile("@use \"sass:string\";\n.a {\n  b: string.unique-id() == string.unique-id();\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  b: false;\n}\n");
+}
+
+#[test]
+fn list_functions_keep_separators_and_brackets() {
+    let css = compile("@use \"sass:list\";\n.a {\n  d: list.length(1 2 3) list.nth(a b c, -1) list.set-nth(a b c, 2, x);\n\
+                       e: list.join(a b, c d, comma);\n  f: list.append([a b], c);\n  g: list.zip(1 2, a b);\n\
+                       h: list.index(a b c, c) list.separator((a, b)) list.is-bracketed([a]);\n  i: list.slash(1, 2, 3);\n}\n");
+    assert_eq!(css.unwrap(), ".a {\n  d: 3 c a x c;\n  e: a, b, c, d;\n  f: [a b c];\n  g: 1 a, 2 b;\n  h: 3 comma true;\n  i: 1/2/3;\n}\n");
+}
+
+#[test]
+fn list_indices_are_checked() {
+    let err = compile("@use \"sass:list\";\n.a {\n  b: list.nth(a b, 3);\n}\n").unwrap_err();
+    assert_eq!(err, "$n: Invalid index 3 for a list with 2 elements.");
+}
@@ -8,6 +8,7 @@ extern crate sassafras;
 extern crate structopt;
 
 use sassafras::c_api_helpers::path_to_cstring;
+use sassafras::deprecation::Deprecation;
 use sassafras::sass_options::*;
 use sassafras::sass_output_options::SassOutputStyle;
 use std::ffi::CString;
@@ -43,6 +44,15 @@ arg_enum! {
     }
 }
 
+arg_enum! {
+    #[derive(Debug)]
+    enum Division
+    {
+        Legacy,
+        Slash
+    }
+}
+
 #[derive(StructOpt, Debug)]
 struct Arguments {
     /// Read input from standard input instead of an input file.
@@ -81,10 +91,26 @@ struct Arguments {
     #[structopt(short = "p", long = "precision", default_value = "5")]
     precision: u8,  // TODO: What is the permissible range here.
 
+    /// How / is evaluated: legacy divides as libsass does, slash only separates values.
+    #[structopt(long = "division", default_value = "Legacy", raw(possible_values = "&Division::variants()", case_insensitive = "true"))]
+    division: Division,
+
     /// Treat input as indented syntax.
     #[structopt(short = "a", long = "sass")]
     input_is_indented: bool,
 
+    /// Treat the warnings of a deprecation, such as slash-div, as errors.
+    #[structopt(long = "fatal-deprecation", raw(number_of_values = "1"))]
+    fatal_deprecations: Vec<Deprecation>,
+
+    /// Don't report the warnings of a deprecation, such as import.
+    #[structopt(long = "silence-deprecation", raw(number_of_values = "1"))]
+    silence_deprecations: Vec<Deprecation>,
+
+    /// Don't report deprecation warnings for stylesheets loaded from load paths.
+    #[structopt(long = "quiet-deps")]
+    quiet_deps: bool,
+
     /// Input file.
     #[structopt(name = "INPUT", parse(from_os_str))]
     input_file: Option<PathBuf>,
@@ -111,6 +137,16 @@ fn rust_inner_main() {
     options.omit_source_map_url = args.omit_sourcemap_comment;
     options.output_options.inspect_options.precision = args.precision;
     options.is_indented_syntax_src = args.input_is_indented;
+    options.division = translate_division(args.division);
+    options.quiet_deps = args.quiet_deps;
+
+    for deprecation in args.fatal_deprecations {
+        options.push_fatal_deprecation(deprecation);
+    }
+
+    for deprecation in args.silence_deprecations {
+        options.push_silence_deprecation(deprecation);
+    }
 
     if let Some(ext) = args.import_extension {
         options.push_import_extension(ext);
@@ -177,6 +213,18 @@ fn c_inner_main() {
     sass_option_set_omit_source_map_url(options, args.omit_sourcemap_comment);
     sass_option_set_precision(options, args.precision);
     sass_option_set_is_indented_syntax_src(options, args.input_is_indented);
+    sass_option_set_division(options, translate_division(args.division));
+    sass_option_set_quiet_deps(options, args.quiet_deps);
+
+    for deprecation in args.fatal_deprecations {
+        let cstring = CString::new(deprecation.id()).unwrap();
+        sass_option_push_fatal_deprecation(options, cstring.as_ptr());
+    }
+
+    for deprecation in args.silence_deprecations {
+        let cstring = CString::new(deprecation.id()).unwrap();
+        sass_option_push_silence_deprecation(options, cstring.as_ptr());
+    }
 
     if let Some(ext) = args.import_extension {
         let cstring = path_to_cstring(&ext);
@@ -380,6 +428,13 @@ fn translate_output_style(arg_style: OutputStyles) -> SassOutputStyle {
     }
 }
 
+fn translate_division(arg_division: Division) -> SassDivision {
+    match arg_division {
+        Division::Legacy => SassDivision::Legacy,
+        Division::Slash => SassDivision::Slash
+    }
+}
+
 pub fn extend_extension(path: &mut PathBuf, extension: &str) {
     let new_extension = match path.extension() {
         None => OsString::from(extension),